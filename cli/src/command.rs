@@ -3,6 +3,7 @@ use std::env;
 use std::fmt::Display;
 use std::process::exit;
 use dashscript_core::{CompilerError, ASTError, TinyString};
+use crate::manifest::{Manifest, PERMISSIONS};
 
 pub trait ErrorWritter {
     const KIND: &'static str;
@@ -28,18 +29,81 @@ impl ErrorWritter for ASTError {
 #[derive(Debug)]
 pub struct Cli {
     pub args: Vec<String>,
-    pub flags: HashMap<TinyString, TinyString>
+    pub flags: HashMap<TinyString, TinyString>,
+    pub preloads: Vec<String>,
+    pub answers: Vec<(String, String)>,
+    pub eval: Option<String>
 }
 
 impl Cli {
 
     pub fn new() -> Cli {
-        let args = env::args();
+        let mut args = env::args();
         let mut complete_args = vec![];
         let mut flags = HashMap::new();
+        let mut preloads = vec![];
+        let mut answers = vec![];
+        let mut eval = None;
 
-        for arg in args {
-            if arg.starts_with("--") {
+        while let Some(arg) = args.next() {
+            // `--preload` can be repeated and also accepts the path as the next argument.
+            if arg == "--preload" {
+                match args.next() {
+                    Some(path) => preloads.push(path),
+                    None => Self::log_error("CliError: Expected a path after \"--preload\".")
+                }
+            } else if let Some(path) = arg.strip_prefix("--preload=") {
+                preloads.push(path.to_owned());
+            // The code is taken as a whole since it can contain "=" unlike the flags.
+            } else if arg == "--eval" {
+                match args.next() {
+                    Some(code) => eval = Some(code),
+                    None => Self::log_error("CliError: Expected the code to evaluate after \"--eval\".")
+                }
+            } else if let Some(code) = arg.strip_prefix("--eval=") {
+                eval = Some(code.to_owned());
+            } else if arg == "--log-level" {
+                match args.next() {
+                    Some(level) => { flags.insert(TinyString::new(b"log-level"), TinyString::new(level.as_bytes())); },
+                    None => Self::log_error("CliError: Expected a level after \"--log-level\".")
+                }
+            } else if arg == "--compat" {
+                match args.next() {
+                    Some(mode) => { flags.insert(TinyString::new(b"compat"), TinyString::new(mode.as_bytes())); },
+                    None => Self::log_error("CliError: Expected a mode after \"--compat\".")
+                }
+            } else if arg == "--profile-output" {
+                match args.next() {
+                    Some(path) => { flags.insert(TinyString::new(b"profile-output"), TinyString::new(path.as_bytes())); },
+                    None => Self::log_error("CliError: Expected a file after \"--profile-output\".")
+                }
+            } else if arg == "--seed" {
+                match args.next() {
+                    Some(seed) => { flags.insert(TinyString::new(b"seed"), TinyString::new(seed.as_bytes())); },
+                    None => Self::log_error("CliError: Expected a number after \"--seed\".")
+                }
+            } else if arg == "--max-heap" {
+                match args.next() {
+                    Some(size) => { flags.insert(TinyString::new(b"max-heap"), TinyString::new(size.as_bytes())); },
+                    None => Self::log_error("CliError: Expected a size after \"--max-heap\".")
+                }
+            // `--global math,print` can be repeated to add more groups.
+            } else if arg == "--global" {
+                match args.next() {
+                    Some(names) => Self::add_globals(&mut flags, &names),
+                    None => Self::log_error("CliError: Expected the names of the globals after \"--global\".")
+                }
+            } else if let Some(names) = arg.strip_prefix("--global=") {
+                Self::add_globals(&mut flags, names);
+            // `--answer key=value` can be repeated to answer the same key again.
+            } else if arg == "--answer" {
+                match args.next() {
+                    Some(answer) => answers.push(Self::parse_answer(&answer)),
+                    None => Self::log_error("CliError: Expected key=value after \"--answer\".")
+                }
+            } else if let Some(answer) = arg.strip_prefix("--answer=") {
+                answers.push(Self::parse_answer(answer));
+            } else if arg.starts_with("--") {
                 let split: Vec<&str> = arg[2..].split("=").collect();
                 flags.insert(TinyString::new(split[0].as_bytes()), match split.get(1) {
                     Some(val) => TinyString::new(val.as_bytes()),
@@ -52,7 +116,50 @@ impl Cli {
 
         Self {
             args: complete_args,
-            flags
+            flags,
+            preloads,
+            answers,
+            eval
+        }
+    }
+
+    fn add_globals(flags: &mut HashMap<TinyString, TinyString>, names: &str) {
+        let key = TinyString::new(b"global");
+        let names = match flags.get(&key) {
+            Some(added) => format!("{},{}", added, names),
+            None => names.to_owned()
+        };
+
+        flags.insert(key, TinyString::new(names.as_bytes()));
+    }
+
+    fn parse_answer(answer: &str) -> (String, String) {
+        match answer.split_once('=') {
+            Some((key, value)) if !key.is_empty() => (key.to_owned(), value.to_owned()),
+            _ => Self::log_error(format!("CliError: Expected the answer \"{}\" to be in the form of key=value.", answer))
+        }
+    }
+
+    // Merges the permissions granted by the manifest into the flags. A permission
+    // denied by the manifest can still be granted with `--use-<name>` but the
+    // `--deny-<name>` flag always wins over both.
+    pub fn permissions(&mut self, manifest: Option<&Manifest>) {
+        if let Some(manifest) = manifest {
+            for (name, granted) in manifest.permissions.iter() {
+                if *granted {
+                    self.flags.insert(TinyString::new(format!("use-{}", name).as_bytes()), TinyString::default());
+                }
+            }
+
+            for (key, value) in manifest.metadata.iter() {
+                self.flags.insert(TinyString::new(format!("manifest.{}", key).as_bytes()), TinyString::new(value.as_bytes()));
+            }
+        }
+
+        for name in PERMISSIONS.iter() {
+            if self.flags.contains_key(&TinyString::new(format!("deny-{}", name).as_bytes())) {
+                self.flags.remove(&TinyString::new(format!("use-{}", name).as_bytes()));
+            }
         }
     }
 
@@ -70,4 +177,58 @@ impl Cli {
         exit(1)
     }
 
-}
\ No newline at end of file
+    pub fn log_warnings<E: ErrorWritter>(warnings: &[E]) {
+        println!("Found {} {} based warnings.", warnings.len(), E::KIND);
+        for (index, warning) in warnings.iter().enumerate() {
+            warning.write_error(index + 1);
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use dashscript_core::TinyString;
+    use crate::manifest::Manifest;
+    use super::Cli;
+
+    fn cli(flags: &[&str]) -> Cli {
+        let flags = flags.iter().map(|flag| (TinyString::new(flag.as_bytes()), TinyString::default())).collect::<HashMap<_, _>>();
+        Cli { args: Vec::new(), flags, preloads: Vec::new(), answers: Vec::new(), eval: None }
+    }
+
+    fn flag(cli: &Cli, name: &str) -> Option<String> {
+        cli.flags.get(&TinyString::new(name.as_bytes())).map(|value| value.to_string())
+    }
+
+    #[test]
+    fn the_flags_win_over_the_manifest() {
+        let manifest = Manifest {
+            path: PathBuf::from("dashscript.toml"),
+            permissions: vec![("read".to_owned(), true), ("write".to_owned(), true), ("crypto".to_owned(), false)],
+            metadata: vec![("name".to_owned(), "app".to_owned())],
+            ..Default::default()
+        };
+
+        let mut cli = cli(&["deny-write", "use-crypto"]);
+        cli.permissions(Some(&manifest));
+
+        assert_eq!(flag(&cli, "use-read").as_deref(), Some(""));
+        assert_eq!(flag(&cli, "use-write"), None);
+        assert_eq!(flag(&cli, "use-crypto").as_deref(), Some(""));
+        assert_eq!(flag(&cli, "manifest.name").as_deref(), Some("app"));
+    }
+
+    #[test]
+    fn deny_flags_apply_without_a_manifest() {
+        let mut cli = cli(&["use-signal", "deny-signal", "use-memory"]);
+        cli.permissions(None);
+
+        assert_eq!(flag(&cli, "use-signal"), None);
+        assert_eq!(flag(&cli, "use-memory").as_deref(), Some(""));
+    }
+
+}
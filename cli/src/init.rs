@@ -0,0 +1,49 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use crate::command::Cli;
+use crate::manifest::MANIFEST_NAME;
+
+const DEFAULT_ENTRY: &str = "main.ds";
+
+pub fn init(cli: &mut Cli) {
+    let directory = match cli.args.get(2) {
+        Some(directory) => PathBuf::from(directory),
+        None => match env::current_dir() {
+            Ok(directory) => directory,
+            Err(e) => Cli::log_error(&format!("InitError: Could not resolve the current directory: {:?}", e))
+        }
+    };
+
+    if let Err(e) = fs::create_dir_all(&directory) {
+        Cli::log_error(&format!("InitError: Could not create the directory {}: {:?}", directory.display(), e));
+    }
+
+    let manifest_path = directory.join(MANIFEST_NAME);
+    if manifest_path.exists() {
+        Cli::log_error(&format!("InitError: A manifest already exists at {}.", manifest_path.display()));
+    }
+
+    let name = fs::canonicalize(&directory)
+        .ok()
+        .and_then(|path| path.file_name().map(|name| name.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "app".to_owned());
+
+    let manifest = format!(
+        "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nentry = \"{}\"\n\n# Permissions granted to the scripts by default.\n# Flags like `--deny-read` still take priority over these.\n[permissions]\nread = false\nwrite = false\nmemory = false\nchild-process = false\ncrypto = false\nsignal = false\n",
+        name, DEFAULT_ENTRY
+    );
+
+    if let Err(e) = fs::write(&manifest_path, manifest) {
+        Cli::log_error(&format!("InitError: Could not write the manifest: {:?}", e));
+    }
+
+    let entry_path = directory.join(DEFAULT_ENTRY);
+    if !entry_path.exists() {
+        if let Err(e) = fs::write(&entry_path, "println(\"Hello world!\");\n") {
+            Cli::log_error(&format!("InitError: Could not write the entry file: {:?}", e));
+        }
+    }
+
+    println!("Initialized project \"{}\" at {}", name, directory.display());
+}
@@ -0,0 +1,134 @@
+// The diagnostics and the symbols of a document. The positions of the parser are
+// indexes of chars while the protocol counts the utf-16 units of a line.
+
+use dashscript_core::{AST, BytecodeCompiler, CompileOptions, CompilerError, Expr};
+use super::json::Json;
+
+const SEVERITY_ERROR: usize = 1;
+const SEVERITY_WARNING: usize = 2;
+
+const SYMBOL_FUNCTION: usize = 12;
+const SYMBOL_VARIABLE: usize = 13;
+const SYMBOL_CONSTANT: usize = 14;
+
+// The syntax errors stop the compilation before the compiler can report its own
+// errors and warnings, just like in `dash run`.
+pub fn diagnostics(filename: &str, text: &str) -> Vec<Json> {
+    let chars: Vec<char> = text.chars().collect();
+    let build = match AST::compile(&filename.to_owned(), &text.to_owned()) {
+        Ok(build) => build,
+        Err(errors) => return errors.iter().map(|error| {
+            let (_, line, col) = error.position();
+            let start = index_of(&chars, line, col);
+            let end = start + error.body().chars().count().max(1);
+            diagnostic(range(&chars, start, end), SEVERITY_ERROR, &error.to_string())
+        }).collect()
+    };
+
+    match BytecodeCompiler::with_options(build, CompileOptions::default()) {
+        Ok(compiler) => compiler.warnings.iter().map(|warning| line_diagnostic(&chars, warning, SEVERITY_WARNING)).collect(),
+        Err(errors) => errors.iter().map(|error| line_diagnostic(&chars, error, SEVERITY_ERROR)).collect()
+    }
+}
+
+// The top level functions and variables. A document which does not parse has none.
+pub fn symbols(filename: &str, text: &str) -> Vec<Json> {
+    let chars: Vec<char> = text.chars().collect();
+    let build = match AST::compile(&filename.to_owned(), &text.to_owned()) {
+        Ok(build) => build,
+        Err(_) => return Vec::new()
+    };
+
+    let mut symbols = Vec::new();
+    for (index, statement) in build.statements.iter().enumerate() {
+        let (name, kind) = match &statement.expr {
+            Expr::Function { name, .. } => (*name, SYMBOL_FUNCTION),
+            Expr::Store(name, _, true) => (*name, SYMBOL_CONSTANT),
+            Expr::Store(name, _, false) => (*name, SYMBOL_VARIABLE),
+            _ => continue
+        };
+
+        let name = build.constant_pool.get_string(name).to_string();
+        let start = word_start(&chars, statement.index);
+        let next = build.statements.get(index + 1).map_or(chars.len(), |next| next.index);
+        let end = statement_end(&chars, start, next);
+        let name_start = find_word(&chars, &name, start, end).unwrap_or(start);
+
+        symbols.push(Json::object(vec![
+            ("name", Json::String(name.clone())),
+            ("kind", kind.into()),
+            ("range", range(&chars, start, end)),
+            ("selectionRange", range(&chars, name_start, name_start + name.chars().count()))
+        ]));
+    }
+
+    symbols
+}
+
+// The compiler only knows the line of its errors so they cover the whole line.
+fn line_diagnostic(chars: &[char], error: &CompilerError, severity: usize) -> Json {
+    let start = index_of(chars, error.line, 1);
+    let end = chars[start..].iter().position(|&char| char == '\n').map_or(chars.len(), |len| start + len);
+    diagnostic(range(chars, start, end), severity, &error.kind.to_string())
+}
+
+fn diagnostic(range: Json, severity: usize, message: &str) -> Json {
+    Json::object(vec![
+        ("range", range),
+        ("severity", severity.into()),
+        ("source", Json::string("dashscript")),
+        ("message", Json::string(message))
+    ])
+}
+
+// A statement ends at its last ";" or "}" before the next statement so that the
+// comments and the blank lines between them are left out.
+fn statement_end(chars: &[char], start: usize, next: usize) -> usize {
+    let next = next.min(chars.len());
+    match chars[start..next].iter().rposition(|&char| char == ';' || char == '}') {
+        Some(end) => start + end + 1,
+        None => start + chars[start..next].iter().rposition(|char| !char.is_whitespace()).map_or(0, |end| end + 1)
+    }
+}
+
+// The statements start at their keyword but the index given by the lexer can be
+// past the first char of the keyword.
+fn word_start(chars: &[char], index: usize) -> usize {
+    let index = index.min(chars.len());
+    chars[..index].iter().rposition(|char| !(char.is_alphanumeric() || *char == '_')).map_or(0, |end| end + 1)
+}
+
+fn find_word(chars: &[char], word: &str, start: usize, end: usize) -> Option<usize> {
+    let word: Vec<char> = word.chars().collect();
+    let is_ident = |index: usize| chars.get(index).is_some_and(|char| char.is_alphanumeric() || *char == '_');
+
+    (start..end.saturating_sub(word.len() - 1)).find(|&index| {
+        chars[index..index + word.len()] == word[..] && (index == 0 || !is_ident(index - 1)) && !is_ident(index + word.len())
+    })
+}
+
+// The index of the char at a line and a column which both start from 1.
+fn index_of(chars: &[char], line: usize, col: usize) -> usize {
+    let mut index = 0;
+    for _ in 1..line {
+        match chars[index..].iter().position(|&char| char == '\n') {
+            Some(len) => index += len + 1,
+            None => return chars.len()
+        }
+    }
+
+    (index + col.saturating_sub(1)).min(chars.len())
+}
+
+fn range(chars: &[char], start: usize, end: usize) -> Json {
+    Json::object(vec![("start", position(chars, start)), ("end", position(chars, end))])
+}
+
+fn position(chars: &[char], index: usize) -> Json {
+    let index = index.min(chars.len());
+    let line_start = chars[..index].iter().rposition(|&char| char == '\n').map_or(0, |newline| newline + 1);
+    let line = chars[..line_start].iter().filter(|&&char| char == '\n').count();
+    let character: usize = chars[line_start..index].iter().map(|char| char.len_utf16()).sum();
+
+    Json::object(vec![("line", line.into()), ("character", character.into())])
+}
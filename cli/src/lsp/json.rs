@@ -0,0 +1,277 @@
+// The subset of json needed by the language server. The objects keep the order
+// of their keys so the messages are written the way they were built.
+
+use std::fmt::{self, Display, Write};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>)
+}
+
+impl Json {
+    pub fn object(entries: Vec<(&str, Json)>) -> Self {
+        Self::Object(entries.into_iter().map(|(key, value)| (key.to_owned(), value)).collect())
+    }
+
+    pub fn string(string: &str) -> Self {
+        Self::String(string.to_owned())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Self::Object(entries) => entries.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(string) => Some(string),
+            _ => None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Self::Array(items) => Some(items),
+            _ => None
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Json, String> {
+        let mut parser = Parser { chars: source.chars().collect(), index: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+
+        match parser.chars.get(parser.index) {
+            None => Ok(value),
+            Some(char) => Err(format!("Unexpected \"{}\" after the value at {}.", char, parser.index))
+        }
+    }
+}
+
+impl From<usize> for Json {
+    fn from(number: usize) -> Self {
+        Self::Number(number as f64)
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Bool(bool) => write!(f, "{}", bool),
+            Self::Number(number) if number.fract() == 0.0 && number.abs() < 1e15 => write!(f, "{}", *number as i64),
+            Self::Number(number) => write!(f, "{}", number),
+            Self::String(string) => write_string(f, string),
+            Self::Array(items) => {
+                f.write_char('[')?;
+                for (index, item) in items.iter().enumerate() {
+                    if index != 0 {
+                        f.write_char(',')?;
+                    }
+
+                    write!(f, "{}", item)?;
+                }
+
+                f.write_char(']')
+            },
+            Self::Object(entries) => {
+                f.write_char('{')?;
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    if index != 0 {
+                        f.write_char(',')?;
+                    }
+
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, string: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for char in string.chars() {
+        match char {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            char if (char as u32) < 0x20 => write!(f, "\\u{:04x}", char as u32)?,
+            char => f.write_char(char)?
+        }
+    }
+
+    f.write_char('"')
+}
+
+struct Parser {
+    chars: Vec<char>,
+    index: usize
+}
+
+impl Parser {
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.chars.get(self.index) {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => self.string().map(Json::String),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('n') => self.keyword("null", Json::Null),
+            Some(char) if *char == '-' || char.is_ascii_digit() => self.number(),
+            Some(char) => Err(format!("Unexpected \"{}\" at {}.", char, self.index)),
+            None => Err("Unexpected end of the input.".to_owned())
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.index += 1;
+        let mut entries = Vec::new();
+
+        self.skip_whitespace();
+        if self.eat('}') {
+            return Ok(Json::Object(entries));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            if !self.eat(':') {
+                return Err(format!("Expected \":\" at {}.", self.index));
+            }
+
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.eat(',') {
+                true => continue,
+                false if self.eat('}') => return Ok(Json::Object(entries)),
+                false => return Err(format!("Expected \",\" or \"}}\" at {}.", self.index))
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.index += 1;
+        let mut items = Vec::new();
+
+        self.skip_whitespace();
+        if self.eat(']') {
+            return Ok(Json::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.eat(',') {
+                true => continue,
+                false if self.eat(']') => return Ok(Json::Array(items)),
+                false => return Err(format!("Expected \",\" or \"]\" at {}.", self.index))
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        if !self.eat('"') {
+            return Err(format!("Expected a string at {}.", self.index));
+        }
+
+        let mut string = String::new();
+        loop {
+            let char = match self.chars.get(self.index) {
+                Some(char) => *char,
+                None => return Err("Unterminated string.".to_owned())
+            };
+
+            self.index += 1;
+            match char {
+                '"' => return Ok(string),
+                '\\' => {
+                    let escaped = self.chars.get(self.index).copied();
+                    self.index += 1;
+                    match escaped {
+                        Some('n') => string.push('\n'),
+                        Some('r') => string.push('\r'),
+                        Some('t') => string.push('\t'),
+                        Some('b') => string.push('\u{8}'),
+                        Some('f') => string.push('\u{c}'),
+                        Some('u') => string.push(self.unicode_escape()?),
+                        Some(char) => string.push(char),
+                        None => return Err("Unterminated string.".to_owned())
+                    }
+                },
+                char => string.push(char)
+            }
+        }
+    }
+
+    // Joins the surrogate pairs which encode the chars outside of the basic plane.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        if (0xD800..0xDC00).contains(&high) && self.chars.get(self.index..self.index + 2) == Some(&['\\', 'u']) {
+            self.index += 2;
+            let low = self.hex4()?;
+            let code = 0x10000 + ((high - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+            return Ok(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+
+        Ok(char::from_u32(high).unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits: String = self.chars.get(self.index..self.index + 4).unwrap_or_default().iter().collect();
+        self.index += 4;
+        u32::from_str_radix(&digits, 16).map_err(|_| format!("Invalid unicode escape \"{}\".", digits))
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.index;
+        while let Some(char) = self.chars.get(self.index) {
+            match char {
+                '0'..='9' | '-' | '+' | '.' | 'e' | 'E' => self.index += 1,
+                _ => break
+            }
+        }
+
+        let number: String = self.chars[start..self.index].iter().collect();
+        number.parse().map(Json::Number).map_err(|_| format!("Invalid number \"{}\".", number))
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, String> {
+        let len = keyword.chars().count();
+        match self.chars.get(self.index..self.index + len) {
+            Some(chars) if chars.iter().copied().eq(keyword.chars()) => {
+                self.index += len;
+                Ok(value)
+            },
+            _ => Err(format!("Unexpected \"{}\" at {}.", self.chars[self.index], self.index))
+        }
+    }
+
+    fn eat(&mut self, char: char) -> bool {
+        let found = self.chars.get(self.index) == Some(&char);
+        if found {
+            self.index += 1;
+        }
+
+        found
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.index).is_some_and(|char| char.is_whitespace()) {
+            self.index += 1;
+        }
+    }
+}
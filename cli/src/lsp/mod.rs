@@ -0,0 +1,148 @@
+// `dash lsp` speaks the language server protocol over stdio. The requests are
+// handled one by one as they arrive so no async runtime is involved. The open
+// documents are fully synced and recompiled on every change to publish their
+// diagnostics.
+
+pub mod json;
+pub mod rpc;
+pub mod analysis;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use crate::command::Cli;
+use json::Json;
+
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+// The whole text of the document is sent on each change.
+const SYNC_FULL: usize = 1;
+
+pub fn lsp(_: &mut Cli) {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+
+    if let Err(error) = Server::default().serve(&mut stdin.lock(), &mut stdout.lock()) {
+        Cli::log_error(format!("LspError: {}", error));
+    }
+}
+
+#[derive(Default)]
+pub struct Server {
+    // The texts of the open documents by their uris.
+    documents: HashMap<String, String>,
+    shutdown: bool
+}
+
+impl Server {
+    // Returns when the client sends the exit notification or closes the input.
+    pub fn serve<R: BufRead, W: Write>(&mut self, reader: &mut R, writer: &mut W) -> io::Result<()> {
+        while let Some(message) = rpc::read_message(reader)? {
+            let method = message.get("method").and_then(Json::as_str).unwrap_or_default();
+            let params = message.get("params").cloned().unwrap_or(Json::Null);
+
+            match message.get("id").cloned() {
+                Some(id) => {
+                    let response = match self.request(method, &params) {
+                        Ok(result) => rpc::response(id, result),
+                        Err((code, error)) => rpc::error_response(id, code, &error)
+                    };
+
+                    rpc::write_message(writer, &response)?;
+                },
+                None if method == "exit" => return Ok(()),
+                None => {
+                    for notification in self.notification(method, &params) {
+                        rpc::write_message(writer, &notification)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn request(&mut self, method: &str, params: &Json) -> Result<Json, (i32, String)> {
+        match method {
+            "initialize" => Ok(Json::object(vec![
+                ("capabilities", Json::object(vec![
+                    ("textDocumentSync", Json::object(vec![
+                        ("openClose", Json::Bool(true)),
+                        ("change", SYNC_FULL.into()),
+                        ("save", Json::object(vec![("includeText", Json::Bool(true))]))
+                    ])),
+                    ("documentSymbolProvider", Json::Bool(true))
+                ])),
+                ("serverInfo", Json::object(vec![
+                    ("name", Json::string("dashscript")),
+                    ("version", Json::string(dashscript_core::core::VERSION))
+                ]))
+            ])),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Json::Null)
+            },
+            "textDocument/documentSymbol" => {
+                let uri = uri_of(params).ok_or((INVALID_PARAMS, "Expected the uri of the document.".to_owned()))?;
+                match self.documents.get(uri) {
+                    Some(text) => Ok(Json::Array(analysis::symbols(&path_of(uri), text))),
+                    None => Ok(Json::Array(Vec::new()))
+                }
+            },
+            method => Err((METHOD_NOT_FOUND, format!("Unknown method \"{}\".", method)))
+        }
+    }
+
+    // Returns the notifications to send back, which are the diagnostics of the
+    // document that has changed.
+    fn notification(&mut self, method: &str, params: &Json) -> Vec<Json> {
+        if self.shutdown {
+            return Vec::new();
+        }
+
+        let uri = match uri_of(params) {
+            Some(uri) => uri.to_owned(),
+            None => return Vec::new()
+        };
+
+        let document = params.get("textDocument");
+        let text = match method {
+            "textDocument/didOpen" => document.and_then(|document| document.get("text")),
+            // The last change holds the whole text as the sync is full.
+            "textDocument/didChange" => params.get("contentChanges")
+                .and_then(Json::as_array)
+                .and_then(|changes| changes.last())
+                .and_then(|change| change.get("text")),
+            "textDocument/didSave" => params.get("text"),
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![publish(&uri, Vec::new())];
+            },
+            _ => return Vec::new()
+        };
+
+        if let Some(text) = text.and_then(Json::as_str) {
+            self.documents.insert(uri.clone(), text.to_owned());
+        }
+
+        match self.documents.get(&uri) {
+            Some(text) => vec![publish(&uri, analysis::diagnostics(&path_of(&uri), text))],
+            None => Vec::new()
+        }
+    }
+}
+
+fn publish(uri: &str, diagnostics: Vec<Json>) -> Json {
+    rpc::notification("textDocument/publishDiagnostics", Json::object(vec![
+        ("uri", Json::string(uri)),
+        ("diagnostics", Json::Array(diagnostics))
+    ]))
+}
+
+fn uri_of(params: &Json) -> Option<&str> {
+    params.get("textDocument")?.get("uri")?.as_str()
+}
+
+// The file name shown by the errors. Only the file uris are turned into paths.
+fn path_of(uri: &str) -> String {
+    uri.strip_prefix("file://").unwrap_or(uri).to_owned()
+}
@@ -0,0 +1,58 @@
+// The framing of json-rpc over stdio where each message is preceded by its
+// headers and an empty line.
+
+use std::io::{self, BufRead, Write};
+use super::json::Json;
+
+// Returns None when the client has closed the input.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Json>> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+
+    let length = match length {
+        Some(length) => length,
+        None => return Err(io::Error::new(io::ErrorKind::InvalidData, "Expected a Content-Length header."))
+    };
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    let body = String::from_utf8(body).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    Json::parse(&body).map(Some).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+pub fn response(id: Json, result: Json) -> Json {
+    Json::object(vec![("jsonrpc", Json::string("2.0")), ("id", id), ("result", result)])
+}
+
+pub fn error_response(id: Json, code: i32, message: &str) -> Json {
+    let error = Json::object(vec![("code", Json::Number(code as f64)), ("message", Json::string(message))]);
+    Json::object(vec![("jsonrpc", Json::string("2.0")), ("id", id), ("error", error)])
+}
+
+pub fn notification(method: &str, params: Json) -> Json {
+    Json::object(vec![("jsonrpc", Json::string("2.0")), ("method", Json::string(method)), ("params", params)])
+}
@@ -1,5 +1,8 @@
 pub mod run;
 pub mod command;
+pub mod manifest;
+pub mod init;
+pub mod lsp;
 // pub mod repl;
 
 use command::Cli;
@@ -16,9 +19,13 @@ pub fn main() {
         Some(cmd_name) => {
             match cmd_name as &str {
                 "run" => run::run(&mut command),
+                "init" => init::init(&mut command),
+                "lsp" => lsp::lsp(&mut command),
                 name => println!("CliError: Detected an unknown command \"{}\"", name)
             }
         },
-        _ => println!("CliError: No command has been provided to execute.")
+        // `--eval` runs the code without the need of the run command.
+        None if command.eval.is_some() => run::run(&mut command),
+        None => println!("CliError: No command has been provided to execute.")
     }
 }
\ No newline at end of file
@@ -0,0 +1,300 @@
+use std::fs;
+use std::fmt::{self, Display, Formatter};
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_NAME: &str = "dashscript.toml";
+
+// Permissions which can be granted by the manifest. These are the same names
+// used by the `--use-<name>` and `--deny-<name>` flags.
+pub const PERMISSIONS: [&str; 6] = ["read", "write", "memory", "child-process", "crypto", "signal"];
+
+// A minimal subset of toml is supported by the manifest to avoid extra dependencies.
+// Only `[package]` and `[permissions]` sections with `key = "string"` or `key = bool`
+// entries, the `preload = ["path", ...]` array and `#` comments are allowed.
+#[derive(Debug, Clone, Default)]
+pub struct Manifest {
+    pub path: PathBuf,
+    pub entry: Option<String>,
+    pub preload: Vec<String>,
+    pub permissions: Vec<(String, bool)>,
+    pub metadata: Vec<(String, String)>
+}
+
+#[derive(Debug, Clone)]
+pub struct ManifestError {
+    pub path: PathBuf,
+    pub line: usize,
+    pub col: usize,
+    pub message: String
+}
+
+impl Display for ManifestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "ManifestError: at [{}:{}:{}]: {}", self.path.display(), self.line, self.col, self.message)
+    }
+}
+
+enum ManifestValue {
+    String(String),
+    Bool(bool),
+    Array(Vec<String>)
+}
+
+impl Manifest {
+
+    pub fn read(path: PathBuf) -> Result<Self, ManifestError> {
+        match fs::read_to_string(&path) {
+            Ok(body) => Self::parse(path, &body),
+            Err(error) => Err(ManifestError { path, line: 0, col: 0, message: format!("Could not read the manifest: {}", error) })
+        }
+    }
+
+    pub fn parse(path: PathBuf, body: &str) -> Result<Self, ManifestError> {
+        let mut manifest = Manifest { path, ..Default::default() };
+        let mut section = String::new();
+
+        for (index, raw_line) in body.lines().enumerate() {
+            let line = strip_comment(raw_line);
+            let trimmed = line.trim();
+            let col = line.len() - line.trim_start().len() + 1;
+
+            macro_rules! error {
+                ($col:expr, $($arg:tt)+) => {
+                    return Err(ManifestError {
+                        path: manifest.path,
+                        line: index + 1,
+                        col: $col,
+                        message: format!($($arg)+)
+                    })
+                };
+            }
+
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            if trimmed.starts_with('[') {
+                if !trimmed.ends_with(']') {
+                    error!(col + trimmed.len(), "Expected a \"]\" to close the section header.");
+                }
+
+                section = trimmed[1..trimmed.len() - 1].trim().to_owned();
+                if !matches!(section.as_str(), "package" | "permissions") {
+                    error!(col + 1, "Unknown section \"{}\". Expected either \"package\" or \"permissions\".", section);
+                }
+
+                continue;
+            }
+
+            let (key, value) = match trimmed.find('=') {
+                Some(eq_index) => (trimmed[..eq_index].trim(), (trimmed[eq_index + 1..].trim(), col + eq_index + 1)),
+                None => error!(col, "Expected a \"key = value\" entry.")
+            };
+
+            if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                error!(col, "Invalid key \"{}\".", key);
+            }
+
+            let (value, value_col) = value;
+            let value = match parse_value(value) {
+                Some(value) => value,
+                None => error!(value_col + 1, "Expected a quoted string, a boolean or an array of strings as the value of \"{}\".", key)
+            };
+
+            match (section.as_str(), value) {
+                ("package", ManifestValue::String(string)) => {
+                    if key == "entry" {
+                        manifest.entry = Some(string.clone());
+                    }
+
+                    manifest.metadata.push((key.to_owned(), string));
+                },
+                ("package", ManifestValue::Array(paths)) if key == "preload" => manifest.preload = paths,
+                ("permissions", ManifestValue::Bool(granted)) => {
+                    if !PERMISSIONS.contains(&key) {
+                        error!(col, "Unknown permission \"{}\". Expected one of {}.", key, PERMISSIONS.join(", "));
+                    }
+
+                    manifest.permissions.push((key.to_owned(), granted));
+                },
+                ("package", _) => error!(value_col + 1, "Expected a quoted string as the value of \"{}\".", key),
+                ("permissions", _) => error!(value_col + 1, "Expected a boolean as the value of the permission \"{}\".", key),
+                _ => error!(col, "Expected a section header before the entry \"{}\".", key)
+            }
+        }
+
+        Ok(manifest)
+    }
+
+    // Looks for the manifest in the directory of the path and then in all of its
+    // parent directories.
+    pub fn find(start: &Path) -> Option<PathBuf> {
+        let start = fs::canonicalize(start).unwrap_or_else(|_| start.to_path_buf());
+        let mut directory = if start.is_dir() { Some(start.as_path()) } else { start.parent() };
+
+        while let Some(path) = directory {
+            let manifest_path = path.join(MANIFEST_NAME);
+            if manifest_path.is_file() {
+                return Some(manifest_path);
+            }
+
+            directory = path.parent();
+        }
+
+        None
+    }
+
+    pub fn directory(&self) -> &Path {
+        self.path.parent().unwrap_or_else(|| Path::new("."))
+    }
+
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..index],
+            _ => ()
+        }
+    }
+
+    line
+}
+
+fn parse_value(value: &str) -> Option<ManifestValue> {
+    match value {
+        "true" => Some(ManifestValue::Bool(true)),
+        "false" => Some(ManifestValue::Bool(false)),
+        _ if value.len() >= 2 && value.starts_with('[') && value.ends_with(']') => {
+            let inner = value[1..value.len() - 1].trim();
+            let mut items = Vec::new();
+
+            let parts: Vec<&str> = inner.split(',').map(str::trim).collect();
+
+            for (index, item) in parts.iter().enumerate() {
+                match parse_value(item) {
+                    Some(ManifestValue::String(string)) => items.push(string),
+                    // Either an empty array or a trailing comma after the last item.
+                    None if item.is_empty() && index == parts.len() - 1 => (),
+                    _ => return None
+                }
+            }
+
+            Some(ManifestValue::Array(items))
+        },
+        _ if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') => {
+            let inner = &value[1..value.len() - 1];
+            if inner.contains('"') {
+                None
+            } else {
+                Some(ManifestValue::String(inner.to_owned()))
+            }
+        },
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::{env, fs, process};
+    use std::path::PathBuf;
+    use super::{Manifest, MANIFEST_NAME};
+
+    fn parse(body: &str) -> Manifest {
+        match Manifest::parse(PathBuf::from("dashscript.toml"), body) {
+            Ok(manifest) => manifest,
+            Err(error) => panic!("{}", error)
+        }
+    }
+
+    // The line, the column and the message of the error of the body.
+    fn error(body: &str) -> (usize, usize, String) {
+        match Manifest::parse(PathBuf::from("dashscript.toml"), body) {
+            Ok(manifest) => panic!("The manifest has been parsed as {:?}", manifest),
+            Err(error) => (error.line, error.col, error.message)
+        }
+    }
+
+    #[test]
+    fn parses_the_package_and_the_permissions() {
+        let manifest = parse(concat!(
+            "# The project.\n",
+            "[package]\n",
+            "name = \"app\" # The name.\n",
+            "entry = \"src/main.ds\"\n",
+            "preload = [\"a.ds\", \"b.ds\",]\n",
+            "\n",
+            "[permissions]\n",
+            "read = true\n",
+            "child-process = false\n"
+        ));
+
+        assert_eq!(manifest.entry.as_deref(), Some("src/main.ds"));
+        assert_eq!(manifest.preload, ["a.ds", "b.ds"]);
+        assert_eq!(manifest.metadata, [("name".to_owned(), "app".to_owned()), ("entry".to_owned(), "src/main.ds".to_owned())]);
+        assert_eq!(manifest.permissions, [("read".to_owned(), true), ("child-process".to_owned(), false)]);
+    }
+
+    #[test]
+    fn empty_manifests_have_no_entry() {
+        let manifest = parse("");
+        assert!(manifest.entry.is_none());
+        assert!(manifest.preload.is_empty() && manifest.permissions.is_empty());
+        assert!(parse("[package]\npreload = []\n").preload.is_empty());
+    }
+
+    #[test]
+    fn comments_are_kept_inside_strings() {
+        let manifest = parse("[package]\nentry = \"#main.ds\" # comment\n");
+        assert_eq!(manifest.entry.as_deref(), Some("#main.ds"));
+    }
+
+    #[test]
+    fn errors_point_at_the_line_and_the_column() {
+        let cases = [
+            ("[package", 1, 9, "Expected a \"]\" to close the section header."),
+            ("[dependencies]", 1, 2, "Unknown section \"dependencies\". Expected either \"package\" or \"permissions\"."),
+            ("[package]\n  entry", 2, 3, "Expected a \"key = value\" entry."),
+            ("[package]\nen try = \"a\"", 2, 1, "Invalid key \"en try\"."),
+            ("[package]\nentry = main.ds", 2, 9, "Expected a quoted string, a boolean or an array of strings as the value of \"entry\"."),
+            ("[package]\nentry = true", 2, 9, "Expected a quoted string as the value of \"entry\"."),
+            ("[permissions]\nread = \"yes\"", 2, 8, "Expected a boolean as the value of the permission \"read\"."),
+            ("[permissions]\nnetwork = true", 2, 1, "Unknown permission \"network\". Expected one of read, write, memory, child-process, crypto, signal."),
+            ("entry = \"a\"", 1, 1, "Expected a section header before the entry \"entry\".")
+        ];
+
+        for &(body, line, col, message) in cases.iter() {
+            assert_eq!(error(body), (line, col, message.to_owned()), "{:?}", body);
+        }
+    }
+
+    #[test]
+    fn the_directory_is_the_parent_of_the_path() {
+        let manifest = Manifest { path: PathBuf::from("project/dashscript.toml"), ..Default::default() };
+        assert_eq!(manifest.directory(), PathBuf::from("project"));
+    }
+
+    #[test]
+    fn find_walks_up_to_the_closest_manifest() {
+        let root = env::temp_dir().join(format!("dashscript-find-{}", process::id()));
+        let nested = root.join("app").join("src");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(MANIFEST_NAME), "").unwrap();
+        fs::write(nested.join("main.ds"), "").unwrap();
+
+        let root_manifest = fs::canonicalize(&root).unwrap().join(MANIFEST_NAME);
+        assert_eq!(Manifest::find(&nested.join("main.ds")), Some(root_manifest.clone()));
+        assert_eq!(Manifest::find(&nested), Some(root_manifest));
+
+        fs::write(root.join("app").join(MANIFEST_NAME), "").unwrap();
+        let app_manifest = fs::canonicalize(root.join("app")).unwrap().join(MANIFEST_NAME);
+        assert_eq!(Manifest::find(&nested.join("main.ds")), Some(app_manifest));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+}
@@ -1,23 +1,151 @@
 extern crate dashscript_core;
 
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Read};
 use std::path::PathBuf;
-use dashscript_core::{AST, BytecodeCompiler, Vm, TinyString};
+use std::process;
+use dashscript_core::{AST, BytecodeCompiler, CompileOptions, Vm, Value, TinyString, Chunk, ConstantPool, RuntimeErrorKind};
+use dashscript_core::core::{log, window, tty, installer::{self, Selection}};
+use dashscript_core::runtime::memory;
 use crate::command::Cli;
+use crate::manifest::Manifest;
 use crate::read_file;
 
+const STDIN_NAME: &str = "<stdin>";
+const EVAL_NAME: &str = "<eval>";
+
 pub fn run(cli: &mut Cli) {
-    if cli.args.len() <= 2 {
-        Cli::log_error("InvalidFileError: No file name specified.");
+    // Without a file name the entry of the manifest in the current directory
+    // or in any of its parents gets executed.
+    let (fname, manifest) = match cli.args.get(2) {
+        _ if cli.eval.is_some() => (EVAL_NAME.to_owned(), find_manifest(env::current_dir().ok())),
+        // `-` reads the script from stdin.
+        Some(fname) if fname == "-" => (STDIN_NAME.to_owned(), find_manifest(env::current_dir().ok())),
+        Some(fname) => (fname.clone(), find_manifest(Some(PathBuf::from(fname)))),
+        None => match find_manifest(env::current_dir().ok()) {
+            Some(manifest) => match &manifest.entry {
+                Some(entry) => (manifest.directory().join(entry).to_string_lossy().into_owned(), Some(manifest)),
+                None => Cli::log_error("InvalidFileError: No file name specified and the manifest has no entry.")
+            },
+            None => Cli::log_error("InvalidFileError: No file name specified.")
+        }
+    };
+
+    cli.permissions(manifest.as_ref());
+
+    let from_stdin = fname == STDIN_NAME;
+    let (pathbuf, body) = if let Some(code) = &cli.eval {
+        // The semicolon of the last statement is optional for the one-liners.
+        (env::current_dir().unwrap_or_default().join(EVAL_NAME), format!("{};", code))
+    } else if from_stdin {
+        let mut body = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut body) {
+            Cli::log_error(&format!("InvalidFileError: Could not read the script from stdin: {:?}", e));
+        }
+
+        (env::current_dir().unwrap_or_default().join(STDIN_NAME), body)
+    } else {
+        let pathbuf = PathBuf::from(&fname);
+        match read_file(pathbuf.clone()) {
+            Ok(content) => (pathbuf, content),
+            Err(e) => Cli::log_error(&format!("InvalidFileError: Could not read file: {:?}", e))
+        }
+    };
+
+    // Limits the number of syntax errors reported with `--max-errors=<n>`.
+    let max_errors = match cli.flags.get(&TinyString::new(b"max-errors")) {
+        Some(max_errors) => match max_errors.to_string().parse() {
+            Ok(max_errors) => max_errors,
+            Err(_) => Cli::log_error("CliError: Expected a positive integer as the value of \"--max-errors\".")
+        },
+        None => AST::DEFAULT_MAX_ERRORS
+    };
+
+    // The level of `window.log` given with `--log-level <level>` which defaults to info.
+    if let Some(level) = cli.flags.get(&TinyString::new(b"log-level")) {
+        if log::Level::from_name(level).is_none() {
+            Cli::log_error(format!("CliError: Unknown log level \"{}\". Expected one of {}.", level, log::Level::NAMES.join(", ")));
+        }
     }
 
-    let fname = &cli.args[2];
-    let pathbuf = PathBuf::from(fname);
-    let body = match read_file(pathbuf.clone()) {
-        Ok(content) => content,
-        Err(e) => Cli::log_error(&format!("InvalidFileError: Could not read file: {:?}", e))
+    // The limit of the heap given with `--max-heap <size>` like 64mb.
+    if let Some(size) = cli.flags.get(&TinyString::new(b"max-heap")) {
+        if memory::parse_byte_size(size).is_none() {
+            Cli::log_error(format!("CliError: Invalid heap limit \"{}\". Expected a size like 512kb, 64mb or 1gb.", size));
+        }
+    }
+
+    // The seed given with `--seed <n>` which makes the runs repeat each other.
+    if let Some(seed) = cli.flags.get(&TinyString::new(b"seed")) {
+        if seed.to_string().parse::<u64>().is_err() {
+            Cli::log_error(format!("CliError: Invalid seed \"{}\". Expected a positive integer.", seed));
+        }
+    }
+
+    // The names familiar from another language given with `--compat <mode>`.
+    if let Some(mode) = cli.flags.get(&TinyString::new(b"compat")) {
+        if mode.to_bytes() != b"js" {
+            Cli::log_error(format!("CliError: Unknown compat mode \"{}\". Expected js.", mode));
+        }
+    }
+
+    // The groups of builtins given with `--global math,print`.
+    if let Some(name) = Selection::of(&cli.flags).unknown() {
+        Cli::log_error(format!("CliError: Unknown global \"{}\". Expected one of {}.", name, installer::names().join(", ")));
+    }
+
+    // `--fine-positions` makes the errors point at the call or the operator which
+    // raised them instead of the start of their statement.
+    let options = CompileOptions {
+        fine_positions: cli.flags.contains_key(&TinyString::new(b"fine-positions")),
+        globals: Selection::of(&cli.flags),
+        ..Default::default()
     };
 
-    let build = match AST::compile(&cli.args[2], &body) {
+    // The preloads of the manifest are resolved from its directory and executed
+    // before the ones given with `--preload=<file>`.
+    let mut preload_paths = Vec::new();
+    if let Some(manifest) = &manifest {
+        preload_paths.extend(manifest.preload.iter().map(|path| manifest.directory().join(path)));
+    }
+
+    preload_paths.extend(cli.preloads.iter().map(PathBuf::from));
+
+    // Each script continues the constant pool of the previous one as all of them
+    // are executed by the same vm.
+    let mut constant_pool = ConstantPool::default();
+    let mut preloads = Vec::new();
+
+    for path in preload_paths {
+        let name = path.to_string_lossy().into_owned();
+        let body = match read_file(path) {
+            Ok(body) => body,
+            Err(e) => Cli::log_error(&format!("InvalidFileError: Could not read the preload \"{}\": {:?}", name, e))
+        };
+
+        let compiler = match AST::compile_with_constant_pool(&name, &body, max_errors, constant_pool) {
+            Ok(build) => match BytecodeCompiler::with_options(build, options.clone()) {
+                Ok(compiler) => compiler,
+                Err(errors) => Cli::log_errors(errors)
+            },
+            Err(errors) => Cli::log_errors(errors)
+        };
+
+        if !compiler.warnings.is_empty() {
+            if cli.flags.contains_key(&TinyString::new(b"strict")) {
+                Cli::log_errors(compiler.warnings)
+            }
+
+            Cli::log_warnings(&compiler.warnings);
+        }
+
+        let chunk: Chunk = compiler.into();
+        constant_pool = chunk.constant_pool().clone();
+        preloads.push((name, chunk));
+    }
+
+    let build = match AST::compile_with_constant_pool(&fname, &body, max_errors, constant_pool) {
         Ok(build) => build,
         Err(errors) => Cli::log_errors(errors)
     };
@@ -28,15 +156,84 @@ pub fn run(cli: &mut Cli) {
         };
     }
 
-    insert_flag!("filename", TinyString::new(cli.args[2].as_bytes()));
+    insert_flag!("filename", TinyString::new(fname.as_bytes()));
 
-    let compiler = match BytecodeCompiler::new(build) {
+    // Lets `readline` know that stdin has already been consumed by the script.
+    if from_stdin {
+        insert_flag!("stdin-script", TinyString::default());
+    }
+
+    // The value of the last expression of the evaluated code gets printed.
+    let options = CompileOptions { keep_result: cli.eval.is_some(), ..options };
+    let compiler = match BytecodeCompiler::with_options(build, options) {
         Ok(compiler) => compiler,
         Err(errors) => Cli::log_errors(errors)
     };
 
-    match Vm::new(compiler.into(), cli.flags.clone(), pathbuf) {
+    if cli.flags.contains_key(&TinyString::new(b"compile-stats")) {
+        eprint!("{}", compiler.stats());
+    }
+
+    // Warnings are treated as errors with `--strict`.
+    if !compiler.warnings.is_empty() {
+        if cli.flags.contains_key(&TinyString::new(b"strict")) {
+            Cli::log_errors(compiler.warnings)
+        }
+
+        Cli::log_warnings(&compiler.warnings);
+    }
+
+    // The arguments after the file name are exposed to the script as `script.args`.
+    let args = match (&cli.eval, cli.args.get(3..)) {
+        (None, Some(args)) => args.to_vec(),
+        _ => Vec::new()
+    };
+
+    let mut builder = Vm::builder(compiler.into(), cli.flags.clone(), pathbuf).args(args);
+    for (name, chunk) in preloads {
+        builder = builder.preload(&name, chunk);
+    }
+
+    for (key, value) in &cli.answers {
+        builder = builder.answer(key, value);
+    }
+
+    // Redirects the output of the script into a file with `--output=<file>`.
+    if let Some(output) = cli.flags.get(&TinyString::new(b"output")) {
+        match File::create(output.to_string()) {
+            Ok(file) => builder = builder.stdout(Box::new(BufWriter::new(file))),
+            Err(e) => Cli::log_error(&format!("InvalidFileError: Could not create the output file: {:?}", e))
+        }
+    }
+
+    match builder.build() {
+        // The vm is dropped first so that the output of the code is flushed before it.
+        Ok(vm) if cli.eval.is_some() => match vm.result() {
+            Value::Null => (),
+            result => {
+                let inspected = window::inspect(&vm, &result);
+                drop(vm);
+                println!("{}", inspected);
+            }
+        },
         Ok(_) => (),
-        Err(e) => println!("{:?}", e)
+        // The trace of an uncaught panic has already been printed by the vm.
+        Err(e) => match e.kind() {
+            RuntimeErrorKind::Exit | RuntimeErrorKind::Panic => process::exit(e.exit_code().unwrap_or_default()),
+            _ => {
+                eprintln!("{}", tty::paint(&e.to_string(), tty::Color::Red, tty::is_terminal(tty::Stream::Stderr)));
+                process::exit(1)
+            }
+        }
     };
-}
\ No newline at end of file
+}
+
+// Finds and parses the manifest of the path. It is read only once and the entry,
+// the permissions and the preloads all come from it.
+fn find_manifest(start: Option<PathBuf>) -> Option<Manifest> {
+    let path = Manifest::find(&start?)?;
+    match Manifest::read(path) {
+        Ok(manifest) => Some(manifest),
+        Err(error) => Cli::log_error(error)
+    }
+}
@@ -0,0 +1,117 @@
+// Canned messages are piped into `dash lsp` and the framed messages it writes
+// back are compared as they are, as the json written by the server keeps the
+// order of its keys.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const URI: &str = "file:///tmp/lsp.ds";
+
+fn frame(body: &str) -> String {
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+// Writes the messages to the server and returns the bodies of its messages once
+// it has exited.
+fn exchange(messages: &[String]) -> Vec<String> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_dash"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("The server starts.");
+
+    let input: String = messages.iter().map(|message| frame(message)).collect();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let mut stdout = String::from_utf8(output.stdout).unwrap();
+    let mut bodies = Vec::new();
+    while !stdout.is_empty() {
+        let (header, rest) = stdout.split_once("\r\n\r\n").expect("The message has headers.");
+        let length: usize = header.strip_prefix("Content-Length: ").expect("The header is the length.").parse().unwrap();
+        bodies.push(rest[..length].to_owned());
+        stdout = rest[length..].to_owned();
+    }
+
+    bodies
+}
+
+fn request(id: usize, method: &str, params: &str) -> String {
+    format!("{{\"jsonrpc\":\"2.0\",\"id\":{},\"method\":\"{}\",\"params\":{}}}", id, method, params)
+}
+
+fn notification(method: &str, params: &str) -> String {
+    format!("{{\"jsonrpc\":\"2.0\",\"method\":\"{}\",\"params\":{}}}", method, params)
+}
+
+fn did_open(text: &str) -> String {
+    notification("textDocument/didOpen", &format!("{{\"textDocument\":{{\"uri\":\"{}\",\"languageId\":\"dashscript\",\"version\":1,\"text\":\"{}\"}}}}", URI, text))
+}
+
+fn did_change(text: &str) -> String {
+    notification("textDocument/didChange", &format!("{{\"textDocument\":{{\"uri\":\"{}\",\"version\":2}},\"contentChanges\":[{{\"text\":\"{}\"}}]}}", URI, text))
+}
+
+fn document_symbol(id: usize) -> String {
+    request(id, "textDocument/documentSymbol", &format!("{{\"textDocument\":{{\"uri\":\"{}\"}}}}", URI))
+}
+
+fn shutdown(id: usize) -> [String; 2] {
+    [request(id, "shutdown", "null"), "{\"jsonrpc\":\"2.0\",\"method\":\"exit\"}".to_owned()]
+}
+
+fn publish(diagnostics: &str) -> String {
+    format!("{{\"jsonrpc\":\"2.0\",\"method\":\"textDocument/publishDiagnostics\",\"params\":{{\"uri\":\"{}\",\"diagnostics\":[{}]}}}}", URI, diagnostics)
+}
+
+#[test]
+fn initialize_advertises_the_capabilities() {
+    let [shutdown, exit] = shutdown(2);
+    let bodies = exchange(&[request(1, "initialize", "{\"capabilities\":{}}"), notification("initialized", "{}"), shutdown, exit]);
+
+    assert_eq!(bodies, vec![
+        format!(
+            "{{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":{{\"capabilities\":{{\"textDocumentSync\":{{\"openClose\":true,\"change\":1,\"save\":{{\"includeText\":true}}}},\"documentSymbolProvider\":true}},\"serverInfo\":{{\"name\":\"dashscript\",\"version\":\"{}\"}}}}}}",
+            dashscript_core::core::VERSION
+        ),
+        "{\"jsonrpc\":\"2.0\",\"id\":2,\"result\":null}".to_owned()
+    ]);
+}
+
+#[test]
+fn opened_documents_publish_their_diagnostics() {
+    let [shutdown, exit] = shutdown(1);
+    let bodies = exchange(&[did_open("let x = 1;\\nlet y = ;\\n"), did_change("let x = 1;\\n"), shutdown, exit]);
+
+    assert_eq!(bodies, vec![
+        publish("{\"range\":{\"start\":{\"line\":1,\"character\":8},\"end\":{\"line\":1,\"character\":9}},\"severity\":1,\"source\":\"dashscript\",\"message\":\"Expected a value here.\"}"),
+        publish(""),
+        "{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":null}".to_owned()
+    ]);
+}
+
+#[test]
+fn document_symbols_are_the_top_level_declarations() {
+    let [shutdown, exit] = shutdown(2);
+    let bodies = exchange(&[did_open("func add(a, b) { return a + b; }\\nconst z = add(1, 2);\\n"), document_symbol(1), shutdown, exit]);
+
+    assert_eq!(bodies[0], publish(""));
+    assert_eq!(bodies[1], concat!(
+        "{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":[",
+        "{\"name\":\"add\",\"kind\":12,\"range\":{\"start\":{\"line\":0,\"character\":0},\"end\":{\"line\":0,\"character\":32}},",
+        "\"selectionRange\":{\"start\":{\"line\":0,\"character\":5},\"end\":{\"line\":0,\"character\":8}}},",
+        "{\"name\":\"z\",\"kind\":14,\"range\":{\"start\":{\"line\":1,\"character\":0},\"end\":{\"line\":1,\"character\":20}},",
+        "\"selectionRange\":{\"start\":{\"line\":1,\"character\":6},\"end\":{\"line\":1,\"character\":7}}}]}"
+    ));
+}
+
+#[test]
+fn unknown_methods_are_errors_and_closed_input_ends_the_server() {
+    let bodies = exchange(&[request(1, "textDocument/hover", "{}")]);
+    assert_eq!(bodies, vec!["{\"jsonrpc\":\"2.0\",\"id\":1,\"error\":{\"code\":-32601,\"message\":\"Unknown method \\\"textDocument/hover\\\".\"}}".to_owned()]);
+}
@@ -0,0 +1,265 @@
+// Runs the `dash` binary on scripts written into the temporary directory of the
+// tests.
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+// A file in a directory of its own so that the tests do not share their files.
+fn file(test: &str, name: &str, source: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(test).join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, source).unwrap();
+    path
+}
+
+fn dash(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_dash"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("The cli starts.");
+
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn text(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+#[test]
+fn output_redirects_the_script_into_a_file() {
+    let script = file("output", "main.ds", "print(\"a\"); println(\"b\");");
+    let output = script.with_file_name("out.txt");
+    let result = dash(&["run", &format!("--output={}", output.display()), script.to_str().unwrap()], "");
+
+    assert!(result.status.success(), "{}", text(&result.stderr));
+    assert_eq!(text(&result.stdout), "");
+    assert_eq!(fs::read_to_string(&output).unwrap(), "ab\n");
+}
+
+#[test]
+fn a_dash_reads_the_script_from_stdin() {
+    let result = dash(&["run", "-"], "let name = \"stdin\";\nprintln(\"hello \" + name);\n");
+
+    assert!(result.status.success(), "{}", text(&result.stderr));
+    assert_eq!(text(&result.stdout), "hello stdin\n");
+    assert_eq!(text(&result.stderr), "");
+}
+
+#[test]
+fn errors_of_a_script_from_stdin_are_reported_in_stdin() {
+    let result = dash(&["run", "-"], "println(1 +);");
+
+    assert_eq!(result.status.code(), Some(1));
+    assert!(text(&result.stdout).contains("at [<stdin>:1:12] Never expected an expression."), "{}", text(&result.stdout));
+}
+
+#[test]
+fn compile_stats_are_printed_before_the_script_runs() {
+    let script = file("compile_stats", "main.ds", "let a = \"x\";\nprintln(a);\n");
+    let result = dash(&["run", "--compile-stats", script.to_str().unwrap()], "");
+
+    assert!(result.status.success(), "{}", text(&result.stderr));
+    assert!(text(&result.stderr).starts_with("Bytecode length: "), "{}", text(&result.stderr));
+    assert_eq!(text(&result.stdout), "x\n");
+}
+
+#[test]
+fn preloads_declare_globals_for_the_main_script() {
+    let lib = file("preload", "lib.ds", "func double(x) { return x * 2; }\nlet prefix = \"lib\";\n");
+    let script = file("preload", "main.ds", "println(prefix, double(21));\n");
+    let result = dash(&["run", &format!("--preload={}", lib.display()), script.to_str().unwrap()], "");
+
+    assert!(result.status.success(), "{}", text(&result.stderr));
+    assert_eq!(text(&result.stdout), "lib\n42\n");
+}
+
+// The preloads of the manifest are resolved from its directory and run before
+// the ones given with the flag.
+#[test]
+fn manifest_preloads_run_before_the_flag_ones() {
+    file("manifest_preload", "dashscript.toml", "[package]\npreload = [\"lib/first.ds\"]\n");
+    file("manifest_preload", "lib/first.ds", "let order = [\"manifest\"];\n");
+    let second = file("manifest_preload", "second.ds", "order.push(\"flag\");\n");
+    let script = file("manifest_preload", "main.ds", "order.push(\"main\");\nprintln(order[0], order[1], order[2]);\n");
+    let result = dash(&["run", &format!("--preload={}", second.display()), script.to_str().unwrap()], "");
+
+    assert!(result.status.success(), "{}", text(&result.stderr));
+    assert_eq!(text(&result.stdout), "manifest\nflag\nmain\n");
+}
+
+#[test]
+fn errors_of_a_preload_stop_the_main_script() {
+    let lib = file("preload_error", "broken.ds", "let ok = 1;\nmissing();\n");
+    let script = file("preload_error", "main.ds", "println(\"main\");\n");
+    let result = dash(&["run", &format!("--preload={}", lib.display()), script.to_str().unwrap()], "");
+
+    assert_eq!(text(&result.stdout), "");
+    assert_eq!(text(&result.stderr), format!("You cannot call a null. (line 2)\n    at runtime ({})\n", lib.display()));
+}
+
+#[test]
+fn traces_label_the_frames_of_the_preloads() {
+    let lib = file("preload_trace", "lib.ds", "func boom() { missing(); }\n");
+    let script = file("preload_trace", "main.ds", "boom();\n");
+    let result = dash(&["run", &format!("--preload={}", lib.display()), script.to_str().unwrap()], "");
+
+    assert_eq!(text(&result.stderr), format!("You cannot call a null. (line 1)\n    at boom ({})\n    at runtime\n", lib.display()));
+}
+
+#[test]
+fn uncaught_errors_exit_with_a_failure() {
+    let script = file("uncaught", "main.ds", "println(\"before\");\nnull();\nprintln(\"after\");\n");
+    let result = dash(&["run", script.to_str().unwrap()], "");
+
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(text(&result.stdout), "before\n");
+    assert!(text(&result.stderr).contains("You cannot call a null."), "{}", text(&result.stderr));
+}
+
+// Starts a script which prints "ready" once it waits for the signals and sends it
+// `count` interrupts.
+#[cfg(unix)]
+fn interrupt(test: &str, source: &str, flags: &[&str], count: usize) -> Output {
+    use std::io::{BufRead, BufReader, Read};
+    use std::thread;
+    use std::time::Duration;
+
+    let script = file(test, "main.ds", source);
+    let mut child = Command::new(env!("CARGO_BIN_EXE_dash"))
+        .arg("run")
+        .args(flags)
+        .arg(&script)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("The cli starts.");
+
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    assert_eq!(line, "ready\n");
+
+    for _ in 0..count {
+        let status = Command::new("kill").args(["-INT", &child.id().to_string()]).status().unwrap();
+        assert!(status.success());
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    let mut output = child.wait_with_output().unwrap();
+    stdout.read_to_end(&mut output.stdout).unwrap();
+    output
+}
+
+#[cfg(unix)]
+#[test]
+fn interrupts_run_the_callback_and_exit_with_130() {
+    let source = "window.signal.onInterrupt(func () { println(\"interrupted\"); });\nprintln(\"ready\");\nwindow.stdout.flush();\nwhile true {}\n";
+    let result = interrupt("interrupt", source, &["--use-signal"], 1);
+
+    assert_eq!(result.status.code(), Some(130), "{}", text(&result.stderr));
+    assert_eq!(text(&result.stdout), "interrupted\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn a_second_interrupt_cuts_the_callback_off() {
+    let source = "window.signal.onInterrupt(func () { println(\"stuck\"); window.stdout.flush(); while true {} });\nprintln(\"ready\");\nwindow.stdout.flush();\nwhile true {}\n";
+    let result = interrupt("interrupt-twice", source, &["--use-signal"], 2);
+
+    assert_eq!(result.status.code(), Some(130), "{}", text(&result.stderr));
+    assert_eq!(text(&result.stdout), "stuck\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn interrupts_without_a_callback_kill_the_script() {
+    use std::os::unix::process::ExitStatusExt;
+
+    let result = interrupt("interrupt-default", "println(\"ready\");\nwindow.stdout.flush();\nwhile true {}\n", &[], 1);
+    assert_eq!(result.status.signal(), Some(2));
+}
+
+#[test]
+fn on_interrupt_needs_the_signal_permission() {
+    let script = file("interrupt-denied", "main.ds", "println(window.signal.onInterrupt);\n");
+    let result = dash(&["run", script.to_str().unwrap()], "");
+
+    assert_eq!(text(&result.stdout), "null\n");
+}
+
+// The stdout of the cli is a pipe here, so the output is buffered and only the
+// flushes make it reach the test before the script ends.
+#[test]
+fn questions_are_shown_before_the_answers_are_read() {
+    use std::io::Read;
+
+    let script = file("readline", "main.ds", "print(\"name? \");\nlet name = readline();\nprintln(\"hi \" + name.trim());\n");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_dash"))
+        .args(["run", script.to_str().unwrap()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("The cli starts.");
+
+    let mut stdout = child.stdout.take().unwrap();
+    let mut question = [0; 6];
+    stdout.read_exact(&mut question).unwrap();
+    assert_eq!(&question, b"name? ");
+
+    child.stdin.take().unwrap().write_all(b"ada\n").unwrap();
+    let mut rest = String::new();
+    stdout.read_to_string(&mut rest).unwrap();
+    assert!(child.wait().unwrap().success());
+    assert_eq!(rest, "hi ada\n");
+}
+
+#[test]
+fn flush_shows_what_has_been_printed_so_far() {
+    use std::io::Read;
+
+    let script = file("flush", "main.ds", "print(\"50%\");\nwindow.stdout.flush();\nwindow.sleep(60000);\n");
+    let mut child = Command::new(env!("CARGO_BIN_EXE_dash"))
+        .args(["run", script.to_str().unwrap()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("The cli starts.");
+
+    let mut progress = [0; 3];
+    child.stdout.take().unwrap().read_exact(&mut progress).unwrap();
+    child.kill().unwrap();
+    child.wait().unwrap();
+    assert_eq!(&progress, b"50%");
+}
+
+#[test]
+fn logs_keep_their_place_between_the_printed_lines() {
+    let script = file("log-order", "main.ds", "println(\"first\");\nwindow.log.info(\"logged\");\nprintln(\"last\");\n");
+    let shared = script.with_file_name("shared.txt");
+    let out = fs::File::create(&shared).unwrap();
+    let status = Command::new(env!("CARGO_BIN_EXE_dash"))
+        .args(["run", "--seed=1", script.to_str().unwrap()])
+        .stdout(out.try_clone().unwrap())
+        .stderr(out)
+        .status()
+        .unwrap();
+
+    assert!(status.success());
+    assert_eq!(fs::read_to_string(&shared).unwrap(), "first\n2000-01-01T00:00:00.000Z INFO  logged\nlast\n");
+}
+
+#[test]
+fn invalid_heap_limits_are_rejected() {
+    let script = file("max-heap", "main.ds", "println(1);\n");
+    let result = dash(&["run", "--max-heap", "12xb", script.to_str().unwrap()], "");
+
+    assert_eq!(result.status.code(), Some(1));
+    assert_eq!(text(&result.stdout), "CliError: Invalid heap limit \"12xb\". Expected a size like 512kb, 64mb or 1gb.\n");
+}
@@ -1,8 +1,10 @@
 use crate::TinyString;
 
+// The pool starts with the names which the compiler refers to by their ids. The
+// name of the anonymous functions is compared with the id of the function names
+// and the local holding the error of an expect block is named "error".
 pub const ANONYMOUS_CONSTANT: u32 = 0;
 pub const ERROR_CONSTANT: u32 = 1;
-pub const PROTOTYPE_CONSTANT: u32 = 3;
 
 #[derive(Debug, Clone)]
 pub struct ConstantPool {
@@ -16,9 +18,7 @@ impl Default for ConstantPool {
         Self {
             strings: vec![
                 TinyString::new(b"anonymous"), 
-                TinyString::new(b"error"), 
-                TinyString::new(b"init"),
-                TinyString::new(b"prototype")
+                TinyString::new(b"error")
             ],
             ints: Vec::new(),
             floats: Vec::new()
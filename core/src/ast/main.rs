@@ -1,7 +1,8 @@
 use std::fmt::{self, Display, Formatter};
+use std::rc::Rc;
 use crate::{
     Token, TokenKind, Position, Lexer, Stmt, ConstantPool, Expr, Keyword,
-    LexerErrorKind, AssignOp, BinOp
+    LexerErrorKind, AssignOp, BinOp, TypeAnnotation, SourceMap
 };
 
 macro_rules! unexpected_token {
@@ -21,7 +22,9 @@ pub struct AST {
     current: Token,
     errors: Vec<ASTError>,
     imports: Vec<u32>,
-    had_error: bool
+    had_error: bool,
+    panicking: bool,
+    max_errors: usize
 }
 
 #[derive(Debug, Clone, Default)]
@@ -29,20 +32,37 @@ pub struct ASTBuild {
     pub constant_pool: ConstantPool,
     pub statements: Vec<Stmt>,
     pub imports: Vec<u32>,
-    pub body: String
+    pub body: String,
+    pub source_map: Rc<SourceMap>
 }
 
 impl AST {
 
+    // The number of errors collected before the parser gives up.
+    pub const DEFAULT_MAX_ERRORS: usize = 20;
+
     pub fn new(filename: &String, body: &String) -> Self {
         AST { 
             lexer: Lexer::new(filename, body),
+            max_errors: Self::DEFAULT_MAX_ERRORS,
             ..Default::default()
         }
     }
 
     pub fn compile(filename: &String, body: &String) -> Result<ASTBuild, Vec<ASTError>> {
+        Self::compile_with_max_errors(filename, body, Self::DEFAULT_MAX_ERRORS)
+    }
+
+    pub fn compile_with_max_errors(filename: &String, body: &String, max_errors: usize) -> Result<ASTBuild, Vec<ASTError>> {
+        Self::compile_with_constant_pool(filename, body, max_errors, ConstantPool::default())
+    }
+
+    // Continues the constant pool of a script compiled earlier so that both of them
+    // can be executed by the same vm.
+    pub fn compile_with_constant_pool(filename: &String, body: &String, max_errors: usize, constant_pool: ConstantPool) -> Result<ASTBuild, Vec<ASTError>> {
         let mut ast = Self::new(filename, body);
+        ast.max_errors = max_errors.max(1);
+        ast.constant_pool = constant_pool;
         ast.parse();
 
         if ast.had_error {
@@ -57,12 +77,8 @@ impl AST {
         self.current = token.clone();
 
         loop {
-            // There might be a chance which can cause many errors
-            // due to the original first one. This is used to prevent
-            // the overflow of duplicate chained errors.
-            if self.had_error {
-                return;
-            }
+            let statement_start = token.position.start as usize;
+            let statement_depth = self.lexer.brace_depth;
 
             match token.kind {
                 TokenKind::Keyword(keyword) => {
@@ -74,15 +90,25 @@ impl AST {
                         Keyword::Func => self.keyword_function(index, false),
                         Keyword::Try => self.keyword_try(index),
                         Keyword::Return => self.keyword_return(index),
-                        Keyword::While => self.keyword_while(index),
-                        Keyword::For => self.keyword_for(index),
-                        Keyword::Break => Stmt { expr: Expr::Break, index },
+                        Keyword::Defer => self.keyword_defer(index),
+                        Keyword::Break => self.keyword_break(index),
                         Keyword::Continue => Stmt { expr: Expr::Continue, index },
-                        Keyword::If => {
-                            let statement = self.keyword_if(index);
-                            self.statements.push(statement);
-                            token = self.current.clone();
-                            continue;
+                        Keyword::If | Keyword::While | Keyword::For => {
+                            let statement = match keyword {
+                                Keyword::If => self.keyword_if(index),
+                                Keyword::While => self.keyword_while(index),
+                                _ => self.keyword_for(index)
+                            };
+
+                            // The token after the statement is already read to look
+                            // for its else branch.
+                            if !self.panicking {
+                                self.statements.push(statement);
+                                token = self.current.clone();
+                                continue;
+                            }
+
+                            statement
                         },
                         _ => Stmt::default()
                     };
@@ -112,6 +138,18 @@ impl AST {
                 }
             }
 
+            if self.panicking {
+                if self.errors.len() >= self.max_errors {
+                    return;
+                }
+
+                // A stray "}" left by the failed statement is skipped at the top level.
+                if self.synchronize(statement_start, statement_depth) && self.current.kind != TokenKind::CurlyBraceClose {
+                    token = self.current.clone();
+                    continue;
+                }
+            }
+
             match self.lexer.next() {
                 Some(next_token) => {
                     token = next_token;
@@ -120,21 +158,72 @@ impl AST {
                 None => break
             }
         }
+    }
 
-        if self.had_error {
-            return;
+    // Skips the rest of a statement which failed to parse until the next statement
+    // boundary which is a ";" in the block of the statement, the "}" closing the
+    // block or a keyword starting a statement on a later line. Returns true if the
+    // current token starts the next statement and false if the next statement starts
+    // after the current token.
+    fn synchronize(&mut self, statement_start: usize, statement_depth: isize) -> bool {
+        let (statement_line, _) = self.lexer.source_map.lookup(statement_start);
+        self.panicking = false;
+
+        loop {
+            let depth = self.lexer.brace_depth - statement_depth;
+            match self.current.kind {
+                TokenKind::Semicolon if depth == 0 => return false,
+                TokenKind::CurlyBraceClose if depth < 0 => return true,
+                TokenKind::Keyword(
+                    Keyword::Let | Keyword::Const | Keyword::Func | Keyword::Return | Keyword::If | Keyword::While |
+                    Keyword::For | Keyword::Break | Keyword::Continue | Keyword::Import | Keyword::Try | Keyword::Defer
+                ) if depth == 0 => {
+                    let (line, _) = self.lexer.source_map.lookup(self.current.position.start as usize);
+                    if line > statement_line {
+                        return true;
+                    }
+                },
+                _ => ()
+            }
+
+            match self.lexer.next() {
+                Some(token) => self.current = token,
+                None => return false
+            }
         }
     }
     
     pub fn error(&mut self, position: Position, kind: ASTErrorKind) {
-        let (line, col) = get_line_col(&self.lexer.body, position.start as usize);
         self.had_error = true;
+
+        // Errors which follow the first error of a statement are mostly caused by
+        // it so they are ignored until the parser recovers at the next statement.
+        if self.panicking || self.errors.len() >= self.max_errors {
+            return;
+        }
+
+        // The errors at the end of the script are caused by the brackets which are
+        // still open, so they point at the innermost of them instead. The next one
+        // is reported by the error of the statement around it.
+        let at_eof = self.lexer.index >= self.lexer.chars.len();
+        let (position, kind) = match self.lexer.delimiters.last() {
+            Some(&(character, start)) if at_eof && !matches!(kind, ASTErrorKind::LexerError(_)) => {
+                self.lexer.delimiters.pop();
+                let (line, _) = self.lexer.source_map.lookup(start as usize);
+                (Position::from((start, start + 1)), ASTErrorKind::UnclosedDelimiter { character, line })
+            },
+            _ => (position, kind)
+        };
+
+        let source_map = self.lexer.source_map.clone();
+        let (line, col) = source_map.lookup(position.start as usize);
+        self.panicking = true;
         self.errors.push(ASTError {
             line, 
             col, 
             kind,
-            filename: self.lexer.filename.clone(),
-            body: self.lexer.body[position.start as usize..position.end as usize].to_string()
+            body: source_map.snippet(position.start as usize, position.end as usize),
+            source_map
         });
     }
 
@@ -156,6 +245,26 @@ impl AST {
         Stmt { expr, index }
     }
 
+    // The deferred expression is wrapped into a function by the compiler, see
+    // `BytecodeCompiler::load_deferred`.
+    pub fn keyword_defer(&mut self, index: usize) -> Stmt {
+        // A word can start an assignment just like at the start of a statement.
+        let expr = match self.next_token() {
+            Token { kind: TokenKind::Word(name), .. } => {
+                let word = Expr::Word(self.constant_pool.add_string(name));
+                self.expression_with_prefix(word, true)
+            },
+            next => self.expression_with_token(next, ASTErrorKind::UnexpectedExpr)
+        };
+
+        if self.current.kind != TokenKind::Semicolon {
+            self.error(self.current.position, ASTErrorKind::ExpectedSemicolon);
+            return Stmt::default();
+        }
+
+        Stmt { expr: Expr::Defer(Box::new(expr)), index }
+    }
+
     pub fn keyword_import(&mut self, index: usize) -> Stmt {
         let name_token = self.next_token();
         let name = self.constant_pool.add_string(match name_token.kind {
@@ -252,7 +361,7 @@ impl AST {
         let mut branches = Vec::new();
         let mut else_branch = None;
 
-        let condition = self.expression(ASTErrorKind::ExpectedValue);
+        let condition = self.condition();
         let inner = match self.current.kind {
             TokenKind::CurlyBraceOpen => self.expression_block(),
             _ => {
@@ -261,37 +370,45 @@ impl AST {
             }
         };
 
-        branches.push((condition, inner));
+        branches.push((condition, inner, index));
 
         loop {
             match self.lexer.next() {
                 Some(token) => {
                     self.current = token;
 
-                    match self.current.kind {
-                        TokenKind::Keyword(Keyword::Elif) => {
-                            let condition = self.expression(ASTErrorKind::ExpectedValue);
-                            let inner = match self.current.kind {
-                                TokenKind::CurlyBraceOpen => self.expression_block(),
-                                _ => {
-                                    unexpected_token!(self, ASTErrorKind::ExpectedBlock, self.current);
-                                    return Stmt { expr: Expr::If { branches, else_branch }, index };
-                                }
-                            };
-
-                            branches.push((condition, inner));
-                        },
-                        TokenKind::Keyword(Keyword::Else) => {
-                            else_branch = Some(match self.next_token().kind {
-                                TokenKind::CurlyBraceOpen => self.expression_block(),
-                                _ => {
-                                    unexpected_token!(self, ASTErrorKind::ExpectedBlock, self.current);
-                                    return Stmt { expr: Expr::If { branches, else_branch }, index };
-                                }
-                            });
+                    let is_branch = match self.current.kind {
+                        TokenKind::Keyword(Keyword::Elif) => true,
+                        // `else if` is the same as `elif`.
+                        TokenKind::Keyword(Keyword::Else) => match self.next_token().kind {
+                            TokenKind::Keyword(Keyword::If) => true,
+                            TokenKind::CurlyBraceOpen => {
+                                else_branch = Some(self.expression_block());
+                                continue;
+                            },
+                            _ => {
+                                unexpected_token!(self, ASTErrorKind::ExpectedBlock, self.current);
+                                return Stmt { expr: Expr::If { branches, else_branch }, index };
+                            }
                         },
-                        _ => return Stmt { expr: Expr::If { branches, else_branch }, index }
+                        _ => false
+                    };
+
+                    if !is_branch {
+                        return Stmt { expr: Expr::If { branches, else_branch }, index };
                     }
+
+                    let branch_index = self.current.position.start as usize;
+                    let condition = self.condition();
+                    let inner = match self.current.kind {
+                        TokenKind::CurlyBraceOpen => self.expression_block(),
+                        _ => {
+                            unexpected_token!(self, ASTErrorKind::ExpectedBlock, self.current);
+                            return Stmt { expr: Expr::If { branches, else_branch }, index };
+                        }
+                    };
+
+                    branches.push((condition, inner, branch_index));
                 },
                 None => break self.current = Token { kind: TokenKind::Semicolon, ..Default::default() }
             }
@@ -317,12 +434,14 @@ impl AST {
             }
         };
 
-        match self.next_token().kind {
+        let return_type = self.expression_return_type();
+        match self.current.kind {
             TokenKind::CurlyBraceOpen => {
                 Stmt {
                     expr: Expr::Function {
                         name,
                         parameters,
+                        return_type,
                         inner: self.expression_block(),
                         is_async
                     },
@@ -337,17 +456,63 @@ impl AST {
     }
 
     pub fn keyword_while(&mut self, index: usize) -> Stmt {
-        let condition = self.expression(ASTErrorKind::ExpectedValue);
+        let condition = self.condition();
         match self.current.kind {
             TokenKind::CurlyBraceOpen => (),
             _ => {
                 unexpected_token!(self, ASTErrorKind::ExpectedBlock, self.current);
-                return Stmt { expr: Expr::While(Box::new(condition), Vec::new()), index };
+                return Stmt { expr: Expr::While(Box::new(condition), Vec::new(), None), index };
             }
         };
 
         let inner = self.expression_block();
-        Stmt { expr: Expr::While(Box::new(condition), inner), index }
+        let else_branch = self.loop_else_branch();
+        Stmt { expr: Expr::While(Box::new(condition), inner, else_branch), index }
+    }
+
+    // Reads the token after the block of a loop which is left as the current token
+    // unless it starts the else branch of the loop.
+    fn loop_else_branch(&mut self) -> Option<Vec<Stmt>> {
+        let end_of_file = Token { kind: TokenKind::Semicolon, ..Default::default() };
+        self.current = match self.lexer.next() {
+            Some(token) => token,
+            None => {
+                self.current = end_of_file;
+                return None;
+            }
+        };
+
+        if !matches!(self.current.kind, TokenKind::Keyword(Keyword::Else)) {
+            return None;
+        }
+
+        let else_branch = match self.next_token().kind {
+            TokenKind::CurlyBraceOpen => self.expression_block(),
+            _ => {
+                unexpected_token!(self, ASTErrorKind::ExpectedBlock, self.current);
+                return None;
+            }
+        };
+
+        self.current = self.lexer.next().unwrap_or(end_of_file);
+        Some(else_branch)
+    }
+
+    pub fn keyword_break(&mut self, index: usize) -> Stmt {
+        let next = self.next_token();
+        let value = match next.kind {
+            TokenKind::Semicolon => None,
+            _ => {
+                let value = self.expression_with_token(next, ASTErrorKind::UnexpectedExpr);
+                if !matches!(self.current.kind, TokenKind::Semicolon) {
+                    self.error(self.current.position, ASTErrorKind::ExpectedSemicolon);
+                }
+
+                Some(Box::new(value))
+            }
+        };
+
+        Stmt { expr: Expr::Break(value), index }
     }
 
     pub fn keyword_for(&mut self, index: usize) -> Stmt {
@@ -363,8 +528,19 @@ impl AST {
             _ => unexpected!(ExpectedIdent)
         };
 
-        match self.next_token().kind {
-            TokenKind::Keyword(Keyword::In) => (),
+        let value_name = match self.next_token().kind {
+            TokenKind::Keyword(Keyword::In) => None,
+            TokenKind::Comma => {
+                let value_name = match self.next_token().kind {
+                    TokenKind::Word(name) => self.constant_pool.add_string(name),
+                    _ => unexpected!(ExpectedIdent)
+                };
+
+                match self.next_token().kind {
+                    TokenKind::Keyword(Keyword::In) => Some(value_name),
+                    _ => unexpected!(ExpectedIn)
+                }
+            },
             _ => unexpected!(ExpectedIn)
         };
 
@@ -375,48 +551,89 @@ impl AST {
         };
 
         let inner = self.expression_block();
-        Stmt { expr: Expr::For { name, in_: Box::new(in_), inner }, index }
+        let else_branch = self.loop_else_branch();
+        Stmt { expr: Expr::For { name, value_name, in_: Box::new(in_), inner, else_branch }, index }
+    }
+
+    // The conditions of the `if`, `elif` and `while` statements. An assignment is
+    // never a condition, so a `=` meant as `==` is reported as such instead of as
+    // the block which is missing after it.
+    fn condition(&mut self) -> Expr {
+        let condition = self.expression(ASTErrorKind::ExpectedValue);
+        self.reject_assignment();
+        condition
+    }
+
+    // Reports an assignment operator after an expression which cannot be assigned
+    // in its place and skips the assigned value so that the parsing continues
+    // after it.
+    fn reject_assignment(&mut self) {
+        if matches!(self.current.kind, TokenKind::Assign | TokenKind::AssignAdd | TokenKind::AssignSub | TokenKind::AssignRem | TokenKind::AssignMerge | TokenKind::AssignNullish) {
+            self.error(self.current.position, ASTErrorKind::AssignmentInCondition);
+            self.expression(ASTErrorKind::ExpectedValue);
+        }
     }
 
     pub fn expression(&mut self, kind: ASTErrorKind) -> Expr {
-        if let Some(token) = self.lexer.next() {
-            self.current = token.clone();
-
-            let prefix = match token.kind {
-                TokenKind::String(string) => Expr::String(self.constant_pool.add_string(string)),
-                TokenKind::Word(string) => Expr::Word(self.constant_pool.add_string(string)),
-                TokenKind::Int(int) => Expr::Int(self.constant_pool.add_int(int)),
-                TokenKind::Float(float) => Expr::Float(self.constant_pool.add_float(float)),
-                TokenKind::True => Expr::Boolean(true),
-                TokenKind::False => Expr::Boolean(false),
-                TokenKind::Null => Expr::Null,
-                TokenKind::Not => return Expr::Not(Box::new(self.expression(ASTErrorKind::UnexpectedNotOp))),
-                TokenKind::SqBraceOpen => Expr::Array(self.expression_array()),
-                TokenKind::CurlyBraceOpen => Expr::Dict(self.expression_dict()),
-                TokenKind::ParenOpen => {
-                    let expr = self.expression(ASTErrorKind::UnclosedParen);
-                    if !matches!(self.current.kind, TokenKind::ParenClose) {
-                        unexpected_token!(self, ASTErrorKind::UnclosedParen, self.current);
-                        return Expr::Null;
-                    }
+        match self.lexer.next() {
+            Some(token) => {
+                self.current = token.clone();
+                self.expression_with_token(token, kind)
+            },
+            None => {
+                self.error(self.current.position, kind);
+                Expr::Null
+            }
+        }
+    }
 
-                    expr
-                },
-                TokenKind::Keyword(Keyword::Func) => self.expression_function(false),
-                _ => {
-                    self.error(token.position, kind);
-                    Expr::Null
-                }
-            };
+    pub fn expression_with_token(&mut self, token: Token, kind: ASTErrorKind) -> Expr {
+        // The token after a loop or an `if` is already read so it cannot be followed
+        // by an operator.
+        let is_loop = matches!(token.kind, TokenKind::Keyword(Keyword::If | Keyword::While | Keyword::For));
+        let operand = self.operand_with_token(token, kind);
+        match is_loop {
+            true => operand,
+            false => self.ternary(operand)
+        }
+    }
+
+    // The assignments can only follow the operands which start a statement.
+    pub fn expression_with_prefix(&mut self, expr: Expr, can_assign: bool) -> Expr {
+        let expr = self.postfix(expr);
+        let op = match self.current.kind {
+            TokenKind::Assign if can_assign => AssignOp::Assign,
+            TokenKind::AssignAdd if can_assign => AssignOp::Add,
+            TokenKind::AssignSub if can_assign => AssignOp::Sub,
+            TokenKind::AssignRem if can_assign => AssignOp::Rem,
+            TokenKind::AssignMerge if can_assign => AssignOp::Merge,
+            TokenKind::AssignNullish if can_assign => AssignOp::Nullish,
+            _ => return self.ternary(expr)
+        };
 
-            return self.expression_with_prefix(prefix, false);
+        Expr::Assign {
+            target: Box::new(expr),
+            op,
+            value: Box::new(self.expression(ASTErrorKind::ExpectedValue))
         }
+    }
 
-        self.error(self.current.position, kind);
-        Expr::Null
+    fn operand(&mut self, kind: ASTErrorKind) -> Expr {
+        match self.lexer.next() {
+            Some(token) => {
+                self.current = token.clone();
+                self.operand_with_token(token, kind)
+            },
+            None => {
+                self.error(self.current.position, kind);
+                Expr::Null
+            }
+        }
     }
 
-    pub fn expression_with_token(&mut self, token: Token, kind: ASTErrorKind) -> Expr {
+    // A value with its attributes, indexes and calls, or a negated operand. The
+    // token which follows it is left as the current token.
+    fn operand_with_token(&mut self, token: Token, kind: ASTErrorKind) -> Expr {
         let expr = match token.kind {
             TokenKind::String(string) => Expr::String(self.constant_pool.add_string(string)),
             TokenKind::Word(string) => Expr::Word(self.constant_pool.add_string(string)),
@@ -425,11 +642,15 @@ impl AST {
             TokenKind::True => Expr::Boolean(true),
             TokenKind::False => Expr::Boolean(false),
             TokenKind::Null => Expr::Null,
-            TokenKind::Not => return Expr::Not(Box::new(self.expression(ASTErrorKind::UnexpectedNotOp))),
+            TokenKind::Not => return Expr::Not(Box::new(self.operand(ASTErrorKind::UnexpectedNotOp))),
+            TokenKind::Sub => return self.negation(token.position.start),
             TokenKind::SqBraceOpen => Expr::Array(self.expression_array()),
             TokenKind::CurlyBraceOpen => Expr::Dict(self.expression_dict()),
+            // The parentheses do not make an assignment a value, so `if (x = 5)` is
+            // rejected like `if x = 5`.
             TokenKind::ParenOpen => {
                 let expr = self.expression(ASTErrorKind::UnclosedParen);
+                self.reject_assignment();
                 if !matches!(self.current.kind, TokenKind::ParenClose) {
                     unexpected_token!(self, ASTErrorKind::UnclosedParen, self.current);
                     return Expr::Null;
@@ -438,34 +659,49 @@ impl AST {
                 expr
             },
             TokenKind::Keyword(Keyword::Func) => self.expression_function(false),
+            TokenKind::Keyword(Keyword::While) => return self.keyword_while(token.position.start as usize).expr,
+            TokenKind::Keyword(Keyword::For) => return self.keyword_for(token.position.start as usize).expr,
+            // The `if` statements are read by `keyword_if` too, but only the ones in
+            // the place of a value give a value.
+            TokenKind::Keyword(Keyword::If) => return match self.keyword_if(token.position.start as usize).expr {
+                Expr::If { branches, else_branch } => Expr::IfValue { branches, else_branch },
+                expr => expr
+            },
+            // The unexpected token is left as the current one so that the recovery
+            // sees the ";" ending the statement when it is the unexpected token.
             _ => {
                 unexpected_token!(self, kind, token);
-                Expr::Null
+                return Expr::Null;
             }
         };
 
-        self.expression_with_prefix(expr, false)
+        self.postfix(expr)
     }
 
-    pub fn expression_with_prefix(&mut self, mut expr: Expr, can_assign: bool) -> Expr {
+    // The power binds tighter than the minus, so `-x ** 2` is `-(x ** 2)`. The
+    // negative literals are folded into constants.
+    fn negation(&mut self, index: u32) -> Expr {
+        let operand = self.operand(ASTErrorKind::ExpectedValue);
+        let operand = self.binary(operand, POWER_PRECEDENCE);
+
+        match operand {
+            Expr::Int(id) => match self.constant_pool.ints[id as usize].checked_neg() {
+                Some(int) => Expr::Int(self.constant_pool.add_int(int)),
+                None => Expr::Positioned(Box::new(Expr::Negate(Box::new(operand))), index)
+            },
+            Expr::Float(id) => Expr::Float(self.constant_pool.add_float(-self.constant_pool.floats[id as usize])),
+            operand => Expr::Positioned(Box::new(Expr::Negate(Box::new(operand))), index)
+        }
+    }
+
+    fn postfix(&mut self, mut expr: Expr) -> Expr {
         self.current = match self.lexer.next() {
             Some(token) => token,
             None => return expr
         };
 
-        macro_rules! bin_op {
-            ($op:ident) => {{
-                expr = Expr::BinaryOperation {
-                    lhs: Box::new(expr),
-                    rhs: Box::new(self.expression(ASTErrorKind::UnexpectedExpr)),
-                    op: BinOp::$op
-                };
-
-                continue;
-            }};
-        }
-
         loop {
+            let index = self.current.position.start;
             match self.current.kind {
                 TokenKind::Dot => {
                     let token = self.next_token();
@@ -478,66 +714,37 @@ impl AST {
                         }
                     };
 
-                    expr = Expr::Attribute(Box::new(expr), Box::new(attr));
+                    expr = Expr::Positioned(Box::new(Expr::Attribute(Box::new(expr), Box::new(attr))), index);
                 },
+                // A colon right inside the brackets makes it a slice. The colons of the
+                // ternaries and dicts are consumed by the expressions of the bounds.
                 TokenKind::SqBraceOpen => {
-                    expr = Expr::Attribute(Box::new(expr), Box::new(self.expression(ASTErrorKind::ImproperPropertyIndexing)));
+                    let start = self.slice_bound();
+                    expr = match (self.current.kind.clone(), start) {
+                        (TokenKind::Colon, start) => {
+                            let end = self.slice_bound();
+                            let step = match self.current.kind {
+                                TokenKind::Colon => self.slice_bound(),
+                                _ => None
+                            };
+
+                            Expr::Positioned(Box::new(Expr::Slice { target: Box::new(expr), start, end, step }), index)
+                        },
+                        (_, Some(attr)) => Expr::Positioned(Box::new(Expr::Attribute(Box::new(expr), attr)), index),
+                        (_, None) => {
+                            unexpected_token!(self, ASTErrorKind::ImproperPropertyIndexing, self.current);
+                            return expr;
+                        }
+                    };
+
                     match self.current.kind {
                         TokenKind::SqBraceClose => (),
                         _ => unexpected_token!(self, ASTErrorKind::ImproperPropertyIndexing, self.current)
                     }
                 },
-                TokenKind::ParenOpen => expr = Expr::Call(Box::new(expr), self.expression_call()),
-                TokenKind::Add => bin_op!(Add),
-                TokenKind::Sub => bin_op!(Subtract),
-                TokenKind::Mul => bin_op!(Multiply),
-                TokenKind::Div => bin_op!(Divide),
-                TokenKind::Pow => bin_op!(Power),
-                TokenKind::Rem => bin_op!(Rem),
-                TokenKind::And => bin_op!(And),
-                TokenKind::BitAnd => bin_op!(BitAnd),
-                TokenKind::BitOr => bin_op!(BitOr),
-                TokenKind::BitXor => bin_op!(BitXor),
-                TokenKind::Shr => bin_op!(Shr),
-                TokenKind::Shl => bin_op!(Shl),
-                TokenKind::Or => bin_op!(Or),
-                TokenKind::Equal => bin_op!(Equal),
-                TokenKind::NotEqual => bin_op!(NotEqual),
-                TokenKind::GreaterThan => bin_op!(GreaterThan),
-                TokenKind::GreaterThanOrEqual => bin_op!(GreaterThanOrEqual),
-                TokenKind::LessThan => bin_op!(LessThan),
-                TokenKind::LessThanOrEqual => bin_op!(LessThanOrEqual),
-                TokenKind::Question => {
-                    let truthy = self.expression(ASTErrorKind::ImproperTernaryOperator);
-                    match self.current.kind {
-                        TokenKind::Colon => (),
-                        _ => unexpected_token!(self, ASTErrorKind::ImproperTernaryOperator, self.current)
-                    }
-
-                    let falsy = self.expression(ASTErrorKind::ImproperTernaryOperator);
-                    expr = Expr::Ternary(Box::new(expr), Box::new(truthy), Box::new(falsy));
-                    continue;
-                },
-                TokenKind::Assign if can_assign => {
-                    return Expr::Assign {
-                        target: Box::new(expr),
-                        op: AssignOp::Assign,
-                        value: Box::new(self.expression(ASTErrorKind::ExpectedValue))
-                    }
-                },
-                TokenKind::AssignAdd if can_assign => {
-                    return Expr::Assign {
-                        target: Box::new(expr),
-                        op: AssignOp::Add,
-                        value: Box::new(self.expression(ASTErrorKind::ExpectedValue))
-                    }
-                },
-                TokenKind::AssignSub if can_assign => {
-                    return Expr::Assign {
-                        target: Box::new(expr),
-                        op: AssignOp::Sub,
-                        value: Box::new(self.expression(ASTErrorKind::ExpectedValue))
-                    }
+                TokenKind::ParenOpen => {
+                    let params = self.expression_call();
+                    expr = Expr::Positioned(Box::new(Expr::Call(Box::new(expr), params)), index);
                 },
                 _ => return expr
             }
@@ -549,6 +756,47 @@ impl AST {
         }
     }
 
+    // The branches of a ternary are whole expressions so the nested ternaries
+    // group to the right.
+    fn ternary(&mut self, condition: Expr) -> Expr {
+        let condition = self.binary(condition, 0);
+        if self.current.kind != TokenKind::Question {
+            return condition;
+        }
+
+        let truthy = self.expression(ASTErrorKind::ImproperTernaryOperator);
+        match self.current.kind {
+            TokenKind::Colon => (),
+            _ => unexpected_token!(self, ASTErrorKind::ImproperTernaryOperator, self.current)
+        }
+
+        let falsy = self.expression(ASTErrorKind::ImproperTernaryOperator);
+        Expr::Ternary(Box::new(condition), Box::new(truthy), Box::new(falsy))
+    }
+
+    // Precedence climbing over the table of `binary_operator`. The right operand
+    // takes the operators which bind tighter, and the ones of the same level too
+    // when the operator groups to the right.
+    fn binary(&mut self, mut lhs: Expr, min_precedence: u8) -> Expr {
+        while let Some((op, precedence)) = binary_operator(&self.current.kind) {
+            if precedence < min_precedence {
+                break;
+            }
+
+            let index = self.current.position.start;
+            let next = match op {
+                BinOp::Power => precedence,
+                _ => precedence + 1
+            };
+
+            let rhs = self.operand(ASTErrorKind::UnexpectedExpr);
+            let rhs = self.binary(rhs, next);
+            lhs = Expr::Positioned(Box::new(Expr::BinaryOperation { lhs: Box::new(lhs), rhs: Box::new(rhs), op }), index);
+        }
+
+        lhs
+    }
+
     pub fn expression_function(&mut self, is_async: bool) -> Expr {
         let parameters = match self.next_token().kind {
             TokenKind::ParenOpen => self.expression_function_params(),
@@ -558,11 +806,13 @@ impl AST {
             }
         };
 
-        match self.next_token().kind {
+        let return_type = self.expression_return_type();
+        match self.current.kind {
             TokenKind::CurlyBraceOpen => {
                 Expr::Function {
                     name: 0,
                     parameters,
+                    return_type,
                     inner: self.expression_block(),
                     is_async
                 }
@@ -600,6 +850,17 @@ impl AST {
         params
     }
 
+    // An omitted bound of a slice leaves the colon or the closing bracket as the
+    // current token just like the expression of a bound does.
+    fn slice_bound(&mut self) -> Option<Box<Expr>> {
+        let token = self.lexer.next()?;
+        self.current = token.clone();
+        match token.kind {
+            TokenKind::Colon | TokenKind::SqBraceClose => None,
+            _ => Some(Box::new(self.expression_with_token(token, ASTErrorKind::ImproperPropertyIndexing)))
+        }
+    }
+
     pub fn expression_array(&mut self) -> Vec<Expr> {
         let mut items = Vec::new();
 
@@ -684,12 +945,8 @@ impl AST {
         let mut token = self.lexer.next().unwrap();
 
         loop {
-            // There might be a chance which can cause many errors
-            // due to the original first one. This is used to prevent
-            // the overflow of duplicate chained errors.
-            if self.had_error {
-                return statements;
-            }
+            let statement_start = token.position.start as usize;
+            let statement_depth = self.lexer.brace_depth;
 
             match token.kind {
                 TokenKind::Keyword(keyword) => {
@@ -701,15 +958,25 @@ impl AST {
                         Keyword::Func => self.keyword_function(index, false),
                         Keyword::Try => self.keyword_try(index),
                         Keyword::Return => self.keyword_return(index),
-                        Keyword::While => self.keyword_while(index),
-                        Keyword::For => self.keyword_for(index),
-                        Keyword::Break => Stmt { expr: Expr::Break, index },
+                        Keyword::Defer => self.keyword_defer(index),
+                        Keyword::Break => self.keyword_break(index),
                         Keyword::Continue => Stmt { expr: Expr::Continue, index },
-                        Keyword::If => {
-                            let statement = self.keyword_if(index);
-                            statements.push(statement);
-                            token = self.current.clone();
-                            continue;
+                        Keyword::If | Keyword::While | Keyword::For => {
+                            let statement = match keyword {
+                                Keyword::If => self.keyword_if(index),
+                                Keyword::While => self.keyword_while(index),
+                                _ => self.keyword_for(index)
+                            };
+
+                            // The token after the statement is already read to look
+                            // for its else branch.
+                            if !self.panicking {
+                                statements.push(statement);
+                                token = self.current.clone();
+                                continue;
+                            }
+
+                            statement
                         },
                         _ => Stmt::default()
                     };
@@ -720,8 +987,11 @@ impl AST {
                     let mut expr = Expr::Word(self.constant_pool.add_string(name));
                     expr = self.expression_with_prefix(expr, true);
                     statements.push(Stmt { expr, index: token.position.start as usize });
+                    // The last statement of a block can leave out its semicolon, such
+                    // as the value of a branch of an `if` used as a value.
                     match self.current.kind {
                         TokenKind::Semicolon => (),
+                        TokenKind::CurlyBraceClose => return statements,
                         _ => unexpected_token!(self, ASTErrorKind::ExpectedSemicolon, self.current)
                     }
                 },
@@ -735,11 +1005,23 @@ impl AST {
 
                     match &self.current.kind {
                         TokenKind::Semicolon => (),
+                        TokenKind::CurlyBraceClose => return statements,
                         _ => unexpected_token!(self, ASTErrorKind::ExpectedSemicolon, self.current)
                     }
                 }
             }
 
+            if self.panicking {
+                if self.errors.len() >= self.max_errors {
+                    return statements;
+                }
+
+                if self.synchronize(statement_start, statement_depth) {
+                    token = self.current.clone();
+                    continue;
+                }
+            }
+
             match self.lexer.next() {
                 Some(next_token) => {
                     token = next_token;
@@ -753,7 +1035,7 @@ impl AST {
         statements
     }
 
-    pub fn expression_function_params(&mut self) -> Vec<u32> {
+    pub fn expression_function_params(&mut self) -> Vec<(u32, TypeAnnotation)> {
         let mut params = Vec::new();
 
         while let Some(token) = self.lexer.next() {
@@ -762,8 +1044,19 @@ impl AST {
             match self.current.kind.clone() {
                 TokenKind::ParenClose => return params,
                 TokenKind::Word(name) => {
-                    params.push(self.constant_pool.add_string(name));
-                    match self.next_token().kind {
+                    let name = self.constant_pool.add_string(name);
+                    let mut next = self.next_token().kind;
+                    let type_ = match next {
+                        TokenKind::Colon => {
+                            let type_ = self.expression_type();
+                            next = self.next_token().kind;
+                            type_
+                        },
+                        _ => TypeAnnotation::Any
+                    };
+
+                    params.push((name, type_));
+                    match next {
                         TokenKind::ParenClose => return params,
                         TokenKind::Comma => (),
                         _ => {
@@ -780,6 +1073,35 @@ impl AST {
         params
     }
 
+    pub fn expression_type(&mut self) -> TypeAnnotation {
+        let type_ = match self.next_token().kind {
+            TokenKind::Word(name) => TypeAnnotation::from_name(&name),
+            TokenKind::Null => Some(TypeAnnotation::Null),
+            _ => None
+        };
+
+        match type_ {
+            Some(type_) => type_,
+            None => {
+                self.error(self.current.position, ASTErrorKind::UnknownType);
+                TypeAnnotation::Any
+            }
+        }
+    }
+
+    // Parses the optional `-> type` after the parameters of a function and moves
+    // to the next token.
+    pub fn expression_return_type(&mut self) -> TypeAnnotation {
+        match self.next_token().kind {
+            TokenKind::Arrow => {
+                let type_ = self.expression_type();
+                self.next_token();
+                type_
+            },
+            _ => TypeAnnotation::Any
+        }
+    }
+
     pub fn validate_current_semicolon(&mut self) {
         match &self.current.kind {
             TokenKind::Semicolon => (),
@@ -805,7 +1127,8 @@ impl AST {
             statements: self.statements,
             imports: self.imports,
             constant_pool: self.constant_pool,
-            body: self.lexer.body
+            body: self.lexer.body,
+            source_map: self.lexer.source_map
         }
     }
 
@@ -819,10 +1142,13 @@ pub enum ASTErrorKind {
     UnexpectedExpr,
     UselessAwait,
     UnclosedParen,
+    // A bracket which is still open at the end of the script.
+    UnclosedDelimiter { character: char, line: usize },
     ImproperPropertyIndexing,
     ImproperTernaryOperator,
     ImproperImport,
     ImproperTryStatement,
+    AssignmentInCondition,
     ExpectedValue,
     ExpectedColon,
     ExpectedIdent,
@@ -830,14 +1156,15 @@ pub enum ASTErrorKind {
     ExpectedBlock,
     ExpectedPropertyExpr,
     ExpectedSemicolon,
-    ExpectedIn
+    ExpectedIn,
+    UnknownType
 }
 
 #[derive(Debug, Clone)]
 pub struct ASTError {
     line: usize,
     col: usize,
-    filename: String,
+    source_map: Rc<SourceMap>,
     body: String,
     kind: ASTErrorKind
 }
@@ -851,10 +1178,12 @@ impl Display for ASTError {
             ASTErrorKind::UnexpectedExpr => write!(f, "Never expected an expression."),
             ASTErrorKind::UselessAwait => write!(f, "Statement \"await\" which is useless to exist."),
             ASTErrorKind::UnclosedParen => write!(f, "Found an unclosed bracket."),
-            ASTErrorKind::ImproperPropertyIndexing => write!(f, "Property indexing is improper. Proper: \"object['key']\""),
+            ASTErrorKind::UnclosedDelimiter { character, line } => write!(f, "This \"{}\" on line {} was never closed.", character, line),
+            ASTErrorKind::ImproperPropertyIndexing => write!(f, "Property indexing is improper. Proper: \"object['key']\" or \"array[start:end:step]\""),
             ASTErrorKind::ImproperTernaryOperator => write!(f, "Ternary operator is improper. Proper: \"target ? truthy : falsy\""),
             ASTErrorKind::ImproperImport => write!(f, "Import statement has an improper pattern of syntax."),
             ASTErrorKind::ImproperTryStatement => write!(f, "Improper try statement. Proper: \"try {{}} expect {{}}\""),
+            ASTErrorKind::AssignmentInCondition => write!(f, "Cannot assign in a condition. Use \"==\" to compare the values or assign in a statement before it."),
             ASTErrorKind::ExpectedValue => write!(f, "Expected a value here."),
             ASTErrorKind::ExpectedColon => write!(f, "Expected a \":\" (colon)."),
             ASTErrorKind::ExpectedIdent => write!(f, "Expected an identifier here."),
@@ -862,7 +1191,8 @@ impl Display for ASTError {
             ASTErrorKind::ExpectedBlock => write!(f, "Expected a \"{{\" (block) here."),
             ASTErrorKind::ExpectedPropertyExpr => write!(f, "Expected identifier after property delaration. Proper: \"object.key\"."),
             ASTErrorKind::ExpectedSemicolon => write!(f, "Expected a \";\" (semicolon) here."),
-            ASTErrorKind::ExpectedIn => write!(f, "Expected \"in\" keyword.")
+            ASTErrorKind::ExpectedIn => write!(f, "Expected \"in\" keyword."),
+            ASTErrorKind::UnknownType => write!(f, "Unknown type. Expected any one of number, string, boolean, array, object, function, null or any.")
         }
     }
 }
@@ -871,19 +1201,47 @@ impl ASTError {
     pub fn kind(&self) -> ASTErrorKind { self.kind }
     pub fn body(&self) -> String { self.body.clone() }
     pub fn position(&self) -> (String, usize, usize) {
-        (self.filename.clone(), self.line, self.col)
+        (self.source_map.name().to_owned(), self.line, self.col)
     }
 }
 
-fn get_line_col(body: &String, start: usize) -> (usize, usize) {
-    let lines: Vec<&str> = body.split("\n").collect();
-    let mut i = 0;
-    for line in 0..lines.len() {
-        i += lines[line].len() + 1;
-        if i > start {
-            return (line+1, i - start);
-        }
-    }
-
-    (lines.len(), 0)
-}
\ No newline at end of file
+// The binary operators from the loosest to the tightest. The assignments, the
+// ternaries, the negations and the calls are handled around them:
+//
+//   assignment < ternary < null coalescing < or < and < equality < comparison < bitwise or
+//   < bitwise xor < bitwise and < shift < additive < multiplicative < power
+//   < not and minus < call, attribute and index
+//
+// The minus is looser than the power of its operand though.
+//
+// All of them group to the left except the power, so `1 - 2 - 3` is `(1 - 2) - 3`
+// and `2 ** 3 ** 2` is `2 ** (3 ** 2)`.
+const POWER_PRECEDENCE: u8 = 12;
+
+fn binary_operator(kind: &TokenKind) -> Option<(BinOp, u8)> {
+    let operator = match kind {
+        TokenKind::Nullish => (BinOp::Nullish, 1),
+        TokenKind::Or => (BinOp::Or, 2),
+        TokenKind::And => (BinOp::And, 3),
+        TokenKind::Equal => (BinOp::Equal, 4),
+        TokenKind::NotEqual => (BinOp::NotEqual, 4),
+        TokenKind::GreaterThan => (BinOp::GreaterThan, 5),
+        TokenKind::GreaterThanOrEqual => (BinOp::GreaterThanOrEqual, 5),
+        TokenKind::LessThan => (BinOp::LessThan, 5),
+        TokenKind::LessThanOrEqual => (BinOp::LessThanOrEqual, 5),
+        TokenKind::BitOr => (BinOp::BitOr, 6),
+        TokenKind::BitXor => (BinOp::BitXor, 7),
+        TokenKind::BitAnd => (BinOp::BitAnd, 8),
+        TokenKind::Shl => (BinOp::Shl, 9),
+        TokenKind::Shr => (BinOp::Shr, 9),
+        TokenKind::Add => (BinOp::Add, 10),
+        TokenKind::Sub => (BinOp::Subtract, 10),
+        TokenKind::Mul => (BinOp::Multiply, 11),
+        TokenKind::Div => (BinOp::Divide, 11),
+        TokenKind::Rem => (BinOp::Rem, 11),
+        TokenKind::Pow => (BinOp::Power, POWER_PRECEDENCE),
+        _ => return None
+    };
+
+    Some(operator)
+}
@@ -7,6 +7,7 @@ pub enum BinOp {
     Power,
     Rem,
     Or,
+    Nullish,
     And,
     GreaterThan,
     GreaterThanOrEqual,
@@ -25,9 +26,71 @@ pub enum BinOp {
 pub enum AssignOp {
     Add,
     Sub,
+    Rem,
+    Merge,
+    Nullish,
     Assign
 }
 
+// The optional type annotations of the parameters and the returned value of a
+// function which are checked at runtime. Unannotated values are `Any`.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TypeAnnotation {
+    #[default]
+    Any,
+    Number,
+    String,
+    Boolean,
+    Array,
+    Object,
+    Function,
+    Null
+}
+
+impl TypeAnnotation {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "any" => Self::Any,
+            "number" => Self::Number,
+            "string" => Self::String,
+            "boolean" => Self::Boolean,
+            "array" => Self::Array,
+            "object" => Self::Object,
+            "function" => Self::Function,
+            "null" => Self::Null,
+            _ => return None
+        })
+    }
+
+    pub fn from_u8(byte: u8) -> Self {
+        match byte {
+            1 => Self::Number,
+            2 => Self::String,
+            3 => Self::Boolean,
+            4 => Self::Array,
+            5 => Self::Object,
+            6 => Self::Function,
+            7 => Self::Null,
+            _ => Self::Any
+        }
+    }
+
+    // The name is the same as the one returned by `typeof`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Any => "any",
+            Self::Number => "number",
+            Self::String => "string",
+            Self::Boolean => "boolean",
+            Self::Array => "array",
+            Self::Object => "object",
+            Self::Function => "function",
+            Self::Null => "null"
+        }
+    }
+}
+
 #[repr(u8)]
 #[derive(Debug, Clone)]
 pub enum Expr {
@@ -39,15 +102,23 @@ pub enum Expr {
     Boolean(bool), // (boolean)
     Ternary(Box<Expr>, Box<Expr>, Box<Expr>), // (target_expr, truthy_expr, falsy_expr)
     Attribute(Box<Expr>, Box<Expr>), // (target_expr, attr_expr)
+    Slice {
+        target: Box<Expr>, // The sliced array or string
+        start: Option<Box<Expr>>, // Defaults to the start or the end if the step is negative
+        end: Option<Box<Expr>>, // Defaults to the other end
+        step: Option<Box<Expr>> // Defaults to 1
+    },
     Call(Box<Expr>, Vec<Expr>), // (target_expr, parameters)
     Array(Vec<Expr>), // [value_expr]
     Dict(Vec<(u32, Expr)>), // [(constant_register_id, value_expr)]
     Group(Box<Expr>), // (grouped_expr)
     Not(Box<Expr>), // (expr_to_invert)
+    Negate(Box<Expr>), // (expr_to_negate)
     Await(Box<Expr>), // (await_expr)
     Function {
         name: u32, // The function name constant register id
-        parameters: Vec<u32>, // The parameters name registers
+        parameters: Vec<(u32, TypeAnnotation)>, // The parameters name registers with their types
+        return_type: TypeAnnotation, // The type of the returned value
         inner: Vec<Stmt>, // The body of the function
         is_async: bool // Boolean stating is the function async or not
     },
@@ -57,9 +128,10 @@ pub enum Expr {
         op: BinOp // The operator
     },
     Null,
+    Positioned(Box<Expr>, u32), // (positioned_expr, index of the token of its operator)
 
     // Statements
-    While(Box<Expr>, Vec<Stmt>), // (condition, [statement_to_execute])
+    While(Box<Expr>, Vec<Stmt>, Option<Vec<Stmt>>), // (condition, [statement_to_execute], else_branch)
     Return(Box<Expr>), // (returned_identifier)
     Store(u32, Box<Expr>, bool), // (constant_register_id, assigned_identifier, is_constant)
     Import {
@@ -71,9 +143,15 @@ pub enum Expr {
         expect_inner: Vec<Stmt> // The code to run if the code fails
     },
     If {
-        branches: Vec<(Expr, Vec<Stmt>)>, // The branches of the if elif
+        branches: Vec<(Expr, Vec<Stmt>, usize)>, // The branches of the if elif with the index of their keyword
         else_branch: Option<Vec<Stmt>> // The final else branch if exists
     },
+    // An `if` used as a value, which is the value of the last expression of the
+    // branch which runs or null.
+    IfValue {
+        branches: Vec<(Expr, Vec<Stmt>, usize)>,
+        else_branch: Option<Vec<Stmt>>
+    },
     Assign {
         target: Box<Expr>, // The target to assing
         op: AssignOp, // The operator used to assign
@@ -81,17 +159,31 @@ pub enum Expr {
     },
     For {
         name: u32,
+        value_name: Option<u32>, // The second name of `for key, value in object`
         in_: Box<Expr>,
-        inner: Vec<Stmt>
+        inner: Vec<Stmt>,
+        else_branch: Option<Vec<Stmt>> // Runs when the loop ends without a break
     },
-    Break,
-    Continue
+    Break(Option<Box<Expr>>), // The value of the loop when it is used as an expression
+    Continue,
+    Defer(Box<Expr>) // (deferred_expr)
 }
 
 impl Default for Expr {
     fn default() -> Self { Expr::Null }
 }
 
+impl Expr {
+    // The calls, attributes and binary operations are wrapped with the position of
+    // their operator by the parser, which only matters for the error positions.
+    pub fn without_position(self) -> Expr {
+        match self {
+            Expr::Positioned(expr, _) => expr.without_position(),
+            expr => expr
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct Stmt {
     pub expr: Expr,
@@ -1,40 +1,92 @@
-use crate::{ConstantPool, Position, BytecodeCompiler};
+use std::rc::Rc;
+use crate::{ConstantPool, Position, BytecodeCompiler, TinyString, SourceMap};
 
 #[derive(Debug, Clone, Default)]
 pub struct Chunk {
     pub(crate) bytes: Vec<u8>,
     pub(crate) constants: ConstantPool,
     pub(crate) try_blocks: Vec<(usize, usize, u8)>,
+    // The ranges of the bytecode of the function bodies.
+    pub(crate) functions: Vec<(usize, usize)>,
     pub(crate) position_map: Vec<(usize, Position)>,
-    pub(crate) line_data: Vec<u32>
+    pub(crate) source_map: Rc<SourceMap>,
+    pub(crate) sources: Vec<ChunkSource>,
+    // The names and slots of the top level declarations of the script which are
+    // turned into globals when the script is preloaded.
+    pub(crate) declarations: Vec<(u32, u8)>
+}
+
+// A script appended to the chunk with its bytecode starting at `start`. These are
+// only tracked when more than one script is executed by the vm.
+#[derive(Debug, Clone, Default)]
+pub struct ChunkSource {
+    pub(crate) start: usize,
+    pub(crate) name: TinyString,
+    pub(crate) source_map: Rc<SourceMap>,
+    pub(crate) preloaded: bool
 }
 
 impl Chunk {
 
-    pub fn get_position(&self, ip: usize) -> Position {
-        for (index, position) in self.position_map.iter() {
-            if ip <= *index {
-                return *position;
-            }
-        }
+    pub fn constant_pool(&self) -> &ConstantPool {
+        &self.constants
+    }
+
+    // Appends the bytecode of a script which gets executed after the current one.
+    // The constant pool of the script has to be continued from the pool of this
+    // chunk so that the constant ids used by the current bytecode stay the same.
+    pub fn append(&mut self, name: TinyString, chunk: Chunk, preloaded: bool) {
+        let offset = self.bytes.len();
 
-        Position::default()
+        self.bytes.extend(chunk.bytes);
+        self.constants = chunk.constants;
+        self.try_blocks.extend(chunk.try_blocks.into_iter().map(|(start, end, slot)| (start + offset, end + offset, slot)));
+        self.functions.extend(chunk.functions.into_iter().map(|(start, end)| (start + offset, end + offset)));
+        self.position_map.extend(chunk.position_map.into_iter().map(|(ip, position)| (ip + offset, position)));
+        self.sources.push(ChunkSource { start: offset, name, source_map: chunk.source_map.clone(), preloaded });
+        self.source_map = chunk.source_map;
+        self.declarations = chunk.declarations;
     }
 
-    pub fn get_line(&self, position: Position) -> u32 {
-        let mut current_index = 0;
-        let mut index = 0;
+    // The innermost try block whose body contains the ip as (start, handler ip, error slot).
+    // The bodies of the functions declared inside of a try block are in its range
+    // but run in their own frames, so the block does not catch their errors.
+    pub(crate) fn try_block_at(&self, ip: usize) -> Option<(usize, usize, u8)> {
+        self.try_blocks.iter()
+            .filter(|&&(start, handler, _)| start <= ip && ip < handler)
+            .filter(|&&(start, _, _)| !self.functions.iter().any(|&(body, end)| start < body && body <= ip && ip < end))
+            .max_by_key(|&&(start, _, _)| start)
+            .copied()
+    }
 
-        for line_length in self.line_data.iter() {
-            current_index += line_length;
-            index += 1;
+    // The script which the bytecode at the ip belongs to.
+    pub fn get_source(&self, ip: usize) -> Option<&ChunkSource> {
+        self.sources.iter().rev().find(|source| source.start <= ip)
+    }
 
-            if current_index >= position.start as u32 {
-                return index;
-            }
+    // The line of the bytecode at the ip, which is unknown for the chunks built
+    // without the line data of their source.
+    pub fn get_line_at(&self, ip: usize) -> Option<u32> {
+        let position = self.get_position(ip);
+        match self.get_source(ip) {
+            Some(source) => source.source_map.line(position.start as usize),
+            None => self.get_line(position)
+        }
+    }
+
+    // The positions are recorded at the end of the bytecode of their statements or
+    // expressions in the order of the bytecode, so the first one which ends at or
+    // after the ip is the innermost one around it.
+    pub fn get_position(&self, ip: usize) -> Position {
+        let index = self.position_map.partition_point(|(end, _)| *end < ip);
+        match self.position_map.get(index) {
+            Some((_, position)) => *position,
+            None => Position::default()
         }
+    }
 
-        index
+    pub fn get_line(&self, position: Position) -> Option<u32> {
+        self.source_map.line(position.start as usize)
     }
     
 }
@@ -45,7 +97,10 @@ impl From<BytecodeCompiler> for Chunk {
             bytes: compiler.bytes,
             constants: compiler.ast.constant_pool,
             position_map: compiler.position_map,
-            line_data: compiler.line_data,
+            source_map: compiler.ast.source_map,
+            sources: Vec::new(),
+            declarations: compiler.declarations,
+            functions: compiler.functions,
             try_blocks: {
                 compiler.try_blocks.reverse();
                 compiler.try_blocks
@@ -5,7 +5,13 @@ pub enum CompilerErrorKind {
     AssignmentToConstant { name: String },
     TooManyLocals,
     TooManyUpvalues,
-    UnknownValue { name: String }
+    UnknownValue { name: String },
+    UsedBeforeDeclaration { name: String },
+    BuiltinRedeclaration { name: String },
+    Redeclaration { name: String },
+    MissingElse,
+    OutsideLoop { keyword: String },
+    ReturnOutsideFunction
 }
 
 impl CompilerErrorKind {
@@ -14,7 +20,13 @@ impl CompilerErrorKind {
             Self::AssignmentToConstant { name } => format!("Assignment to a constant \"{}\".", name),
             Self::TooManyLocals => format!("Found too many locals. Try to save some locals in forms of object and functions into classes."),
             Self::TooManyUpvalues => format!("Found too many upvalues captured in the function. Try to use only few required upvalues."),
-            Self::UnknownValue { name } => format!("Value {} does not exists.", name)
+            Self::UnknownValue { name } => format!("Value {} does not exists.", name),
+            Self::UsedBeforeDeclaration { name } => format!("Value {} is used before its declaration in the same scope.", name),
+            Self::BuiltinRedeclaration { name } => format!("Cannot redeclare built-in '{}'. Shadow it inside a block or function instead.", name),
+            Self::Redeclaration { name } => format!("Value {} is already declared in the same scope. Shadow it inside a block or function instead.", name),
+            Self::MissingElse => "The if used as a value has no else branch, so its value is null when none of its branches run.".to_owned(),
+            Self::OutsideLoop { keyword } => format!("Found \"{}\" outside of a loop. It can only be used in the body of a while or for loop.", keyword),
+            Self::ReturnOutsideFunction => "Found \"return\" outside of a function. Only the code given to --eval can return its result.".to_owned()
         }
     }
 }
@@ -1,6 +1,8 @@
+use std::mem;
 use super::opcode::*;
-use crate::{CompilerError, CompilerErrorKind, Position, ASTBuild, Expr, BinOp, AssignOp};
+use crate::{CompilerError, CompilerErrorKind, Position, ASTBuild, Expr, BinOp, AssignOp, Stmt, TypeAnnotation, TinyString};
 use crate::ast::constant_pool;
+use crate::runtime::core::installer::{self, Selection};
 
 pub type OptionalValue<T> = Option<(T, u8)>;
 
@@ -37,6 +39,7 @@ impl FunctionFlags {
 #[derive(Debug, Clone, Default, Copy)]
 pub struct Local {
     pub name: u32,
+    pub slot: u8,
     pub depth: u16,
     pub is_upvalue: bool,
     pub is_const: bool
@@ -63,50 +66,113 @@ pub struct Closure {
     pub locals: Vec<Local>,
     pub index: u16,
     pub max_slots: u8,
+    pub return_type: TypeAnnotation
+}
+
+impl Closure {
+    // The slots of the dropped locals are given again to the next ones, while
+    // `max_slots` keeps the most slots which were used at once for the frame.
+    pub fn next_slot(&self) -> u8 {
+        self.locals.last().map_or(0, |local| local.slot.saturating_add(1))
+    }
+}
+
+// A `let` or `const` which is declared later in a block which is being compiled.
+#[derive(Debug, Clone, Copy)]
+pub struct PendingDeclaration {
+    pub name: u32,
+    pub closure: usize,
+    pub slots_start: u8
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct LoopHandler {
     pub ip: usize,
-    pub break_offset_holders: Vec<usize>
+    pub break_offset_holders: Vec<usize>,
+    pub result_slot: Option<u8>, // The slot which the value given to `break` is stored in
+    // The first slot of the locals of the body, which are closed at the end of
+    // every iteration.
+    pub body_slot: u8
 }
 
+// The options of the compilation which are given by the flags of the cli.
+#[derive(Debug, Clone, Default)]
+pub struct CompileOptions {
+    // Keeps the value of the last statement as the result of the vm, for `--eval`.
+    pub keep_result: bool,
+    // Records the positions of the calls, attributes and binary operations along
+    // with the statements at the cost of a larger position map.
+    pub fine_positions: bool,
+    // The groups of builtins the vm is started with, whose globals cannot be
+    // redeclared at the top level.
+    pub globals: Selection
+}
+
+// The name of the hidden local holding the value of a loop which cannot clash
+// with the names in the constant pool.
+const LOOP_RESULT: u32 = u32::MAX;
+
+// The names of the hidden locals holding the target and the attribute of a
+// nullish assignment to an attribute.
+const ASSIGN_TARGET: u32 = u32::MAX - 1;
+const ASSIGN_ATTRIBUTE: u32 = u32::MAX - 2;
+
+// The names of the hidden locals holding the callee or the receiver and the
+// arguments of a deferred call. The arguments count down from `DEFER_ARGUMENTS`.
+const DEFER_TARGET: u32 = u32::MAX - 3;
+const DEFER_ARGUMENTS: u32 = u32::MAX - 4;
+
 #[derive(Debug, Clone, Default)]
 pub struct BytecodeCompiler {
     pub ast: ASTBuild,
     pub bytes: Vec<u8>,
     pub position_map: Vec<(usize, Position)>,
     pub errors: Vec<CompilerError>,
+    pub warnings: Vec<CompilerError>,
     pub closures: Vec<Closure>,
     pub(crate) index: usize,
     pub(crate) depth: u16,
-    pub(crate) line_data: Vec<u32>,
     pub(crate) try_blocks: Vec<(usize, usize, u8)>,
+    pub(crate) functions: Vec<(usize, usize)>,
+    pub(crate) declarations: Vec<(u32, u8)>,
+    options: CompileOptions,
+    builtins: Vec<&'static str>,
     loop_handler: LoopHandler,
-    current_statement_index: usize
+    current_statement_index: usize,
+    pending_declarations: Vec<PendingDeclaration>
 }
 
 impl BytecodeCompiler {
 
     pub fn new(ast: ASTBuild) -> Result<Self, Vec<CompilerError>> {
-        let mut line_data = Vec::new();
+        Self::with_options(ast, CompileOptions::default())
+    }
 
-        for line in ast.body.split("\n") {
-            line_data.push(line.len() as u32);
-        }
-        
+    // Compiles the script so that the value of its last statement becomes the
+    // result of the vm instead of being popped, which is used by `--eval`.
+    pub fn new_with_result(ast: ASTBuild) -> Result<Self, Vec<CompilerError>> {
+        Self::with_options(ast, CompileOptions { keep_result: true, ..Default::default() })
+    }
+
+    pub fn with_options(ast: ASTBuild, options: CompileOptions) -> Result<Self, Vec<CompilerError>> {
         let mut this = Self { 
             ast, 
-            line_data,
             closures: vec![Closure::default()],
+            builtins: installer::globals(&options.globals),
+            options,
             ..Default::default()  
         };
 
         this.bytes.push(0);
-        for statement in this.ast.statements.clone()  {
+        let statements = this.ast.statements.clone();
+        this.add_pending_declarations(&statements);
+
+        let statements_len = statements.len();
+        for (index, statement) in statements.into_iter().enumerate() {
             this.current_statement_index = statement.index;
             if this.load_expr(statement.expr) {
-                this.bytes.push(POP);
+                let is_last = index + 1 == statements_len;
+                this.bytes.push(if this.options.keep_result && is_last { SET_RESULT } else { POP });
             }
 
             this.add_position(statement.index);
@@ -114,12 +180,32 @@ impl BytecodeCompiler {
         }
 
         if this.errors.len() == 0 { 
-            this.bytes[0] = this.closures.pop().unwrap().max_slots;
+            let script = this.closures.pop().unwrap();
+            this.bytes[0] = script.max_slots;
+            this.declarations = script.locals.iter()
+                .filter(|local| local.depth == 0)
+                .map(|local| (local.name, local.slot))
+                .collect();
+
             this.end_loop();
             Ok(this) 
         } else { Err(this.errors) }
     }
 
+    // Adds the string to the constant pool unless it is already there and returns
+    // its id. The words and the strings with the same text share the same id.
+    pub fn intern(&mut self, string: &str) -> u32 {
+        self.ast.constant_pool.add_string(TinyString::new(string.as_bytes()))
+    }
+
+    pub fn constants(&self) -> &[TinyString] {
+        &self.ast.constant_pool.strings
+    }
+
+    pub fn constant_at(&self, id: u32) -> Option<&TinyString> {
+        self.ast.constant_pool.strings.get(id as usize)
+    }
+
     // TODO(Scientific-Guy): Make a system to insert `POP` bytecode where the values are useless.
     pub fn load_expr(&mut self, expr: Expr) -> bool {
         macro_rules! load_statement {
@@ -133,13 +219,35 @@ impl BytecodeCompiler {
             }};
         }
 
+        macro_rules! load_block {
+            ($statements:expr) => {{
+                let pending_len = self.pending_declarations.len();
+                self.add_pending_declarations(&$statements);
+
+                for statement in $statements {
+                    load_statement!(statement);
+                }
+
+                self.pending_declarations.truncate(pending_len);
+            }};
+        }
+
         match expr {
             Expr::Store(constant_id, expr_value, is_constant) => {
+                let closure_index = self.closures.len() - 1;
+                if let Some(index) = self.pending_declarations.iter().rposition(|pending| pending.name == constant_id && pending.closure == closure_index) {
+                    self.pending_declarations.remove(index);
+                }
+
                 let slot = self.declare(constant_id, is_constant, self.current_statement_index);
                 self.load_expr(*expr_value);
                 self.bytes.extend_from_slice(&[SET_LOCAL, slot]);
                 return false;
             },
+            Expr::Assign { target, op: AssignOp::Nullish, value } => {
+                self.load_nullish_assign(*target, *value);
+                return false;
+            },
             Expr::Assign { target, op, value } => {
                 macro_rules! write_bytes {
                     ($set_op:expr, $get_op:expr, $index:expr) => {{
@@ -148,23 +256,19 @@ impl BytecodeCompiler {
                                 self.load_expr(*value);
                                 self.bytes.extend_from_slice(&[$set_op, $index])
                             },
-                            AssignOp::Add => {
+                            op => {
                                 self.bytes.extend_from_slice(&[$get_op, $index]);
                                 self.load_expr(*value);
-                                self.bytes.extend_from_slice(&[ADD, $set_op, $index]);
-                            },
-                            AssignOp::Sub => {
-                                self.bytes.extend_from_slice(&[$get_op, $index]);
-                                self.load_expr(*value);
-                                self.bytes.extend_from_slice(&[SUB, $set_op, $index]);
+                                self.bytes.extend_from_slice(&[COMPOUND_ASSIGN, assign_op(op), $set_op, $index]);
                             }
                         }
                     }};
                 }
 
-                match *target {
+                match target.without_position() {
                     Expr::Word(constant_id) => {
                         let last_closure_index = self.closures.len() as u16 - 1;
+                        self.check_declared(constant_id);
                 
                         if let Some((_, index)) = self.get_local(last_closure_index, constant_id) {
                             write_bytes!(SET_LOCAL, GET_LOCAL, index);
@@ -174,34 +278,28 @@ impl BytecodeCompiler {
                             self.error(CompilerErrorKind::UnknownValue { name: self.ast.constant_pool.get_string(constant_id).to_string() }, self.current_statement_index);
                         }
                     },
-                    Expr::Attribute(target, attr) => {
-                        match op {
-                            AssignOp::Assign => {
-                                self.load_expr(*value);
-                                self.load_expr(*target);
-                                self.load_expr(*attr);
-                                self.bytes.push(SET_ATTR);
-                            },
-                            AssignOp::Add => {
-                                self.load_expr(*target.clone());
-                                self.load_expr(*attr.clone());
-                                self.bytes.push(GET_ATTR);
-                                self.load_expr(*value);
-                                self.bytes.push(ADD);
-                                self.load_expr(*target);
-                                self.load_expr(*attr);
-                                self.bytes.push(SET_ATTR);
-                            },
-                            AssignOp::Sub => {
-                                self.load_expr(*target.clone());
-                                self.load_expr(*attr.clone());
-                                self.bytes.push(GET_ATTR);
-                                self.load_expr(*value);
-                                self.bytes.push(SUB);
-                                self.load_expr(*target);
-                                self.load_expr(*attr);
-                                self.bytes.push(SET_ATTR);
-                            }
+                    Expr::Attribute(target, attr) => match op {
+                        AssignOp::Assign => {
+                            self.load_expr(*target);
+                            self.load_expr(*attr);
+                            self.load_expr(*value);
+                            self.bytes.extend_from_slice(&[ASSIGN_ATTR, ASSIGN_OP]);
+                        },
+                        // The old value is read before the value is evaluated like the
+                        // one of a name. The target and the attribute are held by hidden
+                        // locals so that receivers with side effects (such as
+                        // `list()[0] += 1`) are not executed twice.
+                        op => {
+                            self.depth += 1;
+                            let target = self.hoist(ASSIGN_TARGET, *target);
+                            let attr = self.hoist(ASSIGN_ATTRIBUTE, *attr);
+
+                            self.load_expr(target.clone());
+                            self.load_expr(attr.clone());
+                            self.load_expr(Expr::Attribute(Box::new(target), Box::new(attr)));
+                            self.load_expr(*value);
+                            self.bytes.extend_from_slice(&[COMPOUND_ASSIGN, assign_op(op), ASSIGN_ATTR, ASSIGN_OP]);
+                            self.drop_locals();
                         }
                     },
                     _ => ()
@@ -212,14 +310,15 @@ impl BytecodeCompiler {
             Expr::If { branches, else_branch } => {
                 let mut offsets = Vec::new();
 
-                for (condition, statements) in branches {
+                for (condition, statements, branch_index) in branches {
+                    // Errors thrown while evaluating the condition point to the
+                    // keyword of its own branch.
                     self.load_expr(condition);
+                    self.add_position(branch_index);
                     self.bytes.extend_from_slice(&[JUMP_NOT_IF, 0, 0]);
                     let offset_ip = self.bytes.len();
                     self.depth += 1;
-                    for statement in statements {
-                        load_statement!(statement);
-                    }
+                    load_block!(statements);
 
                     self.drop_locals();
                     self.bytes.extend_from_slice(&[JUMP, 0, 0]);
@@ -229,11 +328,49 @@ impl BytecodeCompiler {
 
                 if let Some(statements) = else_branch {
                     self.depth += 1;
-                    for statement in statements {
-                        load_statement!(statement);
-                    }
+                    load_block!(statements);
+
+                    self.drop_locals();
+                }
+
+                for offset in offsets {
+                    let bytes = ((self.bytes.len() - offset) as u16).to_le_bytes();
+                    self.bytes[offset - 2] = bytes[0];
+                    self.bytes[offset - 1] = bytes[1];
+                }
+
+                return false;
+            },
+            // The value of the branch is held by a hidden local like the one of the
+            // loops, since the captured locals of the branch are closed after it.
+            Expr::IfValue { branches, else_branch } => {
+                if else_branch.is_none() {
+                    let line = self.line_from_start_index(self.current_statement_index as u32);
+                    self.warnings.push(CompilerError { kind: CompilerErrorKind::MissingElse, line });
+                }
+
+                self.depth += 1;
+                let result_slot = self.start_loop_result();
+                let mut offsets = Vec::new();
+
+                for (condition, statements, branch_index) in branches {
+                    self.load_expr(condition);
+                    self.add_position(branch_index);
+                    self.bytes.extend_from_slice(&[JUMP_NOT_IF, 0, 0]);
+                    let offset_ip = self.bytes.len();
+                    self.depth += 1;
+                    self.load_branch_value(statements, result_slot);
 
                     self.drop_locals();
+                    self.bytes.extend_from_slice(&[JUMP, 0, 0]);
+                    offsets.push(self.bytes.len());
+                    self.update_offset(offset_ip);
+                }
+
+                if let Some(statements) = else_branch {
+                    self.depth += 1;
+                    self.load_branch_value(statements, result_slot);
+                    self.drop_locals();
                 }
 
                 for offset in offsets {
@@ -242,19 +379,57 @@ impl BytecodeCompiler {
                     self.bytes[offset - 1] = bytes[1];
                 }
 
+                self.drop_locals();
+                self.bytes.extend_from_slice(&[GET_LOCAL, result_slot]);
+            },
+            Expr::Defer(expr) => {
+                self.load_deferred(*expr);
                 return false;
             },
             Expr::Return(return_value) => {
+                // A return at the top level ends the script with its result, which
+                // only the code evaluated for its result has.
+                if self.closures.len() == 1 && !self.options.keep_result {
+                    self.error(CompilerErrorKind::ReturnOutsideFunction, self.current_statement_index);
+                    return false;
+                }
+
                 self.load_expr(*return_value);
+                self.check_return_type();
                 self.bytes.push(RETURN);
                 return false;
             },
-            Expr::Break => {
+            Expr::Break(value) => {
+                if !self.in_loop() {
+                    self.error(CompilerErrorKind::OutsideLoop { keyword: "break".to_owned() }, self.current_statement_index);
+                    return false;
+                }
+
+                if let Some(value) = value {
+                    self.load_expr(*value);
+                    match self.loop_handler.result_slot {
+                        Some(slot) => self.bytes.extend_from_slice(&[SET_LOCAL, slot]),
+                        None => self.bytes.push(POP)
+                    }
+                }
+
+                // The slots of the body are given to the locals after the loop,
+                // so the ones captured in it are closed like on `continue`.
+                self.bytes.extend_from_slice(&[CLOSE_UPVALUE, self.loop_handler.body_slot]);
+
                 self.bytes.extend_from_slice(&[JUMP, 0, 0]);
                 self.loop_handler.break_offset_holders.push(self.bytes.len());
                 return false;
             },
             Expr::Continue => {
+                if !self.in_loop() {
+                    self.error(CompilerErrorKind::OutsideLoop { keyword: "continue".to_owned() }, self.current_statement_index);
+                    return false;
+                }
+
+                // The locals captured in the body are not shared with the next
+                // iteration, which is not known to capture any of them yet.
+                self.bytes.extend_from_slice(&[CLOSE_UPVALUE, self.loop_handler.body_slot]);
                 self.bytes.push(JUMP_BACK);
                 if self.loop_handler.ip == 0 {
                     self.bytes.extend_from_slice(&[1, 0]);
@@ -265,60 +440,74 @@ impl BytecodeCompiler {
 
                 return false;
             },
-            Expr::For { name, in_, inner } => { 
+            Expr::For { name, value_name, in_, inner, else_branch } => { 
+                self.depth += 1;
+                let result_slot = self.start_loop_result();
+
                 self.load_expr(*in_);
-                self.bytes.push(ITER);
+                self.bytes.push(if value_name.is_some() { ITER_ENTRIES } else { ITER });
                 let loop_ip = self.bytes.len();
 
-                // Basically safe because the pointer it is reading is valid one
-                let enclosing = unsafe { std::ptr::read(&mut self.loop_handler) };
-                self.loop_handler = LoopHandler { ip: loop_ip - 4, break_offset_holders: Vec::new() };
-                self.depth += 1;
+                let enclosing = mem::replace(&mut self.loop_handler, LoopHandler {
+                    ip: loop_ip - 4,
+                    break_offset_holders: Vec::new(),
+                    result_slot: Some(result_slot),
+                    body_slot: 0
+                });
 
                 let slot = self.declare(name, false, self.current_statement_index);
-                self.bytes.extend_from_slice(&[ITER_NEXT, slot, 0, 0]);
-
-                for statement in inner {
-                    load_statement!(statement);
+                match value_name {
+                    Some(value_name) => {
+                        let value_slot = self.declare(value_name, false, self.current_statement_index);
+                        self.bytes.extend_from_slice(&[ITER_NEXT_ENTRY, slot, value_slot, 0, 0]);
+                    },
+                    None => self.bytes.extend_from_slice(&[ITER_NEXT, slot, 0, 0])
                 }
 
+                let body_ip = self.bytes.len();
+                self.loop_handler.body_slot = self.closures.last().unwrap().next_slot();
+                load_block!(inner);
+                self.close_body_locals();
+
                 self.bytes.push(JUMP_BACK);
                 self.bytes.extend_from_slice(&((self.bytes.len() - loop_ip + 4) as u16).to_le_bytes());
 
-                let offset_bytes = ((self.bytes.len() - loop_ip) as u16).to_le_bytes();
-                self.bytes[loop_ip + 2] = offset_bytes[0];
-                self.bytes[loop_ip + 3] = offset_bytes[1];
-                
-                self.end_loop();
-                self.drop_locals();
-                self.loop_handler = enclosing;
+                // The offset is relative to the end of the operands of the opcode.
+                let offset_bytes = ((self.bytes.len() - body_ip) as u16).to_le_bytes();
+                self.bytes[body_ip - 2] = offset_bytes[0];
+                self.bytes[body_ip - 1] = offset_bytes[1];
 
-                return false;
+                self.end_loop_with_else(enclosing, else_branch);
+                self.drop_locals();
+                self.bytes.extend_from_slice(&[POP, GET_LOCAL, result_slot]);
             },
-            Expr::While(condition, statements) => { 
+            Expr::While(condition, statements, else_branch) => { 
+                self.depth += 1;
+                let result_slot = self.start_loop_result();
                 let loop_ip = self.bytes.len();
 
-                // Basically safe because the pointer it is reading is valid one
-                let enclosing = unsafe { std::ptr::read(&mut self.loop_handler) };
-                self.loop_handler = LoopHandler { ip: loop_ip, break_offset_holders: Vec::new() };
-                self.depth += 1;
+                // The offsets of JUMP_BACK are relative to 4 bytes before the opcode.
+                let enclosing = mem::replace(&mut self.loop_handler, LoopHandler {
+                    ip: loop_ip - 4,
+                    break_offset_holders: Vec::new(),
+                    result_slot: Some(result_slot),
+                    body_slot: 0
+                });
 
                 self.load_expr(*condition);
                 let offset_ip = self.bytes.len();
                 self.bytes.extend_from_slice(&[JUMP_NOT_IF, 0, 0]);
 
-                for statement in statements {
-                    load_statement!(statement);
-                }
+                self.loop_handler.body_slot = self.closures.last().unwrap().next_slot();
+                load_block!(statements);
+                self.close_body_locals();
 
                 self.bytes.push(JUMP_BACK);
-                self.bytes.extend_from_slice(&((self.bytes.len() - loop_ip) as u16).to_le_bytes());
+                self.bytes.extend_from_slice(&((self.bytes.len() - loop_ip + 4) as u16).to_le_bytes());
                 self.update_offset(offset_ip + 3);
-                self.end_loop();
+                self.end_loop_with_else(enclosing, else_branch);
                 self.drop_locals();
-                self.loop_handler = enclosing;
-
-                return false;
+                self.bytes.extend_from_slice(&[GET_LOCAL, result_slot]);
             },
             Expr::Import { module, as_ } => {
                 self.bytes.push(IMPORT);
@@ -336,29 +525,27 @@ impl BytecodeCompiler {
                 self.depth += 1;
 
                 let try_offset_ip = self.bytes.len();
-                for statement in try_inner {
-                    load_statement!(statement);
-                }
+                load_block!(try_inner);
 
                 self.drop_locals();
                 self.bytes.extend_from_slice(&[JUMP, 0, 0]);
                 self.depth += 1;
 
-                let error_slot = self.declare(1, true, self.current_statement_index);
+                let error_slot = self.declare(constant_pool::ERROR_CONSTANT, true, self.current_statement_index);
                 let offset_ip = self.bytes.len();
-                for statement in expect_inner {
-                    load_statement!(statement);
-                }
+                load_block!(expect_inner);
 
                 self.drop_locals();
                 self.update_offset(offset_ip);
                 self.try_blocks.push((try_offset_ip, offset_ip, error_slot));
+                return false;
             },
             Expr::Null => self.bytes.push(NULL),
             Expr::Boolean(boolean) => self.bytes.push(boolean as u8),
             Expr::String(constant_id) => self.load_constant(constant_id, STRING, STRING_LONG),
             Expr::Word(constant_id) => {
                 let last_closure_index = self.closures.len() as u16 - 1;
+                self.check_declared(constant_id);
                 
                 if let Some((_, index)) = self.get_local(last_closure_index, constant_id) {
                     self.bytes.extend_from_slice(&[GET_LOCAL, index]);
@@ -399,9 +586,10 @@ impl BytecodeCompiler {
                     BinOp::BitXor => push_op!(BITXOR),
                     BinOp::Shr => push_op!(SHR),
                     BinOp::Shl => push_op!(SHL),
-                    BinOp::Or => {
+                    // The right side is only evaluated when it is the result.
+                    BinOp::Or | BinOp::Nullish => {
                         self.load_expr(*lhs);
-                        self.bytes.extend_from_slice(&[JUMP_IF, 0, 0]);
+                        self.bytes.extend_from_slice(&[if matches!(op, BinOp::Or) { JUMP_IF } else { JUMP_IF_NOT_NULL }, 0, 0]);
                         let offset_ip = self.bytes.len();
                         self.bytes.push(POP);
                         self.load_expr(*rhs);
@@ -414,9 +602,40 @@ impl BytecodeCompiler {
                 self.load_expr(*attribute);
                 self.bytes.push(GET_ATTR);
             },
-            Expr::Not(expr) => {
+            // The omitted bounds are loaded as null.
+            Expr::Slice { target, start, end, step } => {
+                self.load_expr(*target);
+                for bound in [start, end, step] {
+                    match bound {
+                        Some(bound) => { self.load_expr(*bound); },
+                        None => self.bytes.push(NULL)
+                    }
+                }
+
+                self.bytes.push(SLICE);
+            },
+            // The negations of the literals are folded, which makes `!!literal`
+            // a constant as well.
+            Expr::Not(expr) => match self.literal_truthiness(&expr) {
+                Some(truthy) => self.bytes.push(!truthy as u8),
+                None => {
+                    self.load_expr(*expr);
+                    self.bytes.push(NOT);
+                }
+            },
+            Expr::Negate(expr) => {
                 self.load_expr(*expr);
-                self.bytes.push(NOT);
+                self.bytes.push(NEG);
+            },
+            // The position is recorded after the instructions of the expression so
+            // that it covers the errors raised by them.
+            Expr::Positioned(expr, index) => {
+                let has_value = self.load_expr(*expr);
+                if self.options.fine_positions {
+                    self.position_map.push((self.bytes.len(), Position { start: index, end: index }));
+                }
+
+                return has_value;
             },
             Expr::Call(target, params) => {
                 let len = params.len() as u8;
@@ -425,7 +644,7 @@ impl BytecodeCompiler {
                     self.load_expr(expr);
                 }
                 
-                let op = match *target {
+                let op = match target.without_position() {
                     Expr::Attribute(parent, method) => {
                         self.load_expr(*parent);
                         self.load_expr(*method);
@@ -458,43 +677,66 @@ impl BytecodeCompiler {
                 self.bytes.push(DICT);
                 self.load_constant_without_op(len);
             },
-            Expr::Function { name, parameters, inner, is_async } => {
+            Expr::Function { name, parameters, return_type, inner, is_async } => {
                 self.bytes.extend_from_slice(&[FUNC, 0, 0]);
                 let offset_ip = self.bytes.len();
 
-                // Basically safe because the pointer it is reading is valid one
-                let enclosing_loop = unsafe { std::ptr::read(&mut self.loop_handler) };
-                self.loop_handler = LoopHandler { ip: self.bytes.len() - 1, break_offset_holders: Vec::new() };
+                let enclosing_loop = mem::replace(&mut self.loop_handler, LoopHandler {
+                    ip: self.bytes.len() - 1,
+                    break_offset_holders: Vec::new(),
+                    result_slot: None,
+                    body_slot: 0
+                });
+
+                // The name is declared before the body so that the function can
+                // call itself through an upvalue of the slot.
+                let slot = match name {
+                    constant_pool::ANONYMOUS_CONSTANT => None,
+                    name => Some(self.declare(name, true, self.current_statement_index))
+                };
 
                 self.depth += 1;
                 let mut closure = Closure {
                     locals: Vec::new(),
                     upvalues: Vec::new(),
                     index: self.depth,
-                    max_slots: parameters.len() as u8
+                    max_slots: parameters.len() as u8,
+                    return_type
                 };
 
-                for constant_id in parameters {
+                for (slot, (constant_id, _)) in parameters.iter().enumerate() {
                     closure.locals.push(Local {
+                        slot: slot as u8,
                         depth: self.depth,
                         is_upvalue: false,
                         is_const: false,
-                        name: constant_id
+                        name: *constant_id
                     });
                 }
 
                 self.closures.push(closure);
-                for statement in inner {
-                    load_statement!(statement);
+
+                // Unannotated parameters are not checked so that they do not cost
+                // anything at runtime.
+                for (slot, (constant_id, type_)) in parameters.into_iter().enumerate() {
+                    if type_ != TypeAnnotation::Any {
+                        self.bytes.extend_from_slice(&[CHECK_TYPE, slot as u8, type_ as u8]);
+                        self.load_constant_without_op(constant_id);
+                    }
                 }
 
+                load_block!(inner);
+
                 // Just incase if there is no return statement at the end of the
                 // function it can mess up the vm.
                 if *self.bytes.last().unwrap() != RETURN {
-                    self.bytes.extend_from_slice(&[NULL, RETURN]);
+                    self.bytes.push(NULL);
+                    self.check_return_type();
+                    self.bytes.push(RETURN);
                 }
             
                 self.depth -= 1;
+                self.functions.push((offset_ip, self.bytes.len()));
                 self.update_offset(offset_ip);
 
                 let closure = self.closures.pop().unwrap();
@@ -507,8 +749,7 @@ impl BytecodeCompiler {
                 self.end_loop();
                 self.loop_handler = enclosing_loop;
 
-                if name != constant_pool::ANONYMOUS_CONSTANT {
-                    let slot = self.declare(name, true, self.current_statement_index);
+                if let Some(slot) = slot {
                     self.bytes.extend_from_slice(&[SET_LOCAL, slot]);
                 }
 
@@ -536,6 +777,32 @@ impl BytecodeCompiler {
         true
     }
 
+    pub fn check_return_type(&mut self) {
+        let return_type = self.closures.last().unwrap().return_type;
+        if return_type != TypeAnnotation::Any {
+            self.bytes.extend_from_slice(&[CHECK_RETURN, return_type as u8]);
+        }
+    }
+
+    // The truthiness of the expressions which are known at compile time. It has to
+    // follow `Value::to_bool`.
+    fn literal_truthiness(&self, expr: &Expr) -> Option<bool> {
+        let pool = &self.ast.constant_pool;
+        match expr {
+            Expr::Null => Some(false),
+            Expr::Boolean(boolean) => Some(*boolean),
+            Expr::Int(id) => pool.ints.get(*id as usize).map(|int| *int != 0),
+            Expr::Float(id) => pool.floats.get(*id as usize).map(|float| *float != 0.0 && !float.is_nan()),
+            Expr::String(id) => pool.strings.get(*id as usize).map(|string| !string.to_bytes().is_empty()),
+            // The items could have side effects so only the empty ones are folded.
+            Expr::Array(items) if items.is_empty() => Some(true),
+            Expr::Dict(entries) if entries.is_empty() => Some(true),
+            Expr::Not(expr) => self.literal_truthiness(expr).map(|truthy| !truthy),
+            Expr::Group(expr) | Expr::Positioned(expr, _) => self.literal_truthiness(expr),
+            _ => None
+        }
+    }
+
     pub fn load_constant(&mut self, constant_id: u32, short_op: u8, long_op: u8) {
         if constant_id < u8::MAX as u32 {
             self.bytes.extend_from_slice(&[short_op, constant_id as u8]);
@@ -555,26 +822,79 @@ impl BytecodeCompiler {
         }
     }
 
+    // Registers all the `let` and `const` declarations of a block before it is
+    // compiled so that the names used before their declaration can be detected.
+    pub fn add_pending_declarations(&mut self, statements: &[Stmt]) {
+        let closure = self.closures.len() - 1;
+        let slots_start = self.closures[closure].next_slot();
+
+        for statement in statements {
+            if let Expr::Store(name, _, _) = statement.expr {
+                self.pending_declarations.push(PendingDeclaration { name, closure, slots_start });
+            }
+        }
+    }
+
+    // Warns if the name is declared later in one of the enclosing blocks. Without
+    // the warning, the name would silently resolve to an outer value or a global.
+    pub fn check_declared(&mut self, name: u32) {
+        let pending = match self.pending_declarations.iter().rev().find(|pending| pending.name == name) {
+            Some(pending) => *pending,
+            None => return
+        };
+
+        for closure_index in (pending.closure..self.closures.len()).rev() {
+            if let Some((_, slot)) = self.get_local(closure_index as u16, name) {
+                if closure_index != pending.closure || slot >= pending.slots_start {
+                    return;
+                }
+
+                break;
+            }
+        }
+
+        let line = self.line_from_start_index(self.current_statement_index as u32);
+        let name = self.ast.constant_pool.get_string(name).to_string();
+        self.warnings.push(CompilerError { kind: CompilerErrorKind::UsedBeforeDeclaration { name }, line });
+    }
+
     pub fn drop_locals(&mut self) {
         self.depth -= 1;
         let closure = self.closures.last_mut().unwrap();
         let mut index = closure.locals.len();
 
-        if index == 0 {
-            return
-        }
+        let mut closed = None;
 
         while index != 0 {
             index -= 1;
 
             let local = closure.locals[index];
             if local.depth <= self.depth { 
-                return;
+                break;
             }
             
-            self.bytes.push(if local.is_upvalue { CLOSE_UPVALUE } else { POP });
+            if local.is_upvalue {
+                closed = Some(local.slot);
+            }
+
             closure.locals.pop();
         }
+
+        // Locals live in their own slots rather than on the top of the stack, so
+        // nothing is popped. The slots of the block follow each other, so its
+        // captured locals are closed from the first one onwards.
+        if let Some(slot) = closed {
+            self.bytes.extend_from_slice(&[CLOSE_UPVALUE, slot]);
+        }
+    }
+
+    // Closes the locals of the body of a loop which are captured by the closures
+    // made in the iteration, so that the next iteration captures its own values.
+    fn close_body_locals(&mut self) {
+        let body_slot = self.loop_handler.body_slot;
+        if self.closures.last().unwrap().next_slot() > body_slot {
+            self.bytes.extend_from_slice(&[CLOSE_UPVALUE, body_slot]);
+        }
     }
 
     pub fn update_offset(&mut self, offset_ip: usize) {
@@ -604,6 +924,12 @@ impl BytecodeCompiler {
         }
     }
     
+    // Only the loops have a result slot. The body of a function starts without
+    // one, so a loop around the declaration of a function does not count.
+    fn in_loop(&self) -> bool {
+        self.loop_handler.result_slot.is_some()
+    }
+
     pub fn end_loop(&mut self) {
         let length = self.bytes.len();
         for holder in self.loop_handler.break_offset_holders.iter() {
@@ -613,6 +939,140 @@ impl BytecodeCompiler {
         }
     }
 
+    // `target ??= value` only evaluates the value when the target is null, just like
+    // `target ?? value`. The target and the attribute of an attribute are evaluated
+    // once into hidden locals, which are read to check and to assign it.
+    fn load_nullish_assign(&mut self, target: Expr, value: Expr) {
+        self.depth += 1;
+
+        let target = match target.without_position() {
+            Expr::Attribute(parent, attr) => {
+                let parent = self.hoist(ASSIGN_TARGET, *parent);
+                Expr::Attribute(Box::new(parent), Box::new(self.hoist(ASSIGN_ATTRIBUTE, *attr)))
+            },
+            target => target
+        };
+
+        self.load_expr(target.clone());
+        self.bytes.extend_from_slice(&[JUMP_IF_NOT_NULL, 0, 0]);
+        let not_null_ip = self.bytes.len();
+
+        self.bytes.push(POP);
+        self.load_expr(Expr::Assign { target: Box::new(target), op: AssignOp::Assign, value: Box::new(value) });
+        self.bytes.extend_from_slice(&[JUMP, 0, 0]);
+        let end_ip = self.bytes.len();
+
+        self.update_offset(not_null_ip);
+        self.bytes.push(POP);
+        self.update_offset(end_ip);
+        self.drop_locals();
+    }
+
+    // Registers a function running the expression when the frame exits. The callee
+    // or the receiver and the arguments of a deferred call are evaluated right away
+    // into hidden locals of their own block, which is closed at once, so the call
+    // uses the values they had when the defer ran, like the ones of a loop.
+    fn load_deferred(&mut self, expr: Expr) {
+        let index = self.current_statement_index;
+        self.depth += 1;
+
+        let expr = self.hoist_deferred_call(expr);
+        self.load_expr(Expr::Function {
+            name: constant_pool::ANONYMOUS_CONSTANT,
+            parameters: Vec::new(),
+            return_type: TypeAnnotation::Any,
+            inner: vec![Stmt { expr, index }],
+            is_async: false
+        });
+
+        self.bytes.push(DEFER);
+        self.drop_locals();
+    }
+
+    fn hoist_deferred_call(&mut self, expr: Expr) -> Expr {
+        match expr {
+            Expr::Positioned(expr, position) => Expr::Positioned(Box::new(self.hoist_deferred_call(*expr)), position),
+            Expr::Call(target, parameters) => {
+                let parameters = parameters.into_iter()
+                    .enumerate()
+                    .map(|(index, parameter)| self.hoist(DEFER_ARGUMENTS - index as u32, parameter))
+                    .collect();
+
+                let target = match target.without_position() {
+                    Expr::Attribute(parent, method) => Expr::Attribute(Box::new(self.hoist(DEFER_TARGET, *parent)), method),
+                    callee => self.hoist(DEFER_TARGET, callee)
+                };
+
+                Expr::Call(Box::new(target), parameters)
+            },
+            expr => expr
+        }
+    }
+
+    // Stores the value of the expression into a hidden local named `name`, which
+    // the returned word reads.
+    fn hoist(&mut self, name: u32, expr: Expr) -> Expr {
+        let slot = self.declare(name, true, self.current_statement_index);
+        self.load_expr(expr);
+        self.bytes.extend_from_slice(&[SET_LOCAL, slot]);
+        Expr::Word(name)
+    }
+
+    // Declares the hidden local which holds the value of the loop being compiled
+    // and resets it to null as the loop can be entered more than once.
+    fn start_loop_result(&mut self) -> u8 {
+        let slot = self.declare(LOOP_RESULT, false, self.current_statement_index);
+        self.bytes.extend_from_slice(&[NULL, SET_LOCAL, slot]);
+        slot
+    }
+
+    // The statements of a branch of an `if` used as a value, whose last one gives
+    // the value when it is an expression. The value stays null otherwise.
+    fn load_branch_value(&mut self, statements: Vec<Stmt>, result_slot: u8) {
+        let pending_len = self.pending_declarations.len();
+        self.add_pending_declarations(&statements);
+
+        let statements_len = statements.len();
+        for (index, statement) in statements.into_iter().enumerate() {
+            self.current_statement_index = statement.index;
+            if self.load_expr(statement.expr) {
+                match index + 1 == statements_len {
+                    true => self.bytes.extend_from_slice(&[SET_LOCAL, result_slot]),
+                    false => self.bytes.push(POP)
+                }
+            }
+
+            self.add_position(statement.index);
+        }
+
+        self.pending_declarations.truncate(pending_len);
+    }
+
+    // The else branch runs when the loop ends without a break, so the breaks jump
+    // past it. A break or continue inside of it belongs to the enclosing loop.
+    fn end_loop_with_else(&mut self, enclosing: LoopHandler, else_branch: Option<Vec<Stmt>>) {
+        let handler = mem::replace(&mut self.loop_handler, enclosing);
+        if let Some(else_branch) = else_branch {
+            let pending_len = self.pending_declarations.len();
+            self.add_pending_declarations(&else_branch);
+
+            for statement in else_branch {
+                self.current_statement_index = statement.index;
+                if self.load_expr(statement.expr) {
+                    self.bytes.push(POP);
+                }
+
+                self.add_position(statement.index);
+            }
+
+            self.pending_declarations.truncate(pending_len);
+        }
+
+        let enclosing = mem::replace(&mut self.loop_handler, handler);
+        self.end_loop();
+        self.loop_handler = enclosing;
+    }
+
     pub fn error(&mut self, kind: CompilerErrorKind, start_index: usize) {
         let line = self.line_from_start_index(start_index as u32);
         self.errors.push(CompilerError { kind, line });
@@ -629,7 +1089,7 @@ impl BytecodeCompiler {
         while index > 0 {
             index -= 1;
             if closure.locals[index].name == name {
-                return Some((closure.locals[index].clone(), index as u8));
+                return Some((closure.locals[index], closure.locals[index].slot));
             }
         }
     
@@ -646,7 +1106,7 @@ impl BytecodeCompiler {
         while index > 0 {
             index -= 1;
             if closure.locals[index].name == name {
-                return Some((closure.locals[index].clone(), index as u8));
+                return Some((closure.locals[index], closure.locals[index].slot));
             }
         }
     
@@ -659,91 +1119,92 @@ impl BytecodeCompiler {
         }
         
         if let Some((local, local_index)) = self.get_local(index - 1, constant_id) {
-            let closure = self.closures.get_mut(index as usize).unwrap();
-            let length = closure.upvalues.len();
-            let upvalue = Upvalue {
-                index: local_index,
-                is_const: local.is_const,
-                is_local: true
-            };
-
-            closure.upvalues.push(upvalue);
-            self.closures[index as usize - 1].locals[local_index as usize].is_upvalue = true;
-
-            if length >= u8::MAX as usize {
-                self.error(CompilerErrorKind::TooManyUpvalues, self.current_statement_index);
+            if let Some(local) = self.closures[index as usize - 1].locals.iter_mut().rev().find(|local| local.slot == local_index) {
+                local.is_upvalue = true;
             }
 
-            return Some((upvalue, length as u8));
+            return Some(self.add_upvalue(index, Upvalue { index: local_index, is_const: local.is_const, is_local: true }));
         }
 
         if let Some((upvalue, upvalue_index)) = self.get_upvalue(index - 1, constant_id) {
-            let closure = self.closures.get_mut(index as usize).unwrap();
-            let length = closure.upvalues.len();
-            let upvalue = Upvalue {
-                index: upvalue_index,
-                is_const: upvalue.is_const,
-                is_local: false
-            };
-
-            closure.upvalues.push(upvalue);
-            if length >= u8::MAX as usize {
-                self.error(CompilerErrorKind::TooManyUpvalues, self.current_statement_index);
-            }
-    
-            return Some((upvalue, length as u8));
+            return Some(self.add_upvalue(index, Upvalue { index: upvalue_index, is_const: upvalue.is_const, is_local: false }));
         }
     
         None
     }
 
-    pub fn declare(&mut self, constant_id: u32, is_constant: bool, start_index: usize) -> u8 {
-        let last_closure = self.closures.last_mut().unwrap();
-        let mut index = last_closure.locals.len();
+    // A name used more than once by a closure refers to the same upvalue so that
+    // the assignments to it are seen by the reads.
+    fn add_upvalue(&mut self, index: u16, upvalue: Upvalue) -> (Upvalue, u8) {
+        let closure = self.closures.get_mut(index as usize).unwrap();
+        if let Some(position) = closure.upvalues.iter().position(|other| other.index == upvalue.index && other.is_local == upvalue.is_local) {
+            return (upvalue, position as u8);
+        }
 
-        while index > 0 {
-            index -= 1;
-            
-            if last_closure.locals[index].name == constant_id {
-                if last_closure.locals[index].is_const {
-                    let name = self.ast.constant_pool.get_string(constant_id);
-                    self.error(CompilerErrorKind::AssignmentToConstant { name: name.to_string() }, start_index);
-                    return 0;
-                }
+        let length = closure.upvalues.len();
+        closure.upvalues.push(upvalue);
+        if length >= u8::MAX as usize {
+            self.error(CompilerErrorKind::TooManyUpvalues, self.current_statement_index);
+        }
+
+        (upvalue, length as u8)
+    }
+
+    pub fn declare(&mut self, constant_id: u32, is_constant: bool, start_index: usize) -> u8 {
+        if self.depth == 0 {
+            let name = self.ast.constant_pool.get_string(constant_id);
+            if self.builtins.contains(&&*name) {
+                self.error(CompilerErrorKind::BuiltinRedeclaration { name: name.to_string() }, start_index);
             }
         }
 
+        // A name declared in an enclosing block or function is shadowed until the
+        // end of the block, but a name cannot be declared twice in the same block.
+        // The locals of the block are the last ones as the inner ones are dropped.
+        let depth = self.depth;
+        let last_closure = self.closures.last_mut().unwrap();
+        let redeclared = last_closure.locals.iter()
+            .rev()
+            .take_while(|local| local.depth == depth)
+            .any(|local| local.name == constant_id);
+
+        if redeclared {
+            let name = self.ast.constant_pool.get_string(constant_id);
+            self.error(CompilerErrorKind::Redeclaration { name: name.to_string() }, start_index);
+            return 0;
+        }
+
+        let last_closure = self.closures.last_mut().unwrap();
+        let slot = last_closure.next_slot();
         last_closure.locals.push(Local {
             name: constant_id,
+            slot,
             depth: self.depth,
             is_const: is_constant,
             is_upvalue: false
         });
 
-        let max_slots = last_closure.max_slots;
-        if max_slots >= u8::MAX {
+        if slot >= u8::MAX {
             self.error(CompilerErrorKind::TooManyLocals, start_index);
         } else {
-            last_closure.max_slots += 1;
+            last_closure.max_slots = last_closure.max_slots.max(slot + 1);
         }
 
-        max_slots
+        slot
     }
 
     pub(crate) fn line_from_start_index(&self, start_index: u32) -> usize {
-        let mut current_index = 0;
-        let mut index = 0;
-
-        for line_length in self.line_data.iter() {
-            current_index += line_length;
-            index += 1;
-
-            if current_index >= start_index {
-                return index;
-            }
-        }
-
-        index
+        self.ast.source_map.lookup(start_index as usize).0
     }
 
-}
\ No newline at end of file
+}
+fn assign_op(op: AssignOp) -> u8 {
+    match op {
+        AssignOp::Assign => ASSIGN_OP,
+        AssignOp::Add => ASSIGN_OP_ADD,
+        AssignOp::Sub => ASSIGN_OP_SUB,
+        AssignOp::Rem => ASSIGN_OP_REM,
+        AssignOp::Merge => ASSIGN_OP_MERGE,
+        AssignOp::Nullish => ASSIGN_OP_NULLISH
+    }
+}
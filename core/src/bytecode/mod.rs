@@ -2,4 +2,5 @@ pub mod main;
 pub mod opcode;
 pub mod error;
 pub mod chunk;
+pub mod stats;
 pub mod fmt;
\ No newline at end of file
@@ -29,10 +29,13 @@ write_main_opcodes!(
     GET_GLOBAL = 5
     GET_UPVALUE = 6
     GET_ATTR = 7
+    SLICE = 59
     SET_LOCAL = 8
     SET_GLOBAL = 9
     SET_ATTR = 10
     SET_UPVALUE = 47
+    ASSIGN_ATTR = 52
+    COMPOUND_ASSIGN = 57
 
     // Opcodes for basic arithmetic operations
     ADD = 11
@@ -67,6 +70,7 @@ write_main_opcodes!(
     LTE = 30
 
     // Opcodes to perform unary operation
+    NEG = 31
     NOT = 32
 
     // --
@@ -79,18 +83,27 @@ write_main_opcodes!(
 
     // --
     RETURN = 38
+    SET_RESULT = 58
     ITER = 39
     ITER_NEXT = 40
+    ITER_ENTRIES = 53
+    ITER_NEXT_ENTRY = 54
 
     // Opcodes to change ip of the vm
     JUMP = 41
     JUMP_BACK = 42
     JUMP_IF = 43
     JUMP_NOT_IF = 44
+    JUMP_IF_NOT_NULL = 61
 
     // Other bytecodes
     IMPORT = 45
     CLOSE_UPVALUE = 46
+    DEFER = 60
+
+    // Opcodes to check the annotated types of functions
+    CHECK_TYPE = 55
+    CHECK_RETURN = 56
 
     // Dead bytecodes 
     POW = 80
@@ -101,6 +114,9 @@ write_opcodes!(
     ASSIGN_OP = 0
     ASSIGN_OP_ADD = 1
     ASSIGN_OP_SUB = 2
+    ASSIGN_OP_MERGE = 3
+    ASSIGN_OP_NULLISH = 4
+    ASSIGN_OP_REM = 5
 );
 
 // Long and short opcodes
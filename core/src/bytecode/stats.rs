@@ -0,0 +1,242 @@
+use std::fmt::{self, Display};
+use std::collections::BTreeMap;
+use super::opcode::{self, *};
+use crate::{BytecodeCompiler, Expr, Stmt};
+use crate::ast::constant_pool::{ANONYMOUS_CONSTANT, ERROR_CONSTANT};
+
+// Aggregated stats of the bytecode produced by the compiler which are printed by
+// the cli with `--compile-stats`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompileStats {
+    pub bytecode_len: usize,
+    pub string_constants: usize,
+    pub string_constant_bytes: usize,
+    pub string_loads: usize,
+    // The number of the references to each string constant in the bytecode, so
+    // the constants which are missing are never used such as the names of the
+    // code which has been eliminated.
+    pub string_uses: BTreeMap<u32, usize>,
+    pub unused_string_constants: usize,
+    pub unused_string_constant_bytes: usize,
+    pub opcodes: BTreeMap<u8, usize>,
+    pub functions: usize,
+    pub loops: usize,
+    pub conditions: usize,
+    pub max_depth: usize
+}
+
+impl BytecodeCompiler {
+
+    pub fn stats(&self) -> CompileStats {
+        let strings = &self.ast.constant_pool.strings;
+        let mut stats = CompileStats {
+            bytecode_len: self.bytes.len(),
+            string_constants: strings.len(),
+            string_constant_bytes: strings.iter().map(|string| string.len()).sum(),
+            ..Default::default()
+        };
+
+        stats.walk_bytes(&self.bytes);
+        stats.walk_statements(&self.ast.statements, 0);
+
+        // The names the vm refers to by their ids and the ones of the declarations
+        // are used without being in the bytecode.
+        for id in [ANONYMOUS_CONSTANT, ERROR_CONSTANT].iter().copied().chain(self.declarations.iter().map(|&(id, _)| id)) {
+            *stats.string_uses.entry(id).or_insert(0) += 1;
+        }
+
+        for (id, string) in strings.iter().enumerate() {
+            if !stats.string_uses.contains_key(&(id as u32)) {
+                stats.unused_string_constants += 1;
+                stats.unused_string_constant_bytes += string.len();
+            }
+        }
+
+        stats
+    }
+
+}
+
+impl CompileStats {
+
+    fn walk_bytes(&mut self, bytes: &[u8]) {
+        // The first byte is the number of slots of the script.
+        let mut ip = 1;
+        // The starting ips of the trailing bytes of the functions being walked.
+        let mut function_ends = Vec::new();
+
+        // The length of a constant id which is either [OP_SHORT, u8] or [OP_LONG, u32].
+        let auto_len = |at: usize| match bytes.get(at) {
+            Some(&OP_LONG) => 5,
+            _ => 2
+        };
+        let read_u32 = |at: usize| u32::from_le_bytes([0, 1, 2, 3].map(|index| bytes.get(at + index).copied().unwrap_or_default()));
+        let read_auto = |at: usize| match bytes.get(at) {
+            Some(&OP_LONG) => read_u32(at + 1),
+            _ => bytes.get(at + 1).copied().unwrap_or_default() as u32
+        };
+
+        while ip < bytes.len() {
+            // A function ends with [MAX_SLOTS, UPVALUES_LEN, IS_ASYNC, [IS_LOCAL, INDEX]..., NAME]
+            if function_ends.last() == Some(&ip) {
+                function_ends.pop();
+                let upvalues_len = bytes.get(ip + 1).copied().unwrap_or_default() as usize;
+                ip += 3 + upvalues_len * 2;
+                *self.string_uses.entry(read_auto(ip)).or_insert(0) += 1;
+                ip += auto_len(ip);
+
+                continue;
+            }
+
+            let byte = bytes[ip];
+            *self.opcodes.entry(byte).or_insert(0) += 1;
+
+            let string_id = match byte {
+                STRING => bytes.get(ip + 1).map(|&id| id as u32),
+                STRING_LONG | IMPORT => Some(read_u32(ip + 1)),
+                SET_GLOBAL | GET_GLOBAL => Some(read_auto(ip + 1)),
+                CHECK_TYPE => Some(read_auto(ip + 3)),
+                _ => None
+            };
+            if let Some(id) = string_id {
+                *self.string_uses.entry(id).or_insert(0) += 1;
+            }
+
+            ip += match byte {
+                STRING | STRING_LONG => {
+                    self.string_loads += 1;
+                    if byte == STRING { 2 } else { 5 }
+                },
+                INT | FLOAT | SET_LOCAL | SET_UPVALUE | GET_LOCAL | GET_UPVALUE | CALL | CALL_CHILD | ASSIGN_ATTR | COMPOUND_ASSIGN | CHECK_RETURN | CLOSE_UPVALUE => 2,
+                INT_LONG | FLOAT_LONG | IMPORT => 5,
+                SET_GLOBAL | GET_GLOBAL | ARRAY | DICT => 1 + auto_len(ip + 1),
+                JUMP | JUMP_BACK | JUMP_IF | JUMP_NOT_IF | JUMP_IF_NOT_NULL => 3,
+                ITER_NEXT => 4,
+                ITER_NEXT_ENTRY => 5,
+                CHECK_TYPE => 3 + auto_len(ip + 3),
+                FUNC => {
+                    let offset = u16::from_le_bytes([
+                        bytes.get(ip + 1).copied().unwrap_or_default(),
+                        bytes.get(ip + 2).copied().unwrap_or_default()
+                    ]);
+
+                    function_ends.push(ip + 3 + offset as usize);
+                    3
+                },
+                _ => 1
+            };
+        }
+    }
+
+    fn walk_statements(&mut self, statements: &[Stmt], depth: usize) {
+        self.max_depth = self.max_depth.max(depth);
+        for statement in statements {
+            self.walk_expr(&statement.expr, depth);
+        }
+    }
+
+    fn walk_expr(&mut self, expr: &Expr, depth: usize) {
+        match expr {
+            Expr::Ternary(condition, truthy, falsy) => {
+                self.conditions += 1;
+                self.walk_expr(condition, depth);
+                self.walk_expr(truthy, depth);
+                self.walk_expr(falsy, depth);
+            },
+            Expr::Attribute(target, attr) => {
+                self.walk_expr(target, depth);
+                self.walk_expr(attr, depth);
+            },
+            Expr::Slice { target, start, end, step } => {
+                self.walk_expr(target, depth);
+                for bound in [start, end, step].iter().copied().flatten() {
+                    self.walk_expr(bound, depth);
+                }
+            },
+            Expr::Call(target, params) => {
+                self.walk_expr(target, depth);
+                for param in params {
+                    self.walk_expr(param, depth);
+                }
+            },
+            Expr::Array(items) => {
+                for item in items {
+                    self.walk_expr(item, depth);
+                }
+            },
+            Expr::Dict(entries) => {
+                for (_, value) in entries {
+                    self.walk_expr(value, depth);
+                }
+            },
+            Expr::Group(expr) | Expr::Not(expr) | Expr::Negate(expr) | Expr::Await(expr) | Expr::Return(expr) | Expr::Defer(expr) | Expr::Store(_, expr, _) | Expr::Positioned(expr, _) => {
+                self.walk_expr(expr, depth);
+            },
+            Expr::BinaryOperation { lhs, rhs, .. } => {
+                self.walk_expr(lhs, depth);
+                self.walk_expr(rhs, depth);
+            },
+            Expr::Assign { target, value, .. } => {
+                self.walk_expr(target, depth);
+                self.walk_expr(value, depth);
+            },
+            Expr::Function { inner, .. } => {
+                self.functions += 1;
+                self.walk_statements(inner, depth + 1);
+            },
+            Expr::While(condition, inner, else_branch) => {
+                self.loops += 1;
+                self.walk_expr(condition, depth);
+                self.walk_statements(inner, depth + 1);
+
+                if let Some(inner) = else_branch {
+                    self.walk_statements(inner, depth + 1);
+                }
+            },
+            Expr::For { in_, inner, else_branch, .. } => {
+                self.loops += 1;
+                self.walk_expr(in_, depth);
+                self.walk_statements(inner, depth + 1);
+
+                if let Some(inner) = else_branch {
+                    self.walk_statements(inner, depth + 1);
+                }
+            },
+            Expr::Break(Some(value)) => self.walk_expr(value, depth),
+            Expr::If { branches, else_branch } | Expr::IfValue { branches, else_branch } => {
+                for (condition, inner, _) in branches {
+                    self.conditions += 1;
+                    self.walk_expr(condition, depth);
+                    self.walk_statements(inner, depth + 1);
+                }
+
+                if let Some(inner) = else_branch {
+                    self.walk_statements(inner, depth + 1);
+                }
+            },
+            Expr::Try { try_inner, expect_inner } => {
+                self.walk_statements(try_inner, depth + 1);
+                self.walk_statements(expect_inner, depth + 1);
+            },
+            _ => ()
+        }
+    }
+
+}
+
+impl Display for CompileStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Bytecode length: {} bytes", self.bytecode_len)?;
+        writeln!(f, "String constants: {} ({} bytes, loaded {} times)", self.string_constants, self.string_constant_bytes, self.string_loads)?;
+        writeln!(f, "Unused string constants: {} ({} bytes)", self.unused_string_constants, self.unused_string_constant_bytes)?;
+        writeln!(f, "Functions: {}, loops: {}, conditions: {}", self.functions, self.loops, self.conditions)?;
+        writeln!(f, "Deepest nesting level: {}", self.max_depth)?;
+        writeln!(f, "Opcodes:")?;
+
+        for (&byte, count) in &self.opcodes {
+            writeln!(f, "    {:<16} {}", opcode::to_string(byte), count)?;
+        }
+
+        Ok(())
+    }
+}
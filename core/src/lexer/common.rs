@@ -17,6 +17,8 @@ impl Lexer {
             } else if character == '\\' {
                 content += &Self::escape_char(self.chars[self.index + 1]).to_string();
                 self.index += 1;
+            } else if character == '\r' && self.chars.get(self.index + 1) == Some(&'\n') {
+                // Line breaks in string literals are always normalized to "\n".
             } else {
                 content += &character.to_string();
             }
@@ -24,9 +26,14 @@ impl Lexer {
             self.index += 1;
         }
 
+        // The error points from the opening quote to the end of its line as the
+        // string was most likely meant to end there.
+        let quote_index = start.start as usize - 1;
+        let line_end = self.chars[quote_index..].iter().position(|&character| matches!(character, '\n' | '\r')).map_or(self.chars.len(), |end| quote_index + end);
+
         Token {
-            kind: TokenKind::Error(LexerErrorKind::UnterminatedString),
-            position: start.update(self)
+            kind: TokenKind::Error(LexerErrorKind::UnterminatedString { quote: pref }),
+            position: Position::from((quote_index as u32, line_end as u32))
         }
     }
 
@@ -71,59 +78,47 @@ impl Lexer {
         }
     }
 
+    // The operators of two characters go first. Any other pair such as `=-` in
+    // `x=-1` is an operator of one character followed by the next one.
     pub fn parse_op(&mut self, character: char) -> Token {
         let mut start = Position::new(self);
 
-        match self.chars.get(self.index + 1) {
-            Some(&next_character) => {
-                match next_character {
-                    '+' | '-' | '=' | '/' | '*' | '^' | '|' | '&' | '<' | '>' => {
-                        self.index += 1;
+        let pair = self.chars.get(self.index + 1).and_then(|&next_character| match (character, next_character) {
+            ('=', '=') => Some(TokenKind::Equal),
+            ('!', '=') => Some(TokenKind::NotEqual),
+            ('>', '=') => Some(TokenKind::GreaterThanOrEqual),
+            ('<', '=') => Some(TokenKind::LessThanOrEqual),
+            ('&', '&') => Some(TokenKind::And),
+            ('|', '|') => Some(TokenKind::Or),
+            ('+', '=') => Some(TokenKind::AssignAdd),
+            ('-', '=') => Some(TokenKind::AssignSub),
+            ('|', '=') => Some(TokenKind::AssignMerge),
+            ('-', '>') => Some(TokenKind::Arrow),
+            ('<', '<') => Some(TokenKind::Shl),
+            ('>', '>') => Some(TokenKind::Shr),
+            _ => None
+        });
+
+        if let Some(kind) = pair {
+            self.index += 1;
+            return Token { kind, position: start.update(self) };
+        }
 
-                        Token {
-                            kind: match std::str::from_utf8(&[character as u8, next_character as u8]).unwrap() {
-                                "==" => TokenKind::Equal,
-                                "!=" => TokenKind::NotEqual,
-                                ">=" => TokenKind::GreaterThanOrEqual,
-                                "<=" => TokenKind::LessThanOrEqual,
-                                "&&" => TokenKind::And,
-                                "||" => TokenKind::Or,
-                                "+=" => TokenKind::AssignAdd,
-                                "-=" => TokenKind::AssignSub,
-                                "<<" => TokenKind::Shl,
-                                ">>" => TokenKind::Shr,
-                                _ => TokenKind::Error(LexerErrorKind::UnexpectedCharacter { character: next_character })
-                            },
-                            position: start.update(self)
-                        }
-                    },
-                    _ => {
-                        Token {
-                            kind: match character {
-                                '-' => {
-                                    if next_character.is_numeric() {
-                                        self.index += 2;
-                                        return self.parse_number("-".to_string() + &next_character.to_string());
-                                    } else { TokenKind::Sub }
-                                }
-                                '+' => TokenKind::Add,
-                                '=' => TokenKind::Assign,
-                                '!' => TokenKind::Not,
-                                '>' => TokenKind::GreaterThan,
-                                '<' => TokenKind::LessThan,
-                                '|' => TokenKind::BitOr,
-                                '&' => TokenKind::BitAnd,
-                                _ => TokenKind::Error(LexerErrorKind::UnexpectedCharacter { character })
-                            },
-                            position: start.update(self)
-                        }
-                    }
-                }
+        Token {
+            kind: match character {
+                // The negative numbers are negations read by the parser so that
+                // `10-5` stays a subtraction.
+                '-' => TokenKind::Sub,
+                '+' => TokenKind::Add,
+                '=' => TokenKind::Assign,
+                '!' => TokenKind::Not,
+                '>' => TokenKind::GreaterThan,
+                '<' => TokenKind::LessThan,
+                '|' => TokenKind::BitOr,
+                '&' => TokenKind::BitAnd,
+                _ => TokenKind::Error(LexerErrorKind::UnexpectedCharacter { character })
             },
-            None => Token {
-                kind: TokenKind::Error(LexerErrorKind::UnexpectedCharacter { character }),
-                position: start.update(self)
-            }
+            position: start.update(self)
         }
     }
 
@@ -1,4 +1,5 @@
 pub mod parser;
 pub mod util;
 pub mod common;
-pub mod types;
\ No newline at end of file
+pub mod types;
+pub mod source_map;
\ No newline at end of file
@@ -1,4 +1,5 @@
-use crate::{Position, Token, TokenKind};
+use std::rc::Rc;
+use crate::{Position, Token, TokenKind, SourceMap};
 
 #[derive(Clone, Default, Debug)]
 pub struct Lexer {
@@ -6,6 +7,13 @@ pub struct Lexer {
     pub filename: String,
     pub body: String,
     pub chars: Vec<char>,
+    // The depth of the curly braces read so far which is used by the parser to
+    // recover from syntax errors.
+    pub brace_depth: isize,
+    // The brackets which have been opened but not closed yet with the index of
+    // their char, so that the parser can name the one which is never closed.
+    pub delimiters: Vec<(char, u32)>,
+    pub source_map: Rc<SourceMap>,
     line: usize,
     last_line_index: usize,
 }
@@ -18,6 +26,7 @@ impl Lexer {
             filename: filename.to_owned(),
             body: body.to_owned(),
             chars,
+            source_map: Rc::new(SourceMap::new(filename, body)),
             line: 1,
             ..Default::default()
         }
@@ -42,6 +51,46 @@ impl Lexer {
         character
     }
 
+    // Pops the bracket which the closing one closes. The brackets left open inside
+    // of it are dropped as the parser reports them where they should have been
+    // closed. A closing bracket without an opening one is an error.
+    fn close_delimiter(&mut self, closer: char) -> Option<Token> {
+        let opener = match closer {
+            ')' => '(',
+            ']' => '[',
+            _ => '{'
+        };
+
+        match self.delimiters.iter().rposition(|&(character, _)| character == opener) {
+            Some(index) => {
+                self.delimiters.truncate(index);
+                None
+            },
+            None => {
+                let mut position = Position::new(self);
+                self.index += 1;
+                Some(Token {
+                    kind: TokenKind::Error(LexerErrorKind::UnmatchedDelimiter { character: closer }),
+                    position: position.update(self)
+                })
+            }
+        }
+    }
+
+}
+
+// Whether the source ends inside of a string or with a bracket which is not closed
+// yet, such as the first line of a function typed into a prompt which should then
+// read the next lines before the source is executed.
+pub fn is_incomplete(source: &str) -> bool {
+    let mut lexer = Lexer::new("", source);
+    for token in lexer.by_ref() {
+        if let TokenKind::Error(LexerErrorKind::UnterminatedString { .. }) = token.kind {
+            return true;
+        }
+    }
+
+    !lexer.delimiters.is_empty()
 }
 
 impl Iterator for Lexer {
@@ -50,10 +99,11 @@ impl Iterator for Lexer {
     fn next(&mut self) -> Option<Self::Item> {
         macro_rules! kind {
             ($kind:ident) => {{
+                let mut position = Position::new(self);
                 self.index += 1;
                 return Some(Token {
                     kind: TokenKind::$kind,
-                    position: Position::new(self)
+                    position: position.update(self)
                 });
             }};
         }
@@ -89,20 +139,60 @@ impl Iterator for Lexer {
                         self.next_line();
                         return self.next();
                     },
+                    '*' if self.chars.get(self.index + 1) == Some(&'*') => {
+                        let mut position = Position::new(self);
+                        self.index += 2;
+                        return Some(Token { kind: TokenKind::Pow, position: position.update(self) });
+                    },
                     '*' => kind!(Mul),
                     '/' => kind!(Div),
                     '^' => kind!(BitXor),
                     ':' => kind!(Colon),
+                    '%' if self.chars.get(self.index + 1) == Some(&'=') => {
+                        let mut position = Position::new(self);
+                        self.index += 2;
+                        return Some(Token { kind: TokenKind::AssignRem, position: position.update(self) });
+                    },
                     '%' => kind!(Rem),
                     ';' => kind!(Semicolon),
                     '.' => kind!(Dot),
                     ',' => kind!(Comma),
-                    '(' => kind!(ParenOpen),
-                    ')' => kind!(ParenClose),
-                    '[' => kind!(SqBraceOpen),
-                    ']' => kind!(SqBraceClose),
-                    '{' => kind!(CurlyBraceOpen), 
-                    '}' => kind!(CurlyBraceClose),
+                    '(' | '[' | '{' => {
+                        self.delimiters.push((character, self.index as u32));
+                        match character {
+                            '(' => kind!(ParenOpen),
+                            '[' => kind!(SqBraceOpen),
+                            _ => {
+                                self.brace_depth += 1;
+                                kind!(CurlyBraceOpen)
+                            }
+                        }
+                    },
+                    ')' | ']' | '}' => {
+                        if let Some(error) = self.close_delimiter(character) {
+                            return Some(error);
+                        }
+
+                        match character {
+                            ')' => kind!(ParenClose),
+                            ']' => kind!(SqBraceClose),
+                            _ => {
+                                self.brace_depth -= 1;
+                                kind!(CurlyBraceClose)
+                            }
+                        }
+                    },
+                    // `??` and `??=` before the `?` of the ternaries.
+                    '?' if self.chars.get(self.index + 1) == Some(&'?') => {
+                        let mut position = Position::new(self);
+                        let (kind, len) = match self.chars.get(self.index + 2) {
+                            Some('=') => (TokenKind::AssignNullish, 3),
+                            _ => (TokenKind::Nullish, 2)
+                        };
+
+                        self.index += len;
+                        return Some(Token { kind, position: position.update(self) });
+                    },
                     '?' => kind!(Question),
                     '#' => {
                         self.parse_comment();
@@ -126,7 +216,8 @@ impl Iterator for Lexer {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LexerErrorKind {
     UnexpectedCharacter { character: char },
-    UnterminatedString,
+    UnterminatedString { quote: char },
+    UnmatchedDelimiter { character: char },
     ImproperEExpression
 }
 
@@ -134,7 +225,8 @@ impl ToString for LexerErrorKind {
     fn to_string(&self) -> String {
         match self {
             Self::UnexpectedCharacter { character } => format!("Character {} was never expected.", character),
-            Self::UnterminatedString => "Found an unterminated string.".to_owned(),
+            Self::UnterminatedString { quote } => format!("Found an unterminated string which is never closed by a {}. Its line ends after the marked text.", quote),
+            Self::UnmatchedDelimiter { character } => format!("Found a \"{}\" which does not close anything.", character),
             Self::ImproperEExpression => "Improper 'e' expression.".to_owned()
         }
     }
@@ -0,0 +1,124 @@
+// The lines of a script, which turn the positions of its tokens into lines and
+// columns. The positions are indexes of chars, and a "\r" before a "\n" is the
+// last char of its line so that both line endings give the same columns. It is
+// built once per script and shared by the lexer, the compiler and the chunk
+// executed by the vm.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    name: String,
+    // The indexes of the char and of the byte which start each line.
+    lines: Vec<(u32, u32)>,
+    // The number of chars counting a "\n" after the last line.
+    len: u32,
+    source: Option<String>
+}
+
+impl SourceMap {
+
+    pub fn new(name: &str, source: &str) -> Self {
+        let mut lines = Vec::new();
+        let (mut char_start, mut byte_start) = (0, 0);
+
+        for line in source.split('\n') {
+            lines.push((char_start, byte_start));
+            char_start += line.chars().count() as u32 + 1;
+            byte_start += line.len() as u32 + 1;
+        }
+
+        Self { name: name.to_owned(), lines, len: char_start, source: Some(source.to_owned()) }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    // The line and the column of the char at the offset, both counted from 1. An
+    // offset past the end of the script is on the last line with a column of 0.
+    pub fn lookup(&self, offset: usize) -> (usize, usize) {
+        let offset = offset as u32;
+        if offset >= self.len {
+            return (self.lines.len(), 0);
+        }
+
+        let line = self.lines.partition_point(|&(start, _)| start <= offset);
+        (line, (offset - self.lines[line - 1].0) as usize + 1)
+    }
+
+    // The line of the char at the offset, which is unknown without any lines.
+    pub fn line(&self, offset: usize) -> Option<u32> {
+        match self.is_empty() {
+            true => None,
+            false => Some(self.lookup(offset).0 as u32)
+        }
+    }
+
+    // The chars between the offsets, which are empty when they are not all in the
+    // script or when the text of the script is not kept.
+    pub fn snippet(&self, start: usize, end: usize) -> String {
+        let source = match &self.source {
+            Some(source) if start <= end && end < self.len as usize => source,
+            _ => return String::new()
+        };
+
+        let (line, _) = self.lookup(start);
+        let (char_start, byte_start) = self.lines[line - 1];
+        source[byte_start as usize..].chars()
+            .skip(start - char_start as usize)
+            .take(end - start)
+            .collect()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::SourceMap;
+
+    #[test]
+    fn lookup_counts_chars_and_lines_from_one() {
+        let map = SourceMap::new("test.ds", "ab\n\u{e9}\u{e9}x\n\nlast");
+
+        assert_eq!(map.lookup(0), (1, 1));
+        assert_eq!(map.lookup(2), (1, 3));
+        assert_eq!(map.lookup(3), (2, 1));
+        assert_eq!(map.lookup(5), (2, 3));
+        assert_eq!(map.lookup(7), (3, 1));
+        assert_eq!(map.lookup(11), (4, 4));
+        assert_eq!(map.lookup(12), (4, 5));
+        assert_eq!(map.lookup(13), (4, 0));
+        assert_eq!(map.lookup(100), (4, 0));
+    }
+
+    #[test]
+    fn carriage_returns_end_their_line() {
+        let crlf = SourceMap::new("test.ds", "a\r\nbc\r\n");
+        let lf = SourceMap::new("test.ds", "a\nbc\n");
+
+        assert_eq!(crlf.lookup(1), (1, 2));
+        assert_eq!(crlf.lookup(3), (2, 1));
+        assert_eq!(crlf.lookup(4), lf.lookup(3));
+        assert_eq!(crlf.snippet(3, 5), "bc");
+    }
+
+    #[test]
+    fn snippets_are_empty_outside_of_the_script() {
+        let map = SourceMap::new("test.ds", "let \u{e9} = 1;\nlet b = ;");
+
+        assert_eq!(map.snippet(4, 5), "\u{e9}");
+        assert_eq!(map.snippet(11, 16), "let b");
+        assert_eq!(map.snippet(5, 4), "");
+        assert_eq!(map.snippet(11, 100), "");
+        assert_eq!(SourceMap::default().snippet(0, 0), "");
+    }
+
+    #[test]
+    fn maps_without_lines_have_no_line() {
+        assert_eq!(SourceMap::default().line(3), None);
+        assert_eq!(SourceMap::new("test.ds", "a\nb").line(2), Some(2));
+    }
+}
@@ -55,7 +55,8 @@ pub enum Keyword {
     Import,
     As,
     Try,
-    Expect
+    Expect,
+    Defer
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -90,10 +91,15 @@ pub enum TokenKind {
     LessThanOrEqual,
     And,
     Or,
+    Nullish,
     Assign,
     AssignAdd,
     AssignSub,
+    AssignRem,
+    AssignMerge,
+    AssignNullish,
     Colon,
+    Arrow,
     Semicolon,
     Dot,
     Comma,
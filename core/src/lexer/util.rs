@@ -39,6 +39,7 @@ impl Lexer {
             "break" => TokenKind::Keyword(Keyword::Break),
             "while" => TokenKind::Keyword(Keyword::While),
             "continue" => TokenKind::Keyword(Keyword::Continue),
+            "defer" => TokenKind::Keyword(Keyword::Defer),
             "let" => TokenKind::Keyword(Keyword::Let),
             "const" => TokenKind::Keyword(Keyword::Const),
             "func" => TokenKind::Keyword(Keyword::Func),
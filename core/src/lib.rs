@@ -6,14 +6,16 @@ pub mod tiny_string;
 
 pub use lexer::parser::*;
 pub use lexer::types::*;
+pub use lexer::source_map::SourceMap;
 pub use ast::main::{AST, ASTError, ASTBuild};
 pub use ast::constant_pool::ConstantPool;
 pub use ast::types::*;
 pub use bytecode::error::*;
 pub use bytecode::opcode;
 pub use bytecode::chunk::Chunk;
-pub use bytecode::main::{BytecodeCompiler, FunctionFlags};
-pub use runtime::vm::{Vm, Permissions};
+pub use bytecode::stats::CompileStats;
+pub use bytecode::main::{BytecodeCompiler, CompileOptions, FunctionFlags};
+pub use runtime::vm::{Vm, VmBuilder, VmReader, VmWriter, CancellationToken, Permissions, Permission, Feature};
 pub use runtime::value::*;
 pub use runtime::upvalue::*;
 pub use runtime::object::*;
@@ -21,5 +23,6 @@ pub use runtime::error::*;
 pub use runtime::iterator::ValueIter;
 pub use runtime::memory::{GcHeader, ValuePtr};
 pub use runtime::core;
+pub use runtime::core::json::JsonError;
 pub use runtime::resources::*;
 pub use tiny_string::TinyString;
\ No newline at end of file
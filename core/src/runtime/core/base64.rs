@@ -557,11 +557,13 @@ fn decode_base64_buf(bytes: &[u8], output: &mut Vec<u8>) -> Option<DecoderError>
     
             if let Some(max_start_index) = length_of_fast_decode_chunks.checked_sub(INPUT_CHUNK_LEN) {
                 while input_index < max_start_index {
-                    decode_chunk(
+                    if let Some(error) = decode_chunk(
                         &bytes[input_index..(input_index + INPUT_CHUNK_LEN)],
                         input_index,
                         &mut output[output_index..(output_index + DECODED_CHUNK_LEN + DECODED_CHUNK_SUFFIX)],
-                    )?;
+                    ) {
+                        return Some(error);
+                    }
     
                     output_index += DECODED_CHUNK_LEN;
                     input_index += INPUT_CHUNK_LEN;
@@ -586,8 +588,6 @@ fn decode_base64_buf(bytes: &[u8], output: &mut Vec<u8>) -> Option<DecoderError>
         debug_assert!(bytes.len() - input_index > 1 || bytes.is_empty());
         debug_assert!(bytes.len() - input_index <= 8);
 
-        println!("{:?}", std::str::from_utf8(output));
-
         let mut leftover_bits = 0_u64;
         let mut morsels_in_leftover = 0;
         let mut padding_bytes = 0;
@@ -678,7 +678,7 @@ fn decode_chunk(bytes: &[u8], start_of_input: usize, output: &mut [u8]) -> Optio
         accum |= (morsel as u64) << CHUNK_DECODE[i];
     }
 
-    output[..8].copy_from_slice(&accum.to_le_bytes());
+    output[..8].copy_from_slice(&accum.to_be_bytes());
 
     None
 }
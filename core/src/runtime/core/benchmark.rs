@@ -0,0 +1,80 @@
+// The harness of `window.benchmark`. The functions which run faster than the
+// resolution worth measuring are called in batches and each sample is the time of
+// a batch divided by its size.
+
+use std::time::{Duration, Instant};
+use crate::{Vm, Value, RuntimeResult};
+
+// The shortest time a sample is expected to take.
+const MIN_SAMPLE: Duration = Duration::from_micros(100);
+const MAX_BATCH: u32 = 1_000_000;
+
+pub struct Report {
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    pub stdev: f64,
+    pub total_ms: f64,
+    pub iterations: usize,
+    pub batch: u32
+}
+
+// Returns the error of the function as soon as it is thrown.
+pub fn run(vm: &mut Vm, function: Value, iterations: usize, warmup: usize) -> RuntimeResult<Report> {
+    for _ in 0..warmup {
+        vm.call_function_with_returned_value(function, 0)?;
+    }
+
+    let single = time(vm, function, 1)?;
+    let batch = match single.as_nanos() {
+        0 => MAX_BATCH,
+        nanos => (MIN_SAMPLE.as_nanos() / nanos).clamp(1, MAX_BATCH as u128) as u32
+    };
+
+    let mut samples = Vec::with_capacity(iterations);
+    let mut total = Duration::ZERO;
+
+    for _ in 0..iterations {
+        let elapsed = time(vm, function, batch)?;
+        total += elapsed;
+        samples.push(elapsed.as_secs_f64() * 1000.0 / batch as f64);
+    }
+
+    Ok(report(samples, total, batch))
+}
+
+fn time(vm: &mut Vm, function: Value, batch: u32) -> RuntimeResult<Duration> {
+    let start = Instant::now();
+    for _ in 0..batch {
+        vm.call_function_with_returned_value(function, 0)?;
+    }
+
+    Ok(start.elapsed())
+}
+
+// The times are in milliseconds per call.
+fn report(mut samples: Vec<f64>, total: Duration, batch: u32) -> Report {
+    samples.sort_by(|a, b| a.total_cmp(b));
+
+    let iterations = samples.len();
+    let count = iterations.max(1) as f64;
+    let mean = samples.iter().sum::<f64>() / count;
+    let variance = samples.iter().map(|sample| (sample - mean).powi(2)).sum::<f64>() / count;
+    let median = match iterations {
+        0 => 0.0,
+        len if len % 2 == 0 => (samples[len / 2 - 1] + samples[len / 2]) / 2.0,
+        len => samples[len / 2]
+    };
+
+    Report {
+        mean,
+        median,
+        min: samples.first().copied().unwrap_or_default(),
+        max: samples.last().copied().unwrap_or_default(),
+        stdev: variance.sqrt(),
+        total_ms: total.as_secs_f64() * 1000.0,
+        iterations,
+        batch
+    }
+}
@@ -1,46 +1,106 @@
-use std::time::{SystemTime, UNIX_EPOCH};
-use crate::{Vm, Value, TinyString, Map, RuntimeError, Instance};
+use std::time::UNIX_EPOCH;
+use std::cmp::Ordering;
+use std::path::Path;
+use crate::{Vm, Value, TinyString, Map, RuntimeError, RuntimeResult, Instance};
+use crate::runtime::vm::RefSlot;
 use super::map_builder::{MapBuilder, ClassBuilder};
 use super::date::{self, UNIX_EPOCH_DATE};
+use super::format;
+
+// Collects the numbers of the array passed as the first argument of the statistics
+// functions of `Math`. Non numbers are skipped with `{ ignoreNonNumbers: true }`.
+// The first of the greatest or of the least values in the order of `Value::total_cmp`.
+fn extreme_of(vm: &Vm, name: &str, args: &[Value], wanted: Ordering) -> RuntimeResult<Value> {
+    let values = match args {
+        [Value::Array(array)] if array.unwrap_ref().is_empty() => {
+            return Err(RuntimeError::new(vm, format!("[Math.{}]: Expected a non-empty array.", name)));
+        },
+        [Value::Array(array)] => array.unwrap_ref(),
+        [] => return Ok(Value::Null),
+        _ => args
+    };
 
-fn random_isize() -> isize {
-    let mut random: isize = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as isize;
-
-    let mut rand = || {
-        random ^= random << 13;
-        random ^= random >> 17;
-        random ^= random << 5;
-        random
+    let mut extreme = values[0];
+    for value in &values[1..] {
+        match value.total_cmp(&extreme, false) {
+            Some(ordering) if ordering == wanted => extreme = *value,
+            Some(_) => (),
+            None => return Err(RuntimeError::new(vm, format!("[Math.{}]: Cannot compare {} with {}.", name, extreme.get_type(), value.get_type())))
+        }
+    }
+
+    Ok(extreme)
+}
+
+fn numbers_of(vm: &Vm, name: &str, args: &[Value]) -> Result<Vec<Value>, RuntimeError> {
+    let array = match args.first() {
+        Some(Value::Array(array)) => array.unwrap_ref(),
+        _ => return Err(RuntimeError::new_arguments(vm, &format!("Math.{}", name), "array, object?", args))
     };
-        
-    if std::mem::size_of::<isize>() <= 4 {
-        rand()
-    } else {
-        (((rand() as i64) << 32) | (rand() as i64)) as isize
+
+    let ignore_non_numbers = math_option(args.get(1), "ignoreNonNumbers");
+    let mut numbers = Vec::with_capacity(array.len());
+
+    for value in array.iter() {
+        match value {
+            Value::Int(_) | Value::Float(_) => numbers.push(*value),
+            _ if ignore_non_numbers => (),
+            _ => return Err(RuntimeError::new(vm, format!("[Math.{}]: Expected an array of numbers but found {}.", name, value.get_type())))
+        }
     }
+
+    if numbers.is_empty() {
+        return Err(RuntimeError::new(vm, format!("[Math.{}]: Expected a non-empty array of numbers.", name)));
+    }
+
+    Ok(numbers)
 }
 
-fn random_usize() -> usize {
-    let mut random: usize = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis() as usize;
-
-    let mut rand = || {
-        random ^= random << 13;
-        random ^= random >> 17;
-        random ^= random << 5;
-        random
+fn math_option(options: Option<&Value>, name: &str) -> bool {
+    match options {
+        Some(Value::Dict(map)) => map.unwrap_ref().iter().any(|(key, (value, _))| {
+            matches!(key, Value::String(key) if key.unwrap_ref() as &str == name) && value.to_bool()
+        }),
+        _ => false
+    }
+}
+
+fn mean(numbers: &[Value]) -> f64 {
+    numbers.iter().map(Value::to_f64).sum::<f64>() / numbers.len() as f64
+}
+
+// The greatest integer which the floats hold exactly along with all the integers
+// below it, 2^53 - 1.
+const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+fn safe_integer(value: &Value) -> Option<i64> {
+    let integer = match *value {
+        Value::Int(int) => int as i64,
+        Value::Float(float) if float.fract() == 0.0 && float.abs() <= MAX_SAFE_INTEGER as f64 => float as i64,
+        _ => return None
     };
-        
-    if std::mem::size_of::<usize>() <= 4 {
-        rand()
-    } else {
-        (((rand() as u64) << 32) | (rand() as u64)) as usize
+
+    (integer.abs() <= MAX_SAFE_INTEGER).then_some(integer)
+}
+
+// The integer arguments of `gcd`, `lcm` and `factorial`, which also accept the
+// integral floats.
+fn safe_integer_of(vm: &Vm, name: &str, index: usize, value: &Value) -> RuntimeResult<i64> {
+    if !value.is_number() {
+        return Err(RuntimeError::new_argument(vm, &format!("Math.{}", name), index, "int", Some(value)));
     }
+
+    safe_integer(value).ok_or_else(|| RuntimeError::new(vm, format!(
+        "[Math.{}]: Expected an integer between -2^53 and 2^53 exclusive but found {}.", name, value.to_tiny_string()
+    )))
+}
+
+fn gcd(mut a: i64, mut b: i64) -> i64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+
+    a.abs()
 }
 
 pub fn init_math(vm: &mut Vm) -> Value {
@@ -79,7 +139,7 @@ pub fn init_math(vm: &mut Vm) -> Value {
 
         (float2 $name:expr, $method:ident) => {
             math.native_fn($name, |_, args| Ok(
-                match args.get(0..1) {
+                match args.get(0..2) {
                     Some([Value::Float(float), num]) => {
                         let num = match num {
                             Value::Int(int) => *int as f64,
@@ -130,23 +190,190 @@ pub fn init_math(vm: &mut Vm) -> Value {
     add_method_based_native_fn!(float2 "log", log);
     add_method_based_native_fn!(float2 "atan2", atan2);
 
-    math.native_fn("max", |_, args| Ok(
-        match args.iter().max() {
-            Some(value) => value.clone(),
-            None => Value::Null
+    // Both `max(1, 2)` and `max([1, 2])` are accepted.
+    math.native_fn("max", |vm, args| extreme_of(vm, "max", args, Ordering::Greater));
+    math.native_fn("min", |vm, args| extreme_of(vm, "min", args, Ordering::Less));
+
+    math.native_fn("sum", |vm, args| {
+        let numbers = numbers_of(vm, "sum", args)?;
+
+        // The sum stays an integer as long as all the numbers are integers.
+        let mut int_sum: Option<isize> = Some(0);
+        for number in numbers.iter() {
+            int_sum = match (int_sum, number) {
+                (Some(sum), Value::Int(int)) => sum.checked_add(*int),
+                _ => None
+            };
+        }
+
+        Ok(match int_sum {
+            Some(sum) => Value::Int(sum),
+            None => Value::Float(numbers.iter().map(Value::to_f64).sum())
+        })
+    });
+
+    math.native_fn("mean", |vm, args| {
+        let numbers = numbers_of(vm, "mean", args)?;
+        Ok(Value::Float(mean(&numbers)))
+    });
+
+    math.native_fn("median", |vm, args| {
+        let mut numbers = numbers_of(vm, "median", args)?;
+        numbers.sort_by(|a, b| a.total_cmp(b, false).unwrap_or(Ordering::Equal));
+        let numbers: Vec<f64> = numbers.iter().map(Value::to_f64).collect();
+
+        let middle = numbers.len() / 2;
+        Ok(Value::Float(
+            if numbers.len() % 2 == 0 {
+                (numbers[middle - 1] + numbers[middle]) / 2.0
+            } else {
+                numbers[middle]
+            }
+        ))
+    });
+
+    // The population standard deviation is returned unless `{ sample: true }`
+    // is passed as the options.
+    math.native_fn("stdev", |vm, args| {
+        let numbers = numbers_of(vm, "stdev", args)?;
+        let sample = math_option(args.get(1), "sample");
+
+        if sample && numbers.len() < 2 {
+            return Err(RuntimeError::new(vm, "[Math.stdev]: Expected at least 2 numbers for the sample standard deviation."));
         }
-    ));
 
-    math.native_fn("min", |_, args| Ok(
-        match args.iter().min() {
-            Some(value) => value.clone(),
-            None => Value::Null
+        let mean = mean(&numbers);
+        let squares: f64 = numbers.iter().map(|number| (number.to_f64() - mean).powi(2)).sum();
+        let len = if sample { numbers.len() - 1 } else { numbers.len() };
+
+        Ok(Value::Float((squares / len as f64).sqrt()))
+    });
+
+    // Whether the numbers differ by at most the epsilon times the larger of them,
+    // or by at most the epsilon itself so that the numbers near zero can be equal.
+    math.native_fn("approxEqual", |vm, args| {
+        const EPSILON: f64 = 1e-9;
+
+        let (a, b, epsilon) = match args {
+            [a, b] if a.is_number() && b.is_number() => (a.to_f64(), b.to_f64(), EPSILON),
+            [a, b, epsilon] if a.is_number() && b.is_number() && epsilon.is_number() => (a.to_f64(), b.to_f64(), epsilon.to_f64()),
+            _ => return Err(RuntimeError::new_arguments(vm, "Math.approxEqual", "number, number, number?", args))
+        };
+
+        // The infinities are only equal to themselves.
+        if a == b {
+            return Ok(Value::Bool(true));
         }
-    ));
 
-    math.native_fn("random", |_, _| Ok(Value::Float(random_usize() as f64 / usize::MAX as f64)));
-    math.native_fn("randomUint", |_, _| Ok(Value::Int(random_usize() as isize)));
-    math.native_fn("randomInt", |_, _| Ok(Value::Int(random_isize())));
+        let difference = (a - b).abs();
+        Ok(Value::Bool(difference <= epsilon || difference <= epsilon * a.abs().max(b.abs())))
+    });
+
+    // Rounds half away from zero on the shortest representation of the number like
+    // `format.number`, so `roundTo(1.005, 2)` is 1.01.
+    math.native_fn("roundTo", |vm, args| {
+        let (number, decimals) = match args {
+            [number, Value::Int(decimals)] if number.is_number() && (0..=100).contains(decimals) => (number.to_f64(), *decimals as usize),
+            _ => return Err(RuntimeError::new_arguments(vm, "Math.roundTo", "number, int", args))
+        };
+
+        let rounded = format::number(number, decimals, "", ".");
+        Ok(Value::Float(rounded.parse().unwrap_or(number)))
+    });
+
+    math.native_fn("isInteger", |_, args| Ok(Value::Bool(match args {
+        [Value::Int(_)] => true,
+        [Value::Float(float)] => float.is_finite() && float.fract() == 0.0,
+        _ => false
+    })));
+
+    math.native_fn("isSafeInteger", |_, args| Ok(Value::Bool(matches!(args, [value] if safe_integer(value).is_some()))));
+
+    // The result is never negative, and `gcd(0, 0)` is 0.
+    math.native_fn("gcd", |vm, args| {
+        match args {
+            [a, b] => {
+                let (a, b) = (safe_integer_of(vm, "gcd", 0, a)?, safe_integer_of(vm, "gcd", 1, b)?);
+                Ok(Value::Int(gcd(a, b) as isize))
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "Math.gcd", "int, int", args))
+        }
+    });
+
+    math.native_fn("lcm", |vm, args| {
+        match args {
+            [a, b] => {
+                let (a, b) = (safe_integer_of(vm, "lcm", 0, a)?, safe_integer_of(vm, "lcm", 1, b)?);
+                if a == 0 || b == 0 {
+                    return Ok(Value::Int(0));
+                }
+
+                match (a / gcd(a, b)).checked_mul(b).map(i64::abs) {
+                    Some(lcm) if lcm <= MAX_SAFE_INTEGER => Ok(Value::Int(lcm as isize)),
+                    _ => Err(RuntimeError::new(vm, "[Math.lcm]: The result is above 2^53 - 1."))
+                }
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "Math.lcm", "int, int", args))
+        }
+    });
+
+    // 18! is the last factorial below 2^53.
+    math.native_fn("factorial", |vm, args| {
+        let n = match args {
+            [n] => safe_integer_of(vm, "factorial", 0, n)?,
+            _ => return Err(RuntimeError::new_arguments(vm, "Math.factorial", "int", args))
+        };
+
+        if n < 0 {
+            return Err(RuntimeError::new(vm, format!("[Math.factorial]: Expected a non-negative integer but found {}.", n)));
+        }
+
+        let mut factorial: i64 = 1;
+        for i in 2..=n {
+            factorial = match factorial.checked_mul(i) {
+                Some(factorial) if factorial <= MAX_SAFE_INTEGER => factorial,
+                _ => return Err(RuntimeError::new(vm, format!("[Math.factorial]: The factorial of {} is above 2^53 - 1.", n)))
+            };
+        }
+
+        Ok(Value::Int(factorial as isize))
+    });
+
+    // The quotient is rounded down, so the remainder has the sign of the divisor
+    // unlike the one of `%`, which has the sign of the dividend. Two integers give
+    // integers and the other numbers floats.
+    math.native_fn("divmod", |vm, args| {
+        let (quotient, remainder) = match args {
+            [_, Value::Int(0)] => return Err(RuntimeError::new(vm, "[Math.divmod]: Division by zero.")),
+            [Value::Int(a), Value::Int(b)] => match (a.checked_div_euclid(*b), a.checked_rem_euclid(*b)) {
+                (Some(quotient), Some(remainder)) if remainder != 0 && *b < 0 => (Value::Int(quotient - 1), Value::Int(remainder + b)),
+                (Some(quotient), Some(remainder)) => (Value::Int(quotient), Value::Int(remainder)),
+                _ => return Err(RuntimeError::new(vm, "[Math.divmod]: The quotient is out of the integer range."))
+            },
+            [a, b] if a.is_number() && b.is_number() => {
+                let (a, b) = (a.to_f64(), b.to_f64());
+                if b == 0.0 {
+                    return Err(RuntimeError::new(vm, "[Math.divmod]: Division by zero."));
+                }
+
+                let quotient = (a / b).floor();
+                let mut remainder = a % b;
+                if remainder != 0.0 && (remainder < 0.0) != (b < 0.0) {
+                    remainder += b;
+                }
+
+                (Value::Float(quotient), Value::Float(remainder))
+            },
+            _ => return Err(RuntimeError::new_arguments(vm, "Math.divmod", "number, number", args))
+        };
+
+        Ok(Value::Array(vm.allocate_value_ptr(vec![quotient, remainder])))
+    });
+
+    // The numbers come from the generator of the vm, which `--seed` seeds.
+    math.native_fn("random", |vm, _| Ok(Value::Float((vm.next_random() >> 11) as f64 / (1u64 << 53) as f64)));
+    math.native_fn("randomUint", |vm, _| Ok(Value::Int((vm.next_random() >> 1) as isize)));
+    math.native_fn("randomInt", |vm, _| Ok(Value::Int(vm.next_random() as isize)));
 
     Value::Dict(math.allocate_value_ptr())
 }
@@ -202,7 +429,7 @@ pub fn init_date(vm: &mut Vm) -> Value {
         match &args {
             [Value::Instance(ptr)] => {
                 let instance = ptr.unwrap_mut();
-                let (date, time) = date::from_system_time(SystemTime::now());
+                let (date, time) = date::from_system_time(vm.clock.now());
                 instance.properties.insert(vm.constants.__date, (Value::Int(date), false)); 
                 instance.properties.insert(vm.constants.__time, (Value::Int(time), false)); 
             },
@@ -233,19 +460,22 @@ pub fn init_date(vm: &mut Vm) -> Value {
                 instance.properties.insert(vm.constants.__date, (Value::Int(date), false)); 
                 instance.properties.insert(vm.constants.__time, (Value::Int(ms), false)); 
             }
-            _ => return Err(RuntimeError::new(vm, "[Date.init]: Expected (Date, int) or (Date, null) or (Date, year, month, date, ms) arguments."))
+            _ => return Err(RuntimeError::new_signatures(vm, "Date.init", &["Date, int?", "Date, int, int, int, int"], args))
         }
 
         Ok(Value::Null)
     });
 
-    date.native_fn("now", |_, _| Ok(Value::Int(
-        SystemTime::now()
+    date.native_fn("now", |vm, _| Ok(Value::Int(
+        vm.clock.now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_millis() as isize
     )));
 
+    // The milliseconds since the vm started, which never go back unlike `now`.
+    date.native_fn("monotonic", |vm, _| Ok(Value::Float(vm.clock.elapsed().as_secs_f64() * 1000.0)));
+
     date.prototype_fn("getDate", |vm, args| {
         let date = unwrap_date(vm, args);
         Ok(Value::Int(date::date_into_ymd(date).2 as isize))
@@ -295,7 +525,7 @@ pub fn init_date(vm: &mut Vm) -> Value {
                 instance.properties.insert(vm.constants.__date, (Value::Int(date::ymd_into_date(year, month, new_date)), false));
                 Ok(Value::Null)
             },
-            _ => Err(RuntimeError::new(vm, "[Date.setDate]: Expected (Date, int) arguments."))
+            _ => Err(RuntimeError::new_arguments(vm, "Date.setDate", "Date, int", args))
         }
     });
 
@@ -313,7 +543,7 @@ pub fn init_date(vm: &mut Vm) -> Value {
                 instance.properties.insert(vm.constants.__date, (Value::Int(date::ymd_into_date(year, month, date)), false));
                 Ok(Value::Null)
             },
-            _ => Err(RuntimeError::new(vm, "[Date.setDate]: Expected (Date, int) arguments."))
+            _ => Err(RuntimeError::new_arguments(vm, "Date.setYear", "Date, int", args))
         }
     });
 
@@ -331,7 +561,7 @@ pub fn init_date(vm: &mut Vm) -> Value {
                 instance.properties.insert(vm.constants.__date, (Value::Int(date::ymd_into_date(year, month, date)), false));
                 Ok(Value::Null)
             },
-            _ => Err(RuntimeError::new(vm, "[Date.setDate]: Expected (Date, int) arguments."))
+            _ => Err(RuntimeError::new_arguments(vm, "Date.setMonth", "Date, int", args))
         }
     });
 
@@ -346,12 +576,12 @@ pub fn init_date(vm: &mut Vm) -> Value {
 
                 Ok(Value::Null)
             },
-            _ => Err(RuntimeError::new(vm, "[Date.setDate]: Expected (Date, int) arguments."))
+            _ => Err(RuntimeError::new_arguments(vm, "Date.setTime", "Date, int", args))
         }
     });
 
     macro_rules! set_amount {
-        ($vm:expr, $args:expr, $x:expr) => {
+        ($vm:expr, $args:expr, $name:expr, $x:expr) => {
             match $args.get(0..2) {
                 Some([Value::Instance(ptr), value]) => {
                     let instance = ptr.unwrap_mut();
@@ -360,21 +590,135 @@ pub fn init_date(vm: &mut Vm) -> Value {
     
                     Ok(Value::Null)
                 },
-                _ => Err(RuntimeError::new($vm, "[Date.setDate]: Expected (Date, int) arguments."))
+                _ => Err(RuntimeError::new_arguments($vm, $name, "Date, int", $args))
             }
         };
     }
 
     date.prototype_fn("setSeconds", |vm, args| {
-        set_amount!(vm, args, 1000)
+        set_amount!(vm, args, "Date.setSeconds", 1000)
     });
 
     date.prototype_fn("setMinutes", |vm, args| {
-        set_amount!(vm, args, date::MS_PER_MINUTE)
+        set_amount!(vm, args, "Date.setMinutes", date::MS_PER_MINUTE)
     });
 
     date.prototype_fn("setHours", |vm, args| {
-        set_amount!(vm, args, date::MS_PER_HOUR)
+        set_amount!(vm, args, "Date.setHours", date::MS_PER_HOUR)
+    });
+
+    // The timestamps of the static helpers are milliseconds since the epoch like
+    // the ones returned by `Date.now`, so they are independent of the locale.
+    fn timestamp(vm: &Vm, name: &str, index: usize, value: Option<&Value>) -> RuntimeResult<isize> {
+        match value {
+            Some(&Value::Int(ms)) => Ok(ms),
+            Some(&Value::Float(ms)) if ms.is_finite() && ms.fract() == 0.0 => Ok(ms as isize),
+            _ => Err(RuntimeError::new_argument(vm, name, index, "timestamp in milliseconds", value))
+        }
+    }
+
+    date.native_fn("toISOString", |vm, args| {
+        let ms = timestamp(vm, "Date.toISOString", 0, args.first())?;
+        match date::to_iso_string(ms) {
+            Some(string) => Ok(Value::String(vm.allocate_string(string))),
+            None => Err(RuntimeError::new(vm, "[Date.toISOString]: Expected the year of the timestamp to be in the range of 0..=9999."))
+        }
+    });
+
+    // Adds the amounts of a { years, months, days, hours, minutes, seconds,
+    // milliseconds } dict, which can be negative or left out. The years and the
+    // months are added first and keep the time of the day.
+    date.native_fn("add", |vm, args| {
+        let (ms, amounts) = match args {
+            [ms, Value::Dict(amounts)] => (timestamp(vm, "Date.add", 0, Some(ms))?, amounts.unwrap_ref()),
+            _ => return Err(RuntimeError::new_arguments(vm, "Date.add", "int, dict", args))
+        };
+
+        let mut months: isize = 0;
+        let mut duration: isize = 0;
+        for (key, (value, _)) in amounts.iter() {
+            let amount = match value {
+                Value::Int(int) => Some(*int),
+                Value::Float(float) if float.fract() == 0.0 && float.abs() < isize::MAX as f64 => Some(*float as isize),
+                _ => None
+            };
+
+            let (unit, amount) = match (key, amount) {
+                (Value::String(unit), Some(amount)) => (unit.unwrap_ref() as &str, amount),
+                _ => return Err(RuntimeError::new(vm, format!("[Date.add]: Expected the amount of {} to be an int.", key)))
+            };
+
+            let added = match unit {
+                "years" => amount.checked_mul(12).and_then(|amount| months.checked_add(amount)).map(|total| months = total),
+                "months" => months.checked_add(amount).map(|total| months = total),
+                _ => {
+                    let size = match unit {
+                        "days" => date::MS_PER_DAY,
+                        "hours" => date::MS_PER_HOUR,
+                        "minutes" => date::MS_PER_MINUTE,
+                        "seconds" => date::MS_PER_SECOND,
+                        "milliseconds" => 1,
+                        _ => return Err(RuntimeError::new(vm, format!("[Date.add]: Unknown unit \"{}\".", unit)))
+                    };
+
+                    amount.checked_mul(size).and_then(|amount| duration.checked_add(amount)).map(|total| duration = total)
+                }
+            };
+
+            if added.is_none() {
+                return Err(RuntimeError::new(vm, "[Date.add]: The amounts are too large."));
+            }
+        }
+
+        // The months are bounded so that the calendar arithmetic cannot overflow.
+        if months.abs() > 12 * 1_000_000 {
+            return Err(RuntimeError::new(vm, "[Date.add]: The amounts are too large."));
+        }
+
+        match date::add_months(ms, months).checked_add(duration) {
+            Some(ms) => Ok(Value::Int(ms)),
+            None => Err(RuntimeError::new(vm, "[Date.add]: The amounts are too large."))
+        }
+    });
+
+    // The duration from the second timestamp to the first one. Every part has the
+    // sign of the duration and `total` holds all of it in milliseconds.
+    date.native_fn("diff", |vm, args| {
+        let a = timestamp(vm, "Date.diff", 0, args.first())?;
+        let b = timestamp(vm, "Date.diff", 1, args.get(1))?;
+        let total = match a.checked_sub(b) {
+            Some(total) => total,
+            None => return Err(RuntimeError::new(vm, "[Date.diff]: The duration is too large."))
+        };
+
+        let mut duration = Map::new();
+        for (key, value) in [
+            ("total", total),
+            ("days", total / date::MS_PER_DAY),
+            ("hours", total % date::MS_PER_DAY / date::MS_PER_HOUR),
+            ("minutes", total % date::MS_PER_HOUR / date::MS_PER_MINUTE),
+            ("seconds", total % date::MS_PER_MINUTE / date::MS_PER_SECOND),
+            ("milliseconds", total % date::MS_PER_SECOND)
+        ] {
+            duration.insert(Value::String(vm.allocate_static_str(key)), (Value::Int(value), false));
+        }
+
+        Ok(Value::Dict(vm.allocate_value_ptr(duration)))
+    });
+
+    // The start of the "second", "minute", "hour", "day", "month" or "year" of a
+    // timestamp in UTC.
+    date.native_fn("startOf", |vm, args| {
+        let ms = timestamp(vm, "Date.startOf", 0, args.first())?;
+        let unit = match args.get(1) {
+            Some(Value::String(unit)) => unit.unwrap_ref() as &str,
+            _ => return Err(RuntimeError::new_arguments(vm, "Date.startOf", "int, string", args))
+        };
+
+        match date::start_of(ms, unit) {
+            Some(ms) => Ok(Value::Int(ms)),
+            None => Err(RuntimeError::new(vm, format!("[Date.startOf]: Unknown unit \"{}\".", unit)))
+        }
     });
 
     Value::Dict(date.allocate_value_ptr())
@@ -398,10 +742,67 @@ pub fn init_memory(vm: &mut Vm) -> Value {
         Ok(Value::Bool(true))
     });
 
+    // Refs are the slot and its generation in the ref table of the vm, which are
+    // validated on each access.
+    memory.native_fn("ref", |vm, args| {
+        let value = args.first().copied();
+        let slot = match vm.ref_slots.iter().position(|slot| slot.value.is_none()) {
+            Some(slot) => {
+                vm.ref_slots[slot].value = value.or(Some(Value::Null));
+                slot
+            },
+            None => {
+                vm.ref_slots.push(RefSlot { value: value.or(Some(Value::Null)), generation: 0 });
+                vm.ref_slots.len() - 1
+            }
+        };
+
+        Ok(Value::Ref(slot as u32, vm.ref_slots[slot].generation))
+    });
+
+    memory.native_fn("deref", |vm, args| {
+        let slot = ref_slot(vm, "deref", args.first())?;
+        Ok(vm.ref_slots[slot].value.unwrap_or(Value::Null))
+    });
+
+    memory.native_fn("getByPointer", |vm, args| {
+        let slot = ref_slot(vm, "getByPointer", args.first())?;
+        Ok(vm.ref_slots[slot].value.unwrap_or(Value::Null))
+    });
+
+    memory.native_fn("setRef", |vm, args| {
+        let slot = ref_slot(vm, "setRef", args.first())?;
+        let value = args.get(1).copied().unwrap_or(Value::Null);
+        vm.ref_slots[slot].value = Some(value);
+        Ok(value)
+    });
+
+    memory.native_fn("release", |vm, args| {
+        let slot = ref_slot(vm, "release", args.first())?;
+        let ref_slot = &mut vm.ref_slots[slot];
+        ref_slot.value = None;
+        ref_slot.generation = ref_slot.generation.wrapping_add(1);
+        Ok(Value::Null)
+    });
+
     Value::Dict(memory.allocate_value_ptr())
 }
 
+// Resolves the slot of a ref created by `Memory.ref` which is still alive.
+fn ref_slot(vm: &Vm, name: &str, reference: Option<&Value>) -> RuntimeResult<usize> {
+    let (slot, generation) = match reference {
+        Some(&Value::Ref(slot, generation)) => (slot as usize, generation),
+        _ => return Err(RuntimeError::new_argument(vm, &format!("Memory.{}", name), 0, "ref", reference))
+    };
+
+    match vm.ref_slots.get(slot) {
+        Some(RefSlot { value: Some(_), generation: current }) if *current == generation => Ok(slot),
+        _ => Err(RuntimeError::new(vm, format!("[Memory.{}]: The ref is stale since its slot has been released.", name)))
+    }
+}
+
 pub fn init_json(vm: &mut Vm) -> Value {
+    vm.register_feature("json", true, Some(super::VERSION));
     let mut json = MapBuilder::new(vm);
 
     json.native_fn("stringify", |vm, args| {
@@ -430,7 +831,7 @@ pub fn init_process(vm: &mut Vm) -> Value {
         }
 
         match args.get(0..3) {
-            Some([Value::Instance(ptr), rid, pid]) => {
+            Some([Value::Instance(ptr), rid @ Value::Resource(..), pid]) => {
                 let instance = ptr.unwrap_mut();
                 instance.properties.insert(vm.constants.rid, (*rid, true));
                 instance.properties.insert(vm.constants.pid, (*pid, true)); 
@@ -443,7 +844,7 @@ pub fn init_process(vm: &mut Vm) -> Value {
 
                 Ok(Value::Null)
             },
-            _ => return Err(RuntimeError::new(vm, "[Process.init]: Expected (Process, rid, pid) arguments."))
+            _ => return Err(RuntimeError::new_arguments(vm, "Process.init", "Process, resource, pid", args))
         }
     });
 
@@ -482,7 +883,7 @@ pub fn init_event_emitter(vm: &mut Vm) -> Value {
 
                 Ok(Value::Null)
             },
-            _ => Err(RuntimeError::new(vm, "[EventEmitter.on]: Expected (EventEmitter, string, function) arguments."))
+            _ => Err(RuntimeError::new_arguments(vm, "EventEmitter.on", "EventEmitter, string, function", args))
         }
     });
 
@@ -504,7 +905,7 @@ pub fn init_event_emitter(vm: &mut Vm) -> Value {
 
                 Ok(Value::Null)
             },
-            _ => Err(RuntimeError::new(vm, "[EventEmitter.emit]: Expected (EventEmitter, string, ...args) arguments."))
+            _ => Err(RuntimeError::new_arguments(vm, "EventEmitter.emit", "EventEmitter, string, ...args", args))
         }
     });
 
@@ -528,7 +929,7 @@ pub fn init_event_emitter(vm: &mut Vm) -> Value {
 
                 Ok(Value::Null)
             },
-            _ => Err(RuntimeError::new(vm, "[EventEmitter.on]: Expected (EventEmitter, string, function) arguments."))
+            _ => Err(RuntimeError::new_arguments(vm, "EventEmitter.off", "EventEmitter, string, function", args))
         }
     });
 
@@ -537,20 +938,20 @@ pub fn init_event_emitter(vm: &mut Vm) -> Value {
 
 pub fn initiate_process_instance(
     vm: &mut Vm, 
-    rid: u32, 
+    rid: Value, 
     pid: u32, 
-    stdout: Option<u32>,
-    stdin: Option<u32>,
-    stderr: Option<u32>
+    stdout: Option<Value>,
+    stdin: Option<Value>,
+    stderr: Option<Value>
 ) -> Value {
     let mut properties = Map::with_capacity(2);
     properties.insert(vm.constants.pid, (Value::Int(pid as _), true));
-    properties.insert(vm.constants.rid, (Value::Int(rid as _), true));
+    properties.insert(vm.constants.rid, (rid, true));
 
     macro_rules! if_let_stdio {
         ($($key:ident)+) => {
             $(if let Some(rid) = $key {
-                properties.insert(vm.constants.$key, (Value::Int(rid as _), true));
+                properties.insert(vm.constants.$key, (rid, true));
             })+
         };
     }
@@ -563,4 +964,29 @@ pub fn initiate_process_instance(
             methods: vm.constants.process_prototype
         }
     ))
-}
\ No newline at end of file
+}
+
+// The `script` global which describes the script being executed. The vm replaces
+// it whenever a preloaded script or the main script starts.
+pub fn init_script(vm: &mut Vm, filename: &str, is_main: bool) -> Value {
+    let dirname = match Path::new(filename).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_string_lossy().into_owned(),
+        _ => ".".to_owned()
+    };
+
+    // Only the main script receives the arguments of the command line.
+    let args = match is_main {
+        true => vm.args.clone().into_iter().map(|arg| Value::String(vm.allocate_string(arg))).collect(),
+        false => Vec::new()
+    };
+
+    let args = Value::Array(vm.allocate_value_ptr(args));
+    let mut script = MapBuilder::new(vm);
+
+    script.string_constant("filename", filename);
+    script.string_constant("dirname", &dirname);
+    script.constant("isMain", Value::Bool(is_main));
+    script.constant("args", args);
+
+    Value::Dict(script.allocate_value_ptr())
+}
@@ -0,0 +1,59 @@
+// The clock of `Date.now`, `Date.monotonic` and `window.sleep`. With `--seed` it
+// is virtual: every run starts at the same instant and the time only moves by
+// sleeping, which returns at once. The natives which still depend on the world
+// outside of the vm, the environment, the files and the child processes, are
+// noted by `Vm::note_nondeterministic` when they are used under `--seed`.
+
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Default)]
+pub struct Clock {
+    started: Option<Instant>,
+    // The time slept since the start, which is all the time passing under `--seed`.
+    slept: Option<Duration>
+}
+
+impl Clock {
+    // 2000-01-01T00:00:00Z, where the virtual clocks start.
+    pub const VIRTUAL_EPOCH: Duration = Duration::from_secs(946_684_800);
+
+    pub fn system() -> Self {
+        Self { started: Some(Instant::now()), slept: None }
+    }
+
+    pub fn virtual_clock() -> Self {
+        Self { started: None, slept: Some(Duration::ZERO) }
+    }
+
+    pub fn is_virtual(&self) -> bool {
+        self.slept.is_some()
+    }
+
+    pub fn now(&self) -> SystemTime {
+        match self.slept {
+            Some(slept) => UNIX_EPOCH + Self::VIRTUAL_EPOCH + slept,
+            None => SystemTime::now()
+        }
+    }
+
+    // The time since the vm started, which never goes back.
+    pub fn elapsed(&self) -> Duration {
+        match (self.slept, self.started) {
+            (Some(slept), _) => slept,
+            (None, Some(started)) => started.elapsed(),
+            (None, None) => Duration::ZERO
+        }
+    }
+
+    // Moves a virtual clock forward and tells whether the sleep is over, while the
+    // sleep of the system clock is up to the caller.
+    pub fn sleep(&mut self, duration: Duration) -> bool {
+        match &mut self.slept {
+            Some(slept) => {
+                *slept += duration;
+                true
+            },
+            None => false
+        }
+    }
+}
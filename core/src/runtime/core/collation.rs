@@ -0,0 +1,101 @@
+// Case insensitive and natural comparisons of strings used by `localeCompare`,
+// `equalsIgnoreCase` and `sort`. Only the simple case folding is done which maps
+// a char to a single char, so the locale specific mappings such as the Turkish
+// dotted and dotless i are not folded into the ascii "i".
+
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::str::Chars;
+
+pub fn fold_char(c: char) -> char {
+    match c {
+        'ς' => 'σ',
+        _ => {
+            let mut lower = c.to_lowercase();
+            match (lower.next(), lower.next()) {
+                (Some(folded), None) => folded,
+                // A char which lowercases into multiple chars such as "İ" has no
+                // simple folding.
+                _ => c
+            }
+        }
+    }
+}
+
+pub fn equals_ignore_case(a: &str, b: &str) -> bool {
+    a.chars().map(fold_char).eq(b.chars().map(fold_char))
+}
+
+pub fn compare_ignore_case(a: &str, b: &str) -> Ordering {
+    a.chars().map(fold_char).cmp(b.chars().map(fold_char))
+}
+
+// Compares the runs of digits by their numeric values so that "file9" is ordered
+// before "file10". The other chars are compared after folding their case.
+pub fn compare_natural(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_char), Some(b_char)) if a_char.is_ascii_digit() && b_char.is_ascii_digit() => {
+                let a_digits = digit_run(&mut a_chars);
+                let b_digits = digit_run(&mut b_chars);
+
+                let ordering = compare_digits(&a_digits, &b_digits);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            },
+            (Some(a_char), Some(b_char)) => {
+                let ordering = fold_char(a_char).cmp(&fold_char(b_char));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+// The strings which are equal after the folding are ordered by their chars so
+// that the result of the comparison does not depend on the order of the items.
+pub fn locale_compare(a: &str, b: &str, numeric: bool) -> Ordering {
+    let ordering = if numeric {
+        compare_natural(a, b)
+    } else {
+        compare_ignore_case(a, b)
+    };
+
+    ordering.then_with(|| a.cmp(b))
+}
+
+fn digit_run(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(&c) = chars.peek() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+
+        digits.push(c);
+        chars.next();
+    }
+
+    digits
+}
+
+// Compares the digits without parsing them so that the runs do not overflow. The
+// run with more leading zeros is ordered later when the values are equal.
+fn compare_digits(a: &str, b: &str) -> Ordering {
+    let a_value = a.trim_start_matches('0');
+    let b_value = b.trim_start_matches('0');
+
+    a_value.len().cmp(&b_value.len())
+        .then_with(|| a_value.cmp(b_value))
+        .then_with(|| a.len().cmp(&b.len()))
+}
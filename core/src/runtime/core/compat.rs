@@ -0,0 +1,136 @@
+// The `--compat js` mode. It adds the names which the people coming from
+// JavaScript keep typing as thin wrappers over the natives which already exist.
+// They are left out of the default globals so that they do not crowd them.
+
+use std::io::Write;
+use crate::{Vm, Value, TinyString, NativeFunctionHandler, RuntimeError, RuntimeResult};
+use super::map_builder::MapBuilder;
+
+pub fn enabled(vm: &Vm) -> bool {
+    matches!(vm.flags.get(&TinyString::new(b"compat")), Some(mode) if mode.to_bytes() == b"js")
+}
+
+// Registered after the other globals since it wraps and extends them.
+pub fn init(vm: &mut Vm) {
+    if !enabled(vm) {
+        return;
+    }
+
+    let console = init_console(vm);
+    vm.add_global("console", console);
+    vm.add_global("undefined", Value::Null);
+    vm.add_global("Infinity", Value::INFINITY);
+
+    let number = init_number(vm);
+    vm.add_global("Number", number);
+
+    // `String(value)` and `Array.isArray(value)` are added to the existing dicts.
+    // `Boolean(value)` is already callable.
+    if let Value::Dict(string) = global(vm, "String") {
+        vm.add_converter(string, "String", |vm, args| {
+            let string = args.first().unwrap_or_default().to_tiny_string();
+            Ok(Value::String(vm.allocate_value_ptr(string)))
+        });
+    }
+
+    extend_global(vm, "Array", "isArray", |_, args| {
+        Ok(Value::Bool(matches!(args.first(), Some(Value::Array(_)))))
+    });
+}
+
+fn init_console(vm: &mut Vm) -> Value {
+    let mut console = MapBuilder::new(vm);
+
+    console.native_fn("log", |vm, args| write_line(vm, args, false));
+    console.native_fn("info", |vm, args| write_line(vm, args, false));
+    console.native_fn("debug", |vm, args| write_line(vm, args, false));
+    console.native_fn("warn", |vm, args| write_line(vm, args, true));
+    console.native_fn("error", |vm, args| write_line(vm, args, true));
+
+    Value::Dict(console.allocate_value_ptr())
+}
+
+// The arguments are separated by spaces like in JavaScript instead of being
+// written on their own lines like with `println`. The buffered output is flushed
+// before writing to the stderr so that the lines keep the order they were
+// written in when both streams go to the same terminal or file.
+fn write_line(vm: &mut Vm, args: &[Value], to_stderr: bool) -> RuntimeResult<Value> {
+    let line = args.iter().map(|arg| format!("{}", arg.rounded(vm.display_digits))).collect::<Vec<_>>().join(" ");
+    let result = match to_stderr {
+        true => vm.out.flush().and_then(|_| writeln!(vm.err, "{}", line)),
+        false => writeln!(vm.out, "{}", line)
+    };
+
+    match result {
+        Ok(_) => Ok(Value::Null),
+        Err(error) => Err(RuntimeError::new_io(vm, error))
+    }
+}
+
+fn init_number(vm: &mut Vm) -> Value {
+    let parse_int = global(vm, "parseInt");
+    let parse_float = global(vm, "parseFloat");
+    let is_nan = global(vm, "isNaN");
+    let mut number = MapBuilder::new(vm);
+
+    number.constant("parseInt", parse_int);
+    number.constant("parseFloat", parse_float);
+    number.constant("isNaN", is_nan);
+    number.constant("NaN", Value::NAN);
+    number.constant("POSITIVE_INFINITY", Value::INFINITY);
+    number.constant("NEGATIVE_INFINITY", Value::Float(f64::NEG_INFINITY));
+    number.constant("MAX_SAFE_INTEGER", Value::Int((1 << 53) - 1));
+    number.constant("MIN_SAFE_INTEGER", Value::Int(-((1 << 53) - 1)));
+
+    number.native_fn("isInteger", |_, args| Ok(Value::Bool(
+        match args.first() {
+            Some(Value::Int(_)) => true,
+            Some(Value::Float(float)) => float.is_finite() && float.fract() == 0.0,
+            _ => false
+        }
+    )));
+
+    // `Number(value)` gives NaN for the strings which are not numbers. The empty
+    // and blank strings are 0 like in JavaScript.
+    let to_number: NativeFunctionHandler = |_, args| Ok(
+        match args.first() {
+            None | Some(Value::Null) => Value::Int(0),
+            Some(&Value::Bool(boolean)) => Value::Int(boolean as isize),
+            Some(&value @ (Value::Int(_) | Value::Float(_))) => value,
+            Some(Value::String(ptr)) => {
+                let string = (ptr.unwrap_ref() as &str).trim();
+                match (string.parse::<isize>(), string.parse::<f64>()) {
+                    _ if string.is_empty() => Value::Int(0),
+                    (Ok(int), _) => Value::Int(int),
+                    (_, Ok(float)) => Value::Float(float),
+                    _ => Value::NAN
+                }
+            },
+            Some(_) => Value::NAN
+        }
+    );
+
+    let number = number.allocate_value_ptr();
+    vm.add_converter(number, "Number", to_number);
+    Value::Dict(number)
+}
+
+fn global(vm: &mut Vm, name: &str) -> Value {
+    let constant_id = vm.chunk.constants.add_string(TinyString::new(name.as_bytes()));
+    match vm.globals.get(&constant_id) {
+        Some(&(value, _)) => value,
+        None => Value::Null
+    }
+}
+
+// Adds a native to the dict of a global, which is left as it is if it is not a dict.
+fn extend_global(vm: &mut Vm, name: &str, key: &str, func: NativeFunctionHandler) {
+    let dict = match global(vm, name) {
+        Value::Dict(ptr) => ptr,
+        _ => return
+    };
+
+    let mut native = MapBuilder::new(vm);
+    native.native_fn(key, func);
+    dict.unwrap_mut().extend(native.map);
+}
@@ -165,4 +165,84 @@ pub fn date_time_to_ms(date: isize, mut time: isize) -> isize {
     }
 
     time
-}
\ No newline at end of file
+}
+// The number of days from 1970-01-01 to a date of the proleptic gregorian calendar,
+// negative before it. The years are split into the 400 years cycles after 0000-03-01
+// so that the leap day is the last day of a year.
+pub fn days_from_civil(year: isize, month: u8, day: u8) -> isize {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as isize;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as isize - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+// The inverse of `days_from_civil`.
+pub fn civil_from_days(days: isize) -> (isize, u8, u8) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * shifted_month + 2) / 5 + 1) as u8;
+    let month = (if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 }) as u8;
+    let year = year_of_era + era * 400 + (month <= 2) as isize;
+
+    (year, month, day)
+}
+
+// A timestamp split into its date and the milliseconds since the start of its day.
+pub fn civil_from_ms(ms: isize) -> ((isize, u8, u8), isize) {
+    (civil_from_days(ms.div_euclid(MS_PER_DAY)), ms.rem_euclid(MS_PER_DAY))
+}
+
+pub fn ms_from_civil(year: isize, month: u8, day: u8, time: isize) -> isize {
+    days_from_civil(year, month, day) * MS_PER_DAY + time
+}
+
+// The RFC 3339 form in UTC, like "2024-02-29T12:30:00.000Z". The years outside of
+// 0..=9999 cannot be written with the four digits it requires.
+pub fn to_iso_string(ms: isize) -> Option<String> {
+    let ((year, month, day), time) = civil_from_ms(ms);
+    if !(0..=9999).contains(&year) {
+        return None;
+    }
+
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year, month, day,
+        time / MS_PER_HOUR,
+        time % MS_PER_HOUR / MS_PER_MINUTE,
+        time % MS_PER_MINUTE / MS_PER_SECOND,
+        time % MS_PER_SECOND
+    ))
+}
+
+// Moves a timestamp by a number of months, keeping its time. The day is clamped
+// to the length of the new month so that Jan 31 + 1 month is the last day of Feb.
+pub fn add_months(ms: isize, months: isize) -> isize {
+    let ((year, month, day), time) = civil_from_ms(ms);
+    let total = year * 12 + month as isize - 1 + months;
+    let (year, month) = (total.div_euclid(12), (total.rem_euclid(12) + 1) as u8);
+    let day = day.min(DAYS_OF_MONTH[leap_year(year) as usize][month as usize - 1]);
+
+    ms_from_civil(year, month, day, time)
+}
+
+// Truncates a timestamp to the start of its second, minute, hour, day, month or year.
+pub fn start_of(ms: isize, unit: &str) -> Option<isize> {
+    let ((year, month, _), _) = civil_from_ms(ms);
+    Some(match unit {
+        "second" => ms - ms.rem_euclid(MS_PER_SECOND),
+        "minute" => ms - ms.rem_euclid(MS_PER_MINUTE),
+        "hour" => ms - ms.rem_euclid(MS_PER_HOUR),
+        "day" => ms - ms.rem_euclid(MS_PER_DAY),
+        "month" => ms_from_civil(year, month, 1, 0),
+        "year" => ms_from_civil(year, 1, 1, 0),
+        _ => return None
+    })
+}
@@ -0,0 +1,151 @@
+// The structural comparison of values behind `window.diff`. The arrays and dicts
+// are compared entry by entry and each difference is reported with the path of
+// the entry from the compared values, like `a.b[2]` or `["some key"]`.
+
+use std::cmp::Ordering;
+use crate::Value;
+
+// The arrays and dicts deeper than this are compared by their pointers, which
+// also stops the comparison of the values which contain themselves.
+const MAX_DEPTH: usize = 32;
+// The comparison stops once this many differences have been found.
+pub const MAX_DIFFERENCES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffKind {
+    Changed,
+    Added,
+    Removed
+}
+
+impl DiffKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Changed => "changed",
+            Self::Added => "added",
+            Self::Removed => "removed"
+        }
+    }
+}
+
+// The sides missing the entry hold null.
+#[derive(Debug, Clone)]
+pub struct Difference {
+    pub path: String,
+    pub left: Value,
+    pub right: Value,
+    pub kind: DiffKind
+}
+
+pub fn diff(left: &Value, right: &Value) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    compare(&mut differences, String::new(), left, right, 0);
+    differences
+}
+
+// Compares the arrays and dicts by their entries instead of their pointers.
+pub fn structurally_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Bool(a), Value::Bool(b)) => a == b,
+        (Value::Array(a), Value::Array(b)) => {
+            let (a, b) = (a.unwrap_ref(), b.unwrap_ref());
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| structurally_equal(a, b))
+        },
+        (Value::Dict(a), Value::Dict(b)) => {
+            let (a, b) = (a.unwrap_ref(), b.unwrap_ref());
+            a.len() == b.len() && a.iter().all(|(key, (a, _))| match b.get(key) {
+                Some((b, _)) => structurally_equal(a, b),
+                None => false
+            })
+        },
+        _ => a == b
+    }
+}
+
+fn compare(differences: &mut Vec<Difference>, path: String, left: &Value, right: &Value, depth: usize) {
+    if differences.len() >= MAX_DIFFERENCES {
+        return;
+    }
+
+    match (left, right) {
+        (Value::Array(a), Value::Array(b)) if depth < MAX_DEPTH && a != b => {
+            let (a, b) = (a.unwrap_ref(), b.unwrap_ref());
+            for index in 0..a.len().max(b.len()) {
+                let path = format!("{}[{}]", path, index);
+                match (a.get(index), b.get(index)) {
+                    (Some(a), Some(b)) => compare(differences, path, a, b, depth + 1),
+                    (Some(&a), None) => push(differences, path, a, Value::Null, DiffKind::Removed),
+                    (None, Some(&b)) => push(differences, path, Value::Null, b, DiffKind::Added),
+                    (None, None) => ()
+                }
+            }
+        },
+        (Value::Dict(a), Value::Dict(b)) if depth < MAX_DEPTH && a != b => {
+            let (a, b) = (a.unwrap_ref(), b.unwrap_ref());
+            // The keys are sorted so that dicts with the same entries inserted in
+            // other orders report their differences alike. `sort` would go through
+            // `PartialOrd` which only orders the numbers.
+            let mut keys: Vec<&Value> = a.keys().chain(b.keys().filter(|key| !a.contains_key(*key))).collect();
+            keys.sort_by(|a, b| a.total_cmp(b, true).unwrap_or(Ordering::Equal));
+
+            for key in keys {
+                let path = key_path(&path, key);
+                match (a.get(key), b.get(key)) {
+                    (Some((a, _)), Some((b, _))) => compare(differences, path, a, b, depth + 1),
+                    (Some(&(a, _)), None) => push(differences, path, a, Value::Null, DiffKind::Removed),
+                    (None, Some(&(b, _))) => push(differences, path, Value::Null, b, DiffKind::Added),
+                    (None, None) => ()
+                }
+            }
+        },
+        // Past the limit the arrays and dicts are only equal to themselves.
+        (Value::Array(_), Value::Array(_)) | (Value::Dict(_), Value::Dict(_)) if left != right => push(differences, path, *left, *right, DiffKind::Changed),
+        (Value::Array(_), Value::Array(_)) | (Value::Dict(_), Value::Dict(_)) => (),
+        _ if !structurally_equal(left, right) => push(differences, path, *left, *right, DiffKind::Changed),
+        _ => ()
+    }
+}
+
+fn push(differences: &mut Vec<Difference>, path: String, left: Value, right: Value, kind: DiffKind) {
+    if differences.len() < MAX_DIFFERENCES {
+        differences.push(Difference { path, left, right, kind });
+    }
+}
+
+// The keys which are identifiers are joined with a dot and the others are put in
+// brackets, quoted when they are strings.
+pub(crate) fn key_path(path: &str, key: &Value) -> String {
+    match key {
+        Value::String(ptr) => {
+            let key = ptr.unwrap_ref() as &str;
+            match is_identifier(key) {
+                true if path.is_empty() => key.to_owned(),
+                true => format!("{}.{}", path, key),
+                false => format!("{}[{}]", path, quote(key))
+            }
+        },
+        key => format!("{}[{}]", path, key)
+    }
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars.next().is_some_and(|char| char.is_alphabetic() || char == '_') && chars.all(|char| char.is_alphanumeric() || char == '_')
+}
+
+fn quote(key: &str) -> String {
+    let mut quoted = String::from("\"");
+    for char in key.chars() {
+        match char {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            char => quoted.push(char)
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
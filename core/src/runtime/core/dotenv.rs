@@ -0,0 +1,128 @@
+// The parser of the dotenv files loaded by `window.env.load`. Each line is either
+// empty, a comment starting with "#" or a `KEY=VALUE` pair which can be prefixed
+// with `export `. The values can be quoted with double quotes, which support the
+// escapes, or with single quotes, which are taken as they are.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DotenvError {
+    pub line: usize,
+    pub message: String
+}
+
+// Returns the variables in the order of the file. A key which appears more than
+// once is returned each time.
+pub fn parse(source: &str) -> Result<Vec<(String, String)>, DotenvError> {
+    let mut variables = Vec::new();
+
+    for (index, line) in source.split('\n').enumerate() {
+        let error = |message: String| DotenvError { line: index + 1, message };
+        let line = line.strip_suffix('\r').unwrap_or(line).trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").map_or(line, str::trim_start);
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => return Err(error("Expected KEY=VALUE".to_owned()))
+        };
+
+        if key.is_empty() || !key.chars().all(|char| char.is_ascii_alphanumeric() || char == '_' || char == '.') {
+            return Err(error(format!("Invalid name \"{}\"", key)));
+        }
+
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => quoted(&value[1..], quote).map_err(|message| error(message.to_owned()))?,
+            // An unquoted value ends at a comment which is separated by a whitespace.
+            _ => match value.find(" #").or_else(|| value.find("\t#")) {
+                Some(end) => value[..end].trim_end().to_owned(),
+                None => value.to_owned()
+            }
+        };
+
+        variables.push((key.to_owned(), value));
+    }
+
+    Ok(variables)
+}
+
+// The value after its opening quote. Only a comment can follow the closing quote.
+fn quoted(value: &str, quote: char) -> Result<String, &'static str> {
+    let mut result = String::new();
+    let mut chars = value.char_indices();
+
+    while let Some((index, char)) = chars.next() {
+        match char {
+            char if char == quote => {
+                let rest = value[index + 1..].trim_start();
+                return match rest.is_empty() || rest.starts_with('#') {
+                    true => Ok(result),
+                    false => Err("Unexpected text after the quoted value")
+                };
+            },
+            '\\' if quote == '"' => match chars.next() {
+                Some((_, 'n')) => result.push('\n'),
+                Some((_, 'r')) => result.push('\r'),
+                Some((_, 't')) => result.push('\t'),
+                Some((_, escaped)) => result.push(escaped),
+                None => break
+            },
+            char => result.push(char)
+        }
+    }
+
+    Err("Unterminated quoted value")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{parse, DotenvError};
+
+    fn pairs(source: &str) -> Vec<(String, String)> {
+        parse(source).unwrap()
+    }
+
+    fn pair(key: &str, value: &str) -> (String, String) {
+        (key.to_owned(), value.to_owned())
+    }
+
+    #[test]
+    fn comments_blank_lines_and_exports_are_skipped() {
+        let source = "# a comment\n\nexport   NAME=value\n  SPACED = around  \nEMPTY=\n";
+        assert_eq!(pairs(source), vec![pair("NAME", "value"), pair("SPACED", "around"), pair("EMPTY", "")]);
+    }
+
+    #[test]
+    fn unquoted_values_end_at_a_separated_comment() {
+        let source = "URL=http://host/#anchor\nPORT=80 # the port\nTAB=1\t# tab\n";
+        assert_eq!(pairs(source), vec![pair("URL", "http://host/#anchor"), pair("PORT", "80"), pair("TAB", "1")]);
+    }
+
+    #[test]
+    fn double_quotes_support_the_escapes_and_single_quotes_do_not() {
+        let source = "A=\"line\\nnext\\t\\\"q\\\" # kept\" # dropped\nB='raw\\n # kept'\nC=\"a=b\"\nD='a\\'\n";
+        assert_eq!(pairs(source), vec![pair("A", "line\nnext\t\"q\" # kept"), pair("B", "raw\\n # kept"), pair("C", "a=b"), pair("D", "a\\")]);
+    }
+
+    #[test]
+    fn crlf_files_are_read_like_lf_ones() {
+        assert_eq!(pairs("A=1\r\nB=\"two\"\r\n# c\r\n"), vec![pair("A", "1"), pair("B", "two")]);
+    }
+
+    #[test]
+    fn repeated_keys_are_returned_each_time() {
+        assert_eq!(pairs("A=1\nA=2\n"), vec![pair("A", "1"), pair("A", "2")]);
+    }
+
+    #[test]
+    fn malformed_lines_give_their_line_number() {
+        let error = |line: usize, message: &str| Err(DotenvError { line, message: message.to_owned() });
+
+        assert_eq!(parse("A=1\nmissing\n"), error(2, "Expected KEY=VALUE"));
+        assert_eq!(parse("\n\nBAD-NAME=1"), error(3, "Invalid name \"BAD-NAME\""));
+        assert_eq!(parse("=1"), error(1, "Invalid name \"\""));
+        assert_eq!(parse("A=\"open"), error(1, "Unterminated quoted value"));
+        assert_eq!(parse("A=\"a\" b"), error(1, "Unexpected text after the quoted value"));
+    }
+}
@@ -0,0 +1,146 @@
+// Formatting of numbers for `window.format`. Nothing depends on the locale of the
+// system, the separators are always given explicitly. The numbers are rounded
+// half up (away from zero) on their shortest decimal representation, so 1.005
+// with 2 decimals becomes "1.01" even though the float is slightly below it.
+
+const BYTE_UNITS: [&str; 7] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB", "ZiB"];
+
+// Beyond this magnitude the numbers are written in the scientific notation which
+// is rounded on the exact value of the float instead.
+const SCIENTIFIC_THRESHOLD: f64 = 1e21;
+
+pub fn number(number: f64, decimals: usize, thousands_sep: &str, decimal_sep: &str) -> String {
+    if number.is_nan() {
+        return "NaN".to_owned();
+    }
+
+    if number.is_infinite() {
+        return if number > 0.0 { "inf".to_owned() } else { "-inf".to_owned() };
+    }
+
+    if number.abs() >= SCIENTIFIC_THRESHOLD {
+        return format!("{:.*e}", decimals, number).replace('.', decimal_sep);
+    }
+
+    let (integer, fraction) = round_half_up(&format!("{}", number.abs()), decimals);
+    let mut result = String::new();
+
+    // A number which is rounded to zero does not keep its sign.
+    if number < 0.0 && integer.chars().chain(fraction.chars()).any(|digit| digit != '0') {
+        result.push('-');
+    }
+
+    for (index, digit) in integer.chars().enumerate() {
+        if index != 0 && (integer.len() - index) % 3 == 0 {
+            result.push_str(thousands_sep);
+        }
+
+        result.push(digit);
+    }
+
+    if decimals != 0 {
+        result.push_str(decimal_sep);
+        result.push_str(&fraction);
+    }
+
+    result
+}
+
+pub fn percent(number: f64, decimals: usize) -> String {
+    self::number(number * 100.0, decimals, ",", ".") + "%"
+}
+
+// Formats the size with the binary units such as "1.2 MiB".
+pub fn bytes(bytes: f64) -> String {
+    let sign = if bytes < 0.0 { "-" } else { "" };
+    let mut size = bytes.abs();
+
+    if size < 1024.0 {
+        return format!("{}{} B", sign, number(size, 0, "", "."));
+    }
+
+    let mut unit = 0;
+    size /= 1024.0;
+
+    // The size is moved to the next unit if it would be rounded up to 1024.
+    while size >= 1023.95 && unit < BYTE_UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{}{} {}", sign, number(size, 1, "", "."), BYTE_UNITS[unit])
+}
+
+// Formats the milliseconds such as "1h 2m 3.5s". The durations under a second
+// are written in milliseconds and the seconds keep a single decimal.
+pub fn duration(milliseconds: f64) -> String {
+    if !milliseconds.is_finite() {
+        return number(milliseconds, 0, "", ".");
+    }
+
+    let sign = if milliseconds < 0.0 { "-" } else { "" };
+    let milliseconds = milliseconds.abs();
+
+    if milliseconds < 999.5 {
+        return format!("{}{}ms", sign, number(milliseconds, 0, "", "."));
+    }
+
+    // Tenths of a second which are rounded before splitting so that 59.96s does not
+    // become "60.0s".
+    let tenths = (milliseconds / 100.0 + 0.5).floor() as u64;
+    let parts = [
+        (tenths / 864_000, "d"),
+        (tenths / 36_000 % 24, "h"),
+        (tenths / 600 % 60, "m")
+    ];
+
+    let mut result: Vec<String> = parts.iter()
+        .filter(|(value, _)| *value != 0)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect();
+
+    let seconds = tenths % 600;
+    if seconds != 0 || result.is_empty() {
+        match seconds % 10 {
+            0 => result.push(format!("{}s", seconds / 10)),
+            tenth => result.push(format!("{}.{}s", seconds / 10, tenth))
+        }
+    }
+
+    format!("{}{}", sign, result.join(" "))
+}
+
+// Rounds the digits of a non negative decimal and returns its integer and its
+// fraction padded to the number of decimals.
+fn round_half_up(decimal: &str, decimals: usize) -> (String, String) {
+    let (integer, fraction) = match decimal.find('.') {
+        Some(index) => (&decimal[..index], &decimal[index + 1..]),
+        None => (decimal, "")
+    };
+
+    let mut digits: Vec<u8> = integer.bytes().chain(fraction.bytes().take(decimals)).collect();
+    digits.resize(integer.len() + decimals, b'0');
+
+    if fraction.as_bytes().get(decimals).is_some_and(|&digit| digit >= b'5') {
+        let mut index = digits.len();
+        loop {
+            if index == 0 {
+                digits.insert(0, b'1');
+                break;
+            }
+
+            index -= 1;
+            if digits[index] == b'9' {
+                digits[index] = b'0';
+            } else {
+                digits[index] += 1;
+                break;
+            }
+        }
+    }
+
+    let split = digits.len() - decimals;
+    let fraction = String::from_utf8_lossy(&digits[split..]).into_owned();
+    let integer = String::from_utf8_lossy(&digits[..split]).into_owned();
+    (integer, fraction)
+}
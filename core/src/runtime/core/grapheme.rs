@@ -0,0 +1,68 @@
+// A small grapheme cluster segmentation which only handles the common cases of the
+// extended grapheme cluster rules: CR LF, combining marks, variation selectors,
+// emoji modifiers, zero width joiner sequences and regional indicator pairs.
+
+const ZWJ: char = '\u{200D}';
+
+fn is_extend(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F     // Combining Diacritical Marks
+        | 0x0483..=0x0489   // Cyrillic combining marks
+        | 0x0591..=0x05BD   // Hebrew points
+        | 0x0610..=0x061A   // Arabic marks
+        | 0x064B..=0x065F
+        | 0x0900..=0x0903   // Devanagari signs
+        | 0x093A..=0x094F
+        | 0x1AB0..=0x1AFF   // Combining Diacritical Marks Extended
+        | 0x1DC0..=0x1DFF   // Combining Diacritical Marks Supplement
+        | 0x200C            // Zero Width Non-Joiner
+        | 0x20D0..=0x20FF   // Combining Diacritical Marks for Symbols
+        | 0xFE00..=0xFE0F   // Variation Selectors
+        | 0xFE20..=0xFE2F   // Combining Half Marks
+        | 0x1F3FB..=0x1F3FF // Emoji skin tone modifiers
+        | 0xE0020..=0xE007F // Tags
+        | 0xE0100..=0xE01EF // Variation Selectors Supplement
+    )
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c as u32, 0x1F1E6..=0x1F1FF)
+}
+
+pub fn graphemes(string: &str) -> Vec<&str> {
+    let mut result = Vec::new();
+    let mut chars = string.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let mut end = start + c.len_utf8();
+        let mut previous = c;
+        let mut regional_indicators = if is_regional_indicator(c) { 1 } else { 0 };
+
+        while let Some(&(index, next)) = chars.peek() {
+            let joins = match (previous, next) {
+                ('\r', '\n') => true,
+                ('\r', _) | ('\n', _) | (_, '\r') | (_, '\n') => false,
+                (_, next) if is_extend(next) || next == ZWJ => true,
+                (ZWJ, _) => true,
+                (previous, next) if is_regional_indicator(previous) && is_regional_indicator(next) => regional_indicators % 2 == 1,
+                _ => false
+            };
+
+            if !joins {
+                break;
+            }
+
+            if is_regional_indicator(next) {
+                regional_indicators += 1;
+            }
+
+            end = index + next.len_utf8();
+            previous = next;
+            chars.next();
+        }
+
+        result.push(&string[start..end]);
+    }
+
+    result
+}
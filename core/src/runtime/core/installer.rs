@@ -0,0 +1,164 @@
+// The groups of builtins which a vm starts with, each of them added by a named
+// installer. Every group is installed by default while `--global math,print`
+// installs only the listed ones and `--no-default-globals` alone none of them,
+// so that an untrusted script cannot reach what the embedder leaves out. The
+// namespaces of the values such as `String` are part of the language and are
+// not in the registry.
+
+use std::collections::HashMap;
+use crate::{Vm, TinyString};
+use super::{builtin, set, string_builder, window};
+
+pub struct Installer {
+    pub name: &'static str,
+    // The globals which the group adds. A script using one of them without the
+    // group gets an error naming the group instead of calling null.
+    pub globals: &'static [&'static str],
+    // Whether the permissions let the group be installed.
+    pub permitted: fn(&Vm) -> bool,
+    pub install: fn(&mut Vm)
+}
+
+const fn installer(name: &'static str, globals: &'static [&'static str], install: fn(&mut Vm)) -> Installer {
+    Installer { name, globals, permitted: |_| true, install }
+}
+
+pub const INSTALLERS: &[Installer] = &[
+    installer("math", &["Math"], |vm| {
+        let math = builtin::init_math(vm);
+        vm.add_global("Math", math);
+    }),
+    installer("date", &["Date"], |vm| {
+        let date = builtin::init_date(vm);
+        vm.add_global("Date", date);
+    }),
+    installer("json", &["JSON"], |vm| {
+        let json = builtin::init_json(vm);
+        vm.add_global("JSON", json);
+    }),
+    installer("events", &["EventEmitter"], |vm| {
+        let event_emitter = builtin::init_event_emitter(vm);
+        vm.add_global("EventEmitter", event_emitter);
+    }),
+    installer("process", &["Process"], |vm| {
+        let process = builtin::init_process(vm);
+        vm.add_global("Process", process);
+    }),
+    installer("set", &["Set"], |vm| {
+        let set = set::init(vm);
+        vm.add_global("Set", set);
+    }),
+    installer("stringbuilder", &["StringBuilder"], |vm| {
+        let string_builder = string_builder::init(vm);
+        vm.add_global("StringBuilder", string_builder);
+    }),
+    Installer {
+        name: "memory",
+        globals: &["Memory"],
+        permitted: |vm| vm.permissions.memory,
+        install: |vm| {
+            let memory = builtin::init_memory(vm);
+            vm.add_global("Memory", memory);
+        }
+    },
+    installer("window", &["window"], |vm| {
+        let window = window::init(vm);
+        vm.add_global("window", window);
+    }),
+    installer("print", &["print", "println"], super::init_print),
+    installer("readline", &["readline"], super::init_readline),
+    installer("numbers", &["NaN", "inf", "isNaN", "parseInt", "parseFloat"], super::init_numbers),
+    installer("types", &["typeof"], super::init_types),
+    installer("errors", &["throw", "panic"], super::init_errors),
+    installer("range", &["range"], super::init_range),
+    installer("base64", &["btoa", "atob"], super::init_base64)
+];
+
+// The namespaces of the values and the `script` of the modules, which every vm
+// has whichever groups are installed.
+pub const NAMESPACES: &[&str] = &["Iterator", "String", "Boolean", "Object", "Function", "Array", "Bytes", "script"];
+
+// The groups named by `--global`, where `None` stands for all of them.
+#[derive(Debug, Clone, Default)]
+pub struct Selection(Option<Vec<String>>);
+
+impl Selection {
+
+    pub fn of(flags: &HashMap<TinyString, TinyString>) -> Self {
+        match flags.get(&TinyString::new(b"global")) {
+            Some(names) => Self(Some(names.to_string()
+                .split(',')
+                .map(|name| name.trim().to_owned())
+                .filter(|name| !name.is_empty())
+                .collect())),
+            None if flags.contains_key(&TinyString::new(b"no-default-globals")) => Self(Some(Vec::new())),
+            None => Self(None)
+        }
+    }
+
+    // Naming `window` installs all of its capabilities while naming one of them
+    // such as `window.fs` installs `window` with only that one.
+    pub fn includes(&self, name: &str) -> bool {
+        let names = match &self.0 {
+            Some(names) => names,
+            None => return true
+        };
+
+        names.iter().any(|selected| selected == name
+            || (selected == "window" && name.starts_with("window."))
+            || (name == "window" && selected.starts_with("window.")))
+    }
+
+    // The first of the names which is not the name of a group.
+    pub fn unknown(&self) -> Option<&str> {
+        self.0.as_ref()?
+            .iter()
+            .map(String::as_str)
+            .find(|&name| !names().contains(&name))
+    }
+
+}
+
+// The namespaces and the globals added by the selected groups, which the scripts
+// cannot redeclare at the top level. The globals of the groups left out are free
+// to declare.
+pub fn globals(selection: &Selection) -> Vec<&'static str> {
+    NAMESPACES.iter()
+        .copied()
+        .chain(INSTALLERS.iter()
+            .filter(|installer| selection.includes(installer.name))
+            .flat_map(|installer| installer.globals.iter().copied()))
+        .collect()
+}
+
+// The names of the groups and of the capabilities of `window`.
+pub fn names() -> Vec<&'static str> {
+    INSTALLERS.iter()
+        .map(|installer| installer.name)
+        .chain(window::CAPABILITIES.iter().map(|capability| capability.name))
+        .collect()
+}
+
+// Registers the features of the groups before any of them is installed so that
+// `window.features` lists the ones installed after it as well.
+pub fn install(vm: &mut Vm) {
+    let selection = Selection::of(&vm.flags);
+    let installed: Vec<bool> = INSTALLERS.iter()
+        .map(|installer| selection.includes(installer.name) && (installer.permitted)(vm))
+        .collect();
+
+    for (installer, &installed) in INSTALLERS.iter().zip(&installed) {
+        vm.register_feature(installer.name, installed, Some(super::VERSION));
+    }
+
+    for (installer, installed) in INSTALLERS.iter().zip(installed) {
+        if installed {
+            (installer.install)(vm);
+        } else if !selection.includes(installer.name) {
+            for &global in installer.globals {
+                let constant_id = vm.chunk.constants.add_string(TinyString::new(global.as_bytes()));
+                vm.uninstalled.insert(constant_id, (global, installer.name));
+            }
+        }
+    }
+}
@@ -1,8 +1,7 @@
 use std::ptr::NonNull;
-use std::collections::HashMap;
 use std::fmt::{self, Formatter};
 use serde::de::{Deserialize, Deserializer, Visitor, SeqAccess, MapAccess, DeserializeSeed};
-use crate::{TinyString, Vm, Value};
+use crate::{TinyString, Vm, Value, Map};
 
 struct MapKey<'a>(&'a mut Vm);
 
@@ -86,7 +85,7 @@ impl<'a, 'de> Visitor<'de> for ValueVisitor<'a> {
     fn visit_map<V: MapAccess<'de>>(self, mut visitor: V) -> Result<Value, V::Error> {
         match visitor.next_key_seed(MapKey(self.0))? {
             Some(initial_key) => {
-                let mut entries = HashMap::new();
+                let mut entries = Map::new();
 
                 macro_rules! insert {
                     ($key:expr, $value:expr) => {{
@@ -1,4 +1,427 @@
-use crate::Value;
+use std::cmp::Ordering;
+use std::fmt::{self, Display, Formatter};
+use crate::{Vm, Value, Map};
+use super::diff::key_path;
+use super::base64;
+
+// The nesting deeper than this is rejected so that the recursion of the parser
+// and the serializer cannot overflow the stack.
+const MAX_DEPTH: usize = 512;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonError {
+    // A value without a JSON form at its path from the serialized value.
+    Unsupported { path: String, kind: String },
+    // An array or dict which contains itself.
+    Cycle { path: String },
+    TooDeep { path: String },
+    // The offset is the one of the byte at which the parsing failed.
+    Syntax { offset: usize, message: &'static str }
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let at = |path: &str| if path.is_empty() { "the value".to_owned() } else { path.to_owned() };
+        match self {
+            Self::Unsupported { path, kind } => write!(f, "Cannot convert {} at {} into JSON", kind, at(path)),
+            Self::Cycle { path } => write!(f, "Cannot convert {} into JSON as it contains itself", at(path)),
+            Self::TooDeep { path } => write!(f, "Cannot convert {} into JSON as it is nested deeper than {}", at(path), MAX_DEPTH),
+            Self::Syntax { offset, message } => write!(f, "{} at offset {}", message, offset)
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+// The conversions for the embedders. They go through the values directly and
+// never run script code.
+impl Vm {
+
+    // The dicts and instances are written with their keys sorted so that the same
+    // value always gives the same string. The keys have to be strings or ints.
+    pub fn value_to_json(&self, value: &Value) -> Result<String, JsonError> {
+        let mut json = String::new();
+        write_json(&mut json, value, &mut String::new(), &mut Vec::new())?;
+        Ok(json)
+    }
+
+    // The numbers without a fraction or an exponent which fit in an int become
+    // ints and the others become floats.
+    pub fn json_to_value(&mut self, json: &str) -> Result<Value, JsonError> {
+        let mut parser = Parser { bytes: json.as_bytes(), index: 0, vm: self };
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+
+        match parser.index < parser.bytes.len() {
+            true => Err(parser.error("Unexpected text after the value")),
+            false => Ok(value)
+        }
+    }
+
+}
+
+// The path is the one of the value from the serialized value and the parents are
+// the pointers of the arrays and dicts around it.
+fn write_json(json: &mut String, value: &Value, path: &mut String, parents: &mut Vec<*const u8>) -> Result<(), JsonError> {
+    let pointer = match value {
+        Value::Array(ptr) => Some(ptr.as_ptr()),
+        Value::Dict(ptr) => Some(ptr.as_ptr()),
+        Value::Instance(ptr) => Some(ptr.as_ptr()),
+        _ => None
+    };
+
+    if let Some(pointer) = pointer {
+        if parents.contains(&pointer) {
+            return Err(JsonError::Cycle { path: path.clone() });
+        }
+
+        if parents.len() >= MAX_DEPTH {
+            return Err(JsonError::TooDeep { path: path.clone() });
+        }
+
+        parents.push(pointer);
+    }
+
+    match value {
+        Value::Null => json.push_str("null"),
+        Value::Bool(boolean) => json.push_str(if *boolean { "true" } else { "false" }),
+        Value::Int(int) => json.push_str(&int.to_string()),
+        Value::Float(float) if float.is_finite() => json.push_str(&format!("{:?}", float)),
+        Value::String(ptr) => write_string(json, ptr.unwrap_ref()),
+        Value::Bytes(ptr) => write_string(json, &bytes_to_base64(ptr.unwrap_ref())),
+        Value::Array(ptr) => {
+            json.push('[');
+            for (index, item) in ptr.unwrap_ref().iter().enumerate() {
+                if index != 0 {
+                    json.push(',');
+                }
+
+                let len = path.len();
+                path.push_str(&format!("[{}]", index));
+                write_json(json, item, path, parents)?;
+                path.truncate(len);
+            }
+
+            json.push(']');
+        },
+        Value::Dict(_) | Value::Instance(_) => {
+            let entries = match value {
+                Value::Instance(ptr) => &ptr.unwrap_ref().properties,
+                Value::Dict(ptr) => ptr.unwrap_ref(),
+                _ => unreachable!()
+            };
+
+            let mut keys: Vec<&Value> = entries.keys().collect();
+            keys.sort_by(|a, b| a.total_cmp(b, true).unwrap_or(Ordering::Equal));
+
+            json.push('{');
+            for (index, key) in keys.into_iter().enumerate() {
+                if index != 0 {
+                    json.push(',');
+                }
+
+                let entry_path = key_path(path, key);
+                match key {
+                    Value::String(ptr) => write_string(json, ptr.unwrap_ref()),
+                    Value::Int(int) => write_string(json, &int.to_string()),
+                    key => return Err(JsonError::Unsupported { path: entry_path, kind: key.get_type().to_string() })
+                }
+
+                json.push(':');
+                let len = path.len();
+                *path = entry_path;
+                write_json(json, &entries[key].0, path, parents)?;
+                path.truncate(len);
+            }
+
+            json.push('}');
+        },
+        Value::Float(_) => return Err(JsonError::Unsupported { path: path.clone(), kind: "non-finite float".to_owned() }),
+        value => return Err(JsonError::Unsupported { path: path.clone(), kind: value.get_type().to_string() })
+    }
+
+    if pointer.is_some() {
+        parents.pop();
+    }
+
+    Ok(())
+}
+
+pub(crate) fn write_string(json: &mut String, string: &str) {
+    json.push('"');
+    for char in string.chars() {
+        match char {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            '\u{8}' => json.push_str("\\b"),
+            '\u{c}' => json.push_str("\\f"),
+            char if char < ' ' => json.push_str(&format!("\\u{:04x}", char as u32)),
+            char => json.push(char)
+        }
+    }
+
+    json.push('"');
+}
+
+// The values which are parsed are kept on the stack of the vm until the array or
+// dict holding them is allocated, so that a collection cannot free them.
+struct Parser<'a> {
+    bytes: &'a [u8],
+    index: usize,
+    vm: &'a mut Vm
+}
+
+impl Parser<'_> {
+
+    fn error(&self, message: &'static str) -> JsonError {
+        JsonError::Syntax { offset: self.index, message }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.bytes.get(self.index), Some(b' ' | b'\n' | b'\r' | b'\t')) {
+            self.index += 1;
+        }
+    }
+
+    fn expect(&mut self, literal: &str, value: Value) -> Result<Value, JsonError> {
+        match self.bytes[self.index..].starts_with(literal.as_bytes()) {
+            true => {
+                self.index += literal.len();
+                Ok(value)
+            },
+            false => Err(self.error("Unexpected character"))
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, JsonError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("The value is nested too deep"));
+        }
+
+        self.skip_whitespace();
+        match self.bytes.get(self.index) {
+            Some(b'n') => self.expect("null", Value::Null),
+            Some(b't') => self.expect("true", Value::Bool(true)),
+            Some(b'f') => self.expect("false", Value::Bool(false)),
+            Some(b'"') => {
+                let string = self.string()?;
+                Ok(Value::String(self.vm.allocate_string(string)))
+            },
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(b'[') => self.array(depth),
+            Some(b'{') => self.dict(depth),
+            Some(_) => Err(self.error("Unexpected character")),
+            None => Err(self.error("Unexpected end of the JSON"))
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<Value, JsonError> {
+        self.index += 1;
+        let start = self.vm.stack.len();
+        let result = self.items(b']', |parser| {
+            let item = parser.value(depth + 1)?;
+            parser.vm.stack.push(item);
+            Ok(())
+        });
+
+        let items = self.vm.stack.split_off(start);
+        result?;
+        Ok(Value::Array(self.vm.allocate_value_ptr(items)))
+    }
+
+    // The keys and the values are pushed in pairs. A key which is repeated keeps
+    // its last value.
+    fn dict(&mut self, depth: usize) -> Result<Value, JsonError> {
+        self.index += 1;
+        let start = self.vm.stack.len();
+        let result = self.items(b'}', |parser| {
+            parser.skip_whitespace();
+            if parser.bytes.get(parser.index) != Some(&b'"') {
+                return Err(parser.error("Expected a string key"));
+            }
+
+            let key = parser.string()?;
+            let key = Value::String(parser.vm.allocate_string(key));
+            parser.vm.stack.push(key);
+
+            parser.skip_whitespace();
+            if parser.bytes.get(parser.index) != Some(&b':') {
+                return Err(parser.error("Expected a colon after the key"));
+            }
+
+            parser.index += 1;
+            let value = parser.value(depth + 1)?;
+            parser.vm.stack.push(value);
+            Ok(())
+        });
+
+        let entries = self.vm.stack.split_off(start);
+        result?;
+
+        let mut map = Map::with_capacity(entries.len() / 2);
+        for pair in entries.chunks_exact(2) {
+            map.insert(pair[0], (pair[1], false));
+        }
+
+        Ok(Value::Dict(self.vm.allocate_value_ptr(map)))
+    }
+
+    // The items separated by commas up to the closing byte, after the opening one.
+    fn items(&mut self, close: u8, mut item: impl FnMut(&mut Self) -> Result<(), JsonError>) -> Result<(), JsonError> {
+        self.skip_whitespace();
+        if self.bytes.get(self.index) == Some(&close) {
+            self.index += 1;
+            return Ok(());
+        }
+
+        loop {
+            item(self)?;
+            self.skip_whitespace();
+            match self.bytes.get(self.index) {
+                Some(b',') => self.index += 1,
+                Some(&byte) if byte == close => {
+                    self.index += 1;
+                    return Ok(());
+                },
+                Some(_) => return Err(self.error("Expected a comma")),
+                None => return Err(self.error("Unexpected end of the JSON"))
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.index += 1;
+        let mut string = Vec::new();
+
+        loop {
+            let byte = match self.bytes.get(self.index) {
+                Some(&byte) => byte,
+                None => return Err(self.error("Unterminated string"))
+            };
+
+            self.index += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let escaped = match self.bytes.get(self.index) {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            self.index += 1;
+                            let char = self.unicode_escape()?;
+                            string.extend_from_slice(char.encode_utf8(&mut [0; 4]).as_bytes());
+                            continue;
+                        },
+                        _ => return Err(self.error("Invalid escape"))
+                    };
+
+                    self.index += 1;
+                    string.extend_from_slice(escaped.encode_utf8(&mut [0; 4]).as_bytes());
+                },
+                byte if byte < b' ' => return Err(self.error("Unescaped control character in a string")),
+                byte => string.push(byte)
+            }
+        }
+
+        // The input is a str and the escapes are encoded so the bytes stay utf-8.
+        Ok(String::from_utf8(string).unwrap_or_default())
+    }
+
+    // The four hex digits after "\u". A high surrogate has to be followed by the
+    // escape of a low one.
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let high = self.hex()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error("Invalid unicode escape"));
+        }
+
+        if !self.bytes[self.index..].starts_with(b"\\u") {
+            return Err(self.error("Expected a low surrogate"));
+        }
+
+        self.index += 2;
+        let low = self.hex()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("Expected a low surrogate"));
+        }
+
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)).ok_or_else(|| self.error("Invalid unicode escape"))
+    }
+
+    fn hex(&mut self) -> Result<u32, JsonError> {
+        let digits = self.bytes.get(self.index..self.index + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .filter(|digits| digits.bytes().all(|byte| byte.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("Invalid unicode escape"))?;
+
+        self.index += 4;
+        Ok(u32::from_str_radix(digits, 16).unwrap_or_default())
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.index;
+        let digits = |parser: &mut Self| {
+            let from = parser.index;
+            while parser.bytes.get(parser.index).is_some_and(u8::is_ascii_digit) {
+                parser.index += 1;
+            }
+
+            parser.index - from
+        };
+
+        if self.bytes[self.index] == b'-' {
+            self.index += 1;
+        }
+
+        match (self.bytes.get(self.index), digits(self)) {
+            (_, 0) => return Err(self.error("Expected a digit")),
+            (Some(b'0'), len) if len > 1 => return Err(JsonError::Syntax { offset: start, message: "Leading zeros are not allowed" }),
+            _ => ()
+        }
+
+        let mut is_float = false;
+        if self.bytes.get(self.index) == Some(&b'.') {
+            self.index += 1;
+            is_float = true;
+            if digits(self) == 0 {
+                return Err(self.error("Expected a digit"));
+            }
+        }
+
+        if matches!(self.bytes.get(self.index), Some(b'e' | b'E')) {
+            self.index += 1;
+            is_float = true;
+            if matches!(self.bytes.get(self.index), Some(b'+' | b'-')) {
+                self.index += 1;
+            }
+
+            if digits(self) == 0 {
+                return Err(self.error("Expected a digit"));
+            }
+        }
+
+        // The bytes of a number are ascii.
+        let text = std::str::from_utf8(&self.bytes[start..self.index]).unwrap_or_default();
+        match text.parse::<isize>() {
+            Ok(int) if !is_float => Ok(Value::Int(int)),
+            _ => match text.parse::<f64>() {
+                Ok(float) if float.is_finite() => Ok(Value::Float(float)),
+                _ => Err(JsonError::Syntax { offset: start, message: "The number is out of range" })
+            }
+        }
+    }
+
+}
+
 
 impl Value {
 
@@ -50,8 +473,19 @@ impl Value {
                 string
             },
             Value::Function(_) | Value::NativeFn(_) => "\"[Function]\"".to_string(),
-            Value::Iterator(_) => "\"[Iterator]\"".to_string()
+            Value::Iterator(_) => "\"[Iterator]\"".to_string(),
+            Value::Bytes(ptr) => format!("\"{}\"", bytes_to_base64(ptr.unwrap_ref())),
+            Value::Resource(..) | Value::Ref(..) => format!("\"{}\"", self)
         }
     }
 
-}
\ No newline at end of file
+}
+
+// The bytes have no JSON form of their own so they are written as a base64
+// string, which `Bytes.from(string, "base64")` turns back into the bytes.
+pub(crate) fn bytes_to_base64(bytes: &[u8]) -> String {
+    match base64::encode(bytes) {
+        Some(string) => string.to_string(),
+        None => String::new()
+    }
+}
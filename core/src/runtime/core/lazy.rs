@@ -0,0 +1,201 @@
+// The lazy adapters of the iterators such as `it.map(fn)`. An adapter is an
+// iterator without values of its own whose stage pulls the values from the
+// iterator it has been made from only when they are asked for, so a chain of
+// them allocates no array per stage. The stages are kept by the vm like the
+// watchers and an adapter ends the iterators it has been made from along with it.
+
+use crate::{Vm, Value, ValueIter, ValuePtr, RuntimeError, RuntimeResult};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Stage {
+    // The callbacks are called with the value and its index like the ones of the
+    // array methods.
+    Map { function: Value, index: usize },
+    Filter { function: Value, index: usize },
+    Take { remaining: usize },
+    Skip { remaining: usize },
+    Enumerate { index: usize },
+    // The lines of the file of `fs.lines`, which is closed once they have all been
+    // read. The source of this stage is empty.
+    Lines { rid: u32 }
+}
+
+impl Stage {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Map { .. } => "map",
+            Self::Filter { .. } => "filter",
+            Self::Take { .. } => "take",
+            Self::Skip { .. } => "skip",
+            Self::Enumerate { .. } => "enumerate",
+            Self::Lines { .. } => "lines"
+        }
+    }
+
+    pub fn function(&self) -> Option<Value> {
+        match *self {
+            Self::Map { function, .. } | Self::Filter { function, .. } => Some(function),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Pipeline {
+    pub source: ValuePtr<ValueIter>,
+    pub stage: Stage
+}
+
+impl Vm {
+
+    pub(crate) fn lazy_iter(&mut self, source: ValuePtr<ValueIter>, stage: Stage) -> Value {
+        let id = self.next_pipeline_id;
+        self.next_pipeline_id += 1;
+        self.pipelines.insert(id, Pipeline { source, stage });
+        Value::Iterator(self.allocate_value_ptr(ValueIter::lazy(id)))
+    }
+
+    // The next value of an iterator, which runs the stages of the adapters. An
+    // adapter which has ended gives no more values.
+    pub(crate) fn iter_next(&mut self, iterator: ValuePtr<ValueIter>) -> RuntimeResult<Option<Value>> {
+        let id = match iterator.unwrap_ref().pipeline() {
+            Some(id) => id,
+            None => return Ok(iterator.unwrap_mut().next())
+        };
+
+        // The pipeline stays with the vm while the callbacks run so that the garbage
+        // collector still sees it. The counters are written back afterwards.
+        let Pipeline { source, mut stage } = match self.pipelines.get(&id) {
+            Some(&pipeline) => pipeline,
+            None => return Ok(None)
+        };
+
+        let next = self.next_of_stage(source, &mut stage)?;
+        match next {
+            Some(_) => if let Some(pipeline) = self.pipelines.get_mut(&id) {
+                pipeline.stage = stage;
+            },
+            None => self.end_pipeline(id)
+        }
+
+        Ok(next)
+    }
+
+    fn next_of_stage(&mut self, source: ValuePtr<ValueIter>, stage: &mut Stage) -> RuntimeResult<Option<Value>> {
+        let name = stage.name();
+        match stage {
+            Stage::Map { function, index } => match self.iter_next(source)? {
+                Some(item) => {
+                    *index += 1;
+                    self.call_stage(name, *function, item, *index - 1).map(Some)
+                },
+                None => Ok(None)
+            },
+            Stage::Filter { function, index } => {
+                while let Some(item) = self.iter_next(source)? {
+                    *index += 1;
+                    if self.call_stage(name, *function, item, *index - 1)?.to_bool() {
+                        return Ok(Some(item));
+                    }
+                }
+
+                Ok(None)
+            },
+            // The source is not pulled once the values have been taken.
+            Stage::Take { remaining } => match *remaining {
+                0 => Ok(None),
+                _ => {
+                    *remaining -= 1;
+                    self.iter_next(source)
+                }
+            },
+            Stage::Skip { remaining } => {
+                while *remaining > 0 {
+                    *remaining -= 1;
+                    if self.iter_next(source)?.is_none() {
+                        return Ok(None);
+                    }
+                }
+
+                self.iter_next(source)
+            },
+            Stage::Enumerate { index } => match self.iter_next(source)? {
+                Some(item) => {
+                    *index += 1;
+                    let entry = vec![Value::Int(*index as isize - 1), item];
+                    Ok(Some(Value::Array(self.allocate_value_ptr(entry))))
+                },
+                None => Ok(None)
+            },
+            Stage::Lines { rid } => self.next_line(*rid)
+        }
+    }
+
+    fn next_line(&mut self, rid: u32) -> RuntimeResult<Option<Value>> {
+        let mut line = Vec::new();
+        let read = match self.get_io_resource(rid) {
+            Some(resource) => resource.read_line(&mut line),
+            None => return Ok(None)
+        };
+
+        match read {
+            Ok(0) => {
+                self.resource_table.remove(&rid);
+                Ok(None)
+            },
+            Ok(_) => {
+                strip_line_break(&mut line);
+
+                let line = String::from_utf8_lossy(&line).into_owned();
+                Ok(Some(Value::String(self.allocate_string(line))))
+            },
+            Err(kind) => Err(RuntimeError::new(self, format!("[fs.lines]: Cannot read the file: {}.", kind)))
+        }
+    }
+
+    fn call_stage(&mut self, name: &str, function: Value, item: Value, index: usize) -> RuntimeResult<Value> {
+        self.stack.extend_from_slice(&[item, Value::Int(index as isize)]);
+        self.call_function_with_returned_value(function, 2)
+            .map_err(|error| RuntimeError::caused_by(self, format!("[Iterator.{}]: The callback threw an error.", name), error))
+    }
+
+    // The remaining values of an iterator as an array.
+    pub(crate) fn collect_iter(&mut self, iterator: ValuePtr<ValueIter>) -> RuntimeResult<Value> {
+        // The values are kept on the stack while the next ones are pulled, which
+        // can run the callbacks of the adapters.
+        let start = self.stack.len();
+        loop {
+            match self.iter_next(iterator) {
+                Ok(Some(value)) => self.stack.push(value),
+                Ok(None) => break,
+                Err(error) => {
+                    self.stack.truncate(start);
+                    return Err(error);
+                }
+            }
+        }
+
+        let values = self.stack.split_off(start);
+        Ok(Value::Array(self.allocate_value_ptr(values)))
+    }
+
+    // Drops the stage of an adapter which has ended and the ones of the adapters
+    // it has been made from.
+    fn end_pipeline(&mut self, id: u32) {
+        let mut next = Some(id);
+        while let Some(id) = next {
+            next = self.pipelines.remove(&id).and_then(|pipeline| pipeline.source.unwrap_ref().pipeline());
+        }
+    }
+
+}
+
+// Drops the line break at the end of a line, where `\r\n` counts as one.
+pub(crate) fn strip_line_break(line: &mut Vec<u8>) {
+    if line.last() == Some(&b'\n') {
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+    }
+}
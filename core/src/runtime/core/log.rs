@@ -0,0 +1,73 @@
+// The leveled logging of `window.log`. The lines are written to the stderr of the
+// vm as "<timestamp> <LEVEL> <message>" with the timestamp in UTC.
+
+use std::time::SystemTime;
+use super::date;
+use super::tty::{self, Color};
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub enum Level {
+    Debug,
+    #[default]
+    Info,
+    Warn,
+    Error
+}
+
+impl Level {
+    pub const NAMES: [&'static str; 4] = ["debug", "info", "warn", "error"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "debug" => Some(Self::Debug),
+            "info" => Some(Self::Info),
+            "warn" => Some(Self::Warn),
+            "error" => Some(Self::Error),
+            _ => None
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES[self as usize]
+    }
+
+    // The labels are padded to the same width so that the messages line up.
+    fn label(self) -> &'static str {
+        match self {
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO ",
+            Self::Warn => "WARN ",
+            Self::Error => "ERROR"
+        }
+    }
+
+    // Only the levels which need the attention of the user are colored.
+    fn color(self) -> Option<Color> {
+        match self {
+            Self::Warn => Some(Color::Yellow),
+            Self::Error => Some(Color::Red),
+            _ => None
+        }
+    }
+}
+
+pub fn line(level: Level, message: &str, colored: bool, now: SystemTime) -> String {
+    let label = match level.color() {
+        Some(color) => tty::paint(level.label(), color, colored),
+        None => level.label().to_owned()
+    };
+
+    format!("{} {} {}\n", timestamp(now), label, message)
+}
+
+// Formats the time such as "2024-01-31T12:00:00.000Z".
+fn timestamp(now: SystemTime) -> String {
+    let (date, time) = date::from_system_time(now);
+    let (year, month, day) = date::date_into_ymd(date);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year, month, day,
+        time / 3_600_000, time / 60_000 % 60, time / 1000 % 60, time % 1000
+    )
+}
@@ -14,7 +14,7 @@ impl MapBuilder<'_> {
     pub fn native_fn(&mut self, name: &str, func: NativeFunctionHandler) {
         let bytes = name.as_bytes();
         let name = self.vm.allocate_str_bytes(bytes);
-        let nf = NativeFunction { name: TinyString::new(bytes), func };
+        let nf = NativeFunction { name: TinyString::new(bytes), func, bound: None };
         let ptr = self.vm.allocate_value_ptr(nf);
         self.map.insert(Value::String(name), (Value::NativeFn(ptr), true));
     }
@@ -30,6 +30,12 @@ impl MapBuilder<'_> {
         self.map.insert(Value::String(allocated), (Value::String(allocated_constant), true));
     }
 
+    // An entry which can be reassigned by the scripts.
+    pub fn variable(&mut self, name: &str, value: Value) {
+        let allocated = self.vm.allocate_static_str(name);
+        self.map.insert(Value::String(allocated), (value, false));
+    }
+
     pub fn allocate(self) -> *mut u8 {
         self.vm.allocate(self.map)
     }
@@ -59,7 +65,7 @@ impl ClassBuilder<'_> {
     pub fn native_fn(&mut self, name: &str, func: NativeFunctionHandler) {
         let bytes = name.as_bytes();
         let name = self.vm.allocate_str_bytes(bytes);
-        let nf = NativeFunction { name: TinyString::new(bytes), func };
+        let nf = NativeFunction { name: TinyString::new(bytes), func, bound: None };
         let ptr = self.vm.allocate_value_ptr(nf);
         self.map.insert(Value::String(name), (Value::NativeFn(ptr), true));
     }
@@ -67,7 +73,7 @@ impl ClassBuilder<'_> {
     pub fn prototype_fn(&mut self, name: &str, func: NativeFunctionHandler) {
         let bytes = name.as_bytes();
         let name = self.vm.allocate_str_bytes(bytes);
-        let nf = NativeFunction { name: TinyString::new(bytes), func };
+        let nf = NativeFunction { name: TinyString::new(bytes), func, bound: None };
         let ptr = self.vm.allocate_value_ptr(nf);
         self.prototype.insert(Value::String(name), (Value::NativeFn(ptr), true));
     }
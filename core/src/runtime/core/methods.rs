@@ -4,21 +4,88 @@ macro_rules! methods {
     }};
 }
 
+use crate::Value;
+
+// Whether the flag is set in the options dict passed to a method.
+fn option_enabled(options: Option<&Value>, name: &str) -> bool {
+    match options {
+        Some(Value::Dict(map)) => map.unwrap_ref().iter().any(|(key, (value, _))| {
+            matches!(key, Value::String(key) if key.unwrap_ref() as &str == name) && value.to_bool()
+        }),
+        _ => false
+    }
+}
+
 pub mod iterator {
 
-    use crate::{Vm, Value, TinyString, ValueIter};
+    use crate::{Vm, Value, TinyString, ValueIter, ValuePtr, RuntimeError, RuntimeResult};
     use crate::runtime::core::map_builder::MapBuilder;
+    use crate::runtime::core::lazy::Stage;
 
     pub fn init(vm: &mut Vm) {
         methods!(vm.iterator_methods, {
-            "clone" => |vm, iterator, _, _| Ok(Value::Iterator(vm.allocate_value_ptr(iterator.clone()))),
+            "clone" => |vm, iterator, _, _| match iterator.pipeline() {
+                Some(_) => Err(RuntimeError::new(vm, "[Iterator.clone]: Cannot clone a lazy iterator.")),
+                None => Ok(Value::Iterator(vm.allocate_value_ptr(iterator.clone())))
+            },
             "current" => |_, iterator, _, _| Ok(iterator.current()),
-            "next" => |_, iterator, _, _| Ok(
-                match iterator.next() {
-                    Some(value) => value,
-                    None => Value::Null
+            "next" => |vm, _, ptr, _| Ok(vm.iter_next(ValuePtr::new_unchecked(ptr))?.unwrap_or_default()),
+            // The adapters return a new iterator which pulls the values from this one
+            // only when they are asked for.
+            "map" => |vm, _, ptr, args| {
+                let function = callback(vm, "map", args)?;
+                Ok(vm.lazy_iter(ValuePtr::new_unchecked(ptr), Stage::Map { function, index: 0 }))
+            },
+            "filter" => |vm, _, ptr, args| {
+                let function = callback(vm, "filter", args)?;
+                Ok(vm.lazy_iter(ValuePtr::new_unchecked(ptr), Stage::Filter { function, index: 0 }))
+            },
+            "take" => |vm, _, ptr, args| match args.first() {
+                Some(&Value::Int(count)) if count >= 0 => Ok(vm.lazy_iter(ValuePtr::new_unchecked(ptr), Stage::Take { remaining: count as usize })),
+                _ => Err(RuntimeError::new_arguments(vm, "Iterator.take", "positive int", args))
+            },
+            "skip" => |vm, _, ptr, args| match args.first() {
+                Some(&Value::Int(count)) if count >= 0 => Ok(vm.lazy_iter(ValuePtr::new_unchecked(ptr), Stage::Skip { remaining: count as usize })),
+                _ => Err(RuntimeError::new_arguments(vm, "Iterator.skip", "positive int", args))
+            },
+            "enumerate" => |vm, _, ptr, _| Ok(vm.lazy_iter(ValuePtr::new_unchecked(ptr), Stage::Enumerate { index: 0 })),
+            // The terminal operations consume the rest of the iterator.
+            "collect" => |vm, _, ptr, _| vm.collect_iter(ValuePtr::new_unchecked(ptr)),
+            "count" => |vm, _, ptr, _| {
+                let mut count = 0;
+                while vm.iter_next(ValuePtr::new_unchecked(ptr))?.is_some() {
+                    count += 1;
                 }
-            ),
+
+                Ok(Value::Int(count))
+            },
+            "reduce" => |vm, _, ptr, args| {
+                let function = match args.first() {
+                    Some(value @ (Value::Function(_) | Value::NativeFn(_))) => *value,
+                    _ => return Err(RuntimeError::new_arguments(vm, "Iterator.reduce", "function, initial", args))
+                };
+
+                let mut accumulator = args.get(1).copied().unwrap_or_default();
+                let mut index = 0;
+                while let Some(item) = vm.iter_next(ValuePtr::new_unchecked(ptr))? {
+                    vm.stack.extend_from_slice(&[accumulator, item, Value::Int(index)]);
+                    accumulator = call_callback(vm, "reduce", function, 3)?;
+                    index += 1;
+                }
+
+                Ok(accumulator)
+            },
+            "forEach" => |vm, _, ptr, args| {
+                let function = callback(vm, "forEach", args)?;
+                let mut index = 0;
+                while let Some(item) = vm.iter_next(ValuePtr::new_unchecked(ptr))? {
+                    vm.stack.extend_from_slice(&[item, Value::Int(index)]);
+                    call_callback(vm, "forEach", function, 2)?;
+                    index += 1;
+                }
+
+                Ok(Value::Null)
+            },
         });
 
         let mut iterator_object = MapBuilder::new(vm);
@@ -36,22 +103,60 @@ pub mod iterator {
             Ok(Value::Iterator(vm.allocate_value_ptr(iter)))
         });
 
+        // Builds an array out of any iterable like a comprehension, with the values
+        // given by the callback which is called with each value and its index.
+        iterator_object.native_fn("collect", |vm, args| {
+            let (iterable, function) = match args {
+                [iterable, function @ (Value::Function(_) | Value::NativeFn(_))] => (*iterable, *function),
+                _ => return Err(RuntimeError::new_arguments(vm, "Iterator.collect", "iterable, function", args))
+            };
+
+            let iterator = match iterable {
+                Value::Iterator(ptr) => ptr,
+                value => vm.allocate_value_ptr(value.into_iter())
+            };
+
+            let mapped = vm.lazy_iter(iterator, Stage::Map { function, index: 0 });
+            match mapped {
+                Value::Iterator(ptr) => vm.collect_iter(ptr),
+                _ => Ok(Value::Null)
+            }
+        });
+
         let iterator = Value::Dict(iterator_object.allocate_value_ptr());
         vm.add_global("Iterator", iterator);
     }
 
+    fn callback(vm: &Vm, name: &str, args: &[Value]) -> RuntimeResult<Value> {
+        match args.first() {
+            Some(value @ (Value::Function(_) | Value::NativeFn(_))) => Ok(*value),
+            _ => Err(RuntimeError::new_arguments(vm, &format!("Iterator.{}", name), "function", args))
+        }
+    }
+
+    fn call_callback(vm: &mut Vm, name: &str, function: Value, args_len: u8) -> RuntimeResult<Value> {
+        vm.call_function_with_returned_value(function, args_len)
+            .map_err(|error| RuntimeError::caused_by(vm, format!("[Iterator.{}]: The callback threw an error.", name), error))
+    }
+
 }
 
 pub mod string {
 
+    use std::cmp::Ordering;
     use std::ops::Deref;
     use crate::{Vm, Value, TinyString};
     use crate::runtime::core::map_builder::MapBuilder;
+    use crate::runtime::core::{grapheme, collation};
+    use super::option_enabled;
 
     pub fn init(vm: &mut Vm) {
         methods!(vm.string_methods, {
             "len" => |_, string, _, _| Ok(Value::Int(string.len() as isize)),
             "isEmpty" => |_, string, _, _| Ok(Value::Bool(string.len() == 0)),
+            // The cached hash of the dict keys as an int, which can be negative. It is
+            // not cryptographic and can change between the versions of the runtime.
+            "hash" => |_, string, _, _| Ok(Value::Int(string.hash_u64() as isize)),
             "toLowerCase" => |vm, string, _, _| Ok(Value::String(vm.allocate_string(string.deref().to_lowercase()))),
             "toUpperCase" => |vm, string, _, _| Ok(Value::String(vm.allocate_string(string.deref().to_uppercase()))),
             "trim" => |vm, string, _, _| Ok(Value::String(vm.allocate_static_str(string.deref().trim()))),
@@ -107,6 +212,72 @@ pub mod string {
                     _ => false
                 }
             )),
+            "indexOf" => |_, string, _, args| Ok(Value::Int(
+                match args.first() {
+                    Some(Value::String(needle)) => {
+                        let start = char_offset(args.get(1), 0);
+                        let from = byte_offset(string, start);
+
+                        match string[from..].find(needle.unwrap_ref() as &str) {
+                            Some(index) => (start.min(string.chars().count()) + string[from..from + index].chars().count()) as isize,
+                            None => -1
+                        }
+                    },
+                    _ => -1
+                }
+            )),
+            "lastIndexOf" => |_, string, _, args| Ok(Value::Int(
+                match args.first() {
+                    Some(Value::String(needle)) => {
+                        let needle = needle.unwrap_ref() as &str;
+                        let before = char_offset(args.get(1), usize::MAX);
+                        let mut last = -1;
+
+                        // The needle may start at the index itself as in js.
+                        for (index, (byte, _)) in string.char_indices().enumerate().take_while(|&(index, _)| index <= before) {
+                            if string[byte..].starts_with(needle) {
+                                last = index as isize;
+                            }
+                        }
+
+                        if needle.is_empty() {
+                            last = before.min(string.chars().count()) as isize;
+                        }
+
+                        last
+                    },
+                    _ => -1
+                }
+            )),
+            "count" => |_, string, _, args| Ok(Value::Int(
+                match args.first() {
+                    // An empty needle matches before and after each of the chars.
+                    Some(Value::String(needle)) if needle.unwrap_ref().is_empty() => string.chars().count() as isize + 1,
+                    Some(Value::String(needle)) => string.matches(needle.unwrap_ref() as &str).count() as isize,
+                    _ => 0
+                }
+            )),
+            "equalsIgnoreCase" => |_, string, _, args| Ok(Value::Bool(
+                match args.first() {
+                    Some(Value::String(other)) => collation::equals_ignore_case(string, other.unwrap_ref()),
+                    _ => false
+                }
+            )),
+            "compare" => |_, string, _, args| Ok(
+                match args.first() {
+                    Some(Value::String(other)) => ordering_to_value((string.deref() as &str).cmp(other.unwrap_ref())),
+                    _ => Value::Null
+                }
+            ),
+            "localeCompare" => |_, string, _, args| Ok(
+                match args.first() {
+                    Some(Value::String(other)) => {
+                        let numeric = option_enabled(args.get(1), "numeric");
+                        ordering_to_value(collation::locale_compare(string, other.unwrap_ref(), numeric))
+                    },
+                    _ => Value::Null
+                }
+            ),
             "repeat" => |vm, string, _, args| Ok(
                 match args.get(0) {
                     Some(times) => Value::String(vm.allocate_string(string.deref().repeat(times.to_usize()))),
@@ -133,6 +304,37 @@ pub mod string {
 
                 Ok(Value::Array(vm.allocate_value_ptr(bytes)))
             },
+            "bytes" => |vm, string, _, _| {
+                let bytes = string.to_bytes().iter().map(|byte| Value::Int(*byte as isize)).collect();
+                Ok(Value::Array(vm.allocate_value_ptr(bytes)))
+            },
+            "byteLength" => |_, string, _, _| Ok(Value::Int(string.to_bytes().len() as isize)),
+            "codePoints" => |vm, string, _, _| {
+                let code_points = string.deref().chars().map(|c| Value::Int(c as isize)).collect();
+                Ok(Value::Array(vm.allocate_value_ptr(code_points)))
+            },
+            "graphemes" => |vm, string, _, _| {
+                let mut clusters = Vec::new();
+                for cluster in grapheme::graphemes(string.deref()) {
+                    clusters.push(Value::String(vm.allocate_static_str(cluster)));
+                }
+
+                Ok(Value::Array(vm.allocate_value_ptr(clusters)))
+            },
+            // A final newline does not add an empty line at the end.
+            "lines" => |vm, string, _, _| {
+                let lines = string.deref().lines().map(|line| Value::String(vm.allocate_static_str(line))).collect();
+                Ok(Value::Array(vm.allocate_value_ptr(lines)))
+            },
+            "words" => |vm, string, _, _| {
+                let words = string.deref().split_whitespace().map(|word| Value::String(vm.allocate_static_str(word))).collect();
+                Ok(Value::Array(vm.allocate_value_ptr(words)))
+            },
+            // Splits by the code points like `charCount` unlike `graphemes`.
+            "chars" => |vm, string, _, _| {
+                let chars = (string.deref() as &str).chars().map(|c| Value::String(vm.allocate_static_str(c.encode_utf8(&mut [0; 4])))).collect();
+                Ok(Value::Array(vm.allocate_value_ptr(chars)))
+            },
         });
 
         let mut string_object = MapBuilder::new(vm);
@@ -165,35 +367,72 @@ pub mod string {
         vm.add_global("String", string)
     }
 
+    fn ordering_to_value(ordering: Ordering) -> Value {
+        Value::Int(ordering as isize)
+    }
+
+    // The indexes of the strings count chars rather than bytes as `charCount` does.
+    fn char_offset(value: Option<&Value>, default: usize) -> usize {
+        match value {
+            Some(Value::Int(index)) => (*index).max(0) as usize,
+            Some(Value::Float(index)) => index.max(0.0) as usize,
+            _ => default
+        }
+    }
+
+    // The byte offset of the char at the index or the length of the string if the
+    // index is out of bounds.
+    fn byte_offset(string: &str, index: usize) -> usize {
+        string.char_indices().nth(index).map_or(string.len(), |(byte, _)| byte)
+    }
+
 }
 
 pub mod boolean {
 
-    use crate::{Vm, Value};
+    use crate::{Vm, Value, RuntimeResult};
     use crate::runtime::core::map_builder::MapBuilder;
 
     pub fn init(vm: &mut Vm) {
         let mut boolean_object = MapBuilder::new(vm);
 
-        boolean_object.native_fn("from", |_, args| Ok(Value::Bool(
+        boolean_object.native_fn("from", to_boolean);
+
+        let boolean = boolean_object.allocate_value_ptr();
+        vm.add_converter(boolean, "Boolean", to_boolean);
+        vm.add_global("Boolean", Value::Dict(boolean));
+    }
+
+    fn to_boolean(_: &mut Vm, args: &[Value]) -> RuntimeResult<Value> {
+        Ok(Value::Bool(
             match args.get(0) {
                 Some(value) => value.to_bool(),
                 None => false
             }
-        )));
-
-        let boolean = Value::Dict(boolean_object.allocate_value_ptr());
-        vm.add_global("Boolean", boolean);
+        ))
     }
 
 }
 
 pub mod object {
 
-    use crate::{Vm, Value, RuntimeError, Map};
+    use crate::{Vm, Value, RuntimeError, Map, TinyString, ValuePtr};
     use crate::runtime::core::map_builder::MapBuilder; 
 
     pub fn init(vm: &mut Vm) {
+        methods!(vm.dict_methods, {
+            // The method form of `|=` which returns the object for chaining.
+            "update" => |vm, _, ptr, args| {
+                match args.first() {
+                    Some(&Value::Dict(entries)) => {
+                        vm.merge_dict(ValuePtr::new_unchecked(ptr), entries)?;
+                        Ok(Value::Dict(ValuePtr::new_unchecked(ptr)))
+                    },
+                    _ => Err(RuntimeError::new_arguments(vm, "Object.update", "object", args))
+                }
+            },
+        });
+
         let mut object_ = MapBuilder::new(vm);
 
         object_.native_fn("create", |vm, args| Ok(
@@ -203,18 +442,20 @@ pub mod object {
                     object.insert(vm.constants.prototype, (Value::Dict(*ptr), true));
                     Value::Dict(vm.allocate_value_ptr(object))
                 },
-                _ => return Err(RuntimeError::new(vm, "[Object.create]: Expected (object) arguments."))
+                _ => return Err(RuntimeError::new_arguments(vm, "Object.create", "object", args))
             }
         ));
 
         object_.native_fn("defineProperty", |vm, args| {
             match args.get(0..3) {
                 Some(&[Value::Dict(ptr), key, value]) => {
+                    let key = vm.map_key(key)?;
                     if let Some((_, true)) = ptr.unwrap_mut().insert(key, (value, false)) {
                         return Err(RuntimeError::new_uncatchable(vm, format!("[Object.defineReadonlyProperty]: The key {:?} is private to assign.", key)))
                     }
                 },
                 Some(&[Value::Instance(ptr), key, value]) => {
+                    let key = vm.map_key(key)?;
                     if let Some((_, true)) = ptr.unwrap_map_mut().insert(key, (value, false)) {
                         return Err(RuntimeError::new_uncatchable(vm, format!("[Object.defineReadonlyProperty]: The key {:?} is private to assign.", key)))
                     }
@@ -228,11 +469,13 @@ pub mod object {
         object_.native_fn("defineReadonlyProperty", |vm, args| {
             match args.get(0..3) {
                 Some(&[Value::Dict(ptr), key, value]) => {
+                    let key = vm.map_key(key)?;
                     if let Some((_, true)) = ptr.unwrap_mut().insert(key, (value, true)) {
                         return Err(RuntimeError::new_uncatchable(vm, format!("[Object.defineReadonlyProperty]: The key {:?} is private to assign.", key)))
                     }
                 },
                 Some(&[Value::Instance(ptr), key, value]) => {
+                    let key = vm.map_key(key)?;
                     if let Some((_, true)) = ptr.unwrap_map_mut().insert(key, (value, true)) {
                         return Err(RuntimeError::new_uncatchable(vm, format!("[Object.defineReadonlyProperty]: The key {:?} is private to assign.", key)))
                     }
@@ -285,6 +528,49 @@ pub mod object {
             }
         ));
 
+        // The pairs are either `[key, value]` arrays or `{ key, value }` objects.
+        object_.native_fn("fromEntries", |vm, args| {
+            let entries = match args.first() {
+                Some(Value::Array(ptr)) => ptr.unwrap_ref(),
+                _ => return Err(RuntimeError::new_arguments(vm, "Object.fromEntries", "array", args))
+            };
+
+            let mut object = Map::with_capacity(entries.len());
+            for (index, entry) in entries.iter().enumerate() {
+                let pair = match entry {
+                    Value::Array(pair) => match pair.unwrap_ref().as_slice() {
+                        &[key, value] => Some((key, value)),
+                        _ => None
+                    },
+                    Value::Dict(pair) => {
+                        let field = |name: &str| pair.unwrap_ref().iter().find_map(|(key, (value, _))| match key {
+                            Value::String(key) if key.unwrap_ref() as &str == name => Some(*value),
+                            _ => None
+                        });
+
+                        field("key").zip(field("value"))
+                    },
+                    _ => None
+                };
+
+                let (key, value) = match pair {
+                    Some(pair) => pair,
+                    None => return Err(RuntimeError::new(vm, format!(
+                        "[Object.fromEntries]: Expected the entry at index {} to be a [key, value] pair but found {}.", index, entry.get_type()
+                    )))
+                };
+
+                let key = match vm.map_key(key) {
+                    Ok(key) => key,
+                    Err(_) => return Err(RuntimeError::new(vm, format!("[Object.fromEntries]: The entry at index {} has NaN as its key.", index)))
+                };
+
+                object.insert(key, (value, false));
+            }
+
+            Ok(Value::Dict(vm.allocate_value_ptr(object)))
+        });
+
         object_.native_fn("keys", |vm, args| Ok(
             match args.get(0) {
                 Some(Value::Dict(ptr)) => {
@@ -334,7 +620,7 @@ pub mod object {
                 Some(Value::Dict(ptr)) => {
                     let map = ptr.unwrap_mut();
                     for key in args.get(1..).unwrap() {
-                        map.remove(key);
+                        map.shift_remove(&vm.map_key(*key)?);
                     }
 
                     Ok(Value::Null)
@@ -342,12 +628,12 @@ pub mod object {
                 Some(Value::Instance(ptr)) => {
                     let map = ptr.unwrap_map_mut();
                     for key in args.get(1..).unwrap() {
-                        map.remove(key);
+                        map.shift_remove(&vm.map_key(*key)?);
                     }
 
                     Ok(Value::Null)
                 },
-                _ => return Err(RuntimeError::new(vm, "[Object.remove]: Expected (object, ..keys) arguments."))
+                _ => return Err(RuntimeError::new_arguments(vm, "Object.remove", "object, ...any", args))
             }
         });
 
@@ -405,7 +691,8 @@ pub mod function {
         let mut function_object = MapBuilder::new(vm);
         let noop = function_object.vm.allocate_value_ptr(NativeFunction {
             func: |_, _| Ok(Value::Null),
-            name: TinyString::new(b"noop")
+            name: TinyString::new(b"noop"),
+            bound: None
         });
 
         function_object.constant("noop", Value::NativeFn(noop));
@@ -435,18 +722,134 @@ pub mod function {
 
 pub mod array {
 
-    use crate::{Vm, Value, TinyString, ValuePtr};
+    use std::cmp::Ordering;
+    use crate::{Vm, Value, TinyString, ValuePtr, ValueIter, RuntimeError, RuntimeResult};
     use crate::runtime::core::map_builder::MapBuilder;
+    use crate::runtime::core::collation;
+    use crate::runtime::core::diff::structurally_equal;
+    use crate::runtime::memory::VALUE_BYTES;
+    use super::option_enabled;
     
     fn ptr_as_value_array(ptr: *const u8) -> Value {
         Value::Array(ValuePtr::new_unchecked(ptr))
     }
 
+    // Calls the callback of the higher order methods with the (item, index) arguments.
+    fn call_with_item(vm: &mut Vm, name: &str, function: Value, item: Value, index: usize) -> RuntimeResult<Value> {
+        vm.stack.extend_from_slice(&[item, Value::Int(index as isize)]);
+        call_callback(vm, name, function, 2)
+    }
+
+    // The errors thrown by the callbacks are chained to an error naming the method.
+    fn call_callback(vm: &mut Vm, name: &str, function: Value, args_len: u8) -> RuntimeResult<Value> {
+        vm.call_function_with_returned_value(function, args_len)
+            .map_err(|error| RuntimeError::caused_by(vm, format!("[Array.{}]: The callback threw an error.", name), error))
+    }
+
+    // The default order of `sort` which is the one of `Value::total_cmp` except for
+    // the strings which can be compared by their natural order.
+    fn compare_values(a: &Value, b: &Value, natural: bool, lenient: bool) -> Ordering {
+        match (a, b) {
+            (Value::String(a), Value::String(b)) if natural => collation::locale_compare(a.unwrap_ref(), b.unwrap_ref(), true),
+            _ => a.total_cmp(b, lenient).unwrap_or(Ordering::Equal)
+        }
+    }
+
+    // A stable bottom-up merge sort of a copy of the items. It never panics when
+    // the order is not total like the one of a random comparator, which only
+    // gives an unspecified order, and it stops at the first error of the order.
+    fn merge_sort<F>(items: &[Value], mut order: F) -> RuntimeResult<Vec<Value>>
+    where F: FnMut(Value, Value) -> RuntimeResult<Ordering> {
+        let mut from = items.to_vec();
+        let mut to = from.clone();
+        let len = from.len();
+        let mut width = 1;
+
+        while width < len {
+            for start in (0..len).step_by(width * 2) {
+                let middle = (start + width).min(len);
+                let end = (start + width * 2).min(len);
+                let (mut left, mut right) = (start, middle);
+
+                for slot in &mut to[start..end] {
+                    // The left item goes first unless the right one is strictly less,
+                    // which keeps the sort stable.
+                    let take_left = left < middle && (right >= end || order(from[right], from[left])? != Ordering::Less);
+                    if take_left {
+                        *slot = from[left];
+                        left += 1;
+                    } else {
+                        *slot = from[right];
+                        right += 1;
+                    }
+                }
+            }
+
+            std::mem::swap(&mut from, &mut to);
+            width *= 2;
+        }
+
+        Ok(from)
+    }
+
+    // An index argument of the methods working on a range of the array. The
+    // negative indexes count from the end and every index is clamped to the array.
+    fn bound(vm: &Vm, name: &str, args: &[Value], index: usize, len: usize, default: usize) -> RuntimeResult<usize> {
+        match args.get(index) {
+            None | Some(Value::Null) => Ok(default),
+            Some(&Value::Int(int)) if int < 0 => Ok(len.saturating_sub(int.unsigned_abs())),
+            Some(&Value::Int(int)) => Ok((int as usize).min(len)),
+            value => Err(RuntimeError::new_argument(vm, &format!("Array.{}", name), index, "int", value))
+        }
+    }
+
+    // The allocations which cannot be made are thrown instead of aborting.
+    fn reserve(vm: &Vm, name: &str, array: &mut Vec<Value>, additional: usize) -> RuntimeResult<()> {
+        array.try_reserve(additional)
+            .map_err(|_| RuntimeError::new(vm, format!("[Array.{}]: Cannot allocate {} more items.", name, additional)))
+    }
+
+    // The first index whose item is not before the searched one, found by halving
+    // the range so that it ends even when the array is not sorted.
+    fn lower_bound<F>(vm: &mut Vm, array: &[Value], mut before: F) -> RuntimeResult<usize>
+    where
+        F: FnMut(&mut Vm, Value) -> RuntimeResult<bool>
+    {
+        let (mut low, mut high) = (0, array.len());
+        while low < high {
+            let middle = low + (high - low) / 2;
+            match before(vm, array[middle])? {
+                true => low = middle + 1,
+                false => high = middle
+            }
+        }
+
+        Ok(low)
+    }
+
+    fn search_result(vm: &mut Vm, found: bool, index: usize) -> Value {
+        let mut result = MapBuilder::new(vm);
+        result.constant("found", Value::Bool(found));
+        result.constant("index", Value::Int(index as isize));
+        Value::Dict(result.allocate_value_ptr())
+    }
+
+    fn flatten(result: &mut Vec<Value>, array: &[Value], depth: usize) {
+        for &item in array {
+            match item {
+                Value::Array(ptr) if depth != 0 => flatten(result, ptr.unwrap_ref(), depth - 1),
+                item => result.push(item)
+            }
+        }
+    }
+
     pub fn init(vm: &mut Vm) {
         methods!(vm.array_methods, {
             "len" => |_, array, _, _| Ok(Value::Int(array.len() as isize)),
             "clone" => |vm, array, _,  _| Ok(Value::Array(vm.allocate_value_ptr(array.clone()))),
             "isEmpty" => |_, array, _, _| Ok(Value::Bool(array.len() == 0)),
+            // The start of a lazy chain such as `array.iter().map(fn).take(n)`.
+            "iter" => |vm, array, _, _| Ok(Value::Iterator(vm.allocate_value_ptr(ValueIter::new(array)))),
             "concat" => |vm, array, ptr, args| Ok(
                 match args.get(0) {
                     Some(Value::Array(ptr)) => {
@@ -471,88 +874,217 @@ pub mod array {
                 Ok(ptr_as_value_array(ptr))
             },
             "forEach" => |vm, array, _, args| {
-                let mut index = 0;
-                let function = match args.get(0) {
+                let function = match args.first() {
                     Some(value) => *value,
                     None => return Ok(Value::Null)
                 };
                 
-                for item in array.iter() {
-                    vm.stack.extend_from_slice(&[*item, Value::Int(index)]);
-                    if let Err(error) = vm.call_function_with_returned_value(function, 2) {
-                        return Err(error);
-                    }
-
-                    index += 1;
+                for (index, &item) in array.iter().enumerate() {
+                    call_with_item(vm, "forEach", function, item, index)?;
                 }
 
                 Ok(Value::Null)
             },
             "filter" => |vm, array, _, args| {
-                let mut index = 0;
                 let mut values = Vec::new();
-                let function = match args.get(0) {
+                let function = match args.first() {
                     Some(value) => *value,
                     None => return Ok(Value::Null)
                 };
 
-                for &item in array.iter() {
-                    vm.stack.extend_from_slice(&[item, Value::Int(index)]);
-                    match vm.call_function_with_returned_value(function, 2) {
-                        Ok(value) => values.push(value),
-                        Err(error) => return Err(error)
+                for (index, &item) in array.iter().enumerate() {
+                    if call_with_item(vm, "filter", function, item, index)?.to_bool() {
+                        values.push(item);
                     }
-
-                    index += 1;
                 }
 
                 Ok(Value::Array(vm.allocate_value_ptr(values)))
             },
             "find" => |vm, array, _, args| {
-                let mut index = 0;
-                let function = match args.get(0) {
+                let function = match args.first() {
                     Some(value) => *value,
                     None => return Ok(Value::Null)
                 };
                 
-                for &item in array.iter() {
-                    vm.stack.extend_from_slice(&[item, Value::Int(index)]);
-                    match vm.call_function_with_returned_value(function, 2) {
-                        Ok(value) => {
-                            if value.to_bool() {
-                                return Ok(item);
-                            }
-                        },
-                        Err(error) => return Err(error)
+                for (index, &item) in array.iter().enumerate() {
+                    if call_with_item(vm, "find", function, item, index)?.to_bool() {
+                        return Ok(item);
                     }
-
-                    index += 1;
                 }
 
                 Ok(Value::Null)
             },
             "findIndex" => |vm, array, _, args| {
-                let mut index = 0;
-                let function = match args.get(0) {
+                let function = match args.first() {
                     Some(value) => *value,
                     None => return Ok(Value::Null)
                 };
                 
+                for (index, &item) in array.iter().enumerate() {
+                    if call_with_item(vm, "findIndex", function, item, index)?.to_bool() {
+                        return Ok(Value::Int(index as isize));
+                    }
+                }
+
+                Ok(Value::Int(-1))
+            },
+            "some" => |vm, array, _, args| {
+                let function = match args.first() {
+                    Some(value) => *value,
+                    None => return Ok(Value::Bool(false))
+                };
+
+                for (index, &item) in array.iter().enumerate() {
+                    if call_with_item(vm, "some", function, item, index)?.to_bool() {
+                        return Ok(Value::Bool(true));
+                    }
+                }
+
+                Ok(Value::Bool(false))
+            },
+            "every" => |vm, array, _, args| {
+                let function = match args.first() {
+                    Some(value) => *value,
+                    None => return Ok(Value::Bool(true))
+                };
+
+                for (index, &item) in array.iter().enumerate() {
+                    if !call_with_item(vm, "every", function, item, index)?.to_bool() {
+                        return Ok(Value::Bool(false));
+                    }
+                }
+
+                Ok(Value::Bool(true))
+            },
+            "reduce" => |vm, array, _, args| {
+                let function = match args.first() {
+                    Some(value) => *value,
+                    None => return Err(RuntimeError::new_arguments(vm, "Array.reduce", "function, initial?", args))
+                };
+
+                // The first item is used as the initial value if there is no one.
+                let (mut accumulator, skip) = match args.get(1) {
+                    Some(&initial) => (initial, 0),
+                    None => match array.first() {
+                        Some(&first) => (first, 1),
+                        None => return Err(RuntimeError::new(vm, "[Array.reduce]: Cannot reduce an empty array without an initial value."))
+                    }
+                };
+
+                for (index, &item) in array.iter().enumerate().skip(skip) {
+                    vm.stack.extend_from_slice(&[accumulator, item, Value::Int(index as isize)]);
+                    accumulator = call_callback(vm, "reduce", function, 3)?;
+                }
+
+                Ok(accumulator)
+            },
+            "flat" => |vm, array, _, args| {
+                let depth = match args.first() {
+                    Some(value) => value.to_usize(),
+                    None => 1
+                };
+
+                let mut result = Vec::new();
+                flatten(&mut result, array, depth);
+                Ok(Value::Array(vm.allocate_value_ptr(result)))
+            },
+            "flatMap" => |vm, array, _, args| {
+                let function = match args.first() {
+                    Some(value) => *value,
+                    None => return Ok(Value::Null)
+                };
+
+                let start = vm.stack.len();
+                for (index, &item) in array.iter().enumerate() {
+                    match call_with_item(vm, "flatMap", function, item, index) {
+                        Ok(Value::Array(ptr)) => vm.stack.extend_from_slice(ptr.unwrap_ref()),
+                        Ok(value) => vm.stack.push(value),
+                        Err(error) => {
+                            vm.stack.truncate(start);
+                            return Err(error);
+                        }
+                    }
+                }
+
+                let result = vm.stack.split_off(start);
+                Ok(Value::Array(vm.allocate_value_ptr(result)))
+            },
+            // The values built by the methods below are kept on the stack until the
+            // array holding them is allocated so that they stay rooted.
+            "unique" => |vm, array, _, _| {
+                let start = vm.stack.len();
                 for &item in array.iter() {
-                    vm.stack.extend_from_slice(&[item, Value::Int(index)]);
-                    match vm.call_function_with_returned_value(function, 2) {
-                        Ok(value) => {
-                            if value.to_bool() {
-                                return Ok(Value::Int(index))
-                            }
-                        },
-                        Err(error) => return Err(error)
+                    if !vm.stack[start..].iter().any(|value| structurally_equal(value, &item)) {
+                        vm.stack.push(item);
                     }
+                }
 
-                    index += 1;
+                let result = vm.stack.split_off(start);
+                Ok(Value::Array(vm.allocate_value_ptr(result)))
+            },
+            "groupBy" => |vm, array, _, args| {
+                let function = match args.first() {
+                    Some(&function @ (Value::Function(_) | Value::NativeFn(_))) => function,
+                    _ => return Err(RuntimeError::new_arguments(vm, "Array.groupBy", "function", args))
+                };
+
+                // The groups keep the order in which their keys were first returned.
+                let mut groups: Vec<(String, Vec<Value>)> = Vec::new();
+                for (index, &item) in array.iter().enumerate() {
+                    let key = format!("{}", call_with_item(vm, "groupBy", function, item, index)?);
+                    match groups.iter_mut().find(|(group_key, _)| *group_key == key) {
+                        Some((_, items)) => items.push(item),
+                        None => groups.push((key, vec![item]))
+                    }
                 }
 
-                Ok(Value::Int(-1))
+                // The keys and the groups are pushed in pairs.
+                let start = vm.stack.len();
+                for (key, items) in groups {
+                    let key = vm.allocate_string(key);
+                    let items = vm.allocate_value_ptr(items);
+                    vm.stack.extend_from_slice(&[Value::String(key), Value::Array(items)]);
+                }
+
+                let entries = vm.stack.split_off(start);
+                let mut dict = MapBuilder::new(vm);
+                for pair in entries.chunks_exact(2) {
+                    dict.map.insert(pair[0], (pair[1], false));
+                }
+
+                Ok(Value::Dict(dict.allocate_value_ptr()))
+            },
+            "chunk" => |vm, array, _, args| {
+                let size = match args.first() {
+                    Some(&Value::Int(size)) if size > 0 => size as usize,
+                    Some(Value::Int(_)) => return Err(RuntimeError::new(vm, "[Array.chunk]: Expected the size to be a positive int.")),
+                    _ => return Err(RuntimeError::new_arguments(vm, "Array.chunk", "int", args))
+                };
+
+                let start = vm.stack.len();
+                for chunk in array.chunks(size) {
+                    let chunk = vm.allocate_value_ptr(chunk.to_vec());
+                    vm.stack.push(Value::Array(chunk));
+                }
+
+                let chunks = vm.stack.split_off(start);
+                Ok(Value::Array(vm.allocate_value_ptr(chunks)))
+            },
+            "zip" => |vm, array, _, args| {
+                let other = match args.first() {
+                    Some(Value::Array(other)) => other.unwrap_ref(),
+                    _ => return Err(RuntimeError::new_arguments(vm, "Array.zip", "array", args))
+                };
+
+                // The pairs stop at the end of the shorter array.
+                let start = vm.stack.len();
+                for (&a, &b) in array.iter().zip(other.iter()) {
+                    let pair = vm.allocate_value_ptr(vec![a, b]);
+                    vm.stack.push(Value::Array(pair));
+                }
+
+                let pairs = vm.stack.split_off(start);
+                Ok(Value::Array(vm.allocate_value_ptr(pairs)))
             },
             "includes" => |_, array, _, args| {
                 let value = match args.get(0) {
@@ -603,6 +1135,49 @@ pub mod array {
 
                 Ok(Value::Int(result))
             },
+            // The searches of a sorted array in the order of `sort`. They give the
+            // leftmost index of the value, or the index where it would be inserted
+            // when it is not found. The result on an unsorted array is some index.
+            "binarySearch" => |vm, array, _, args| {
+                let value = args.first().copied().unwrap_or(Value::Null);
+                let index = lower_bound(vm, array, |_, item| Ok(item.cmp(&value) == Ordering::Less))?;
+                let found = array.get(index).is_some_and(|item| item.cmp(&value) == Ordering::Equal);
+                Ok(search_result(vm, found, index))
+            },
+            // The callback gives a negative number for the items before the searched
+            // one, 0 for a match and a positive number for the items after it.
+            "binarySearchBy" => |vm, array, _, args| {
+                let function = match args.first() {
+                    Some(&function) if function.is_function() => function,
+                    _ => return Err(RuntimeError::new_arguments(vm, "Array.binarySearchBy", "function", args))
+                };
+
+                let order = |vm: &mut Vm, item: Value| {
+                    vm.stack.push(item);
+                    call_callback(vm, "binarySearchBy", function, 1).map(|order| order.to_f64())
+                };
+
+                let index = lower_bound(vm, array, |vm, item| Ok(order(vm, item)? < 0.0))?;
+                let found = match array.get(index) {
+                    Some(&item) => order(vm, item)? == 0.0,
+                    None => false
+                };
+
+                Ok(search_result(vm, found, index))
+            },
+            // Inserts the value after the equal ones and returns its index.
+            "insertSorted" => |vm, array, _, args| {
+                let value = args.first().copied().unwrap_or(Value::Null);
+                let index = lower_bound(vm, array, |_, item| Ok(item.cmp(&value) != Ordering::Greater))?;
+
+                reserve(vm, "insertSorted", array, 1)?;
+                array.insert(index, value);
+                vm.approx_bytes += VALUE_BYTES;
+                Ok(Value::Int(index as isize))
+            },
+            "isSorted" => |_, array, _, _| {
+                Ok(Value::Bool(array.windows(2).all(|pair| pair[0].cmp(&pair[1]) != Ordering::Greater)))
+            },
             "join" => |vm, array, _, args| {
                 let seperator = match args.get(0) {
                     Some(Value::String(ptr)) if array.len() != 0 => ptr.unwrap_bytes(),
@@ -626,49 +1201,143 @@ pub mod array {
 
                 Ok(Value::String(vm.allocate_str_bytes(bytes.as_slice())))
             },
+            // The results are kept on the stack so that they stay rooted while the
+            // callback runs for the next items.
             "map" => |vm, array, _, args| {
-                let mut index = 0;
-                let mut result = Vec::new();
-                let function = match args.get(0) {
+                let function = match args.first() {
                     Some(value) => *value,
                     None => return Ok(Value::Null)
                 };
-                
-                for &item in array.iter() {
-                    vm.stack.extend_from_slice(&[item, Value::Int(index)]);
-                    match vm.call_function_with_returned_value(function, 2) {
-                        Ok(value) => result.push(value),
-                        Err(error) => return Err(error)
-                    }
 
-                    index += 1;
+                let start = vm.stack.len();
+                for (index, &item) in array.iter().enumerate() {
+                    match call_with_item(vm, "map", function, item, index) {
+                        Ok(value) => vm.stack.push(value),
+                        Err(error) => {
+                            vm.stack.truncate(start);
+                            return Err(error);
+                        }
+                    }
                 }
 
+                let result = vm.stack.split_off(start);
                 Ok(Value::Array(vm.allocate_value_ptr(result)))
             },
             "pop" => |_, array, _, _| {
                 Ok(array.pop().unwrap_or(Value::Null))
             },
-            "push" => |_, array, _, args| {
+            "push" => |vm, array, _, args| {
                 let value = match args.get(0) {
                     Some(value) => *value,
                     None => Value::Null
                 };
 
                 array.push(value);
+                vm.approx_bytes += VALUE_BYTES;
                 Ok(value)
             },
             "reverse" => |_, array, ptr, _| {
                 array.reverse();
                 Ok(ptr_as_value_array(ptr))
             },
-            "resize" => |_, array, ptr, args| {
-                array.resize_with(match args.get(0) {
+            "sort" => |vm, array, ptr, args| {
+                match args.first() {
+                    Some(&function @ (Value::Function(_) | Value::NativeFn(_))) => {
+                        // The first error thrown by the comparator stops the sort and
+                        // leaves the array as it was. The items are rooted on the stack
+                        // in case the comparator removes them from the array.
+                        let start = vm.stack.len();
+                        vm.stack.extend_from_slice(array);
+                        let sorted = merge_sort(array, |a, b| {
+                            vm.stack.extend_from_slice(&[a, b]);
+                            let result = call_callback(vm, "sort", function, 2)?;
+                            Ok(result.to_f64().partial_cmp(&0.0).unwrap_or(Ordering::Equal))
+                        });
+
+                        vm.stack.truncate(start);
+                        *array = sorted?;
+                    },
+                    options => {
+                        let natural = option_enabled(options, "natural");
+                        let lenient = option_enabled(options, "lenient");
+
+                        // The values which cannot be compared are found before sorting
+                        // so that the array is left as it is. Every value other than
+                        // null has to be comparable with the first one for that.
+                        if !lenient {
+                            let mut values = array.iter().filter(|value| **value != Value::Null);
+                            if let Some(first) = values.next() {
+                                if let Some(value) = values.find(|value| value.total_cmp(first, false).is_none()) {
+                                    return Err(RuntimeError::new(vm, format!(
+                                        "[Array.sort]: Cannot compare {} with {}. Pass {{ lenient: true }} to order the values by their types.",
+                                        first.get_type(),
+                                        value.get_type()
+                                    )));
+                                }
+                            }
+                        }
+
+                        if let Ok(sorted) = merge_sort(array, |a, b| Ok(compare_values(&a, &b, natural, lenient))) {
+                            *array = sorted;
+                        }
+                    }
+                }
+
+                Ok(ptr_as_value_array(ptr))
+            },
+            // The new items are set to the second argument, or null without it.
+            "resize" => |vm, array, ptr, args| {
+                let len = match args.first() {
                     Some(&Value::Int(int)) if int >= 0 => int as usize,
                     Some(&Value::Float(float)) if float >= 0.0 => float as usize,
                     _ => 0
-                }, || Value::Null);
+                };
 
+                let added = len.saturating_sub(array.len());
+                reserve(vm, "resize", array, added)?;
+                array.resize(len, args.get(1).copied().unwrap_or(Value::Null));
+                vm.approx_bytes += added * VALUE_BYTES;
+                Ok(ptr_as_value_array(ptr))
+            },
+            "fill" => |vm, array, ptr, args| {
+                let value = args.first().copied().unwrap_or(Value::Null);
+                let start = bound(vm, "fill", args, 1, array.len(), 0)?;
+                let end = bound(vm, "fill", args, 2, array.len(), array.len())?;
+
+                if start < end {
+                    array[start..end].fill(value);
+                }
+
+                Ok(ptr_as_value_array(ptr))
+            },
+            // Copies the items between the start and the end to the target index,
+            // stopping at the end of the array. The ranges can overlap.
+            "copyWithin" => |vm, array, ptr, args| {
+                let len = array.len();
+                let target = match args.first() {
+                    Some(_) => bound(vm, "copyWithin", args, 0, len, 0)?,
+                    None => return Err(RuntimeError::new_arguments(vm, "Array.copyWithin", "int, int?, int?", args))
+                };
+
+                let start = bound(vm, "copyWithin", args, 1, len, 0)?;
+                let end = bound(vm, "copyWithin", args, 2, len, len)?;
+                let count = end.saturating_sub(start).min(len - target);
+                array.copy_within(start..start + count, target);
+
+                Ok(ptr_as_value_array(ptr))
+            },
+            // Pushes the items of an array with a single reservation.
+            "pushAll" => |vm, array, ptr, args| {
+                let items = match args.first() {
+                    // The array can be pushed into itself.
+                    Some(Value::Array(items)) if items.as_ptr() == ptr => array.clone(),
+                    Some(Value::Array(items)) => items.unwrap_ref().clone(),
+                    _ => return Err(RuntimeError::new_arguments(vm, "Array.pushAll", "array", args))
+                };
+
+                reserve(vm, "pushAll", array, items.len())?;
+                array.extend_from_slice(&items);
+                vm.approx_bytes += items.len() * VALUE_BYTES;
                 Ok(ptr_as_value_array(ptr))
             },
             "remove" => |_, array, ptr, args| {
@@ -727,8 +1396,160 @@ pub mod array {
             Ok(Value::Array(vm.allocate_value_ptr(array)))
         });
 
+        // An empty array with room for the given number of items.
+        array_object.native_fn("withCapacity", |vm, args| {
+            let capacity = match args.first() {
+                Some(&Value::Int(int)) if int >= 0 => int as usize,
+                _ => return Err(RuntimeError::new_arguments(vm, "Array.withCapacity", "non-negative int", args))
+            };
+
+            let mut array = Vec::new();
+            reserve(vm, "withCapacity", &mut array, capacity)?;
+            Ok(Value::Array(vm.allocate_value_ptr(array)))
+        });
+
         let array = Value::Dict(array_object.allocate_value_ptr());
         vm.add_global("Array", array);
     }
 
-}
\ No newline at end of file
+}
+pub mod bytes {
+
+    use crate::{Vm, Value, TinyString, RuntimeError, RuntimeResult};
+    use crate::runtime::core::map_builder::MapBuilder;
+    use crate::runtime::core::base64::{self, DecoderError};
+    use crate::runtime::core::json::bytes_to_base64;
+
+    #[derive(Clone, Copy)]
+    enum Encoding {
+        Utf8,
+        Hex,
+        Base64
+    }
+
+    // The encoding is utf-8 unless it is given.
+    fn encoding_of(vm: &Vm, name: &str, args: &[Value], index: usize) -> RuntimeResult<Encoding> {
+        match args.get(index) {
+            None | Some(Value::Null) => Ok(Encoding::Utf8),
+            Some(Value::String(ptr)) => match ptr.unwrap_ref() as &str {
+                "utf-8" | "utf8" => Ok(Encoding::Utf8),
+                "hex" => Ok(Encoding::Hex),
+                "base64" => Ok(Encoding::Base64),
+                encoding => Err(RuntimeError::new(vm, format!("[{}]: Unknown encoding \"{}\". Expected utf-8, hex or base64.", name, encoding)))
+            },
+            value => Err(RuntimeError::new_argument(vm, name, index, "string", value))
+        }
+    }
+
+    // Negative indexes count from the end and every index is clamped to the bytes.
+    fn bound(vm: &Vm, args: &[Value], index: usize, len: usize, default: usize) -> RuntimeResult<usize> {
+        match args.get(index) {
+            None | Some(Value::Null) => Ok(default),
+            Some(&Value::Int(int)) if int < 0 => Ok(len.saturating_sub(int.unsigned_abs())),
+            Some(&Value::Int(int)) => Ok((int as usize).min(len)),
+            value => Err(RuntimeError::new_argument(vm, "Bytes.slice", index, "int", value))
+        }
+    }
+
+    fn decode_hex(vm: &Vm, string: &str) -> RuntimeResult<Vec<u8>> {
+        if !string.len().is_multiple_of(2) {
+            return Err(RuntimeError::new(vm, "[Bytes.from]: The hex string has an odd length."));
+        }
+
+        let digits = string.as_bytes();
+        let mut bytes = Vec::with_capacity(digits.len() / 2);
+        for (index, pair) in digits.chunks(2).enumerate() {
+            let byte = std::str::from_utf8(pair).ok().and_then(|pair| u8::from_str_radix(pair, 16).ok());
+            match byte {
+                Some(byte) => bytes.push(byte),
+                None => return Err(RuntimeError::new(vm, format!("[Bytes.from]: Invalid hex digits at index {}.", index * 2)))
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    fn decode_base64(vm: &Vm, string: &str) -> RuntimeResult<Vec<u8>> {
+        match base64::decode(string.as_bytes()) {
+            Ok(decoded) => Ok(decoded.to_bytes().to_vec()),
+            Err(error) => Err(RuntimeError::new(vm, match error {
+                DecoderError::InvalidLength => "[Bytes.from]: The base64 string has invalid length.".to_owned(),
+                DecoderError::InvalidByte(index, byte) => format!("[Bytes.from]: Invalid byte {} at index {}.", byte, index),
+                DecoderError::InvalidLastSymbol(index, byte) => format!("[Bytes.from]: Invalid last symbol {} at index {}.", byte, index)
+            }))
+        }
+    }
+
+    pub fn init(vm: &mut Vm) {
+        methods!(vm.bytes_methods, {
+            "len" => |_, bytes, _, _| Ok(Value::Int(bytes.len() as isize)),
+            "isEmpty" => |_, bytes, _, _| Ok(Value::Bool(bytes.is_empty())),
+            // A copy of the bytes between the indexes, so that the slice does not
+            // change with the bytes it was taken from.
+            "slice" => |vm, bytes, _, args| {
+                let start = bound(vm, args, 0, bytes.len(), 0)?;
+                let end = bound(vm, args, 1, bytes.len(), bytes.len())?;
+                let slice = bytes.get(start..end).unwrap_or_default().to_vec();
+                Ok(Value::Bytes(vm.allocate_value_ptr(slice)))
+            },
+            "toArray" => |vm, bytes, _, _| {
+                let array = bytes.iter().map(|&byte| Value::Int(byte as isize)).collect();
+                Ok(Value::Array(vm.allocate_value_ptr(array)))
+            },
+            "toString" => |vm, bytes, _, args| {
+                let string = match encoding_of(vm, "Bytes.toString", args, 0)? {
+                    Encoding::Utf8 => match std::str::from_utf8(bytes) {
+                        Ok(string) => string.to_owned(),
+                        Err(error) => return Err(RuntimeError::new(vm, format!(
+                            "[Bytes.toString]: The bytes are not valid utf-8 from index {}.", error.valid_up_to()
+                        )))
+                    },
+                    Encoding::Hex => bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+                    Encoding::Base64 => bytes_to_base64(bytes)
+                };
+
+                Ok(Value::String(vm.allocate_string(string)))
+            },
+        });
+
+        let mut bytes_object = MapBuilder::new(vm);
+
+        // The bytes of a string in the given encoding, or of an array of ints
+        // between 0 and 255.
+        bytes_object.native_fn("from", |vm, args| {
+            let bytes = match args.first() {
+                Some(Value::String(ptr)) => {
+                    let string = ptr.unwrap_ref() as &str;
+                    match encoding_of(vm, "Bytes.from", args, 1)? {
+                        Encoding::Utf8 => string.as_bytes().to_vec(),
+                        Encoding::Hex => decode_hex(vm, string)?,
+                        Encoding::Base64 => decode_base64(vm, string)?
+                    }
+                },
+                Some(Value::Array(ptr)) => {
+                    let mut bytes = Vec::with_capacity(ptr.unwrap_ref().len());
+                    for (index, item) in ptr.unwrap_ref().iter().enumerate() {
+                        match *item {
+                            Value::Int(int @ 0..=255) => bytes.push(int as u8),
+                            item => return Err(RuntimeError::new(vm, format!(
+                                "[Bytes.from]: Expected the item at index {} to be an int between 0 and 255 but found {}.", index, item
+                            )))
+                        }
+                    }
+
+                    bytes
+                },
+                Some(Value::Bytes(ptr)) => ptr.unwrap_ref().clone(),
+                _ => return Err(RuntimeError::new_arguments(vm, "Bytes.from", "string | array | Bytes, string?", args))
+            };
+
+            Ok(Value::Bytes(vm.allocate_value_ptr(bytes)))
+        });
+
+        bytes_object.native_fn("isBytes", |_, args| Ok(Value::Bool(matches!(args.first(), Some(Value::Bytes(_))))));
+
+        let bytes = Value::Dict(bytes_object.allocate_value_ptr());
+        vm.add_global("Bytes", bytes);
+    }
+
+}
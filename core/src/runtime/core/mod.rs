@@ -5,86 +5,131 @@ pub mod window;
 pub mod base64;
 pub mod methods;
 pub mod date;
-
-use std::io;
+pub mod grapheme;
+pub mod crypto;
+pub mod signal;
+pub mod tty;
+pub mod collation;
+pub mod format;
+pub mod log;
+pub mod prompt;
+pub mod watch;
+pub mod benchmark;
+pub mod dotenv;
+pub mod diff;
+pub mod set;
+pub mod compat;
+pub mod lazy;
+pub mod installer;
+pub mod clock;
+pub mod string_builder;
+pub mod temp;
+
+use std::io::{Write, BufRead, BufReader};
+use std::fs::File;
 use std::ffi::OsStr;
 use std::path::Path;
 use std::convert::AsRef;
 use crate::{Vm, Value, TinyString, NativeFunction, RuntimeError, ValueIter};
 use base64::DecoderError;
 
-pub fn init(vm: &mut Vm) {
-
-    vm.add_global("NaN", Value::NAN);
-    vm.add_global("inf", Value::INFINITY);
-
-    macro_rules! init_module {
-        ($($name:expr => $method:ident)+) => {
-            $(
-                let $method = builtin::$method(vm);
-                vm.add_global($name, $method);
-            )+
-        };
-    }
-
-    init_module! {
-        "Math" => init_math
-        "Date" => init_date
-        "JSON" => init_json
-        "EventEmitter" => init_event_emitter
-        "Process" => init_process
-    }
+// The version of the runtime reported by `window.version`.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-    if vm.permissions.memory {
-        init_module! { "Memory" => init_memory }
-    }
+pub fn init(vm: &mut Vm) {
 
-    let window = window::init(vm);
-    vm.add_global("window", window);
+    vm.register_feature("forLoops", true, Some(VERSION));
+    vm.register_feature("tryCatch", true, Some(VERSION));
+    vm.register_feature("compoundAssign", true, Some(VERSION));
+    vm.register_feature("asyncAwait", false, None);
+    vm.register_feature("optionalChaining", false, None);
+    vm.register_feature("compatJs", compat::enabled(vm), Some(VERSION));
 
+    // The methods of the values are part of the language so that they are set up
+    // along with their namespaces like `String` whichever groups are installed.
     methods::iterator::init(vm);
     methods::string::init(vm);
     methods::boolean::init(vm);
     methods::object::init(vm);
     methods::function::init(vm);
     methods::array::init(vm);
+    methods::bytes::init(vm);
 
-    macro_rules! native_fn {
-        ($bytes:expr, $value:expr) => {{
-            let name = TinyString::new($bytes);
-            let constant_id = vm.chunk.constants.add_string(name.clone());
-            let nf = NativeFunction { name, func: $value };
-            let ptr = vm.allocate_value_ptr(nf);
-            vm.globals.insert(constant_id, (Value::NativeFn(ptr), false));
-        }};
-    }
+    installer::install(vm);
+    compat::init(vm);
+}
+
+macro_rules! native_fn {
+    ($vm:expr, $bytes:expr, $value:expr) => {{
+        let name = TinyString::new($bytes);
+        let constant_id = $vm.chunk.constants.add_string(name.clone());
+        let nf = NativeFunction { name, func: $value, bound: None };
+        let ptr = $vm.allocate_value_ptr(nf);
+        $vm.globals.insert(constant_id, (Value::NativeFn(ptr), true));
+    }};
+}
 
-    native_fn!(b"print", |_, args| {
+fn init_print(vm: &mut Vm) {
+    native_fn!(vm, b"print", |vm, args| {
         for arg in args {
-            print!("{}", arg);
+            if let Err(e) = write!(vm.out, "{}", arg.rounded(vm.display_digits)) {
+                return Err(RuntimeError::new_io(vm, e));
+            }
         }
 
         Ok(Value::Null)
     });
 
-    native_fn!(b"println", |_, args| {
+    native_fn!(vm, b"println", |vm, args| {
         for arg in args {
-            println!("{}", arg);
+            if let Err(e) = writeln!(vm.out, "{}", arg.rounded(vm.display_digits)) {
+                return Err(RuntimeError::new_io(vm, e));
+            }
         }
 
         Ok(Value::Null)
     });
+}
 
-    native_fn!(b"typeof", |vm, args| {
-        let type_ = vm.allocate_value_ptr(args.get(0).unwrap_or_default().get_type());
-        Ok(Value::String(type_))
-    });
+fn init_readline(vm: &mut Vm) {
+    native_fn!(vm, b"readline", |vm, _| {
+        // The prompt printed before is shown even if it does not end with a newline.
+        if let Err(e) = vm.out.flush() {
+            return Err(RuntimeError::new_io(vm, e));
+        }
 
-    native_fn!(b"throw", |vm, args| {
-        Err(RuntimeError::new(vm, format!("{:?}", args.get(0).unwrap_or_default())))
+        let mut line = String::new();
+
+        // When the script itself was piped through stdin, the lines are read
+        // from the terminal instead.
+        let result = if vm.flags.contains_key(&TinyString::new(b"stdin-script")) {
+            match File::open("/dev/tty") {
+                Ok(tty) => BufReader::new(tty).read_line(&mut line),
+                Err(_) => return Err(RuntimeError::new(vm, "Cannot read a line since stdin has been used to read the script."))
+            }
+        } else {
+            vm.input.read_line(&mut line)
+        };
+
+        match result {
+            Ok(_) => Ok(Value::String(vm.allocate_str_bytes(line.as_bytes()))),
+            Err(_) => Ok(Value::Null) // Incase if there is an error it returns null
+        }
     });
+}
 
-    native_fn!(b"parseInt", |_, args| {
+fn init_numbers(vm: &mut Vm) {
+    vm.add_global("NaN", Value::NAN);
+    vm.add_global("inf", Value::INFINITY);
+
+    native_fn!(vm, b"isNaN", |_, args| Ok(Value::Bool(
+        match args.get(0) {
+            Some(value) => value.is_nan(),
+            None => false
+        }
+    )));
+
+    native_fn!(vm, b"parseInt", |_, args| {
         match args.get(0)
             .unwrap_or_default()
             .unwrap_string()
@@ -95,7 +140,7 @@ pub fn init(vm: &mut Vm) {
         }
     });
 
-    native_fn!(b"parseFloat", |_, args| {
+    native_fn!(vm, b"parseFloat", |_, args| {
         match args.get(0)
             .unwrap_or_default()
             .unwrap_string()
@@ -105,23 +150,34 @@ pub fn init(vm: &mut Vm) {
             Err(_) => Ok(Value::NAN)
         }
     });
+}
 
-    native_fn!(b"readline", |vm, _| {
-        let mut line = String::new();
-        match io::stdin().read_line(&mut line) {
-            Ok(_) => Ok(Value::String(vm.allocate_str_bytes(line.as_bytes()))),
-            Err(_) => Ok(Value::Null) // Incase if there is an error it returns null
-        }
+fn init_types(vm: &mut Vm) {
+    native_fn!(vm, b"typeof", |vm, args| {
+        let type_ = vm.allocate_value_ptr(args.get(0).unwrap_or_default().get_type());
+        Ok(Value::String(type_))
     });
+}
 
-    native_fn!(b"isNaN", |_, args| Ok(Value::Bool(
-        match args.get(0) {
-            Some(value) => value.is_nan(),
-            None => false
-        }
-    )));
+fn init_errors(vm: &mut Vm) {
+    native_fn!(vm, b"throw", |vm, args| {
+        Err(RuntimeError::new(vm, format!("{:?}", args.get(0).unwrap_or_default())))
+    });
 
-    native_fn!(b"range", |vm, args| {
+    // Unlike `throw` an uncaught panic ends the process with a failure after the
+    // hook of `window.atPanic` has seen it.
+    native_fn!(vm, b"panic", |vm, args| {
+        let message = match args.first() {
+            Some(value) => format!("{}", value),
+            None => "The script has panicked.".to_owned()
+        };
+
+        Err(RuntimeError::new_panic(vm, message))
+    });
+}
+
+fn init_range(vm: &mut Vm) {
+    native_fn!(vm, b"range", |vm, args| {
         let (start, end) = match args {
             [value] => (0, value.to_isize()),
             [start, end] => (start.to_isize(), end.to_isize()),
@@ -135,8 +191,10 @@ pub fn init(vm: &mut Vm) {
 
         Ok(Value::Iterator(vm.allocate_value_ptr(ValueIter::new(vec.as_slice()))))
     });
+}
 
-    native_fn!(b"btoa", |vm, args| Ok(
+fn init_base64(vm: &mut Vm) {
+    native_fn!(vm, b"btoa", |vm, args| Ok(
         match args.get(0) {
             Some(Value::String(ptr)) => Value::String(
                 match base64::encode(ptr.unwrap_bytes()) {
@@ -148,7 +206,7 @@ pub fn init(vm: &mut Vm) {
         }
     ));
 
-    native_fn!(b"atob", |vm, args| Ok(
+    native_fn!(vm, b"atob", |vm, args| Ok(
         match args.get(0) {
             Some(Value::String(ptr)) => {
                 match base64::decode(ptr.unwrap_bytes()) {
@@ -167,7 +225,6 @@ pub fn init(vm: &mut Vm) {
             _ => Value::Null
         }
     ));
-
 }
 
 impl Value {
@@ -0,0 +1,280 @@
+// The menus of `window.select` and `window.multiSelect`, the text input of
+// `window.editor`, `window.confirm` and `window.prompt` and the permission prompts
+// of `--prompt-permissions`. The menus are navigated with the arrow keys when both
+// stdin and stdout are terminals and otherwise read the numbers of the options
+// line by line, which is also how the input given by embedders drives them.
+
+use std::{env, fs};
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Write, BufRead};
+use std::path::Path;
+use std::process::Command;
+use crate::Vm;
+use super::tty::{self, RawMode, Key};
+use super::temp::TempKind;
+
+// The answers given with `--assume-yes` and `--answer key=value`. They are served
+// to `window.confirm` and `window.prompt` before anything is read from the input.
+#[derive(Default)]
+pub struct Answers {
+    pub assume_yes: bool,
+    keyed: HashMap<String, VecDeque<String>>
+}
+
+impl Answers {
+    pub fn add(&mut self, key: &str, value: &str) {
+        self.keyed.entry(key.to_owned()).or_default().push_back(value.to_owned());
+    }
+
+    // The answers of a key are served in the order they were given.
+    pub fn take(&mut self, key: &str) -> Option<String> {
+        self.keyed.get_mut(key)?.pop_front()
+    }
+}
+
+pub fn parse_yes_no(answer: &str) -> Option<bool> {
+    match answer.to_lowercase().as_str() {
+        "y" | "yes" | "true" => Some(true),
+        "n" | "no" | "false" => Some(false),
+        _ => None
+    }
+}
+
+// Asks again until the answer is yes or no. An empty answer takes the default and
+// None is returned if the input has ended.
+pub fn confirm(vm: &mut Vm, message: &str, default: bool) -> io::Result<Option<bool>> {
+    let hint = if default { "[Y/n]" } else { "[y/N]" };
+
+    loop {
+        write!(vm.out, "{} {} ", message, hint)?;
+        vm.out.flush()?;
+
+        let mut line = String::new();
+        if vm.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        match line.trim() {
+            "" => return Ok(Some(default)),
+            answer => match parse_yes_no(answer) {
+                Some(answer) => return Ok(Some(answer)),
+                None => writeln!(vm.out, "Expected yes or no.")?
+            }
+        }
+    }
+}
+
+// The answer to a permission prompt of `--prompt-permissions`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Grant {
+    Once,
+    Always,
+    Deny
+}
+
+// Asks again until the answer is y, n or a. There is no default and `--assume-yes`
+// is ignored so that the permissions are never granted without the user. None is
+// returned if the input has ended.
+pub fn permission(vm: &mut Vm, message: &str) -> io::Result<Option<Grant>> {
+    loop {
+        write!(vm.out, "{} Allow? [y/n/a] ", message)?;
+        vm.out.flush()?;
+
+        let mut line = String::new();
+        if vm.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        match line.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Ok(Some(Grant::Once)),
+            "n" | "no" => return Ok(Some(Grant::Deny)),
+            "a" | "all" | "always" => return Ok(Some(Grant::Always)),
+            _ => writeln!(vm.out, "Expected y to allow once, a to allow for the rest of the run or n to deny.")?
+        }
+    }
+}
+
+// Reads a line of text where an empty line takes the default if there is one.
+pub fn text(vm: &mut Vm, message: &str, default: Option<&str>) -> io::Result<Option<String>> {
+    match default {
+        Some(default) => write!(vm.out, "{} ({}) ", message, default)?,
+        None => write!(vm.out, "{} ", message)?
+    }
+
+    vm.out.flush()?;
+    let mut line = String::new();
+    if vm.input.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+
+    let line = line.trim_end_matches(['\r', '\n']);
+    Ok(Some(match default {
+        Some(default) if line.is_empty() => default.to_owned(),
+        _ => line.to_owned()
+    }))
+}
+
+// Returns the indices of the chosen options or None if the input has ended or the
+// menu was cancelled with Ctrl+C.
+pub fn select(vm: &mut Vm, message: &str, options: &[String], multiple: bool) -> io::Result<Option<Vec<usize>>> {
+    if vm.input.is_terminal() && vm.out.is_terminal() {
+        if let Some(raw_mode) = RawMode::enable() {
+            let result = select_with_keys(vm, message, options, multiple);
+            drop(raw_mode);
+
+            // The cursor is shown again even if the menu failed.
+            write!(vm.out, "\x1b[?25h")?;
+            vm.out.flush()?;
+            return result;
+        }
+    }
+
+    select_with_lines(vm, message, options, multiple)
+}
+
+fn select_with_keys(vm: &mut Vm, message: &str, options: &[String], multiple: bool) -> io::Result<Option<Vec<usize>>> {
+    let mut current = 0;
+    let mut chosen = vec![false; options.len()];
+
+    // The output is not translated in the raw mode so the lines end with "\r\n".
+    write!(vm.out, "{}\r\n\x1b[?25l", message)?;
+    render_menu(vm, options, current, &chosen, multiple)?;
+
+    loop {
+        match tty::read_key(&mut vm.input)? {
+            Some(Key::Up) => current = (current + options.len() - 1) % options.len(),
+            Some(Key::Down) => current = (current + 1) % options.len(),
+            Some(Key::Space) if multiple => chosen[current] = !chosen[current],
+            Some(Key::Char(digit @ '1'..='9')) => {
+                let index = digit as usize - '1' as usize;
+                if index < options.len() {
+                    current = index;
+                }
+            },
+            Some(Key::Enter) if multiple => {
+                return Ok(Some((0..options.len()).filter(|&index| chosen[index]).collect()));
+            },
+            Some(Key::Enter) => return Ok(Some(vec![current])),
+            Some(Key::Cancel) | None => return Ok(None),
+            Some(_) => continue
+        }
+
+        write!(vm.out, "\x1b[{}A", options.len())?;
+        render_menu(vm, options, current, &chosen, multiple)?;
+    }
+}
+
+fn render_menu(vm: &mut Vm, options: &[String], current: usize, chosen: &[bool], multiple: bool) -> io::Result<()> {
+    for (index, option) in options.iter().enumerate() {
+        let pointer = if index == current { ">" } else { " " };
+        let checkbox = match (multiple, chosen[index]) {
+            (false, _) => "",
+            (true, true) => "[x] ",
+            (true, false) => "[ ] "
+        };
+
+        write!(vm.out, "\r\x1b[2K{} {}{}\r\n", pointer, checkbox, option)?;
+    }
+
+    vm.out.flush()
+}
+
+// The options are numbered from 1 and the answer is asked again until it is valid.
+fn select_with_lines(vm: &mut Vm, message: &str, options: &[String], multiple: bool) -> io::Result<Option<Vec<usize>>> {
+    writeln!(vm.out, "{}", message)?;
+    for (index, option) in options.iter().enumerate() {
+        writeln!(vm.out, "  {}) {}", index + 1, option)?;
+    }
+
+    loop {
+        match multiple {
+            true => write!(vm.out, "Enter the numbers separated by commas: ")?,
+            false => write!(vm.out, "Enter a number: ")?
+        }
+
+        vm.out.flush()?;
+        let mut line = String::new();
+        if vm.input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let numbers: Option<Vec<usize>> = line
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|number| !number.is_empty())
+            .map(|number| number.parse::<usize>().ok().filter(|number| (1..=options.len()).contains(number)))
+            .collect();
+
+        match numbers {
+            Some(mut numbers) if multiple => {
+                numbers.sort_unstable();
+                numbers.dedup();
+                return Ok(Some(numbers.into_iter().map(|number| number - 1).collect()));
+            },
+            Some(numbers) if numbers.len() == 1 => return Ok(Some(vec![numbers[0] - 1])),
+            _ => writeln!(vm.out, "Expected {} from 1 to {}.", if multiple { "numbers" } else { "a number" }, options.len())?
+        }
+    }
+}
+
+// Opens the editor given by $VISUAL or $EDITOR on a temporary file with the text.
+// Without a terminal the lines are read until the end of the input or a line
+// with a single ".". Returns None if the input has ended before any line.
+pub fn editor(vm: &mut Vm, text: &str) -> Result<Option<String>, String> {
+    if !vm.input.is_terminal() {
+        return edit_with_lines(vm).map_err(|error| error.to_string());
+    }
+
+    // The file is created like the ones of `fs.tempFile`, so that no other user
+    // can read the text or have placed a file under its name before.
+    vm.out.flush().map_err(|error| error.to_string())?;
+    let path = vm.create_temp(TempKind::File, "dashscript-", ".txt", true).map_err(|error| error.to_string())?;
+    if let Err(error) = fs::write(&path, text) {
+        let _ = fs::remove_file(&path);
+        return Err(error.to_string());
+    }
+
+    let result = run_editor(&path);
+    let content = result.and_then(|_| fs::read_to_string(&path).map_err(|error| error.to_string()));
+    let _ = fs::remove_file(&path);
+    content.map(Some)
+}
+
+fn run_editor(path: &Path) -> Result<(), String> {
+    let editor = env::var("VISUAL").or_else(|_| env::var("EDITOR")).unwrap_or_else(|_| {
+        if cfg!(windows) { "notepad".to_owned() } else { "vi".to_owned() }
+    });
+
+    // The editor can be given with its arguments such as "code --wait".
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().ok_or("The editor is empty.")?;
+
+    match Command::new(program).args(parts).arg(path).status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("The editor \"{}\" exited with {}.", editor, status)),
+        Err(error) => Err(format!("Could not start the editor \"{}\": {}.", editor, error))
+    }
+}
+
+fn edit_with_lines(vm: &mut Vm) -> io::Result<Option<String>> {
+    writeln!(vm.out, "Enter the text and end it with a line containing only \".\":")?;
+    vm.out.flush()?;
+
+    let mut text = String::new();
+    let mut empty = true;
+
+    loop {
+        let mut line = String::new();
+        if vm.input.read_line(&mut line)? == 0 {
+            break;
+        }
+
+        empty = false;
+        if line.trim_end_matches(['\r', '\n']) == "." {
+            break;
+        }
+
+        text.push_str(&line);
+    }
+
+    Ok(if empty { None } else { Some(text) })
+}
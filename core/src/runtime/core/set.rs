@@ -0,0 +1,207 @@
+// The `Set` class. The members of an instance are the keys of its `__members`
+// dict, which is what the lookups go through, and are kept in the order they
+// were added in its `__values` array. Only the values which can be compared by
+// the dicts can be members, so the arrays and dicts are rejected until they
+// can be hashed by their contents.
+
+use crate::{Vm, Value, Map, Instance, ValuePtr, RuntimeError, RuntimeResult};
+use super::map_builder::ClassBuilder;
+
+pub fn init(vm: &mut Vm) -> Value {
+    let mut set = ClassBuilder::new(vm);
+
+    set.init(|vm, args| {
+        let members = match args {
+            [Value::Instance(_)] | [Value::Instance(_), Value::Null] => Vec::new(),
+            [Value::Instance(_), Value::Array(ptr)] => ptr.unwrap_ref().clone(),
+            _ => return Err(RuntimeError::new_arguments(vm, "Set.init", "Set, array?", args))
+        };
+
+        let (members, values) = collect(vm, "Set.init", &members)?;
+        if let Value::Instance(ptr) = args[0] {
+            store(vm, ptr.unwrap_mut(), members, values);
+        }
+
+        Ok(Value::Null)
+    });
+
+    // Returns the set so that the calls can be chained.
+    set.prototype_fn("add", |vm, args| {
+        match args {
+            [this @ Value::Instance(ptr), value] => {
+                let (members, values) = parts(vm, "Set.add", ptr)?;
+                let member = member_of(vm, "Set.add", *value)?;
+
+                if !members.unwrap_ref().contains_key(&member) {
+                    members.unwrap_mut().insert(member, (Value::Null, false));
+                    values.unwrap_mut().push(member);
+                    update_size(vm, ptr);
+                }
+
+                Ok(*this)
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "Set.add", "Set, any", args))
+        }
+    });
+
+    set.prototype_fn("has", |vm, args| {
+        match args {
+            [Value::Instance(ptr), value] => {
+                let (members, _) = parts(vm, "Set.has", ptr)?;
+                let found = match member_of(vm, "Set.has", *value) {
+                    Ok(member) => members.unwrap_ref().contains_key(&member),
+                    Err(_) => false
+                };
+
+                Ok(Value::Bool(found))
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "Set.has", "Set, any", args))
+        }
+    });
+
+    // Returns whether the value was a member.
+    set.prototype_fn("delete", |vm, args| {
+        match args {
+            [Value::Instance(ptr), value] => {
+                let (members, values) = parts(vm, "Set.delete", ptr)?;
+                let member = match member_of(vm, "Set.delete", *value) {
+                    Ok(member) => member,
+                    Err(_) => return Ok(Value::Bool(false))
+                };
+
+                if members.unwrap_mut().shift_remove(&member).is_none() {
+                    return Ok(Value::Bool(false));
+                }
+
+                let values = values.unwrap_mut();
+                if let Some(index) = values.iter().position(|value| *value == member) {
+                    values.remove(index);
+                }
+
+                update_size(vm, ptr);
+                Ok(Value::Bool(true))
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "Set.delete", "Set, any", args))
+        }
+    });
+
+    set.prototype_fn("values", |vm, args| {
+        match args {
+            [Value::Instance(ptr), ..] => {
+                let (_, values) = parts(vm, "Set.values", ptr)?;
+                let values = values.unwrap_ref().clone();
+                Ok(Value::Array(vm.allocate_value_ptr(values)))
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "Set.values", "Set", args))
+        }
+    });
+
+    // The `for` loops iterate over the values of the set.
+    set.prototype_fn("__iter", |vm, args| {
+        match args {
+            [Value::Instance(ptr), ..] => {
+                let (_, values) = parts(vm, "Set.__iter", ptr)?;
+                Ok(Value::Array(values))
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "Set.__iter", "Set", args))
+        }
+    });
+
+    // The results keep the order of the set on the left and then the one of the
+    // set on the right.
+    set.prototype_fn("union", |vm, args| {
+        combine(vm, "Set.union", args, |_, _| true, true)
+    });
+
+    set.prototype_fn("intersect", |vm, args| {
+        combine(vm, "Set.intersect", args, |other, member| other.contains_key(member), false)
+    });
+
+    set.prototype_fn("difference", |vm, args| {
+        combine(vm, "Set.difference", args, |other, member| !other.contains_key(member), false)
+    });
+
+    let (class, prototype) = set.allocate_value_ptr_with_prototype();
+    vm.constants.set_prototype = prototype;
+
+    Value::Dict(class)
+}
+
+// The key of a value in the dict of the members. The floats without a fraction
+// are turned into ints so that `1` and `1.0` are the same member.
+fn member_of(vm: &Vm, name: &str, value: Value) -> RuntimeResult<Value> {
+    match value {
+        Value::Float(float) if float.is_nan() => Err(RuntimeError::new(vm, format!("[{}]: NaN cannot be a member of a set.", name))),
+        Value::Float(float) if float.fract() == 0.0 && float.abs() < isize::MAX as f64 => Ok(Value::Int(float as isize)),
+        Value::Null | Value::Bool(_) | Value::Int(_) | Value::Float(_) | Value::String(_) => Ok(value),
+        value => Err(RuntimeError::new(vm, format!(
+            "[{}]: Cannot use a {} as a member of a set. Only numbers, strings, booleans and null can be members.", name, value.get_type()
+        )))
+    }
+}
+
+// The members without their duplicates, in the order of their first occurrence.
+fn collect(vm: &Vm, name: &str, values: &[Value]) -> RuntimeResult<(Map, Vec<Value>)> {
+    let mut members = Map::with_capacity(values.len());
+    let mut ordered = Vec::with_capacity(values.len());
+
+    for &value in values {
+        let member = member_of(vm, name, value)?;
+        if members.insert(member, (Value::Null, false)).is_none() {
+            ordered.push(member);
+        }
+    }
+
+    Ok((members, ordered))
+}
+
+fn store(vm: &mut Vm, instance: &mut Instance, members: Map, values: Vec<Value>) {
+    let size = Value::Int(values.len() as isize);
+    let members = Value::Dict(vm.allocate_value_ptr(members));
+    let values = Value::Array(vm.allocate_value_ptr(values));
+
+    instance.properties.insert(vm.constants.__members, (members, true));
+    instance.properties.insert(vm.constants.__values, (values, true));
+    instance.properties.insert(vm.constants.size, (size, true));
+}
+
+fn parts(vm: &Vm, name: &str, ptr: &ValuePtr<Instance>) -> RuntimeResult<(ValuePtr<Map>, ValuePtr<Vec<Value>>)> {
+    let properties = &ptr.unwrap_ref().properties;
+    match (properties.get(&vm.constants.__members), properties.get(&vm.constants.__values)) {
+        (Some(&(Value::Dict(members), _)), Some(&(Value::Array(values), _))) => Ok((members, values)),
+        _ => Err(RuntimeError::new_receiver(vm, name, "Set", Some(&Value::Instance(*ptr))))
+    }
+}
+
+fn update_size(vm: &mut Vm, ptr: &ValuePtr<Instance>) {
+    let instance = ptr.unwrap_mut();
+    if let Some(&(Value::Array(values), _)) = instance.properties.get(&vm.constants.__values) {
+        instance.properties.insert(vm.constants.size, (Value::Int(values.unwrap_ref().len() as isize), true));
+    }
+}
+
+// A new set with the members of the left set which are kept by the filter and,
+// when `append` is set, the ones of the right set as well.
+fn combine(vm: &mut Vm, name: &str, args: &[Value], filter: fn(&Map, &Value) -> bool, append: bool) -> RuntimeResult<Value> {
+    let (left, right) = match args {
+        [Value::Instance(left), Value::Instance(right)] => (parts(vm, name, left)?, parts(vm, name, right)?),
+        _ => return Err(RuntimeError::new_arguments(vm, name, "Set, Set", args))
+    };
+
+    let ((_, left_values), (right_members, right_values)) = (left, right);
+    let right_members = right_members.unwrap_ref();
+    let mut values: Vec<Value> = left_values.unwrap_ref().iter()
+        .filter(|member| filter(right_members, member))
+        .copied()
+        .collect();
+
+    if append {
+        values.extend_from_slice(right_values.unwrap_ref());
+    }
+
+    let (members, values) = collect(vm, name, &values)?;
+    let mut instance = Instance { properties: Map::new(), methods: vm.constants.set_prototype };
+    store(vm, &mut instance, members, values);
+
+    Ok(Value::Instance(vm.allocate_value_ptr(instance)))
+}
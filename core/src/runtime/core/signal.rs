@@ -0,0 +1,42 @@
+// Handles the interrupt signals of the process for `window.signal`. The handler
+// only sets a flag which is checked by the vm between instructions so that the
+// callbacks of the scripts are never executed from the signal handler itself.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const SIGINT: i32 = 2;
+#[cfg(windows)]
+const SIGBREAK: i32 = 21;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" {
+    fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+}
+
+extern "C" fn on_signal(_: i32) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+// Replaces the default handlers which would kill the process. This is only done
+// once a script registers a callback.
+pub fn install() {
+    unsafe {
+        signal(SIGINT, on_signal);
+        #[cfg(windows)]
+        signal(SIGBREAK, on_signal);
+    }
+}
+
+// Simulates an interrupt signal. Used by the embedders to stop the scripts.
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+pub fn reset() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
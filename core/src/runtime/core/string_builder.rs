@@ -0,0 +1,118 @@
+// The `StringBuilder` class, which collects the pieces of a string in a single
+// growing buffer kept as the bytes of its `__buffer` property. Appending to it
+// takes the time of the piece only, unlike `s = s + piece` which copies the whole
+// string every time. Its `length` is in bytes like the one of the strings.
+
+use crate::{Vm, Value, ValuePtr, Instance, RuntimeError, RuntimeResult};
+use super::map_builder::ClassBuilder;
+
+pub fn init(vm: &mut Vm) -> Value {
+    let mut builder = ClassBuilder::new(vm);
+
+    builder.init(|vm, args| {
+        let initial = match args {
+            [Value::Instance(_)] | [Value::Instance(_), Value::Null] => Vec::new(),
+            [Value::Instance(_), value] => piece_of(vm, *value),
+            _ => return Err(RuntimeError::new_arguments(vm, "StringBuilder.init", "StringBuilder, any?", args))
+        };
+
+        if let Value::Instance(ptr) = args[0] {
+            let length = Value::Int(initial.len() as isize);
+            let buffer = Value::Bytes(vm.allocate_value_ptr(initial));
+            let properties = &mut ptr.unwrap_mut().properties;
+            properties.insert(vm.constants.__buffer, (buffer, true));
+            properties.insert(vm.constants.length, (length, true));
+        }
+
+        Ok(Value::Null)
+    });
+
+    // Returns the builder so that the calls can be chained.
+    builder.prototype_fn("append", |vm, args| {
+        match args {
+            [this @ Value::Instance(ptr), value] => {
+                append(vm, "StringBuilder.append", ptr, Some(*value), false)?;
+                Ok(*this)
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "StringBuilder.append", "StringBuilder, any", args))
+        }
+    });
+
+    builder.prototype_fn("appendLine", |vm, args| {
+        match args {
+            [this @ Value::Instance(ptr)] => {
+                append(vm, "StringBuilder.appendLine", ptr, None, true)?;
+                Ok(*this)
+            },
+            [this @ Value::Instance(ptr), value] => {
+                append(vm, "StringBuilder.appendLine", ptr, Some(*value), true)?;
+                Ok(*this)
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "StringBuilder.appendLine", "StringBuilder, any?", args))
+        }
+    });
+
+    // Keeps the capacity of the buffer for the next pieces.
+    builder.prototype_fn("clear", |vm, args| {
+        match args {
+            [this @ Value::Instance(ptr)] => {
+                buffer(vm, "StringBuilder.clear", ptr)?.unwrap_mut().clear();
+                update_length(vm, ptr);
+                Ok(*this)
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "StringBuilder.clear", "StringBuilder", args))
+        }
+    });
+
+    builder.prototype_fn("toString", |vm, args| {
+        match args {
+            [Value::Instance(ptr)] => {
+                let buffer = buffer(vm, "StringBuilder.toString", ptr)?;
+                Ok(Value::String(vm.allocate_str_bytes(buffer.unwrap_ref())))
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "StringBuilder.toString", "StringBuilder", args))
+        }
+    });
+
+    Value::Dict(builder.allocate_value_ptr())
+}
+
+// The bytes of a piece, which are the contents of the strings and of the other
+// builders and the text of the other values as `+` writes them.
+fn piece_of(vm: &Vm, value: Value) -> Vec<u8> {
+    match value {
+        Value::Instance(ptr) => match ptr.unwrap_ref().properties.get(&vm.constants.__buffer) {
+            Some((Value::Bytes(buffer), _)) => buffer.unwrap_ref().clone(),
+            _ => value.to_tiny_string().to_bytes().to_vec()
+        },
+        value => value.to_tiny_string().to_bytes().to_vec()
+    }
+}
+
+fn append(vm: &mut Vm, name: &str, ptr: &ValuePtr<Instance>, value: Option<Value>, line: bool) -> RuntimeResult<()> {
+    let piece = value.map(|value| piece_of(vm, value)).unwrap_or_default();
+
+    let buffer = buffer(vm, name, ptr)?.unwrap_mut();
+    buffer.extend_from_slice(&piece);
+    if line {
+        buffer.push(b'\n');
+    }
+
+    update_length(vm, ptr);
+    Ok(())
+}
+
+fn buffer(vm: &Vm, name: &str, ptr: &ValuePtr<Instance>) -> RuntimeResult<ValuePtr<Vec<u8>>> {
+    match ptr.unwrap_ref().properties.get(&vm.constants.__buffer) {
+        Some(&(Value::Bytes(buffer), _)) => Ok(buffer),
+        _ => Err(RuntimeError::new_receiver(vm, name, "StringBuilder", Some(&Value::Instance(*ptr))))
+    }
+}
+
+fn update_length(vm: &mut Vm, ptr: &ValuePtr<Instance>) {
+    let instance = ptr.unwrap_mut();
+    if let Some(&(Value::Bytes(buffer), _)) = instance.properties.get(&vm.constants.__buffer) {
+        instance.properties.insert(vm.constants.length, (Value::Int(buffer.unwrap_ref().len() as isize), true));
+    }
+}
+
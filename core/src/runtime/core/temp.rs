@@ -0,0 +1,84 @@
+// The temporary files and directories of `fs.tempFile` and `fs.tempDir`. They
+// are created in the temporary directory of the system under a random name,
+// which is tried again when it is taken, and only their owner can access them
+// on unix. The ones which are not kept are removed once the scripts have ended.
+
+use std::{env, fs, io};
+use std::fs::OpenOptions;
+use std::path::{Path, PathBuf};
+use crate::Vm;
+
+const ATTEMPTS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TempKind {
+    File,
+    Dir
+}
+
+impl Vm {
+
+    pub(crate) fn create_temp(&mut self, kind: TempKind, prefix: &str, suffix: &str, keep: bool) -> io::Result<PathBuf> {
+        let dir = env::temp_dir();
+        let mut last_error = None;
+
+        for _ in 0..ATTEMPTS {
+            let path = dir.join(format!("{}{:016x}{}", prefix, self.next_random(), suffix));
+            let created = match kind {
+                TempKind::File => create_file(&path),
+                TempKind::Dir => create_dir(&path)
+            };
+
+            match created {
+                Ok(()) => {
+                    if !keep {
+                        self.temp_paths.push((path.clone(), kind));
+                    }
+
+                    return Ok(path);
+                },
+                Err(error) if error.kind() == io::ErrorKind::AlreadyExists => last_error = Some(error),
+                Err(error) => return Err(error)
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| io::Error::from(io::ErrorKind::AlreadyExists)))
+    }
+
+    // Removes the temporary entries, the last created first. The ones which
+    // cannot be removed are left behind.
+    pub(crate) fn remove_temp_paths(&mut self) {
+        for (path, kind) in self.temp_paths.drain(..).rev() {
+            let _ = match kind {
+                TempKind::File => fs::remove_file(&path),
+                TempKind::Dir => fs::remove_dir_all(&path)
+            };
+        }
+    }
+
+}
+
+fn create_file(path: &Path) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(path).map(|_| ())
+}
+
+fn create_dir(path: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+
+    builder.create(path)
+}
@@ -0,0 +1,358 @@
+// Terminal detection used by `window.tty` and the raw mode used by the menus of
+// `window.select`. The platform apis are declared here directly to avoid
+// depending on the libc and winapi crates.
+
+use std::env;
+use std::io::{self, Read};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stream {
+    Stdin,
+    Stdout,
+    Stderr
+}
+
+impl Stream {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "stdin" => Some(Self::Stdin),
+            "stdout" => Some(Self::Stdout),
+            "stderr" => Some(Self::Stderr),
+            _ => None
+        }
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::os::raw::{c_int, c_ulong};
+    use super::Stream;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct WinSize {
+        ws_row: u16,
+        ws_col: u16,
+        ws_xpixel: u16,
+        ws_ypixel: u16
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const TIOCGWINSZ: c_ulong = 0x5413;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    const TIOCGWINSZ: c_ulong = 0x40087468;
+
+    // The layout of termios differs between the platforms, so it is only handled
+    // through `cfmakeraw` and kept in a buffer which is large enough for all of them.
+    #[repr(C, align(8))]
+    #[derive(Clone, Copy)]
+    pub struct Termios([u8; 256]);
+
+    const TCSANOW: c_int = 0;
+
+    extern "C" {
+        fn isatty(fd: c_int) -> c_int;
+        fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+        fn tcgetattr(fd: c_int, termios: *mut Termios) -> c_int;
+        fn tcsetattr(fd: c_int, optional_actions: c_int, termios: *const Termios) -> c_int;
+        fn cfmakeraw(termios: *mut Termios);
+    }
+
+    fn fd(stream: Stream) -> c_int {
+        match stream {
+            Stream::Stdin => 0,
+            Stream::Stdout => 1,
+            Stream::Stderr => 2
+        }
+    }
+
+    pub fn is_terminal(stream: Stream) -> bool {
+        unsafe { isatty(fd(stream)) == 1 }
+    }
+
+    pub fn size(stream: Stream) -> Option<(usize, usize)> {
+        let mut size = WinSize::default();
+        match unsafe { ioctl(fd(stream), TIOCGWINSZ, &mut size as *mut WinSize) } {
+            0 if size.ws_col != 0 => Some((size.ws_col as usize, size.ws_row as usize)),
+            _ => None
+        }
+    }
+
+    // Returns the previous mode of stdin which is given back to `restore`.
+    pub fn enable_raw_mode() -> Option<Termios> {
+        let mut termios = Termios([0; 256]);
+        unsafe {
+            if tcgetattr(0, &mut termios) != 0 {
+                return None;
+            }
+
+            let previous = termios;
+            cfmakeraw(&mut termios);
+
+            match tcsetattr(0, TCSANOW, &termios) {
+                0 => Some(previous),
+                _ => None
+            }
+        }
+    }
+
+    pub fn restore(termios: &Termios) {
+        unsafe { tcsetattr(0, TCSANOW, termios) };
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use super::Stream;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct Coord {
+        x: i16,
+        y: i16
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct SmallRect {
+        left: i16,
+        top: i16,
+        right: i16,
+        bottom: i16
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct ConsoleScreenBufferInfo {
+        size: Coord,
+        cursor_position: Coord,
+        attributes: u16,
+        window: SmallRect,
+        maximum_window_size: Coord
+    }
+
+    pub type Termios = u32;
+
+    const ENABLE_PROCESSED_INPUT: u32 = 0x1;
+    const ENABLE_LINE_INPUT: u32 = 0x2;
+    const ENABLE_ECHO_INPUT: u32 = 0x4;
+    const ENABLE_VIRTUAL_TERMINAL_INPUT: u32 = 0x200;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetStdHandle(std_handle: u32) -> *mut u8;
+        fn GetConsoleMode(handle: *mut u8, mode: *mut u32) -> i32;
+        fn SetConsoleMode(handle: *mut u8, mode: u32) -> i32;
+        fn GetConsoleScreenBufferInfo(handle: *mut u8, info: *mut ConsoleScreenBufferInfo) -> i32;
+    }
+
+    fn handle(stream: Stream) -> *mut u8 {
+        let std_handle = match stream {
+            Stream::Stdin => -10i32,
+            Stream::Stdout => -11,
+            Stream::Stderr => -12
+        };
+
+        unsafe { GetStdHandle(std_handle as u32) }
+    }
+
+    pub fn is_terminal(stream: Stream) -> bool {
+        let mut mode = 0;
+        unsafe { GetConsoleMode(handle(stream), &mut mode) != 0 }
+    }
+
+    pub fn size(stream: Stream) -> Option<(usize, usize)> {
+        let mut info = ConsoleScreenBufferInfo::default();
+        match unsafe { GetConsoleScreenBufferInfo(handle(stream), &mut info) } {
+            0 => None,
+            _ => Some((
+                (info.window.right - info.window.left + 1) as usize,
+                (info.window.bottom - info.window.top + 1) as usize
+            ))
+        }
+    }
+
+    // The arrow keys are read as the same escape sequences as on unix.
+    pub fn enable_raw_mode() -> Option<Termios> {
+        let mut mode = 0;
+        unsafe {
+            let handle = handle(Stream::Stdin);
+            if GetConsoleMode(handle, &mut mode) == 0 {
+                return None;
+            }
+
+            let raw = (mode & !(ENABLE_PROCESSED_INPUT | ENABLE_LINE_INPUT | ENABLE_ECHO_INPUT)) | ENABLE_VIRTUAL_TERMINAL_INPUT;
+            match SetConsoleMode(handle, raw) {
+                0 => None,
+                _ => Some(mode)
+            }
+        }
+    }
+
+    pub fn restore(mode: &Termios) {
+        unsafe { SetConsoleMode(handle(Stream::Stdin), *mode) };
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod sys {
+    use super::Stream;
+
+    pub fn is_terminal(_: Stream) -> bool {
+        false
+    }
+
+    pub fn size(_: Stream) -> Option<(usize, usize)> {
+        None
+    }
+
+    pub type Termios = ();
+
+    pub fn enable_raw_mode() -> Option<Termios> {
+        None
+    }
+
+    pub fn restore(_: &Termios) {}
+}
+
+pub fn is_terminal(stream: Stream) -> bool {
+    sys::is_terminal(stream)
+}
+
+// The size of the terminal as (columns, rows). Falls back to the COLUMNS and LINES
+// environment variables which are set by most shells when the platform api fails.
+// These are read without the env permission as the terminal size is not sensitive.
+pub fn size(stream: Stream) -> Option<(usize, usize)> {
+    if !is_terminal(stream) {
+        return None;
+    }
+
+    sys::size(stream).or_else(|| {
+        let columns = env::var("COLUMNS").ok()?.parse().ok()?;
+        let rows = env::var("LINES").ok()?.parse().ok()?;
+        Some((columns, rows))
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    Red,
+    Yellow
+}
+
+impl Color {
+    fn code(self) -> &'static str {
+        match self {
+            Self::Red => "\x1b[31m",
+            Self::Yellow => "\x1b[33m"
+        }
+    }
+}
+
+// Wraps the text in the color when it is written to a terminal, which is given by
+// `is_terminal` of the stream or of the writer of the vm. The colors of the logs
+// and of the reported errors all go through here.
+pub fn paint(text: &str, color: Color, colored: bool) -> String {
+    match colored {
+        true => format!("{}{}\x1b[0m", color.code(), text),
+        false => text.to_owned()
+    }
+}
+
+// Puts the terminal of stdin into the raw mode where the keys are read one by one
+// without being echoed. The previous mode is restored when this gets dropped.
+pub struct RawMode(sys::Termios);
+
+impl RawMode {
+    pub fn enable() -> Option<Self> {
+        if !is_terminal(Stream::Stdin) {
+            return None;
+        }
+
+        sys::enable_raw_mode().map(Self)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        sys::restore(&self.0);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Up,
+    Down,
+    Enter,
+    Space,
+    // Ctrl+C, which does not raise the signal in the raw mode.
+    Cancel,
+    Char(char),
+    Other
+}
+
+// Reads a key from the input which has to be in the raw mode. Returns None at the
+// end of the input.
+pub fn read_key<R: Read>(input: &mut R) -> io::Result<Option<Key>> {
+    let mut byte = [0u8];
+    if input.read(&mut byte)? == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(match byte[0] {
+        b'\r' | b'\n' => Key::Enter,
+        b' ' => Key::Space,
+        3 => Key::Cancel,
+        // The arrow keys are sent as ESC [ A or ESC O A depending on the mode of the terminal.
+        0x1b => {
+            let mut sequence = [0u8; 2];
+            input.read_exact(&mut sequence)?;
+            match sequence {
+                [b'[' | b'O', b'A'] => Key::Up,
+                [b'[' | b'O', b'B'] => Key::Down,
+                _ => Key::Other
+            }
+        },
+        byte if byte.is_ascii_graphic() => Key::Char(byte as char),
+        _ => Key::Other
+    }))
+}
+
+// Removes the ANSI escape sequences such as colors and cursor movements.
+pub fn strip_ansi(string: &str) -> String {
+    let mut result = String::with_capacity(string.len());
+    let mut chars = string.chars().peekable();
+
+    while let Some(character) = chars.next() {
+        if character != '\u{1b}' {
+            result.push(character);
+            continue;
+        }
+
+        match chars.next() {
+            // Control sequences end with a byte in the range of @ to ~.
+            Some('[') => {
+                for character in chars.by_ref() {
+                    if ('@'..='~').contains(&character) {
+                        break;
+                    }
+                }
+            },
+            // Operating system commands end with BEL or ESC \.
+            Some(']') => {
+                while let Some(character) = chars.next() {
+                    if character == '\u{7}' || (character == '\u{1b}' && chars.peek() == Some(&'\\')) {
+                        if character == '\u{1b}' {
+                            chars.next();
+                        }
+
+                        break;
+                    }
+                }
+            },
+            _ => ()
+        }
+    }
+
+    result
+}
@@ -0,0 +1,59 @@
+// The polling watcher of `window.fs.watch`. The paths are compared by their
+// modification time and size whenever `window.fs.pollWatchers` is called, so no
+// threads or notifications of the operating system are involved.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use crate::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change {
+    Create,
+    Modify,
+    Remove
+}
+
+impl Change {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Modify => "modify",
+            Self::Remove => "remove"
+        }
+    }
+}
+
+pub struct Watcher {
+    pub path: PathBuf,
+    pub callback: Value,
+    // None while the path does not exist.
+    state: Option<(Option<SystemTime>, u64)>
+}
+
+impl Watcher {
+    // Fails if the path cannot be read, such as when it does not exist.
+    pub fn new(path: PathBuf, callback: Value) -> io::Result<Self> {
+        let state = Some(state_of(&fs::metadata(&path)?));
+        Ok(Self { path, callback, state })
+    }
+
+    // Compares the path with the state seen by the previous poll.
+    pub fn poll(&mut self) -> Option<Change> {
+        let state = fs::metadata(&self.path).ok().map(|metadata| state_of(&metadata));
+        let change = match (self.state, state) {
+            (None, Some(_)) => Some(Change::Create),
+            (Some(_), None) => Some(Change::Remove),
+            (Some(previous), Some(current)) if previous != current => Some(Change::Modify),
+            _ => None
+        };
+
+        self.state = state;
+        change
+    }
+}
+
+fn state_of(metadata: &fs::Metadata) -> (Option<SystemTime>, u64) {
+    (metadata.modified().ok(), metadata.len())
+}
@@ -1,125 +1,472 @@
-use std::{env, thread, process};
-use std::fs::{self, File};
-use std::time::Duration;
-use std::process::{Command};
-use crate::{Value, Vm, Map, TinyString, RuntimeError, RuntimeResult};
-use crate::runtime::resources::{ChildResource, ChildStdinResource, ChildStdoutResource, ChildStderrResource};
+use std::{env, thread, mem};
+use std::path::PathBuf;
+use std::io::{self, Read, Write, BufReader, SeekFrom};
+use std::fs::{self, File, OpenOptions};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::process::{Command, Stdio};
+use crate::{Value, Vm, Map, TinyString, ValueIter, RuntimeError, RuntimeResult, Feature, Instance, Permission, CancellationToken};
+use crate::runtime::resources::{ChildResource, ChildStdinResource, ChildStdoutResource, ChildStderrResource, FileResource};
 use super::builtin::{initiate_process_instance};
+use super::{crypto, signal, tty, format, log, prompt, benchmark, dotenv, diff};
+use super::watch::Watcher;
+use super::temp::TempKind;
+use super::lazy::{Stage, strip_line_break};
 use super::map_builder::MapBuilder;
+use super::installer::Selection;
+
+// A group of the members of `window` which can be left out with `--global`,
+// such as `sleep` which lets a script stall its embedder.
+pub struct Capability {
+    pub name: &'static str,
+    pub install: fn(&mut MapBuilder)
+}
+
+pub const CAPABILITIES: &[Capability] = &[
+    Capability { name: "window.env", install: |window| {
+        let env = init_env(window.vm);
+        window.constant("env", env);
+    } },
+    Capability { name: "window.crypto", install: install_crypto },
+    Capability { name: "window.signal", install: |window| {
+        let signal = init_signal(window.vm);
+        window.constant("signal", signal);
+    } },
+    Capability { name: "window.tty", install: |window| {
+        let tty = init_tty(window.vm);
+        let stdout = init_stdout(window.vm);
+        window.constant("tty", tty);
+        window.constant("stdout", stdout);
+    } },
+    Capability { name: "window.format", install: |window| {
+        let format = init_format(window.vm);
+        window.constant("format", format);
+    } },
+    Capability { name: "window.log", install: |window| {
+        let log = init_log(window.vm);
+        window.constant("log", log);
+    } },
+    Capability { name: "window.time", install: init_time },
+    Capability { name: "window.prompt", install: init_prompts },
+    Capability { name: "window.inspect", install: init_inspect },
+    Capability { name: "window.io", install: init_io },
+    Capability { name: "window.fs", install: init_fs },
+    Capability { name: "window.process", install: init_process }
+];
 
 pub fn init(vm: &mut Vm) -> Value {
-    let env = init_env(vm);
     let permissions = init_permissions(vm);
+    let manifest = init_manifest(vm);
+    let flags = init_flags(vm);
+    let memory = init_memory(vm);
+    let selection = Selection::of(&vm.flags);
     let mut window = MapBuilder::new(vm);
 
-    init_fs(&mut window);
-    init_process(&mut window);
+    for capability in CAPABILITIES {
+        let installed = selection.includes(capability.name);
+        window.vm.register_feature(capability.name, installed, Some(super::VERSION));
+
+        if installed {
+            (capability.install)(&mut window);
+        }
+    }
+
+    // Generated last so that every module above has registered its features.
+    let features = init_features(window.vm);
 
-    window.string_constant("version", "1.0.0-dev");
+    // The entry script even when read from a preloaded script, unlike `script.filename`.
+    let filename = window.vm.filename();
+    window.string_constant("filename", &filename);
+    window.string_constant("version", super::VERSION);
     window.string_constant("platform", env::consts::OS);
     window.string_constant("platformFamily", env::consts::FAMILY);
     window.string_constant("arch", env::consts::ARCH);
-    window.constant("env", env);
     window.constant("permissions", permissions);
+    window.constant("manifest", manifest);
+    window.constant("flags", flags);
+    window.constant("memory", memory);
+    window.constant("features", features);
 
-    window.native_fn("sleep", |_, args| {
-        if let Some(value) = args.get(0) {
-            let duration = value.to_usize();
-            thread::sleep(Duration::from_millis(duration as u64));
+    window.native_fn("requireFeature", |vm, args| {
+        let name = match args.first() {
+            Some(Value::String(name)) => name.unwrap_ref() as &str,
+            _ => return Err(RuntimeError::new_arguments(vm, "window.requireFeature", "string", args))
+        };
+
+        let message = match vm.features.iter().find(|feature| feature.name == name) {
+            Some(feature) if feature.enabled => return Ok(Value::Null),
+            Some(Feature { since: Some(since), .. }) => format!(
+                "[window.requireFeature]: The feature \"{}\" requires DashScript {} or later along with the permission which enables it.", name, since
+            ),
+            Some(_) => format!("[window.requireFeature]: The feature \"{}\" is not supported by DashScript {}.", name, super::VERSION),
+            None => format!("[window.requireFeature]: The feature \"{}\" is not known to DashScript {}.", name, super::VERSION)
+        };
+
+        Err(RuntimeError::new(vm, message))
+    });
+
+    // Ends the scripts with the code after the callbacks of `onExit` have run. The
+    // process is not exited here so that the embedders decide what the code means.
+    window.native_fn("exit", |vm, args| {
+        let code = args.first().map_or(0, |value| value.to_i32());
+        vm.run_exit_callbacks();
+
+        let _ = vm.out.flush();
+        let _ = vm.err.flush();
+        Err(RuntimeError::new_exit(vm, code))
+    });
+
+    window.native_fn("onExit", |vm, args| {
+        match args.first() {
+            Some(callback @ (Value::Function(_) | Value::NativeFn(_))) => {
+                vm.exit_callbacks.push(*callback);
+                Ok(Value::Null)
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "window.onExit", "function", args))
         }
+    });
 
-        Ok(Value::Null)
+    // The hook receives the error of an uncaught panic before its trace is printed.
+    // A later call replaces the hook.
+    window.native_fn("atPanic", |vm, args| {
+        match args.first() {
+            Some(hook @ (Value::Function(_) | Value::NativeFn(_))) => {
+                vm.panic_hook = Some(*hook);
+                Ok(Value::Null)
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "window.atPanic", "function", args))
+        }
     });
 
-    window.native_fn("exit", |_, args| {
+    Value::Dict(window.allocate_value_ptr())
+}
+
+fn init_time(window: &mut MapBuilder) {
+    // The sleep is split into short ones so that a cancelled vm stops promptly.
+    // The virtual clock of `--seed` moves forward without waiting.
+    window.native_fn("sleep", |vm, args| {
+        const SLICE: Duration = Duration::from_millis(10);
+
         if let Some(value) = args.get(0) {
-            process::exit(value.to_i32())
+            let mut remaining = Duration::from_millis(value.to_usize() as u64);
+            if vm.clock.sleep(remaining) {
+                return Ok(Value::Null);
+            }
+
+            while !remaining.is_zero() && !vm.cancellation.is_cancelled() {
+                let slice = remaining.min(SLICE);
+                thread::sleep(slice);
+                remaining -= slice;
+            }
         }
 
         Ok(Value::Null)
     });
 
+    // The times of the report are in milliseconds per call. `batch` is the number
+    // of calls each sample was measured over.
+    window.native_fn("benchmark", |vm, args| {
+        vm.note_nondeterministic("window.benchmark");
+        let function = match args.first() {
+            Some(function) if function.is_function() => *function,
+            _ => return Err(RuntimeError::new_arguments(vm, "window.benchmark", "function, object?", args))
+        };
+
+        let options = args.get(1);
+        let iterations = count_option(vm, options, "iterations", 100)?.max(1);
+        let warmup = count_option(vm, options, "warmup", 10)?;
+
+        let report = match benchmark::run(vm, function, iterations, warmup) {
+            Ok(report) => report,
+            Err(error) => return Err(RuntimeError::caused_by(vm, "[window.benchmark]: The function threw an error.", error))
+        };
+
+        let mut result = MapBuilder::new(vm);
+        result.constant("mean", Value::Float(report.mean));
+        result.constant("median", Value::Float(report.median));
+        result.constant("min", Value::Float(report.min));
+        result.constant("max", Value::Float(report.max));
+        result.constant("stdev", Value::Float(report.stdev));
+        result.constant("totalMs", Value::Float(report.total_ms));
+        result.constant("iterations", Value::Int(report.iterations as isize));
+        result.constant("batch", Value::Int(report.batch as isize));
+
+        Ok(Value::Dict(result.allocate_value_ptr()))
+    });
+}
+
+fn init_prompts(window: &mut MapBuilder) {
+    window.native_fn("select", |vm, args| {
+        let (message, options) = menu_args(vm, "select", args)?;
+        match prompt::select(vm, &message, &options, false) {
+            Ok(Some(chosen)) => Ok(Value::Int(chosen[0] as isize)),
+            Ok(None) => Ok(Value::Null),
+            Err(e) => Err(RuntimeError::new_io(vm, e))
+        }
+    });
+
+    window.native_fn("multiSelect", |vm, args| {
+        let (message, options) = menu_args(vm, "multiSelect", args)?;
+        match prompt::select(vm, &message, &options, true) {
+            Ok(Some(chosen)) => {
+                let indices: Vec<Value> = chosen.into_iter().map(|index| Value::Int(index as isize)).collect();
+                Ok(Value::Array(vm.allocate_value_ptr(indices)))
+            },
+            Ok(None) => Ok(Value::Null),
+            Err(e) => Err(RuntimeError::new_io(vm, e))
+        }
+    });
+
+    // `--answer key=value` serves the prompts with the key in their options and
+    // `--assume-yes` answers the remaining ones without reading the input.
+    window.native_fn("confirm", |vm, args| {
+        let message = match args.first() {
+            Some(Value::String(message)) => message.unwrap_ref().to_string(),
+            _ => return Err(RuntimeError::new_arguments(vm, "window.confirm", "string, object?", args))
+        };
+
+        let options = args.get(1);
+        if let Some(answer) = keyed_answer(vm, options) {
+            return match prompt::parse_yes_no(&answer) {
+                Some(answer) => Ok(Value::Bool(answer)),
+                None => Err(RuntimeError::new(vm, format!("[window.confirm]: Expected yes or no as the answer but found \"{}\".", answer)))
+            };
+        }
+
+        if vm.answers.assume_yes {
+            return Ok(Value::Bool(true));
+        }
+
+        let default = option_of(options, "default").map(|value| value.to_bool()).unwrap_or(false);
+        match prompt::confirm(vm, &message, default) {
+            Ok(Some(answer)) => Ok(Value::Bool(answer)),
+            Ok(None) => Ok(Value::Null),
+            Err(e) => Err(RuntimeError::new_io(vm, e))
+        }
+    });
+
+    window.native_fn("prompt", |vm, args| {
+        let (message, default) = match args.get(0..2).or_else(|| args.get(0..1)) {
+            Some([Value::String(message)]) | Some([Value::String(message), Value::Null]) => (message.unwrap_ref().to_string(), None),
+            Some([Value::String(message), Value::String(default)]) => (message.unwrap_ref().to_string(), Some(default.unwrap_ref().to_string())),
+            _ => return Err(RuntimeError::new_arguments(vm, "window.prompt", "string, string?, object?", args))
+        };
+
+        let answer = match keyed_answer(vm, args.get(2)) {
+            Some(answer) => Some(answer),
+            None if vm.answers.assume_yes => default,
+            None => match prompt::text(vm, &message, default.as_deref()) {
+                Ok(answer) => answer,
+                Err(e) => return Err(RuntimeError::new_io(vm, e))
+            }
+        };
+
+        Ok(match answer {
+            Some(answer) => Value::String(vm.allocate_string(answer)),
+            None => Value::Null
+        })
+    });
+}
+
+// The text of `window.inspect`, which is also how the cli shows the result of
+// `--eval`. The functions are shown with their name, which their display leaves out.
+pub fn inspect(vm: &Vm, value: &Value) -> String {
+    match value {
+        Value::NativeFn(ptr) => format!("[Function {}]", ptr.unwrap_ref().name),
+        Value::Function(ptr) => format!("[Function {}]", ptr.unwrap_ref().name),
+        value => format!("{}", value.rounded(vm.display_digits))
+    }
+}
+
+fn init_inspect(window: &mut MapBuilder) {
     window.native_fn("inspect", |vm, args| {
-        match args.get(0) {
-            Some(value) => Ok(Value::String(vm.allocate_string(format!("{}", value)))),
-            None => Err(RuntimeError::new(vm, "Expected (any) parameters."))
+        let inspected = match args.get(0) {
+            Some(value) => inspect(vm, value),
+            None => return Err(RuntimeError::new_arguments(vm, "window.inspect", "any", args))
+        };
+
+        Ok(Value::String(vm.allocate_string(inspected)))
+    });
+
+    // `{ roundDisplay: n }` makes `print` and `inspect` show the floats rounded to
+    // n significant digits, and null shows them as they are again. Only the display
+    // changes, the values and their comparisons stay exact.
+    window.native_fn("inspectDefaults", |vm, args| {
+        if !matches!(args.first(), Some(Value::Dict(_))) {
+            return Err(RuntimeError::new_arguments(vm, "window.inspectDefaults", "object", args));
         }
+
+        match option_of(args.first(), "roundDisplay") {
+            Some(Value::Int(digits)) if (1..=17).contains(&digits) => vm.display_digits = Some(digits as usize),
+            Some(Value::Null) => vm.display_digits = None,
+            None => (),
+            Some(_) => return Err(RuntimeError::new(vm, "[window.inspectDefaults]: Expected roundDisplay to be an integer from 1 to 17 or null."))
+        }
+
+        Ok(Value::Null)
     });
 
-    window.native_fn("close", |vm, args| {
-        match args.get(0) {
-            Some(Value::Int(rid)) => {
-                if let Some(resource) = vm.resource_table.remove(&(*rid as u32)) {
-                    if let Err(kind) = resource.close() {
-                        return Err(RuntimeError::new(vm, kind))
-                    }
-                }
+    // The differences between two values as { path, left, right, kind } records
+    // where the kind is one of "changed", "added" and "removed".
+    window.native_fn("diff", |vm, args| {
+        let (left, right) = match args {
+            [left, right] => (left, right),
+            _ => return Err(RuntimeError::new_arguments(vm, "window.diff", "any, any", args))
+        };
 
-                Ok(Value::Null)
-            },
-            _ => return Err(RuntimeError::new(vm, "[window.close]: Expected (rid) arguments."))
+        let mut records = Vec::new();
+        for difference in diff::diff(left, right) {
+            let mut record = Map::new();
+            let path = Value::String(vm.allocate_string(difference.path));
+            let kind = Value::String(vm.allocate_static_str(difference.kind.name()));
+
+            for (key, value) in [("path", path), ("left", difference.left), ("right", difference.right), ("kind", kind)] {
+                record.insert(Value::String(vm.allocate_static_str(key)), (value, false));
+            }
+
+            records.push(Value::Dict(vm.allocate_value_ptr(record)));
         }
+
+        Ok(Value::Array(vm.allocate_value_ptr(records)))
     });
+}
 
-    window.native_fn("flush", |vm, args| {
-        match args.get(0) {
-            Some(Value::Int(rid)) => {
-                if let Some(resource) = vm.get_io_resource(*rid as u32) {
-                    if let Err(kind) = resource.flush() {
-                        return Err(RuntimeError::new(vm, kind))
-                    }
-                }
+fn init_io(window: &mut MapBuilder) {
+    // Closing a resource invalidates every copy of its handle.
+    window.native_fn("close", |vm, args| {
+        let rid = vm.resource_of("window.close", args.first())?;
+        if let Some(resource) = vm.resource_table.remove(&rid) {
+            if let Err(kind) = resource.close() {
+                return Err(RuntimeError::new(vm, kind))
+            }
+        }
 
-                Ok(Value::Null)
-            },
-            _ => return Err(RuntimeError::new(vm, "[window.flush]: Expected (rid) arguments."))
+        Ok(Value::Null)
+    });
+
+    window.native_fn("flush", |vm, args| {
+        let resource = vm.io_resource_of("window.flush", args.first())?;
+        match resource.flush() {
+            Ok(_) => Ok(Value::Null),
+            Err(kind) => Err(RuntimeError::new(vm, kind))
         }
     });
 
     window.native_fn("write", |vm, args| {
-        match args.get(0..2) {
-            Some([Value::Int(rid), Value::Array(bytes)]) => {
-                if let Some(resource) = vm.get_io_resource(*rid as u32) {
-                    match resource.write(&*bytes.unwrap_bytes()) {
-                        Ok(n) => Ok(Value::Int(n as _)),
-                        Err(kind) => Err(RuntimeError::new(vm, kind))
-                    }
-                } else { 
-                    Ok(Value::Null) 
-                }
-            },
-            _ => return Err(RuntimeError::new(vm, "[window.write]: Expected (rid, array[u8]) arguments."))
+        let resource = vm.io_resource_of("window.write", args.first())?;
+        let result = match args.get(1) {
+            Some(Value::Array(bytes)) => resource.write(&bytes.unwrap_bytes()),
+            Some(Value::Bytes(bytes)) => resource.write(bytes.unwrap_ref()),
+            _ => return Err(RuntimeError::new_arguments(vm, "window.write", "resource, Bytes | array[u8]", args))
+        };
+
+        match result {
+            Ok(n) => Ok(Value::Int(n as _)),
+            Err(kind) => Err(RuntimeError::new(vm, kind))
         }
     });
 
+    // Fills an array of bytes and returns the number of the bytes read, or reads up
+    // to the given number of bytes and returns them, which are empty at the end.
     window.native_fn("read", |vm, args| {
-        match args.get(0..2) {
-            Some([Value::Int(rid), Value::Array(bytes)]) => {
-                if let Some(resource) = vm.get_io_resource(*rid as u32) {
-                    let mut buf = bytes.unwrap_bytes();
-                    match resource.read(&mut buf) {
-                        Ok(n) => {
-                            bytes.write_bytes(&*buf);
-                            Ok(Value::Int(n as _))
-                        },
-                        Err(kind) => Err(RuntimeError::new(vm, kind))
-                    }
-                } else { 
-                    Ok(Value::Null) 
+        let resource = vm.io_resource_of("window.read", args.first())?;
+        match args.get(1) {
+            Some(&Value::Int(len)) if len >= 0 => {
+                let mut buf = Vec::new();
+                if buf.try_reserve_exact(len as usize).is_err() {
+                    return Err(RuntimeError::new(vm, format!("[window.read]: Cannot allocate {} bytes.", len)));
+                }
+
+                buf.resize(len as usize, 0);
+                match resource.read(&mut buf) {
+                    Ok(n) => {
+                        buf.truncate(n);
+                        Ok(Value::Bytes(vm.allocate_value_ptr(buf)))
+                    },
+                    Err(kind) => Err(RuntimeError::new(vm, kind))
+                }
+            },
+            Some(Value::Array(bytes)) => {
+                let mut buf = bytes.unwrap_bytes();
+                match resource.read(&mut buf) {
+                    Ok(n) => {
+                        bytes.write_bytes(&buf);
+                        Ok(Value::Int(n as _))
+                    },
+                    Err(kind) => Err(RuntimeError::new(vm, kind))
                 }
             },
-            _ => return Err(RuntimeError::new(vm, "[window.read]: Expected (rid, array[u8]) arguments."))
+            _ => Err(RuntimeError::new_arguments(vm, "window.read", "resource, array[u8] | int", args))
         }
     });
+}
 
-    Value::Dict(window.allocate_value_ptr())
+fn install_crypto(window: &mut MapBuilder) {
+    let crypto = init_crypto(window.vm);
+    window.constant("crypto", crypto);
+
+    // Generates a random RFC 4122 version 4 uuid from the random source of the os.
+    window.native_fn("uuid", |vm, _| {
+        vm.note_nondeterministic("window.uuid");
+        let mut bytes = [0u8; 16];
+        if let Err(e) = crypto::os_random(&mut bytes) {
+            return Err(RuntimeError::new_io(vm, e));
+        }
+
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        let hex = crypto::to_hex(&bytes);
+        let uuid = format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..]);
+        Ok(Value::String(vm.allocate_string(uuid)))
+    });
+}
+
+// The options of the menus are shown as they would be printed.
+fn count_option(vm: &Vm, options: Option<&Value>, name: &str, default: usize) -> RuntimeResult<usize> {
+    match option_of(options, name) {
+        None | Some(Value::Null) => Ok(default),
+        Some(Value::Int(count)) if count >= 0 => Ok(count as usize),
+        Some(_) => Err(RuntimeError::new(vm, format!("[window.benchmark]: Expected {} to be a non-negative integer.", name)))
+    }
+}
+
+fn keyed_answer(vm: &mut Vm, options: Option<&Value>) -> Option<String> {
+    match option_of(options, "key") {
+        Some(Value::String(key)) => vm.answers.take(key.unwrap_ref()),
+        _ => None
+    }
+}
+
+fn menu_args(vm: &Vm, name: &str, args: &[Value]) -> RuntimeResult<(String, Vec<String>)> {
+    match args.get(0..2) {
+        Some([Value::String(message), Value::Array(options)]) => {
+            let options: Vec<String> = options.unwrap_ref().iter().map(|option| format!("{}", option)).collect();
+            if options.is_empty() {
+                return Err(RuntimeError::new(vm, format!("[window.{}]: Expected at least one option.", name)));
+            }
+
+            Ok((message.unwrap_ref().to_string(), options))
+        },
+        _ => Err(RuntimeError::new_arguments(vm, &format!("window.{}", name), "string, array", args))
+    }
+}
+
+// The flags of the cli which change how the scripts should behave.
+pub fn init_flags(vm: &mut Vm) -> Value {
+    let assume_yes = vm.answers.assume_yes;
+    let mut flags = MapBuilder::new(vm);
+    flags.constant("assumeYes", Value::Bool(assume_yes));
+
+    Value::Dict(flags.allocate_value_ptr())
 }
 
 pub fn init_env(vm: &mut Vm) -> Value {
     let mut env = MapBuilder::new(vm);
 
-    env.native_fn("get", |vm, args| Ok(
-        match args.get(0) {
+    env.native_fn("get", |vm, args| {
+        vm.note_nondeterministic("window.env.get");
+        Ok(match args.get(0) {
             Some(Value::String(bytes)) => {
                 match env::var(bytes.unwrap_ref() as &str) {
                     Ok(var) => Value::String(vm.allocate_str_bytes(var.as_bytes())),
@@ -127,15 +474,12 @@ pub fn init_env(vm: &mut Vm) -> Value {
                 }
             },
             _ => Value::Null
-        }
-    ));
+        })
+    });
 
     env.native_fn("set", |_, args| Ok(Value::Bool(
         match args.get(0..2) {
-            Some(&[Value::String(key), Value::String(value)]) => {
-                env::set_var(key.unwrap_ref() as &str, value.unwrap_ref() as &str);
-                true
-            },
+            Some(&[Value::String(key), Value::String(value)]) => set_env_var(key.unwrap_ref(), value.unwrap_ref()),
             _ => false
         }
     )));
@@ -160,9 +504,71 @@ pub fn init_env(vm: &mut Vm) -> Value {
         Ok(Value::Dict(vm.allocate_value_ptr(map)))
     });
 
+    // Loads a dotenv file, `.env` by default, and returns the variables which have
+    // been set. The variables which are already set are kept unless the `override`
+    // option is enabled.
+    if env.vm.permissions.read || env.vm.prompt_permissions {
+        env.native_fn("load", |vm, args| {
+            let (path, options) = match args.first() {
+                None | Some(Value::Null) => (".env".to_owned(), args.get(1)),
+                Some(Value::String(path)) => (path.unwrap_ref().to_string(), args.get(1)),
+                Some(Value::Dict(_)) => (".env".to_owned(), args.first()),
+                _ => return Err(RuntimeError::new_arguments(vm, "window.env.load", "string?, object?", args))
+            };
+
+            vm.require_permission(Permission::Read, "window.env.load", &format!("read \"{}\"", path))?;
+            let source = match fs::read_to_string(&path) {
+                Ok(source) => source,
+                Err(error) => return Err(RuntimeError::new_io(vm, error))
+            };
+
+            let variables = match dotenv::parse(&source) {
+                Ok(variables) => variables,
+                Err(error) => return Err(RuntimeError::new(vm, format!("[window.env.load]: {} at line {} of \"{}\".", error.message, error.line, path)))
+            };
+
+            // A key which appears again in the file replaces the value loaded before.
+            let override_ = option_of(options, "override").is_some_and(|value| value.to_bool());
+            let mut loaded: Vec<(String, String)> = Vec::new();
+            for (key, value) in variables {
+                let index = loaded.iter().position(|(loaded_key, _)| *loaded_key == key);
+                if index.is_none() && !override_ && env::var_os(&key).is_some() {
+                    continue;
+                }
+
+                if !set_env_var(&key, &value) {
+                    return Err(RuntimeError::new(vm, format!("[window.env.load]: Cannot set the variable \"{}\".", key)));
+                }
+
+                match index {
+                    Some(index) => loaded[index].1 = value,
+                    None => loaded.push((key, value))
+                }
+            }
+
+            let mut map = Map::new();
+            for (key, value) in loaded {
+                map.insert(Value::String(vm.allocate_string(key)), (Value::String(vm.allocate_string(value)), true));
+            }
+
+            Ok(Value::Dict(vm.allocate_value_ptr(map)))
+        });
+    }
+
     Value::Dict(env.allocate_value_ptr())
 }
 
+// `env::set_var` panics on the names and the values which the platform does not
+// accept, so these are refused instead.
+fn set_env_var(key: &str, value: &str) -> bool {
+    if key.is_empty() || key.contains(['=', '\0']) || value.contains('\0') {
+        return false;
+    }
+
+    env::set_var(key, value);
+    true
+}
+
 pub fn init_permissions(vm: &mut Vm) -> Value {
     let vm_permissions = vm.permissions;
     let mut permissions = MapBuilder::new(vm);
@@ -171,14 +577,591 @@ pub fn init_permissions(vm: &mut Vm) -> Value {
     permissions.constant("write", Value::Bool(vm_permissions.write));
     permissions.constant("memory", Value::Bool(vm_permissions.memory));
     permissions.constant("childProcess", Value::Bool(vm_permissions.child_process));
+    permissions.constant("crypto", Value::Bool(vm_permissions.crypto));
+    permissions.constant("signal", Value::Bool(vm_permissions.signal));
     permissions.constant("unsafe", Value::Bool(vm_permissions.unsafe_libs));
 
     Value::Dict(permissions.allocate_value_ptr())
 }
 
+// The dict of the features which have been registered by the modules. Its entries
+// are constants since the features cannot change while the script runs.
+pub fn init_features(vm: &mut Vm) -> Value {
+    let registered = vm.features.clone();
+    let mut features = MapBuilder::new(vm);
+
+    for feature in registered {
+        features.constant(feature.name, Value::Bool(feature.enabled));
+    }
+
+    Value::Dict(features.allocate_value_ptr())
+}
+
+pub fn init_crypto(vm: &mut Vm) -> Value {
+    let use_crypto = vm.permissions.crypto;
+    vm.register_feature("crypto", use_crypto, Some(super::VERSION));
+    let mut crypto = MapBuilder::new(vm);
+
+    crypto.native_fn("hash", |vm, args| {
+        match (args.first(), args.get(1).and_then(to_bytes)) {
+            (Some(Value::String(algorithm)), Some(data)) => {
+                let digest = match algorithm.unwrap_ref() as &str {
+                    "fnv1a64" => crypto::to_hex(&crypto::fnv1a64(&data).to_be_bytes()),
+                    "sha256" => crypto::to_hex(&crypto::sha256(&data)),
+                    algorithm => return Err(RuntimeError::new(vm, format!("[crypto.hash]: Unsupported algorithm \"{}\". Expected either \"fnv1a64\" or \"sha256\".", algorithm)))
+                };
+
+                Ok(Value::String(vm.allocate_string(digest)))
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "crypto.hash", "string, string | array", args))
+        }
+    });
+
+    // With `--prompt-permissions` the gated natives exist without their permission
+    // and ask for it when they are called.
+    if use_crypto || crypto.vm.prompt_permissions {
+        crypto.native_fn("hmac", |vm, args| {
+            match (args.first(), args.get(1).and_then(to_bytes), args.get(2).and_then(to_bytes)) {
+                (Some(Value::String(algorithm)), Some(key), Some(data)) => {
+                    vm.require_permission(Permission::Crypto, "crypto.hmac", "compute an hmac")?;
+                    let digest = match algorithm.unwrap_ref() as &str {
+                        "sha256" => crypto::to_hex(&crypto::hmac_sha256(&key, &data)),
+                        algorithm => return Err(RuntimeError::new(vm, format!("[crypto.hmac]: Unsupported algorithm \"{}\". Expected \"sha256\".", algorithm)))
+                    };
+
+                    Ok(Value::String(vm.allocate_string(digest)))
+                },
+                _ => Err(RuntimeError::new_arguments(vm, "crypto.hmac", "string, string | array, string | array", args))
+            }
+        });
+
+        crypto.native_fn("randomBytes", |vm, args| {
+            vm.require_permission(Permission::Crypto, "crypto.randomBytes", "generate random bytes")?;
+            vm.note_nondeterministic("crypto.randomBytes");
+            let mut bytes = vec![0u8; args.first().unwrap_or_default().to_usize()];
+            if let Err(e) = crypto::os_random(&mut bytes) {
+                return Err(RuntimeError::new_io(vm, e));
+            }
+
+            let bytes = bytes.iter().map(|byte| Value::Int(*byte as isize)).collect();
+            Ok(Value::Array(vm.allocate_value_ptr(bytes)))
+        });
+    }
+
+    Value::Dict(crypto.allocate_value_ptr())
+}
+
+pub fn init_signal(vm: &mut Vm) -> Value {
+    let use_signal = vm.permissions.signal;
+    vm.register_feature("signal", use_signal, Some(super::VERSION));
+    let mut signal = MapBuilder::new(vm);
+
+    if use_signal || signal.vm.prompt_permissions {
+        // The process is exited with the code 130 after the callback returns.
+        signal.native_fn("onInterrupt", |vm, args| {
+            match args.first() {
+                Some(callback @ Value::Function(_)) | Some(callback @ Value::NativeFn(_)) => {
+                    vm.require_permission(Permission::Signal, "signal.onInterrupt", "handle the interrupts")?;
+                    if vm.interrupt_callback.is_none() {
+                        signal::install();
+                    }
+
+                    vm.interrupt_callback = Some(*callback);
+                    Ok(Value::Null)
+                },
+                _ => Err(RuntimeError::new_arguments(vm, "signal.onInterrupt", "function", args))
+            }
+        });
+    }
+
+    Value::Dict(signal.allocate_value_ptr())
+}
+
+// The accounting of the heap which `--max-heap` is checked against. Unlike the
+// `Memory` global it cannot reach the objects, so it needs no permission.
+pub fn init_memory(vm: &mut Vm) -> Value {
+    let mut memory = MapBuilder::new(vm);
+
+    memory.native_fn("stats", |vm, _| {
+        let max_heap = vm.max_heap.map_or(Value::Null, |max_heap| Value::Int(max_heap as isize));
+        let mut stats = MapBuilder::new(vm);
+        stats.constant("approxBytes", Value::Int(stats.vm.approx_bytes as isize));
+        stats.constant("maxHeap", max_heap);
+        stats.constant("objects", Value::Int(stats.vm.objects.len() as isize));
+        Ok(Value::Dict(stats.allocate_value_ptr()))
+    });
+
+    Value::Dict(memory.allocate_value_ptr())
+}
+
+// The output of the scripts is buffered when stdout is not a terminal, so `flush`
+// shows what has been printed so far such as a progress line.
+pub fn init_stdout(vm: &mut Vm) -> Value {
+    let mut stdout = MapBuilder::new(vm);
+
+    stdout.native_fn("flush", |vm, _| {
+        match vm.out.flush() {
+            Ok(_) => Ok(Value::Null),
+            Err(error) => Err(RuntimeError::new_io(vm, error))
+        }
+    });
+
+    Value::Dict(stdout.allocate_value_ptr())
+}
+
+pub fn init_tty(vm: &mut Vm) -> Value {
+    vm.register_feature("tty", true, Some(super::VERSION));
+    let mut tty_ = MapBuilder::new(vm);
+
+    tty_.native_fn("isTerminal", |vm, args| {
+        let stream = stream_of(vm, "isTerminal", args)?;
+        Ok(Value::Bool(tty::is_terminal(stream)))
+    });
+
+    tty_.native_fn("size", |vm, args| {
+        let stream = stream_of(vm, "size", args)?;
+        match tty::size(stream) {
+            Some((columns, rows)) => {
+                let mut size = MapBuilder::new(vm);
+                size.constant("columns", Value::Int(columns as isize));
+                size.constant("rows", Value::Int(rows as isize));
+                Ok(Value::Dict(size.allocate_value_ptr()))
+            },
+            None => Err(RuntimeError::new(vm, "[tty.size]: The stream is not a terminal."))
+        }
+    });
+
+    tty_.native_fn("stripColors", |vm, args| {
+        match args.first() {
+            Some(Value::String(string)) => {
+                let stripped = tty::strip_ansi(string.unwrap_ref());
+                Ok(Value::String(vm.allocate_string(stripped)))
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "tty.stripColors", "string", args))
+        }
+    });
+
+    Value::Dict(tty_.allocate_value_ptr())
+}
+
+// The stream defaults to stdout if there is no argument.
+fn stream_of(vm: &Vm, name: &str, args: &[Value]) -> RuntimeResult<tty::Stream> {
+    match args.first() {
+        None => Ok(tty::Stream::Stdout),
+        Some(Value::String(stream)) => match tty::Stream::from_name(stream.unwrap_ref()) {
+            Some(stream) => Ok(stream),
+            None => Err(RuntimeError::new(vm, format!("[tty.{}]: Unknown stream \"{}\". Expected either \"stdin\", \"stdout\" or \"stderr\".", name, stream.unwrap_ref())))
+        },
+        _ => Err(RuntimeError::new_arguments(vm, &format!("tty.{}", name), "string?", args))
+    }
+}
+
+// Strings are hashed by their utf-8 bytes and arrays are treated as arrays of bytes.
+fn to_bytes(value: &Value) -> Option<Vec<u8>> {
+    match value {
+        Value::String(string) => Some(string.unwrap_ref().to_bytes().to_vec()),
+        Value::Array(array) => Some(array.unwrap_ref().iter().map(|byte| byte.to_usize() as u8).collect()),
+        _ => None
+    }
+}
+
+pub fn init_log(vm: &mut Vm) -> Value {
+    vm.register_feature("log", true, Some(super::VERSION));
+    let level = vm.log_level.name();
+    let mut log_ = MapBuilder::new(vm);
+
+    let level = Value::String(log_.vm.allocate_static_str(level));
+    log_.variable("level", level);
+    log_.native_fn("debug", |vm, args| write_log(vm, log::Level::Debug, args));
+    log_.native_fn("info", |vm, args| write_log(vm, log::Level::Info, args));
+    log_.native_fn("warn", |vm, args| write_log(vm, log::Level::Warn, args));
+    log_.native_fn("error", |vm, args| write_log(vm, log::Level::Error, args));
+
+    let log_ = Value::Dict(log_.allocate_value_ptr());
+    vm.log = log_;
+    log_
+}
+
+// The level is read back from `window.log.level` as the scripts can reassign it.
+fn write_log(vm: &mut Vm, level: log::Level, args: &[Value]) -> RuntimeResult<Value> {
+    let name = level.name();
+    if args.is_empty() {
+        return Err(RuntimeError::new_arguments(vm, &format!("log.{}", name), "any, ...any", args));
+    }
+
+    match option_of(Some(&vm.log), "level") {
+        Some(Value::String(current)) => match log::Level::from_name(current.unwrap_ref()) {
+            Some(current) => vm.log_level = current,
+            None => return Err(RuntimeError::new(vm, format!(
+                "[log.{}]: Unknown level \"{}\". Expected one of {}.", name, current.unwrap_ref() as &str, log::Level::NAMES.join(", ")
+            )))
+        },
+        _ => return Err(RuntimeError::new(vm, format!("[log.{}]: Expected the level to be a string.", name)))
+    }
+
+    if level < vm.log_level {
+        return Ok(Value::Null);
+    }
+
+    let message = args.iter().map(|value| format!("{}", value)).collect::<Vec<String>>().join(" ");
+    let line = log::line(level, &message, vm.err.is_terminal(), vm.clock.now());
+    // Keeps the order of the lines when stdout and stderr go to the same place.
+    if let Err(e) = vm.out.flush().and_then(|_| vm.err.write_all(line.as_bytes())) {
+        return Err(RuntimeError::new_io(vm, e));
+    }
+
+    Ok(Value::Null)
+}
+
+pub fn init_format(vm: &mut Vm) -> Value {
+    vm.register_feature("format", true, Some(super::VERSION));
+    let mut format_ = MapBuilder::new(vm);
+
+    format_.native_fn("number", |vm, args| {
+        let number = number_of(vm, "number", args)?;
+        let options = args.get(1);
+        let decimals = match option_of(options, "decimals") {
+            Some(decimals) => decimals_of(vm, "number", decimals)?,
+            None => 2
+        };
+
+        let thousands_sep = string_option(vm, options, "thousandsSep", ",")?;
+        let decimal_sep = string_option(vm, options, "decimalSep", ".")?;
+        let formatted = format::number(number, decimals, &thousands_sep, &decimal_sep);
+        Ok(Value::String(vm.allocate_string(formatted)))
+    });
+
+    format_.native_fn("percent", |vm, args| {
+        let number = number_of(vm, "percent", args)?;
+        let decimals = match args.get(1) {
+            Some(decimals) => decimals_of(vm, "percent", *decimals)?,
+            None => 0
+        };
+
+        Ok(Value::String(vm.allocate_string(format::percent(number, decimals))))
+    });
+
+    format_.native_fn("bytes", |vm, args| {
+        let bytes = number_of(vm, "bytes", args)?;
+        Ok(Value::String(vm.allocate_string(format::bytes(bytes))))
+    });
+
+    format_.native_fn("duration", |vm, args| {
+        let milliseconds = number_of(vm, "duration", args)?;
+        Ok(Value::String(vm.allocate_string(format::duration(milliseconds))))
+    });
+
+    Value::Dict(format_.allocate_value_ptr())
+}
+
+fn number_of(vm: &Vm, name: &str, args: &[Value]) -> RuntimeResult<f64> {
+    match args.first() {
+        Some(&Value::Int(int)) => Ok(int as f64),
+        Some(&Value::Float(float)) => Ok(float),
+        value => Err(RuntimeError::new_argument(vm, &format!("format.{}", name), 0, "number", value))
+    }
+}
+
+// The decimals are limited as the numbers never have more significant digits.
+fn decimals_of(vm: &Vm, name: &str, decimals: Value) -> RuntimeResult<usize> {
+    match decimals {
+        Value::Int(int) if (0..=100).contains(&int) => Ok(int as usize),
+        _ => Err(RuntimeError::new(vm, format!("[format.{}]: The decimals must be an integer from 0 to 100.", name)))
+    }
+}
+
+fn option_of(options: Option<&Value>, name: &str) -> Option<Value> {
+    match options {
+        Some(Value::Dict(map)) => map.unwrap_ref().iter().find_map(|(key, (value, _))| match key {
+            Value::String(key) if key.unwrap_ref() as &str == name => Some(*value),
+            _ => None
+        }),
+        _ => None
+    }
+}
+
+fn string_option(vm: &Vm, options: Option<&Value>, name: &str, default: &str) -> RuntimeResult<String> {
+    match option_of(options, name) {
+        Some(Value::String(string)) => Ok(string.unwrap_ref().to_string()),
+        None => Ok(default.to_owned()),
+        Some(_) => Err(RuntimeError::new(vm, format!("[format.number]: Expected a string as the \"{}\" option.", name)))
+    }
+}
+
+// The metadata of the project manifest is passed by the cli as `manifest.<key>` flags.
+pub fn init_manifest(vm: &mut Vm) -> Value {
+    let mut entries = Vec::new();
+    for (key, value) in vm.flags.iter() {
+        if let Some(key) = key.strip_prefix("manifest.") {
+            entries.push((key.to_owned(), value.to_string()));
+        }
+    }
+
+    let mut manifest = MapBuilder::new(vm);
+    for (key, value) in entries {
+        manifest.string_constant(&key, &value);
+    }
+
+    Value::Dict(manifest.allocate_value_ptr())
+}
+
+// The `window.fs` object. The watchers are polled by the scripts themselves as
+// there is no event loop which could deliver the changes.
+fn init_fs_object(vm: &mut Vm) -> Value {
+    let mut prototype = MapBuilder::new(vm);
+
+    prototype.native_fn("close", |vm, args| {
+        match args.first() {
+            Some(Value::Instance(ptr)) => {
+                if let Some((Value::Int(id), _)) = ptr.unwrap_ref().properties.get(&vm.constants.__watcher) {
+                    vm.watchers.remove(&(*id as u32));
+                }
+
+                Ok(Value::Null)
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "Watcher.close", "Watcher", args))
+        }
+    });
+
+    vm.constants.watcher_prototype = prototype.allocate_value_ptr();
+    let mut fs_ = MapBuilder::new(vm);
+
+    fs_.native_fn("watch", |vm, args| {
+        let (path, callback) = match args.get(0..2) {
+            Some([Value::String(path), callback]) if callback.is_function() => (path, *callback),
+            _ => return Err(RuntimeError::new_arguments(vm, "fs.watch", "string, function", args))
+        };
+
+        vm.require_permission(Permission::Read, "fs.watch", &format!("watch \"{}\"", path.unwrap_ref() as &str))?;
+
+        let watcher = match Watcher::new(PathBuf::from(path.unwrap_ref() as &str), callback) {
+            Ok(watcher) => watcher,
+            Err(error) => return Err(RuntimeError::new(vm, format!(
+                "[fs.watch]: Cannot watch \"{}\": {}.", path.unwrap_ref() as &str, error
+            )))
+        };
+
+        let id = vm.next_watcher_id;
+        vm.next_watcher_id += 1;
+        vm.watchers.insert(id, watcher);
+
+        let mut properties = Map::with_capacity(2);
+        properties.insert(vm.constants.__watcher, (Value::Int(id as _), true));
+        properties.insert(vm.constants.path, (Value::String(*path), true));
+
+        Ok(Value::Instance(vm.allocate_value_ptr(
+            Instance {
+                properties,
+                methods: vm.constants.watcher_prototype
+            }
+        )))
+    });
+
+    // Calls the callbacks of the changed paths with `{ path, kind }` and returns
+    // the number of the changes.
+    fs_.native_fn("pollWatchers", |vm, _| {
+        let changes: Vec<_> = vm.watchers.iter_mut()
+            .filter_map(|(&id, watcher)| watcher.poll().map(|change| (id, change)))
+            .collect();
+
+        for &(id, change) in &changes {
+            // A callback can close the watchers which have not been notified yet.
+            let (path, callback) = match vm.watchers.get(&id) {
+                Some(watcher) => (watcher.path.to_string_lossy().into_owned(), watcher.callback),
+                None => continue
+            };
+
+            let mut event = MapBuilder::new(vm);
+            event.string_constant("path", &path);
+            event.string_constant("kind", change.name());
+            let event = Value::Dict(event.allocate_value_ptr());
+
+            vm.stack.push(event);
+            if let Err(error) = vm.call_function_with_returned_value(callback, 1) {
+                return Err(RuntimeError::caused_by(vm, "[fs.pollWatchers]: The callback threw an error.", error));
+            }
+        }
+
+        Ok(Value::Int(changes.len() as _))
+    });
+
+    fs_.native_fn("readBytes", |vm, args| {
+        match args.first() {
+            Some(Value::String(file_path)) => {
+                vm.require_permission(Permission::Read, "fs.readBytes", &format!("read \"{}\"", file_path.unwrap_ref() as &str))?;
+                match fs::read(file_path.unwrap_ref() as &str) {
+                    Ok(bytes) => Ok(Value::Bytes(vm.allocate_value_ptr(bytes))),
+                    Err(error) => Err(RuntimeError::new_io(vm, error))
+                }
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "fs.readBytes", "string", args))
+        }
+    });
+
+    fs_.native_fn("writeBytes", |vm, args| {
+        match args.get(0..2) {
+            Some([Value::String(file_path), Value::Bytes(bytes)]) => {
+                vm.require_permission(Permission::Write, "fs.writeBytes", &format!("write \"{}\"", file_path.unwrap_ref() as &str))?;
+                match fs::write(file_path.unwrap_ref() as &str, bytes.unwrap_ref()) {
+                    Ok(_) => Ok(Value::Null),
+                    Err(error) => Err(RuntimeError::new_io(vm, error))
+                }
+            },
+            _ => Err(RuntimeError::new_arguments(vm, "fs.writeBytes", "string, Bytes", args))
+        }
+    });
+
+    // Opens a file with the mode "r", which is the default, "w", "a" or "r+". The
+    // handle is read, written and closed with `read`, `write` and `close` like the
+    // other resources.
+    fs_.native_fn("open", |vm, args| {
+        let (path, mode) = match args {
+            [Value::String(path)] => (*path, "r"),
+            [Value::String(path), Value::String(mode)] => (*path, mode.unwrap_ref() as &str),
+            _ => return Err(RuntimeError::new_arguments(vm, "fs.open", "string, string?", args))
+        };
+
+        open_file(vm, "fs.open", path.unwrap_ref(), mode)
+    });
+
+    // The line without its line break, or null at the end of the file.
+    fs_.native_fn("readLine", |vm, args| {
+        let resource = vm.io_resource_of("fs.readLine", args.first())?;
+        let mut line = Vec::new();
+        match resource.read_line(&mut line) {
+            Ok(0) => Ok(Value::Null),
+            Ok(_) => {
+                strip_line_break(&mut line);
+
+                Ok(Value::String(vm.allocate_string(String::from_utf8_lossy(&line).into_owned())))
+            },
+            Err(kind) => Err(RuntimeError::new(vm, kind))
+        }
+    });
+
+    // The offset is from "start", which is the default, "current" or "end". The
+    // new position is returned.
+    fs_.native_fn("seek", |vm, args| {
+        let resource = vm.io_resource_of("fs.seek", args.first())?;
+        let (offset, whence) = match args.get(1..) {
+            Some([Value::Int(offset)] | [Value::Int(offset), Value::Null]) => (*offset, "start"),
+            Some([Value::Int(offset), Value::String(whence)]) => (*offset, whence.unwrap_ref() as &str),
+            _ => return Err(RuntimeError::new_arguments(vm, "fs.seek", "resource, int, string?", args))
+        };
+
+        let position = match (whence, offset) {
+            ("start", offset) if offset >= 0 => SeekFrom::Start(offset as u64),
+            ("current", offset) => SeekFrom::Current(offset as i64),
+            ("end", offset) => SeekFrom::End(offset as i64),
+            _ => return Err(RuntimeError::new(vm, "[fs.seek]: Expected a non-negative offset from \"start\" or any offset from \"current\" or \"end\"."))
+        };
+
+        match resource.seek(position) {
+            Ok(position) => Ok(Value::Int(position as isize)),
+            Err(kind) => Err(RuntimeError::new(vm, kind))
+        }
+    });
+
+    fs_.native_fn("size", |vm, args| {
+        let resource = vm.io_resource_of("fs.size", args.first())?;
+        match resource.size() {
+            Ok(size) => Ok(Value::Int(size as isize)),
+            Err(kind) => Err(RuntimeError::new(vm, kind))
+        }
+    });
+
+    // An iterator over the lines of a file which reads them only when they are
+    // asked for, so the file is never read whole.
+    fs_.native_fn("lines", |vm, args| {
+        let path = match args {
+            [Value::String(path)] => *path,
+            _ => return Err(RuntimeError::new_arguments(vm, "fs.lines", "string", args))
+        };
+
+        let Value::Resource(rid, _) = open_file(vm, "fs.lines", path.unwrap_ref(), "r")? else {
+            unreachable!("open_file returns a resource")
+        };
+
+        let source = vm.allocate_value_ptr(ValueIter::default());
+        Ok(vm.lazy_iter(source, Stage::Lines { rid }))
+    });
+
+    // `fs.tempFile(prefix?, suffix?, { keep })` and `fs.tempDir(prefix?, { keep })`
+    // return the path of the new entry, which is removed once the scripts have
+    // ended unless `keep` is true.
+    fs_.native_fn("tempFile", |vm, args| create_temp(vm, "fs.tempFile", TempKind::File, args));
+    fs_.native_fn("tempDir", |vm, args| create_temp(vm, "fs.tempDir", TempKind::Dir, args));
+
+    Value::Dict(fs_.allocate_value_ptr())
+}
+
+fn open_file(vm: &mut Vm, name: &str, path: &str, mode: &str) -> RuntimeResult<Value> {
+    let mut options = OpenOptions::new();
+    match mode {
+        "r" => options.read(true),
+        "w" => options.write(true).create(true).truncate(true),
+        "a" => options.append(true).create(true),
+        "r+" => options.read(true).write(true),
+        _ => return Err(RuntimeError::new(vm, format!("[{}]: Expected the mode \"r\", \"w\", \"a\" or \"r+\" but found \"{}\".", name, mode)))
+    };
+
+    if mode != "w" && mode != "a" {
+        vm.require_permission(Permission::Read, name, &format!("read \"{}\"", path))?;
+    }
+    if mode != "r" {
+        vm.require_permission(Permission::Write, name, &format!("write \"{}\"", path))?;
+    }
+
+    match options.open(path) {
+        Ok(file) => Ok(vm.add_resource(FileResource(Box::new(BufReader::new(file))))),
+        Err(error) => Err(RuntimeError::new_io(vm, error))
+    }
+}
+
+fn create_temp(vm: &mut Vm, name: &'static str, kind: TempKind, args: &[Value]) -> RuntimeResult<Value> {
+    let (names, options) = match args.split_last() {
+        Some((options @ Value::Dict(_), names)) => (names, Some(options)),
+        _ => (args, None)
+    };
+
+    let max_names = if kind == TempKind::File { 2 } else { 1 };
+    let mut parts = ["dashscript-".to_owned(), String::new()];
+    for (index, part) in names.iter().enumerate() {
+        match part {
+            Value::String(string) if index < max_names => {
+                let string = string.unwrap_ref() as &str;
+                if string.contains(['/', '\\']) {
+                    return Err(RuntimeError::new(vm, format!("[{}]: The prefix and the suffix cannot contain a path separator.", name)));
+                }
+
+                parts[index] = string.to_owned();
+            },
+            Value::Null if index < max_names => (),
+            _ => return Err(RuntimeError::new_arguments(vm, name, match kind {
+                TempKind::File => "string?, string?, object?",
+                TempKind::Dir => "string?, object?"
+            }, args))
+        }
+    }
+
+    let keep = option_of(options, "keep").is_some_and(|keep| keep.to_bool());
+    vm.require_permission(Permission::Write, name, "create a temporary file")?;
+
+    match vm.create_temp(kind, &parts[0], &parts[1], keep) {
+        Ok(path) => Ok(Value::String(vm.allocate_string(path.to_string_lossy().into_owned()))),
+        Err(error) => Err(RuntimeError::new_io(vm, error))
+    }
+}
+
 pub fn init_fs<'a>(window: &mut MapBuilder<'a>) {
-    if window.vm.permissions.read {
+    let permissions = window.vm.permissions;
+    let prompt_permissions = window.vm.prompt_permissions;
+    window.vm.register_feature("fs", permissions.read, Some(super::VERSION));
+    window.vm.register_feature("fsWrite", permissions.write, Some(super::VERSION));
+
+    if permissions.read || prompt_permissions {
         window.native_fn("cwd", |vm, _| {
+            vm.require_permission(Permission::Read, "window.cwd", "read the directory of the script")?;
             let cwd = match vm.path.parent() {
                 Some(path) => {
                     match path.to_str() {
@@ -193,6 +1176,7 @@ pub fn init_fs<'a>(window: &mut MapBuilder<'a>) {
         });
 
         window.native_fn("execPath", |vm, _| {
+            vm.require_permission(Permission::Read, "window.execPath", "read the working directory")?;
             let exec_path = match env::current_dir() {
                 Ok(path) => {
                     match path.to_str() {
@@ -209,62 +1193,82 @@ pub fn init_fs<'a>(window: &mut MapBuilder<'a>) {
         window.native_fn("readTextFile", |vm, args| {
             match args.get(0) {
                 Some(Value::String(file_path)) => {
+                    vm.require_permission(Permission::Read, "window.readTextFile", &format!("read \"{}\"", file_path.unwrap_ref() as &str))?;
                     match fs::read_to_string(file_path.unwrap_ref() as &str) {
                         Ok(string) => Ok(Value::String(vm.allocate_string(string))),
                         Err(error) => Err(RuntimeError::new_io(vm, error))
                     }
                 },
-                _ => Err(RuntimeError::new(vm, "[window.readTextFile]: Expected (string) parameters."))
+                _ => Err(RuntimeError::new_arguments(vm, "window.readTextFile", "string", args))
             }
         });
     }
 
-    if window.vm.permissions.write {
+    // The natives of `window.fs` check the permission they need when called.
+    if permissions.read || permissions.write || prompt_permissions {
+        let fs_ = init_fs_object(window.vm);
+        window.constant("fs", fs_);
+    }
+
+    if permissions.write || prompt_permissions {
         window.native_fn("chdir", |vm, args| {
             match args.get(0) {
                 Some(Value::String(new_dir)) => {
+                    vm.require_permission(Permission::Write, "window.chdir", &format!("change the working directory to \"{}\"", new_dir.unwrap_ref() as &str))?;
                     match env::set_current_dir(new_dir.unwrap_ref() as &str) {
                         Ok(_) => Ok(Value::Null),
                         Err(error) => Err(RuntimeError::new_io(vm, error))
                     }
                 },
-                _ => Err(RuntimeError::new(vm, "[window.chdir]: Expected (string) parameters."))
+                _ => Err(RuntimeError::new_arguments(vm, "window.chdir", "string", args))
             }
         });
 
         window.native_fn("copyFile", |vm, args| {
             match args.get(0..2) {
                 Some([Value::String(from), Value::String(to)]) => {
+                    vm.require_permission(Permission::Write, "window.copyFile", &format!("write \"{}\"", to.unwrap_ref() as &str))?;
                     match std::fs::copy(from.unwrap_ref() as &str, to.unwrap_ref() as &str) {
                         Ok(_) => Ok(Value::Null),
                         Err(error) => Err(RuntimeError::new_io(vm, error))
                     }
                 },
-                _ => Err(RuntimeError::new(vm, "[window.copyFile]: Expected (string, string) parameters."))
+                _ => Err(RuntimeError::new_arguments(vm, "window.copyFile", "string, string", args))
             }
         });
 
         window.native_fn("createFile", |vm, args| {
             match args.get(0) {
                 Some(Value::String(file_path)) => {
+                    vm.require_permission(Permission::Write, "window.createFile", &format!("create \"{}\"", file_path.unwrap_ref() as &str))?;
                     match File::create(file_path.unwrap_ref() as &str) {
                         Ok(_) => Ok(Value::Null),
                         Err(error) => Err(RuntimeError::new_io(vm, error))
                     }
                 },
-                _ => Err(RuntimeError::new(vm, "[window.createFile]: Expected (string) parameters."))
+                _ => Err(RuntimeError::new_arguments(vm, "window.createFile", "string", args))
             }
         });
     }
 }
 
 pub fn init_process<'a>(window: &mut MapBuilder<'a>) {
-    if !window.vm.permissions.child_process {
+    let use_child_process = window.vm.permissions.child_process;
+    window.vm.register_feature("childProcess", use_child_process, Some(super::VERSION));
+
+    if !use_child_process && !window.vm.prompt_permissions {
         return;
     }
 
     window.native_fn("run", |vm, args| {
         let mut command = resolve_run_args(vm, args)?;
+        let program = command.get_program().to_string_lossy().into_owned();
+        vm.require_permission(Permission::ChildProcess, "window.run", &format!("run \"{}\"", program))?;
+
+        // The child can write to the same stdout so the output of the script goes first.
+        if let Err(error) = vm.out.flush() {
+            return Err(RuntimeError::new_io(vm, error));
+        }
         let mut child = match command.spawn() {
             Ok(child) => child,
             Err(error) => return Err(RuntimeError::new_io(vm, error))
@@ -290,6 +1294,156 @@ pub fn init_process<'a>(window: &mut MapBuilder<'a>) {
         let rid = vm.add_resource(ChildResource(Box::new(child)));
         Ok(initiate_process_instance(vm, rid, pid, stdout_rid, stdin_rid, stderr_rid))
     });
+
+    // Runs the command to its end and returns { status, stdout, stderr, timedOut }.
+    // The status is null when the child has been killed by a signal, and a child
+    // which exits with an error is not an error of the script.
+    window.native_fn("runCapture", |vm, args| {
+        let (program, rest) = match args {
+            [Value::String(program), rest @ ..] => (program.unwrap_ref().to_string(), rest),
+            _ => return Err(RuntimeError::new_arguments(vm, "window.runCapture", "string, array?, object?", args))
+        };
+
+        let (arguments, options) = match rest {
+            [] | [Value::Null] => (Vec::new(), None),
+            [options @ Value::Dict(_)] | [Value::Null, options @ Value::Dict(_)] => (Vec::new(), Some(options)),
+            [Value::Array(ptr)] => (ptr.unwrap_ref().clone(), None),
+            [Value::Array(ptr), options @ (Value::Dict(_) | Value::Null)] => (ptr.unwrap_ref().clone(), Some(options)),
+            _ => return Err(RuntimeError::new_arguments(vm, "window.runCapture", "string, array?, object?", args))
+        };
+
+        vm.require_permission(Permission::ChildProcess, "window.runCapture", &format!("run \"{}\"", program))?;
+
+        let mut command = Command::new(&program);
+        for argument in arguments {
+            command.arg(argument.to_tiny_string().to_string());
+        }
+
+        if let Some(cwd) = option_of(options, "cwd") {
+            command.current_dir(cwd.to_tiny_string().to_string());
+        }
+
+        if let Some(Value::Dict(env_ptr)) = option_of(options, "env") {
+            for (key, (value, _)) in env_ptr.unwrap_ref() {
+                command.env(key.to_tiny_string().to_string(), value.to_tiny_string().to_string());
+            }
+        }
+
+        let timeout = match option_of(options, "timeoutMs") {
+            None | Some(Value::Null) => None,
+            Some(timeout @ (Value::Int(_) | Value::Float(_))) => Some(Duration::from_millis(timeout.to_usize() as u64)),
+            Some(_) => return Err(RuntimeError::new(vm, "[window.runCapture]: Expected a number as the \"timeoutMs\" option."))
+        };
+
+        let captured = match capture(command, timeout, &vm.cancellation) {
+            Ok(captured) => captured,
+            Err(error) => return Err(RuntimeError::new_io(vm, error))
+        };
+
+        let status = captured.status.map_or(Value::Null, |code| Value::Int(code as isize));
+        let stdout = Value::String(vm.allocate_string(String::from_utf8_lossy(&captured.stdout).into_owned()));
+        let stderr = Value::String(vm.allocate_string(String::from_utf8_lossy(&captured.stderr).into_owned()));
+
+        let mut result = Map::new();
+        for (key, value) in [("status", status), ("stdout", stdout), ("stderr", stderr), ("timedOut", Value::Bool(captured.timed_out))] {
+            result.insert(Value::String(vm.allocate_static_str(key)), (value, false));
+        }
+
+        Ok(Value::Dict(vm.allocate_value_ptr(result)))
+    });
+
+    // Spawns the editor of the user so it needs the same permission as `run`.
+    window.native_fn("editor", |vm, args| {
+        let text = match args.first() {
+            Some(Value::String(text)) => text.unwrap_ref().to_string(),
+            None | Some(Value::Null) => String::new(),
+            _ => return Err(RuntimeError::new_arguments(vm, "window.editor", "string?", args))
+        };
+
+        vm.require_permission(Permission::ChildProcess, "window.editor", "open the editor")?;
+
+        match prompt::editor(vm, &text) {
+            Ok(Some(text)) => Ok(Value::String(vm.allocate_string(text))),
+            Ok(None) => Ok(Value::Null),
+            Err(message) => Err(RuntimeError::new(vm, format!("[window.editor]: {}", message)))
+        }
+    });
+}
+
+struct Captured {
+    status: Option<i32>,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    timed_out: bool
+}
+
+// Both of the outputs are read by their own threads while the child runs, as a
+// child blocks once the pipe it writes to is full. The child gets killed when
+// the timeout expires or when the vm is cancelled.
+fn capture(mut command: Command, timeout: Option<Duration>, cancellation: &CancellationToken) -> io::Result<Captured> {
+    const POLL: Duration = Duration::from_millis(5);
+    // A killed child can leave its own children holding the pipes open, so the
+    // readers only get this long to collect what is left in them.
+    const GRACE: Duration = Duration::from_millis(100);
+
+    let mut child = command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let (sender, receiver) = mpsc::channel();
+    let stdout = read_in_background(child.stdout.take(), sender.clone());
+    let stderr = read_in_background(child.stderr.take(), sender);
+
+    let start = Instant::now();
+    let mut timed_out = false;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        timed_out = timeout.is_some_and(|timeout| start.elapsed() >= timeout);
+        if timed_out || cancellation.is_cancelled() {
+            let _ = child.kill();
+            break child.wait()?;
+        }
+
+        thread::sleep(POLL);
+    };
+
+    let killed_at = Instant::now();
+    for _ in 0..2 {
+        let received = match timed_out || cancellation.is_cancelled() {
+            true => receiver.recv_timeout(GRACE.saturating_sub(killed_at.elapsed())).is_ok(),
+            false => receiver.recv().is_ok()
+        };
+
+        if !received {
+            break;
+        }
+    }
+
+    let take = |buffer: Arc<Mutex<Vec<u8>>>| mem::take(&mut *buffer.lock().unwrap_or_else(|error| error.into_inner()));
+    Ok(Captured { status: status.code(), stdout: take(stdout), stderr: take(stderr), timed_out })
+}
+
+fn read_in_background<R: Read + Send + 'static>(reader: Option<R>, done: mpsc::Sender<()>) -> Arc<Mutex<Vec<u8>>> {
+    let buffer = Arc::new(Mutex::new(Vec::new()));
+    let shared = Arc::clone(&buffer);
+
+    thread::spawn(move || {
+        if let Some(mut reader) = reader {
+            let mut chunk = [0; 8192];
+            loop {
+                match reader.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(len) => shared.lock().unwrap_or_else(|error| error.into_inner()).extend_from_slice(&chunk[..len]),
+                    Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(_) => break
+                }
+            }
+        }
+
+        let _ = done.send(());
+    });
+
+    buffer
 }
 
 fn stdio_map(string: &str) -> Option<std::process::Stdio> {
@@ -345,6 +1499,6 @@ fn resolve_run_args(vm: &mut Vm, args: &[Value]) -> RuntimeResult<Command> {
                 _ => Err(RuntimeError::new(vm, "[window.run]: Expected `cmd` field as array in the options."))
             }
         },
-        _ => Err(RuntimeError::new(vm, "[window.run]: Expected (object[options]) arguments."))
+        _ => Err(RuntimeError::new_arguments(vm, "window.run", "object[options]", args))
     }
 }
\ No newline at end of file
@@ -1,70 +1,329 @@
+use std::error::Error;
+use std::fmt::{self, Display};
 use std::io::{Error as IoError, ErrorKind as IoErrorKind};
 use crate::{Value, TinyString, Vm};
 
+// The category of a runtime error so that the embedders can branch on it without
+// matching the messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeErrorKind {
+    TypeError,
+    // A global which has been left out with `--global` is used.
+    ReferenceError,
+    AssignmentError,
+    ArgumentError,
+    PermissionDenied,
+    IoError,
+    InternalError,
+    // The execution has been stopped by the `CancellationToken` of the vm.
+    Cancelled,
+    // The scripts have retained more memory than the `--max-heap` limit allows.
+    ResourceExhausted,
+    // Thrown by `panic`, which can be caught like any other error.
+    Panic,
+    // The scripts have called `window.exit`. It is not a failure, so the embedders
+    // only have to end the script with its `exit_code`.
+    Exit,
+    Error
+}
+
+impl RuntimeErrorKind {
+
+    // Derives the kind from the prefix of the message of the error.
+    pub fn of(message: &str) -> Self {
+        let native_message = match message.find("]: ") {
+            Some(index) if message.starts_with('[') => &message[index + 3..],
+            _ => message
+        };
+
+        if message.starts_with("TypeError:") || message.starts_with("You cannot call") || message.starts_with("Cannot call") {
+            Self::TypeError
+        } else if message.starts_with("ReferenceError:") {
+            Self::ReferenceError
+        } else if message.starts_with("Cannot assign") || message.starts_with("Cannot set") || message.starts_with("Cannot redeclare") {
+            Self::AssignmentError
+        } else if message.starts_with("InternalError:") || message.starts_with("[VM]:") || message.starts_with("[BytecodeReader]:") {
+            Self::InternalError
+        } else if native_message.starts_with("expected ") {
+            Self::ArgumentError
+        } else if native_message.contains("requires the `--") {
+            Self::PermissionDenied
+        } else {
+            Self::Error
+        }
+    }
+
+}
+
 #[derive(Clone, Debug)]
 pub struct RuntimeError {
     pub(crate) message: TinyString,
     line: Option<u32>, 
+    // The offset of the bytecode which has thrown the error, shown in place of
+    // the line when the chunk has no line data.
+    offset: usize,
     trace: Option<Vec<TinyString>>,
-    pub(super) catchable: bool
+    pub(super) catchable: bool,
+    kind: RuntimeErrorKind,
+    exit_code: Option<i32>,
+    cause: Option<Box<RuntimeError>>
 }
 
 impl RuntimeError {
-    pub(crate) fn new<M: Into<TinyString>>(vm: &Vm, message: M) -> Self {
-        let position_ = vm.chunk.get_position(vm.ip);
-        let mut trace_ = Vec::new();
+    // The code of the process which ends with an uncaught panic.
+    pub const PANIC_EXIT_CODE: i32 = 101;
 
-        for frame in vm.call_stack.iter() {
-            trace_.push(frame.name());
-        }
+    pub(crate) fn new<M: Into<TinyString>>(vm: &Vm, message: M) -> Self {
+        let message = message.into();
 
         Self {
-            message: message.into(),
-            line: Some(vm.chunk.get_line(position_)),
-            trace: Some(trace_),
-            catchable: true
+            kind: RuntimeErrorKind::of(&message),
+            message,
+            line: vm.chunk.get_line_at(vm.ip),
+            offset: vm.ip,
+            trace: Some(vm.trace()),
+            catchable: true,
+            exit_code: None,
+            cause: None
         }
     }
 
     pub(crate) fn new_uncatchable<M: Into<TinyString>>(vm: &Vm, message: M) -> Self {
-        let position_ = vm.chunk.get_position(vm.ip);
-        let mut trace_ = Vec::new();
+        Self { catchable: false, ..Self::new(vm, message) }
+    }
+
+    // The error of a native which has been called with the wrong arguments. These
+    // are programmer errors so they are always catchable unlike a failed io which
+    // may be recovered from by the scripts. The signature lists the types of the
+    // parameters such as `string, int?, ...any`, where a leading class name like
+    // `Set` is the receiver of a method, and names the first argument which does
+    // not fit it.
+    pub(crate) fn new_arguments(vm: &Vm, name: &str, signature: &str, args: &[Value]) -> Self {
+        Self::new_signatures(vm, name, &[signature], args)
+    }
 
-        for frame in vm.call_stack.iter() {
-            trace_.push(frame.name());
+    // The same as `new_arguments` for the natives which take several signatures.
+    // The argument is reported against the signatures which fit the most arguments.
+    pub(crate) fn new_signatures(vm: &Vm, name: &str, signatures: &[&str], args: &[Value]) -> Self {
+        let mismatches: Vec<(usize, &str)> = signatures.iter().map(|signature| mismatch(signature, args)).collect();
+        let furthest = mismatches.iter().map(|&(index, _)| index).max().unwrap_or(0);
+
+        let mut expected: Vec<String> = Vec::new();
+        for &(index, parameter) in &mismatches {
+            let parameter = parameter.replace(" | ", " or ");
+            if index == furthest && !expected.contains(&parameter) {
+                expected.push(parameter);
+            }
         }
 
+        let expected = expected.join(" or ");
+        match receiver_offset(signatures[0]) {
+            1 if furthest == 0 => Self::new_receiver(vm, name, &expected, args.first()),
+            offset => Self::new_argument(vm, name, furthest - offset, &expected, args.get(furthest))
+        }
+    }
+
+    // The error of a native whose argument at the index does not have the type it
+    // expects, with None for a missing argument. The receiver of a method is not
+    // counted, so the first argument given in the scripts is at the index 0.
+    pub(crate) fn new_argument(vm: &Vm, name: &str, index: usize, expected: &str, value: Option<&Value>) -> Self {
+        Self::new(vm, format!("[{}]: expected {} at argument {}, got {}.", name, expected, index + 1, describe(value)))
+    }
+
+    // The error of a method which has been called on another value than its class.
+    pub(crate) fn new_receiver(vm: &Vm, name: &str, expected: &str, value: Option<&Value>) -> Self {
+        Self::new(vm, format!("[{}]: expected {} as the receiver, got {}.", name, expected, describe(value)))
+    }
+
+    // Stops the execution of the scripts, so it cannot be caught by them.
+    pub(crate) fn new_cancelled(vm: &Vm) -> Self {
+        Self { kind: RuntimeErrorKind::Cancelled, ..Self::new_uncatchable(vm, "[VM]: The execution has been cancelled.") }
+    }
+
+    pub(crate) fn new_exhausted(vm: &Vm, max_heap: usize) -> Self {
         Self {
-            message: message.into(),
-            line: Some(vm.chunk.get_line(position_)),
-            trace: Some(trace_),
-            catchable: false
+            kind: RuntimeErrorKind::ResourceExhausted,
+            ..Self::new_uncatchable(vm, format!("[VM]: The scripts have exceeded the heap limit of {} bytes.", max_heap))
         }
     }
 
+    // Ends the scripts with the code given to `window.exit`.
+    pub(crate) fn new_exit(vm: &Vm, code: i32) -> Self {
+        Self {
+            kind: RuntimeErrorKind::Exit,
+            exit_code: Some(code),
+            ..Self::new_uncatchable(vm, format!("[window.exit]: The script has exited with the code {}.", code))
+        }
+    }
+
+    // The message is the one given to `panic` so that it is what the scripts catch.
+    pub(crate) fn new_panic<M: Into<TinyString>>(vm: &Vm, message: M) -> Self {
+        Self { kind: RuntimeErrorKind::Panic, exit_code: Some(Self::PANIC_EXIT_CODE), ..Self::new(vm, message) }
+    }
+
     pub(crate) fn new_io(vm: &mut Vm, error: IoError) -> Self {
-        let position_ = vm.chunk.get_position(vm.ip);
-        let mut trace_ = Vec::new();
+        let kind = match error.kind() {
+            IoErrorKind::PermissionDenied => RuntimeErrorKind::PermissionDenied,
+            _ => RuntimeErrorKind::IoError
+        };
 
-        for frame in vm.call_stack.iter() {
-            trace_.push(frame.name());
-        }
+        Self { kind, ..Self::new(vm, io_error_to_string(error.kind())) }
+    }
 
+    // Wraps the error thrown by a callback which has been called by a native. The
+    // scripts still catch the message of the innermost error.
+    pub(crate) fn caused_by<M: Into<TinyString>>(vm: &Vm, message: M, cause: RuntimeError) -> Self {
         Self {
-            message: TinyString::from(io_error_to_string(error.kind())),
-            line: Some(vm.chunk.get_line(position_)),
-            trace: Some(trace_),
-            catchable: true
+            catchable: cause.catchable,
+            kind: cause.kind,
+            exit_code: cause.exit_code,
+            cause: Some(Box::new(cause)),
+            ..Self::new(vm, message)
         }
     }
 
+    pub fn kind(&self) -> RuntimeErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    // The code the process should exit with for `window.exit` and an uncaught
+    // panic, which are none for the other errors.
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    pub fn cause(&self) -> Option<&RuntimeError> {
+        self.cause.as_deref()
+    }
+
     pub(crate) fn to_value(self, vm: &mut Vm) -> Value {
-        Value::String(vm.allocate_value_ptr(self.message))
+        match self.cause {
+            Some(cause) => cause.to_value(vm),
+            None => Value::String(vm.allocate_value_ptr(self.message))
+        }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+
+        match self.line {
+            Some(line) => write!(f, " (line {})", line)?,
+            None => write!(f, " (offset {})", self.offset)?
+        }
+
+        if let Some(trace) = &self.trace {
+            for frame in trace.iter().rev() {
+                write!(f, "\n    at {}", frame)?;
+            }
+        }
+
+        if let Some(cause) = &self.cause {
+            write!(f, "\ncaused by: {}", cause)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Error for RuntimeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.cause.as_deref().map(|cause| cause as &(dyn Error + 'static))
     }
 }
 
 pub type RuntimeResult<T> = Result<T, RuntimeError>;
 
+// The first argument which does not fit the signature, as its index in the
+// arguments and the parameter it was expected to be. When every argument fits,
+// the value is wrong rather than its type, such as a negative size, so the first
+// parameter after the receiver is blamed.
+fn mismatch<'a>(signature: &'a str, args: &[Value]) -> (usize, &'a str) {
+    let parameters: Vec<&str> = signature.split(", ").collect();
+
+    for (index, &parameter) in parameters.iter().enumerate() {
+        if let Some(variadic) = parameter.strip_prefix("...") {
+            match args.iter().skip(index).position(|arg| !fits(arg, variadic)) {
+                Some(position) => return (index + position, variadic),
+                None => break
+            }
+        }
+
+        let (expected, optional) = match parameter.strip_suffix('?') {
+            Some(expected) => (expected, true),
+            None => (parameter, false)
+        };
+
+        let fitting = match args.get(index) {
+            Some(arg) => fits(arg, expected) || (optional && matches!(arg, Value::Null)),
+            None => optional
+        };
+
+        if !fitting {
+            return (index, expected);
+        }
+    }
+
+    let index = receiver_offset(signature).min(parameters.len() - 1);
+    (index, parameters[index].trim_end_matches('?'))
+}
+
+// A signature which starts with a class name such as `Set` is the one of a method
+// whose receiver is the first argument.
+fn receiver_offset(signature: &str) -> usize {
+    let first = signature.split(", ").next().unwrap_or_default();
+    (first.starts_with(char::is_uppercase) && !first.contains(" | ")) as usize
+}
+
+// Whether the value is one of the types of the parameter such as `string | array`.
+// The words which are not types, like `initial`, accept any value.
+fn fits(value: &Value, parameter: &str) -> bool {
+    parameter.split(" | ").any(|expected| {
+        // The adjectives and the element types only constrain the value, as in
+        // `positive int` and `array[u8]`.
+        let expected = expected.rsplit(' ').next().unwrap_or(expected);
+        let expected = expected.split('[').next().unwrap_or(expected);
+
+        match expected {
+            "number" | "float" => matches!(value, Value::Int(_) | Value::Float(_)),
+            "int" => matches!(value, Value::Int(_)),
+            "string" => matches!(value, Value::String(_)),
+            "bool" | "boolean" => matches!(value, Value::Bool(_)),
+            "array" => matches!(value, Value::Array(_)),
+            "object" | "dict" => matches!(value, Value::Dict(_) | Value::Instance(_)),
+            "function" => matches!(value, Value::Function(_) | Value::NativeFn(_)),
+            "null" => matches!(value, Value::Null),
+            "Bytes" => matches!(value, Value::Bytes(_)),
+            "resource" => matches!(value, Value::Resource(..)),
+            class if class.starts_with(char::is_uppercase) => matches!(value, Value::Instance(_)),
+            _ => true
+        }
+    })
+}
+
+// The type of an argument in the errors, which tells the ints from the floats.
+fn describe(value: Option<&Value>) -> TinyString {
+    match value {
+        Some(Value::Int(_)) => TinyString::new(b"int"),
+        Some(Value::Float(_)) => TinyString::new(b"float"),
+        Some(value) => value.get_type(),
+        None => TinyString::new(b"nothing")
+    }
+}
+
 fn io_error_to_string<'a>(io_error_kind: IoErrorKind) -> &'a str {
     match io_error_kind {
         IoErrorKind::NotFound => "Not found.",
@@ -0,0 +1,134 @@
+// The entry point for fuzzing the bytecode reader of the vm. A `cargo fuzz` target
+// only has to call `execute_bytes` with its input, while the tests below run it
+// over seeded random and mutated bytecode. Whatever the bytes are, the vm has to
+// end with an error instead of panicking.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use crate::{AST, BytecodeCompiler, Vm, TinyString, RuntimeResult};
+
+// The instructions executed before the bytes are given up on, as the jumps can
+// loop forever.
+pub const INSTRUCTION_BUDGET: usize = 10_000;
+
+// The groups of the globals which the bytes can reach. The ones which exit, read
+// the input or touch the system are left out.
+const GLOBALS: &[u8] = b"math,print,range,errors,types";
+
+// Executes the bytes as the top level of a script in a vm without permissions,
+// with its output discarded.
+pub fn execute_bytes(bytes: &[u8]) -> RuntimeResult<()> {
+    execute_in(vm_of("null;"), bytes)
+}
+
+// Executes the bytes with the constants of the script, so that the bytecode of
+// the script can be mutated while its constant ids stay valid.
+fn execute_in(mut vm: Vm, bytes: &[u8]) -> RuntimeResult<()> {
+    vm.chunk.bytes = bytes.to_vec();
+    vm.ip = 0;
+    vm.stack.clear();
+
+    let max_slots = match vm.chunk.bytes.first() {
+        Some(&max_slots) => max_slots,
+        None => return Err(vm.truncated("the slots of the script"))
+    };
+
+    vm.stack.resize(max_slots as usize, Default::default());
+    vm.ip = 1;
+
+    let mut budget = INSTRUCTION_BUDGET;
+    while vm.ip < vm.chunk.bytes.len() && budget != 0 {
+        let byte = vm.chunk.bytes[vm.ip];
+        if let Err(error) = vm.execute_byte(byte) {
+            vm.handle_error(error)?;
+        }
+
+        budget -= 1;
+    }
+
+    Ok(())
+}
+
+fn vm_of(source: &str) -> Vm {
+    let filename = String::from("fuzz.ds");
+    let build = AST::compile(&filename, &source.to_owned()).expect("The script parses.");
+    let compiler = BytecodeCompiler::new(build).expect("The script compiles.");
+
+    let mut flags = HashMap::new();
+    flags.insert(TinyString::new(b"global"), TinyString::new(GLOBALS));
+
+    Vm::builder(compiler.into(), flags, PathBuf::from(filename))
+        .stdin(Box::new(io::empty()))
+        .stdout(Box::new(io::sink()))
+        .stderr(Box::new(io::sink()))
+        .build()
+        .expect("The script runs.")
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{execute_bytes, execute_in, vm_of};
+
+    // A xorshift generator so that the failing inputs can be reproduced from the seed.
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, bound: usize) -> usize {
+            (self.next() % bound as u64) as usize
+        }
+    }
+
+    const SCRIPTS: [&str; 4] = [
+        "let a = [1, 2, 3]; let b = a[1] + 2.5; println(b);",
+        "func add(x, y) { return x + y; } let total = 0; for i in range(0, 5) { total = add(total, i); }",
+        "let d = { \"a\": 1 }; d.a += 1; if (d.a == 2) { println(\"two\"); } else { println(\"other\"); }",
+        "try { throw(\"x\"); } expect { println(error); } let s = \"abc\".toUpperCase();"
+    ];
+
+    #[test]
+    fn random_bytes_do_not_panic() {
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+        for _ in 0..2_000 {
+            let len = random.below(64);
+            let bytes: Vec<u8> = (0..len).map(|_| random.next() as u8).collect();
+            let _ = execute_bytes(&bytes);
+        }
+    }
+
+    #[test]
+    fn truncated_scripts_do_not_panic() {
+        for source in SCRIPTS.iter() {
+            let bytes = vm_of(source).chunk.bytes;
+            for len in 0..bytes.len() {
+                let _ = execute_in(vm_of(source), &bytes[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn mutated_scripts_do_not_panic() {
+        let mut random = Random(0x9e37_79b9_7f4a_7c15);
+        for source in SCRIPTS.iter() {
+            let bytes = vm_of(source).chunk.bytes;
+            for _ in 0..300 {
+                let mut mutated = bytes.clone();
+                for _ in 0..1 + random.below(3) {
+                    let index = 1 + random.below(mutated.len() - 1);
+                    mutated[index] = random.next() as u8;
+                }
+
+                let _ = execute_in(vm_of(source), &mutated);
+            }
+        }
+    }
+
+}
@@ -0,0 +1,240 @@
+use std::collections::HashSet;
+use crate::{Vm, Value, RuntimeError, RuntimeResult, opcode};
+use super::memory::GcHeader;
+use super::object::ObjectKind;
+use super::upvalue::UpvalueState;
+
+// The live objects of the heap which are only collected again when the heap has
+// changed since the last check.
+#[derive(Debug, Default)]
+pub struct IntegrityState {
+    objects: HashSet<usize>,
+    heap_version: (usize, usize, usize)
+}
+
+// The invariants of the vm which are validated after each instruction with the
+// `--paranoid` flag. These are slow checks meant to catch the bugs of the vm as
+// close as possible to the instruction which caused them.
+impl Vm {
+
+    pub(crate) fn check_integrity(&mut self, last_byte: u8) -> RuntimeResult<()> {
+        // The objects are only freed by the collection which changes the threshold.
+        let heap_version = (self.objects.len(), self.bytes_allocated, self.next_gc);
+        let heap_changed = heap_version != self.integrity.heap_version;
+
+        if heap_changed {
+            self.integrity.objects = self.objects.iter().map(|handle| handle.0 as usize).collect();
+            self.integrity.heap_version = heap_version;
+        }
+
+        match self.find_violation(heap_changed) {
+            Some(violation) => Err(RuntimeError::new_uncatchable(
+                self,
+                format!("InternalError: {} after the {} instruction.", violation, opcode::to_string(last_byte))
+            )),
+            None => Ok(())
+        }
+    }
+
+    fn find_violation(&self, heap_changed: bool) -> Option<String> {
+        if self.ip > self.chunk.bytes.len() {
+            return Some(format!("The ip {} is out of the bounds of the chunk with {} bytes", self.ip, self.chunk.bytes.len()));
+        }
+
+        let mut stack_start = 0;
+        for (index, frame) in self.call_stack.iter().enumerate() {
+            // The frames of the native functions do not own any slots.
+            if index != 0 && frame.ip == 0 {
+                continue;
+            }
+
+            if frame.stack_start < stack_start {
+                return Some(format!("The frame \"{}\" starts at the slot {} before its caller at {}", frame.name, frame.stack_start, stack_start));
+            }
+
+            if frame.stack_start + frame.max_slots as usize > self.stack.len() {
+                return Some(format!("The slots of the frame \"{}\" exceed the stack of length {}", frame.name, self.stack.len()));
+            }
+
+            if frame.ip > self.chunk.bytes.len() {
+                return Some(format!("The return ip {} of the frame \"{}\" is out of the bounds of the chunk", frame.ip, frame.name));
+            }
+
+            stack_start = frame.stack_start;
+        }
+
+        for upvalue in &self.open_upvalues {
+            if let UpvalueState::Open(index) = upvalue.state() {
+                if index >= self.stack.len() {
+                    return Some(format!("An open upvalue points to the slot {} of the stack of length {}", index, self.stack.len()));
+                }
+            }
+        }
+
+        let check = |value: &Value, place: &str| match value.heap_pointer() {
+            Some(pointer) if !self.integrity.objects.contains(&(pointer as usize)) => {
+                Some(format!("The {} at {} in {} is not a live object", value.get_type(), pointer as usize, place))
+            },
+            _ => None
+        };
+
+        for value in &self.stack {
+            if let Some(violation) = check(value, "the stack") {
+                return Some(violation);
+            }
+        }
+
+        for (value, _) in self.globals.values() {
+            if let Some(violation) = check(value, "the globals") {
+                return Some(violation);
+            }
+        }
+
+        // The entries of the arrays and dicts can only become dangling when objects
+        // are freed, so these are only walked once the heap has changed.
+        if !heap_changed {
+            return None;
+        }
+
+        for handle in &self.objects {
+            let pointer = handle.0 as *const u8;
+            let violation = match handle.1 {
+                ObjectKind::Array => unsafe { GcHeader::unwrap_ref_::<Vec<Value>>(pointer) }
+                    .iter()
+                    .find_map(|item| check(item, "an array")),
+                ObjectKind::Map => unsafe { GcHeader::unwrap_ref_::<crate::Map>(pointer) }
+                    .iter()
+                    .find_map(|(key, (value, _))| check(key, "the keys of a dict").or_else(|| check(value, "a dict"))),
+                _ => None
+            };
+
+            if violation.is_some() {
+                return violation;
+            }
+        }
+
+        None
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::collections::HashMap;
+    use std::io;
+    use std::path::PathBuf;
+    use crate::{AST, BytecodeCompiler, Vm, Value, Map, RuntimeResult, Upvalue, opcode};
+
+    // A vm which has executed the script and is left with the frame of its top
+    // level and its locals on the stack.
+    fn vm_of(source: &str) -> Vm {
+        let filename = String::from("integrity.ds");
+        let build = AST::compile(&filename, &source.to_owned()).unwrap();
+        let compiler = BytecodeCompiler::new(build).unwrap();
+
+        Vm::builder(compiler.into(), HashMap::new(), PathBuf::from(filename))
+            .stdout(Box::new(io::sink()))
+            .build()
+            .unwrap()
+    }
+
+    fn violation(result: RuntimeResult<()>) -> String {
+        match result {
+            Ok(()) => panic!("The vm has passed the check."),
+            Err(error) => error.message().to_owned()
+        }
+    }
+
+    fn assert_violation(result: RuntimeResult<()>, expected: &str) {
+        let message = violation(result);
+        assert!(message.starts_with("InternalError: "), "{}", message);
+        assert!(message.contains(expected), "{}", message);
+        assert!(message.ends_with("after the POP instruction."), "{}", message);
+    }
+
+    const SCRIPT: &str = "let a = [1, 2]; let d = { \"a\": a };";
+
+    #[test]
+    fn a_sound_vm_passes() {
+        let mut vm = vm_of(SCRIPT);
+        assert!(vm.check_integrity(opcode::POP).is_ok());
+        assert!(vm.check_integrity(opcode::POP).is_ok());
+    }
+
+    #[test]
+    fn the_ip_out_of_the_chunk_is_a_violation() {
+        let mut vm = vm_of(SCRIPT);
+        vm.ip = vm.chunk.bytes.len() + 1;
+        assert_violation(vm.check_integrity(opcode::POP), "out of the bounds of the chunk");
+    }
+
+    #[test]
+    fn a_frame_before_its_caller_is_a_violation() {
+        let mut vm = vm_of(SCRIPT);
+        let mut caller = vm.call_stack[0].clone();
+        caller.stack_start = 1;
+        caller.ip = 1;
+
+        let mut callee = caller.clone();
+        callee.stack_start = 0;
+        vm.call_stack.extend([caller, callee]);
+
+        assert_violation(vm.check_integrity(opcode::POP), "starts at the slot 0 before its caller at 1");
+    }
+
+    #[test]
+    fn the_slots_of_a_frame_beyond_the_stack_are_a_violation() {
+        let mut vm = vm_of(SCRIPT);
+        vm.call_stack[0].max_slots = vm.stack.len() as u8 + 1;
+        assert_violation(vm.check_integrity(opcode::POP), "exceed the stack of length");
+    }
+
+    #[test]
+    fn the_return_ip_out_of_the_chunk_is_a_violation() {
+        let mut vm = vm_of(SCRIPT);
+        let mut frame = vm.call_stack[0].clone();
+        frame.ip = vm.chunk.bytes.len() + 1;
+        vm.call_stack.push(frame);
+
+        assert_violation(vm.check_integrity(opcode::POP), "out of the bounds of the chunk");
+    }
+
+    #[test]
+    fn an_open_upvalue_beyond_the_stack_is_a_violation() {
+        let mut vm = vm_of(SCRIPT);
+        vm.open_upvalues.push(Upvalue::new_open(vm.stack.len()));
+        assert_violation(vm.check_integrity(opcode::POP), "An open upvalue points to the slot");
+    }
+
+    #[test]
+    fn a_freed_object_on_the_stack_is_a_violation() {
+        let mut vm = vm_of(SCRIPT);
+        assert!(vm.check_integrity(opcode::POP).is_ok());
+
+        // The object is left out of the heap as if it had been freed, and handed
+        // back afterwards so that the vm still frees it.
+        let ptr = vm.allocate_value_ptr(vec![Value::Int(1)]);
+        let handle = vm.objects.pop().unwrap();
+        vm.stack.push(Value::Array(ptr));
+
+        assert_violation(vm.check_integrity(opcode::POP), "in the stack is not a live object");
+        vm.objects.push(handle);
+    }
+
+    #[test]
+    fn a_freed_object_in_a_dict_is_a_violation() {
+        let mut vm = vm_of(SCRIPT);
+        let array = vm.allocate_value_ptr(Vec::new());
+        let handle = vm.objects.pop().unwrap();
+
+        let mut map = Map::new();
+        map.insert(Value::Int(0), (Value::Array(array), false));
+        let dict = vm.allocate_value_ptr(map);
+        vm.stack.push(Value::Dict(dict));
+
+        assert_violation(vm.check_integrity(opcode::POP), "in a dict is not a live object");
+        vm.objects.push(handle);
+    }
+
+}
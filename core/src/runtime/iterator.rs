@@ -8,7 +8,10 @@ use crate::Value;
 pub struct ValueIter {
     ptr: *const Value,
     len: usize,
-    index: usize
+    index: usize,
+    // The id of the stage of the vm which produces the values of a lazy adapter
+    // such as `it.map(fn)`, which has no values of its own.
+    pipeline: Option<u32>
 }
 
 impl Default for ValueIter {
@@ -34,11 +37,27 @@ impl ValueIter {
             Self {
                 ptr: buf as _,
                 index: 0,
-                len: count
+                len: count,
+                pipeline: None
             }
         }
     }
 
+    // A copy at the same position with a buffer of its own. The copies of the
+    // struct share the buffer, which is freed with the first one collected.
+    pub fn copied(&self) -> Self {
+        let values = unsafe { std::slice::from_raw_parts(self.ptr, self.len) };
+        Self { index: self.index, pipeline: self.pipeline, ..Self::new(values) }
+    }
+
+    pub(crate) fn lazy(pipeline: u32) -> Self {
+        Self { pipeline: Some(pipeline), ..Self::default() }
+    }
+
+    pub fn pipeline(&self) -> Option<u32> {
+        self.pipeline
+    }
+
     pub fn len(&self) -> usize {
         self.len
     }
@@ -101,12 +101,55 @@ macro_rules! deallocate {
     }};
 }
 
+// The sizes which the contents of the arrays and the dicts are counted with by
+// the approximate accounting of `--max-heap`.
+pub(crate) const VALUE_BYTES: usize = mem::size_of::<Value>();
+pub(crate) const ENTRY_BYTES: usize = mem::size_of::<(Value, (Value, bool))>();
+
+// Parses sizes like "512", "64kb", "64mb" or "1gb" into bytes. The units are
+// powers of 1024.
+pub fn parse_byte_size(size: &str) -> Option<usize> {
+    let size = size.trim().to_ascii_lowercase();
+    let (number, unit) = match size.find(|char: char| !char.is_ascii_digit()) {
+        Some(index) => size.split_at(index),
+        None => (size.as_str(), "")
+    };
+
+    let multiplier: usize = match unit.trim() {
+        "" | "b" => 1,
+        "k" | "kb" => 1 << 10,
+        "m" | "mb" => 1 << 20,
+        "g" | "gb" => 1 << 30,
+        _ => return None
+    };
+
+    number.parse::<usize>().ok()?.checked_mul(multiplier)
+}
+
 // An handler to handle the pointer.
 #[derive(Debug, Clone)]
 pub struct GcHandle(pub(crate) *const GcHeader, pub(crate) ObjectKind);
 
 impl GcHandle {
 
+    // The bytes retained by the object. The strings count their length and the
+    // arrays and dicts the number of their entries.
+    pub fn approx_bytes(&self) -> usize {
+        let pointer = self.0 as *const u8;
+        unsafe {
+            match self.1 {
+                ObjectKind::NativeFunction => get_layout_size::<object::NativeFunction>(),
+                ObjectKind::Array => get_layout_size::<Vec<Value>>() + GcHeader::unwrap_ref_::<Vec<Value>>(pointer).len() * VALUE_BYTES,
+                ObjectKind::Bytes => get_layout_size::<Vec<u8>>() + GcHeader::unwrap_ref_::<Vec<u8>>(pointer).len(),
+                ObjectKind::Map => get_layout_size::<Map>() + GcHeader::unwrap_ref_::<Map>(pointer).len() * ENTRY_BYTES,
+                ObjectKind::Function => get_layout_size::<object::Function>(),
+                ObjectKind::Iterator => get_layout_size::<ValueIter>() + GcHeader::unwrap_ref_::<ValueIter>(pointer).len() * VALUE_BYTES,
+                ObjectKind::String => get_layout_size::<TinyString>() + GcHeader::unwrap_ref_::<TinyString>(pointer).len(),
+                ObjectKind::Instance => get_layout_size::<object::Instance>() + GcHeader::unwrap_ref_::<object::Instance>(pointer).properties.len() * ENTRY_BYTES
+            }
+        }
+    }
+
     pub fn unwrap_header(&self) -> &GcHeader {
         // GcHeader is required for a value in GcHandle
         if self.0.is_null() {
@@ -129,6 +172,7 @@ impl GcHandle {
         match self.1 {
             ObjectKind::NativeFunction => deallocate!(self.0, object::NativeFunction),
             ObjectKind::Array => deallocate!(self.0, Vec<Value>),
+            ObjectKind::Bytes => deallocate!(self.0, Vec<u8>),
             ObjectKind::Map => deallocate!(self.0, Map),
             ObjectKind::Function => deallocate!(self.0, object::Function),
             ObjectKind::Iterator => deallocate!(self.0, ValueIter),
@@ -148,6 +192,7 @@ impl GcHandle {
             match self.1 {
                 ObjectKind::NativeFunction => deallocate!(self.0, object::NativeFunction),
                 ObjectKind::Array => deallocate!(self.0, Vec<Value>),
+                ObjectKind::Bytes => deallocate!(self.0, Vec<u8>),
                 ObjectKind::Map => deallocate!(self.0, Map),
                 ObjectKind::Function => deallocate!(self.0, object::Function),
                 ObjectKind::Iterator => deallocate!(self.0, ValueIter),
@@ -6,4 +6,6 @@ pub mod memory;
 pub mod object;
 pub mod iterator;
 pub mod core;
-pub mod resources;
\ No newline at end of file
+pub mod resources;
+pub mod integrity;
+pub mod profile;pub mod fuzz;
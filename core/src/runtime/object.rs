@@ -1,5 +1,5 @@
 use std::fmt::{self, Debug, Formatter};
-use std::collections::HashMap;
+use indexmap::IndexMap;
 use crate::{RuntimeResult, Value, TinyString, Vm, Upvalue, ValueIter, ValuePtr};
 
 #[derive(Debug, Clone, Copy)]
@@ -7,6 +7,7 @@ pub enum ObjectKind {
     NativeFunction,
     Function,
     Array,
+    Bytes,
     Map,
     Iterator,
     String,
@@ -29,18 +30,23 @@ macro_rules! impl_default_object_trait {
     };
 }
 
-pub type Map = HashMap<Value, (Value, bool)>;
+// The entries keep the order they were inserted in, which is the one they are
+// iterated and printed in.
+pub type Map = IndexMap<Value, (Value, bool)>;
 pub type NativeFunctionHandler = fn (&mut Vm, &[Value]) -> RuntimeResult<Value>;
 
 #[derive(Clone)]
 pub struct NativeFunction {
     pub(crate) func: NativeFunctionHandler,
-    pub(crate) name: TinyString
+    pub(crate) name: TinyString,
+    // The value and the name of a method read off the value, which is called
+    // instead of the handler. See `Vm::bind_method`.
+    pub(crate) bound: Option<(Value, Value)>
 }
 
 impl Default for NativeFunction {
     fn default() -> Self {
-        Self { func: |_, _| Ok(Value::Null), name: TinyString::new(b"anonymous") }
+        Self { func: |_, _| Ok(Value::Null), name: TinyString::new(b"anonymous"), bound: None }
     }
 }
 
@@ -68,6 +74,7 @@ pub struct Instance {
 impl_default_object_trait! {
     NativeFunction: NativeFunction
     Vec<Value>: Array
+    Vec<u8>: Bytes
     Map: Map
     Function: Function
     ValueIter: Iterator
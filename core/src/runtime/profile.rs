@@ -0,0 +1,130 @@
+use std::fs;
+use std::path::Path;
+use std::collections::HashMap;
+use crate::{Vm, RuntimeError, RuntimeResult};
+use super::core::json;
+
+// The sampling profiler of `--profile-output <file>`. The frames of the call stack
+// are recorded every `INTERVAL` instructions and the samples of the same stack
+// are counted together, so the counts are proportional to the instructions run
+// by each stack. The natives are only seen while they call back into a script.
+#[derive(Debug, Default)]
+pub struct Profiler {
+    countdown: u32,
+    samples: HashMap<Vec<String>, u64>
+}
+
+impl Profiler {
+    pub const INTERVAL: u32 = 100;
+
+    pub(crate) fn tick(&mut self, frames: impl Iterator<Item = String>) {
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            return;
+        }
+
+        self.countdown = Self::INTERVAL - 1;
+        *self.samples.entry(frames.collect()).or_default() += 1;
+    }
+
+    // The stacks sorted by their frames, which keeps the files stable between runs.
+    fn stacks(&self) -> Vec<(&Vec<String>, u64)> {
+        let mut stacks: Vec<_> = self.samples.iter().map(|(stack, &count)| (stack, count)).collect();
+        stacks.sort();
+        stacks
+    }
+
+    // The collapsed stacks read by flamegraph.pl and speedscope, one
+    // `main;outer;inner 12` line per stack.
+    pub fn collapsed(&self) -> String {
+        let mut collapsed = String::new();
+        for (stack, count) in self.stacks() {
+            collapsed.push_str(&stack.join(";"));
+            collapsed.push_str(&format!(" {}\n", count));
+        }
+
+        collapsed
+    }
+
+    // A sampled profile of the file format of speedscope, whose frames are shared
+    // by the stacks and weighted by their counts.
+    pub fn speedscope(&self, name: &str) -> String {
+        let mut frames: Vec<&str> = Vec::new();
+        let mut samples = Vec::new();
+        let mut weights = Vec::new();
+
+        for (stack, count) in self.stacks() {
+            let indexes: Vec<String> = stack.iter().map(|frame| {
+                let index = match frames.iter().position(|known| known == frame) {
+                    Some(index) => index,
+                    None => {
+                        frames.push(frame);
+                        frames.len() - 1
+                    }
+                };
+
+                index.to_string()
+            }).collect();
+
+            samples.push(format!("[{}]", indexes.join(",")));
+            weights.push(count.to_string());
+        }
+
+        let mut json = String::from("{\"$schema\":\"https://www.speedscope.app/file-format-schema.json\",\"shared\":{\"frames\":[");
+        for (index, frame) in frames.iter().enumerate() {
+            if index != 0 {
+                json.push(',');
+            }
+
+            json.push_str("{\"name\":");
+            json::write_string(&mut json, frame);
+            json.push('}');
+        }
+
+        json.push_str("]},\"profiles\":[{\"type\":\"sampled\",\"name\":");
+        json::write_string(&mut json, name);
+        json.push_str(&format!(
+            ",\"unit\":\"none\",\"startValue\":0,\"endValue\":{},\"samples\":[{}],\"weights\":[{}]}}],\"exporter\":\"DashScript\"}}\n",
+            self.samples.values().sum::<u64>(),
+            samples.join(","),
+            weights.join(",")
+        ));
+
+        json
+    }
+}
+
+impl Vm {
+
+    pub(crate) fn sample_profile(&mut self) {
+        if let Some(profiler) = &mut self.profiler {
+            // The top level is named like the root of the usual flamegraphs.
+            let frames = self.call_stack.iter().enumerate().map(|(index, frame)| match index {
+                0 => "main".to_owned(),
+                _ => frame.name.to_string().replace(';', ":")
+            });
+
+            profiler.tick(frames);
+        }
+    }
+
+    // Writes the speedscope format for the files ending with `.json` and the
+    // collapsed stacks for the other ones.
+    pub(crate) fn write_profile(&self, path: &Path) -> RuntimeResult<()> {
+        let profiler = match &self.profiler {
+            Some(profiler) => profiler,
+            None => return Ok(())
+        };
+
+        let contents = match path.extension() {
+            Some(extension) if extension == "json" => profiler.speedscope(&self.filename()),
+            _ => profiler.collapsed()
+        };
+
+        fs::write(path, contents).map_err(|error| RuntimeError::new(
+            self,
+            format!("[VM]: Could not write the profile to \"{}\": {}.", path.display(), error)
+        ))
+    }
+
+}
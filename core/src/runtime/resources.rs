@@ -1,21 +1,37 @@
 use std::any::{Any};
+use std::fs::File;
 use std::process::{Child, ChildStdin, ChildStdout, ChildStderr};
-use std::io::{ErrorKind, Write, Read};
+use std::io::{ErrorKind, Write, Read, BufRead, BufReader, Seek, SeekFrom};
 
 pub type ResourceError<T = ()> = Result<T, ErrorKind>;
 
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum ResourceKind {
     Io,
     Child,
     None
 }
 
+impl ResourceKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Io => "io",
+            Self::Child => "child",
+            Self::None => "none"
+        }
+    }
+}
+
 pub trait Resource: Any + 'static {
     fn kind(&self) -> ResourceKind;
     fn close(&self) -> ResourceError { 
         Ok(()) 
     }
+
+    // The io resources return themselves so that they can be read and written.
+    fn as_io(&self) -> Option<&dyn IoResource> {
+        None
+    }
 }
 
 pub trait IoResource: Resource + Any + 'static {
@@ -30,6 +46,20 @@ pub trait IoResource: Resource + Any + 'static {
     fn flush(&self) -> ResourceError {
         Err(ErrorKind::Interrupted)
     }
+
+    // Appends the bytes up to and including the next newline to the buffer and
+    // returns their number, which is 0 at the end.
+    fn read_line(&self, _buf: &mut Vec<u8>) -> ResourceError<usize> {
+        Err(ErrorKind::Unsupported)
+    }
+
+    fn seek(&self, _position: SeekFrom) -> ResourceError<u64> {
+        Err(ErrorKind::Unsupported)
+    }
+
+    fn size(&self) -> ResourceError<u64> {
+        Err(ErrorKind::Unsupported)
+    }
 }
 
 pub struct ChildResource(pub Box<Child>);
@@ -37,6 +67,11 @@ pub struct ChildStdinResource(pub Box<ChildStdin>);
 pub struct ChildStdoutResource(pub Box<ChildStdout>);
 pub struct ChildStderrResource(pub Box<ChildStderr>);
 
+// The files of `fs.open`. The reads are buffered so that the lines can be read
+// one at a time, and the buffer is dropped before a write so that the write
+// lands right after the bytes which have been read.
+pub struct FileResource(pub Box<BufReader<File>>);
+
 impl Resource for ChildResource {
     fn kind(&self) -> ResourceKind {
         ResourceKind::Child
@@ -54,18 +89,40 @@ impl Resource for ChildStdinResource {
     fn kind(&self) -> ResourceKind {
         ResourceKind::Io
     }
+
+    fn as_io(&self) -> Option<&dyn IoResource> {
+        Some(self)
+    }
 }
 
 impl Resource for ChildStdoutResource {
     fn kind(&self) -> ResourceKind {
         ResourceKind::Io
     }
+
+    fn as_io(&self) -> Option<&dyn IoResource> {
+        Some(self)
+    }
 }
 
 impl Resource for ChildStderrResource {
     fn kind(&self) -> ResourceKind {
         ResourceKind::Io
     }
+
+    fn as_io(&self) -> Option<&dyn IoResource> {
+        Some(self)
+    }
+}
+
+impl Resource for FileResource {
+    fn kind(&self) -> ResourceKind {
+        ResourceKind::Io
+    }
+
+    fn as_io(&self) -> Option<&dyn IoResource> {
+        Some(self)
+    }
 }
 
 impl IoResource for ChildStdinResource {
@@ -102,6 +159,38 @@ impl IoResource for ChildStderrResource {
     }
 }
 
+impl IoResource for FileResource {
+    fn read(&self, buf: &mut [u8]) -> ResourceError<usize> {
+        unwrap_ref_as_mut(self.0.as_ref()).read(buf).map_err(|error| error.kind())
+    }
+
+    fn write(&self, buf: &[u8]) -> ResourceError<usize> {
+        let file = unwrap_ref_as_mut(self.0.as_ref());
+        let position = file.stream_position().map_err(|error| error.kind())?;
+        file.seek(SeekFrom::Start(position)).map_err(|error| error.kind())?;
+        file.get_mut().write(buf).map_err(|error| error.kind())
+    }
+
+    fn flush(&self) -> ResourceError {
+        unwrap_ref_as_mut(self.0.as_ref()).get_mut().flush().map_err(|error| error.kind())
+    }
+
+    fn read_line(&self, buf: &mut Vec<u8>) -> ResourceError<usize> {
+        unwrap_ref_as_mut(self.0.as_ref()).read_until(b'\n', buf).map_err(|error| error.kind())
+    }
+
+    fn seek(&self, position: SeekFrom) -> ResourceError<u64> {
+        unwrap_ref_as_mut(self.0.as_ref()).seek(position).map_err(|error| error.kind())
+    }
+
+    fn size(&self) -> ResourceError<u64> {
+        match self.0.get_ref().metadata() {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(error) => Err(error.kind())
+        }
+    }
+}
+
 fn unwrap_ref_as_mut<'a, T>(ref_: &T) -> &'a mut T {
     unsafe { &mut *(ref_ as *const T as *mut T) }
 }
\ No newline at end of file
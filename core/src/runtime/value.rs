@@ -4,8 +4,9 @@ use std::ops;
 use std::hash::{Hash, Hasher};
 use std::string::ToString;
 use std::cmp::Ordering;
+use std::convert::TryInto;
 use std::fmt::{self, Display, Formatter};
-use crate::{TinyString, Map, ValueIter, Vm, ValuePtr, Function, NativeFunction, Instance};
+use crate::{TinyString, Map, ValueIter, Vm, ValuePtr, Function, NativeFunction, Instance, ResourceKind};
 
 #[derive(Debug, Clone, Copy)]
 pub enum Value {
@@ -14,24 +15,40 @@ pub enum Value {
     Float(f64), // The basic float value
     String(ValuePtr<TinyString>),
     Array(ValuePtr<Vec<Value>>),
+    // The raw bytes of the files and the resources, with `Bytes.from` creating them
+    // from the scripts.
+    Bytes(ValuePtr<Vec<u8>>),
     Dict(ValuePtr<Map>),
     Function(ValuePtr<Function>),
     NativeFn(ValuePtr<NativeFunction>),
     Iterator(ValuePtr<ValueIter>),
     Instance(ValuePtr<Instance>),
+    // A handle of the resource table of the vm. Only the natives create them so
+    // the scripts cannot forge one from a number.
+    Resource(u32, ResourceKind),
+    // A slot of the ref table of the vm and its generation, created by `Memory.ref`.
+    // Like the resources the scripts cannot forge one.
+    Ref(u32, u32),
     Null // The basic null or empty value
 }
 
+// Values are copied in and out of the stack by almost every instruction, so a
+// new variant must not grow the enum beyond the 16 bytes noted above.
+const _: () = assert!(std::mem::size_of::<Value>() <= 16);
+
 impl Value {
 
     pub const NAN: Self = Self::Float(f64::NAN);
     pub const INFINITY: Self = Self::Float(f64::INFINITY);
 
+    // Only false, null, 0, NaN and "" are falsy. Arrays and objects are always
+    // truthy regardless of their length.
     pub fn to_bool(&self) -> bool {
         match self {
             Self::Bool(boolean) => *boolean,
             Self::Int(0) | Self::Null => false,
-            Self::Float(float) => *float == 0.0,
+            Self::Float(float) => *float != 0.0 && !float.is_nan(),
+            Self::String(ptr) => !ptr.unwrap_ref().to_bytes().is_empty(),
             _ => true
         }
     }
@@ -48,15 +65,22 @@ impl Value {
             Self::String(ptr) => ptr.unwrap(),
             Self::Dict(_) | Self::Instance(_) => TinyString::new(b"[Object]"),
             Self::Array(_) => TinyString::new(b"[Array]"),
+            Self::Bytes(ptr) => TinyString::new(bytes_preview(ptr.unwrap_ref()).as_bytes()),
             Self::Function(_) | Self::NativeFn(_) => TinyString::new(b"[Function]"),
             Self::Iterator(_) => TinyString::new(b"[Iterator]"),
+            Self::Resource(..) | Self::Ref(..) => TinyString::new(self.to_string().as_bytes()),
             Self::Null => TinyString::new(b"null")
         }
     }
 
+    // The negative powers and the ones out of the integer range of two integers
+    // are floats.
     pub fn pow(self, rhs: Self) -> Self {
         match (self, rhs) {
-            (Self::Int(a), Self::Int(b)) => Self::Int(a.pow(b as u32)),
+            (Self::Int(a), Self::Int(b)) => match b.try_into().ok().and_then(|b| a.checked_pow(b)) {
+                Some(power) => Self::Int(power),
+                None => Self::Float((a as f64).powf(b as f64))
+            },
             (Self::Float(a), Self::Float(b)) => Self::Float(a.powf(b)),
             (Self::Int(a), Self::Float(b)) => Self::Float((a as f64).powf(b)),
             (Self::Float(a), Self::Int(b)) => Self::Float(a.powf(b as f64)),
@@ -80,8 +104,11 @@ impl Value {
             Self::String(bytes) => format!("\"{}\"", bytes.unwrap_ref()),
             Self::Dict(_)  | Self::Instance(_) => "[Object]".to_owned(),
             Self::Array(_) => "[Array]".to_owned(),
+            Self::Bytes(ptr) => bytes_preview(ptr.unwrap_ref()),
             Self::Function(_) | Self::NativeFn(_) => "[Function]".to_owned(),
             Self::Iterator(_) => "[Iterator]".to_owned(),
+            Self::Resource(rid, kind) => format!("[Resource {} #{}]", kind.name(), rid),
+            Self::Ref(slot, _) => format!("[Ref #{}]", slot),
             Self::Null => "null".to_string()
         }
     }
@@ -142,17 +169,56 @@ impl Value {
                 Self::Int(_) | Self::Float(_) => b"number",
                 Self::String(_) => b"string",
                 Self::Array(_) => b"array",
+                Self::Bytes(_) => b"bytes",
                 Self::Dict(_) | Self::Instance(_) => b"object",
                 Self::Iterator(_) => b"iterator",
-                Self::Function(_) | Self::NativeFn(_) => b"function"
+                Self::Function(_) | Self::NativeFn(_) => b"function",
+                Self::Resource(..) => b"resource",
+                Self::Ref(..) => b"ref"
             }
         )
     }
 
+    // The order shared by `sort`, `Math.min`, `Math.max` and `Math.median`. The
+    // numbers are in their total order with NaN after all of them and null comes
+    // before everything. The values of different types cannot be compared unless
+    // `lenient` is set, which orders them by the names of their types.
+    pub fn total_cmp(&self, other: &Value, lenient: bool) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+            (Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) => {
+                let (a, b) = (self.to_f64(), other.to_f64());
+                Some(match (a.is_nan(), b.is_nan()) {
+                    (true, true) => Ordering::Equal,
+                    (true, false) => Ordering::Greater,
+                    (false, true) => Ordering::Less,
+                    _ => a.total_cmp(&b)
+                })
+            },
+            (Value::String(a), Value::String(b)) => Some((a.unwrap_ref() as &str).cmp(b.unwrap_ref())),
+            (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+            (Value::Null, Value::Null) => Some(Ordering::Equal),
+            (Value::Null, _) => Some(Ordering::Less),
+            (_, Value::Null) => Some(Ordering::Greater),
+            _ => {
+                let (a, b) = (self.get_type(), other.get_type());
+                match (a == b, lenient) {
+                    (true, _) => Some(Ordering::Equal),
+                    (false, true) => Some((&a as &str).cmp(&b)),
+                    (false, false) => None
+                }
+            }
+        }
+    }
+
     pub fn into_iter(&self) -> ValueIter {
         match self {
-            Self::Iterator(ptr) => ptr.unwrap(),
+            Self::Iterator(ptr) => ptr.unwrap_ref().copied(),
             Self::Array(ptr) => ValueIter::new(ptr.unwrap_ref()),
+            Self::Bytes(ptr) => {
+                let bytes: Vec<Value> = ptr.unwrap_ref().iter().map(|&byte| Value::Int(byte as isize)).collect();
+                ValueIter::new(&bytes)
+            },
             _ => ValueIter::default()
         }
     }
@@ -182,10 +248,44 @@ impl Value {
         }
     }
 
+    // The pointer of the object allocated by the vm if the value is not a primitive.
+    pub fn heap_pointer(&self) -> Option<*const u8> {
+        match self {
+            Self::String(ptr) => Some(ptr.as_ptr()),
+            Self::Array(ptr) => Some(ptr.as_ptr()),
+            Self::Bytes(ptr) => Some(ptr.as_ptr()),
+            Self::Dict(ptr) => Some(ptr.as_ptr()),
+            Self::Function(ptr) => Some(ptr.as_ptr()),
+            Self::NativeFn(ptr) => Some(ptr.as_ptr()),
+            Self::Iterator(ptr) => Some(ptr.as_ptr()),
+            Self::Instance(ptr) => Some(ptr.as_ptr()),
+            Self::Bool(_) | Self::Int(_) | Self::Float(_) | Self::Resource(..) | Self::Ref(..) | Self::Null => None
+        }
+    }
+
     pub fn is_function(&self) -> bool {
         matches!(self, Value::Function(_) | Value::NativeFn(_))
     }
 
+    pub fn is_number(&self) -> bool {
+        matches!(self, Value::Int(_) | Value::Float(_))
+    }
+
+    // Canonicalizes a value before it is used as a key of a map. Floats holding an
+    // integral value (including -0.0) are converted into ints so `d[1]`, `d[1.0]`
+    // and `d[-0.0]`/`d[0]` resolve to the same entry. Other floats keep their exact
+    // bits, so `d[0.1 + 0.2]` and `d[0.3]` stay different keys. Returns None for NaN
+    // as it never equals itself and would create unreachable entries.
+    pub fn to_map_key(self) -> Option<Value> {
+        match self {
+            Self::Float(float) if float.is_nan() => None,
+            Self::Float(float) if float.fract() == 0.0 && float >= isize::MIN as f64 && float < isize::MAX as f64 => {
+                Some(Self::Int(float as isize))
+            },
+            value => Some(value)
+        }
+    }
+
 }
 
 impl Default for Value {
@@ -353,11 +453,25 @@ impl Hash for Value {
                 ptr.unwrap_ref().hash(state);
             },
             Self::Array(ptr) => hash_ptr!(ptr),
+            // Hashed by their contents as they are compared by them.
+            Self::Bytes(ptr) => {
+                state.write_u8(8);
+                ptr.unwrap_ref().hash(state);
+            },
             Self::Dict(ptr) => hash_ptr!(ptr),
             Self::Function(ptr) => hash_ptr!(ptr),
             Self::NativeFn(ptr) => hash_ptr!(ptr),
             Self::Iterator(ptr) => hash_ptr!(ptr),
-            Self::Instance(ptr) => hash_ptr!(ptr)
+            Self::Instance(ptr) => hash_ptr!(ptr),
+            Self::Resource(rid, _) => {
+                state.write_u8(7);
+                rid.hash(state);
+            },
+            Self::Ref(slot, generation) => {
+                state.write_u8(9);
+                slot.hash(state);
+                generation.hash(state);
+            }
         }
     }
 }
@@ -367,12 +481,16 @@ impl PartialEq for Value {
         match (self, other) {
             (Value::Float(a), Value::Float(b)) => a == b,
             (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
             (Value::String(a), Value::String(b)) => a.unwrap_ref() == b.unwrap_ref(),
             (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Bytes(a), Value::Bytes(b)) => a.unwrap_ref() == b.unwrap_ref(),
             (Value::Dict(a), Value::Dict(b)) => a == b,
             (Value::Function(a), Value::Function(b)) => a == b,
             (Value::NativeFn(a), Value::NativeFn(b)) => a == b,
             (Value::Instance(a), Value::Instance(b)) => a == b,
+            (Value::Resource(a, _), Value::Resource(b, _)) => a == b,
+            (Value::Ref(a, a_generation), Value::Ref(b, b_generation)) => a == b && a_generation == b_generation,
             (Value::Null, Value::Null) => true,
             _ => false
         }
@@ -393,16 +511,7 @@ impl PartialOrd for Value {
 
 impl Ord for Value {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.partial_cmp(other) {
-            Some(ordering) => ordering,
-            None => {
-                match (self.is_nan(), other.is_nan()) {
-                    (false, true) => Ordering::Greater,
-                    (true, false) => Ordering::Equal,
-                    _ => Ordering::Equal
-                }
-            }
-        }
+        self.total_cmp(other, true).unwrap_or(Ordering::Equal)
     }
 }
 
@@ -410,16 +519,42 @@ impl Eq for Value {}
 
 impl Display for Value {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.fmt_rounded(f, None)
+    }
+}
+
+// The display of a value whose floats are rounded to the significant digits set
+// with `window.inspectDefaults`, which leaves the value itself as it is.
+pub struct Rounded {
+    value: Value,
+    digits: Option<usize>
+}
+
+impl Display for Rounded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.value.fmt_rounded(f, self.digits)
+    }
+}
+
+impl Value {
+    pub fn rounded(self, digits: Option<usize>) -> Rounded {
+        Rounded { value: self, digits }
+    }
+
+    fn fmt_rounded(&self, f: &mut Formatter<'_>, digits: Option<usize>) -> fmt::Result {
         match self {
             Value::String(bytes) => write!(f, "{}", bytes.unwrap_ref()),
             Value::Int(int) => write!(f, "{}", int),
-            Value::Float(float) => write!(f, "{}", float),
+            Value::Float(float) => match digits {
+                Some(digits) => write!(f, "{}", round_significant(*float, digits)),
+                None => write!(f, "{}", float)
+            },
             Value::Null => write!(f, "null"),
             Value::Bool(boolean) => write!(f, "{}", boolean),
             Value::Dict(entries) => {
                 write!(f, "{{\n")?;
                 for (key, value) in entries.unwrap_ref() {
-                    write!(f, "    {}: {},\n", key.to_string(), value.0.to_string()).unwrap();
+                    write!(f, "    {}: {},\n", key.to_string(), item_string(&value.0, digits)).unwrap();
                 }
 
                 write!(f, "}}")
@@ -427,7 +562,7 @@ impl Display for Value {
             Value::Array(vector) => {
                 write!(f, "[\n")?;
                 for item in vector.unwrap_ref()  {
-                    write!(f, "    {},\n", item.to_string()).unwrap();
+                    write!(f, "    {},\n", item_string(item, digits)).unwrap();
                 }
                 
                 write!(f, "]")
@@ -435,13 +570,51 @@ impl Display for Value {
             Value::Instance(instance) => {
                 write!(f, "{{\n")?;
                 for (key, value) in instance.unwrap_ref().properties.iter() {
-                    write!(f, "    {}: {},\n", key.to_string(), value.0.to_string()).unwrap();
+                    write!(f, "    {}: {},\n", key.to_string(), item_string(&value.0, digits)).unwrap();
                 }
 
                 write!(f, "}}")
             },
             Value::Function(_) | Value::NativeFn(_) => write!(f, "[Function]"),
-            Value::Iterator(_) => write!(f, "[Iterator]")
+            Value::Iterator(_) => write!(f, "[Iterator]"),
+            Value::Bytes(ptr) => write!(f, "{}", bytes_preview(ptr.unwrap_ref())),
+            Value::Resource(rid, kind) => write!(f, "[Resource {} #{}]", kind.name(), rid),
+            Value::Ref(slot, _) => write!(f, "[Ref #{}]", slot)
         }
     }
+}
+
+// The items of the containers are shown in the short form of `to_string`.
+fn item_string(value: &Value, digits: Option<usize>) -> String {
+    match (value, digits) {
+        (&Value::Float(float), Some(digits)) => round_significant(float, digits).to_string(),
+        _ => value.to_string()
+    }
+}
+
+// The float rounded to the significant digits, whose shortest representation is
+// then shown so that 0.1 + 0.2 with 12 digits is shown as 0.3.
+fn round_significant(float: f64, digits: usize) -> f64 {
+    match float.is_finite() && float != 0.0 {
+        true => format!("{:.*e}", digits.max(1) - 1, float).parse().unwrap_or(float),
+        false => float
+    }
+}
+
+// Bytes are shown by their length and the hex of their first bytes, like
+// `<Bytes 1024 89 50 4e 47 0d 0a 1a 0a ...>`.
+fn bytes_preview(bytes: &[u8]) -> String {
+    const PREVIEW_LEN: usize = 8;
+
+    let mut preview = format!("<Bytes {}", bytes.len());
+    for byte in bytes.iter().take(PREVIEW_LEN) {
+        preview.push_str(&format!(" {:02x}", byte));
+    }
+
+    if bytes.len() > PREVIEW_LEN {
+        preview.push_str(" ...");
+    }
+
+    preview.push('>');
+    preview
 }
\ No newline at end of file
@@ -3,76 +3,94 @@ extern crate alloc;
 use std::rc::Rc;
 use std::{mem, ptr};
 use std::any::TypeId;
+use std::io::{self, Write, Read, BufRead};
 use std::path::PathBuf;
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
 use std::convert::TryInto;
 use std::collections::{HashMap, BTreeMap};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use super::memory::*;
 use crate::{
-    Value, RuntimeResult, RuntimeError, ObjectTrait, Chunk, TinyString, Function, Upvalue, 
-    UpvalueState, ValueIter, ValuePtr, Instance, Map, Resource, IoResource, ResourceKind,
+    Value, RuntimeResult, RuntimeError, RuntimeErrorKind, ObjectTrait, Chunk, TinyString, Function, Upvalue, 
+    UpvalueState, NativeFunction, NativeFunctionHandler, ValueIter, ValuePtr, Instance, Map, Resource, IoResource, TypeAnnotation,
     opcode, core
 };
+use super::core::{signal, tty, log, clock::Clock, watch::Watcher, lazy::Pipeline, temp::TempKind, installer::{self, Selection}, prompt::{self, Answers, Grant}};
+use super::integrity::IntegrityState;
+use super::profile::Profiler;
 
+// The operands are read after the opcode so `$opcode` names the instruction
+// being decoded when the chunk ends before all of its operands.
 macro_rules! read_u8 {
-    ($self:expr) => {
+    ($self:expr, $opcode:expr) => {
         match $self.chunk.bytes.get($self.ip) {
             Some(byte) => {
                 $self.ip += 1;
                 *byte
             },
-            None => return Err(RuntimeError::new_uncatchable($self, "[BytecodeReader]: Corrupted Bytecode. Expected 1 more byte to form u8."))
+            None => return Err($self.truncated(&opcode::to_string($opcode)))
         }
     };
 }
 
 macro_rules! read_u16 {
-    ($self:expr) => {
+    ($self:expr, $opcode:expr) => {
         match $self.chunk.bytes.get($self.ip..$self.ip + 2) {
             Some(byte) => {
                 $self.ip += 2;
                 u16::from_le_bytes(byte.try_into().unwrap())
             },
-            None => return Err(RuntimeError::new_uncatchable($self, "[BytecodeReader]: Corrupted Bytecode. Expected 2 more bytes to form u16."))
+            None => return Err($self.truncated(&opcode::to_string($opcode)))
         }
     };
 }
 
 macro_rules! read_u32 {
-    ($self:expr) => {
+    ($self:expr, $opcode:expr) => {
         match $self.chunk.bytes.get($self.ip..$self.ip + 4) {
             Some(byte) => {
                 $self.ip += 4;
                 u32::from_le_bytes(byte.try_into().unwrap())
             },
-            None => return Err(RuntimeError::new_uncatchable($self, "[BytecodeReader]: Corrupted Bytecode. Expected 4 more bytes to form u32."))
+            None => return Err($self.truncated(&opcode::to_string($opcode)))
         }
     };
 }
 
 macro_rules! read_auto {
-    ($self:expr) => {
-        match $self.chunk.bytes[$self.ip] {
-            0 => {
+    ($self:expr, $opcode:expr) => {
+        match $self.chunk.bytes.get($self.ip) {
+            Some(0) => {
                 $self.ip += 1;
-                match $self.chunk.bytes.get($self.ip) {
-                    Some(byte) => {
-                        $self.ip += 1;
-                        *byte as u32
-                    },
-                    None => return Err(RuntimeError::new_uncatchable($self, "[BytecodeReader]: Corrupted Bytecode. Expected 1 more byte to form u8."))
-                }
+                read_u8!($self, $opcode) as u32
             },
-            1 => {
+            Some(1) => {
                 $self.ip += 1;
-                match $self.chunk.bytes.get($self.ip..$self.ip + 4) {
-                    Some(byte) => {
-                        $self.ip += 4;
-                        u32::from_le_bytes(byte.try_into().unwrap())
-                    },
-                    None => return Err(RuntimeError::new_uncatchable($self, "[BytecodeReader]: Corrupted Bytecode. Expected 4 more bytes to form u32."))
-                }
+                read_u32!($self, $opcode)
             },
-            _ => return Err(RuntimeError::new_uncatchable($self, "[BytecodeReader]: Corrupted Bytecode. Expected 1 more byte as either 1[LONG] or 0[SHORT]."))
+            Some(_) => return Err(RuntimeError::new_uncatchable($self, "[BytecodeReader]: Corrupted Bytecode. Expected 1 more byte as either 1[LONG] or 0[SHORT].")),
+            None => return Err($self.truncated(&opcode::to_string($opcode)))
+        }
+    };
+}
+
+macro_rules! read_constant {
+    ($self:expr, $pool:ident, $index:expr, $opcode:expr) => {{
+        let index = $index as usize;
+        match $self.chunk.constants.$pool.get(index) {
+            Some(constant) => *constant,
+            None => return Err(RuntimeError::new_uncatchable($self, format!("[BytecodeReader]: Corrupted Bytecode. Unknown constant {} while decoding {}.", index, opcode::to_string($opcode))))
+        }
+    }};
+}
+
+macro_rules! pop {
+    ($self:expr) => {
+        match $self.stack.pop() {
+            Some(value) => value,
+            None => return Err(RuntimeError::new_uncatchable($self, "[VM]: Stack Manipulation Failed. Expected a value on the stack to pop."))
         }
     };
 }
@@ -89,19 +107,11 @@ macro_rules! pop_two {
 macro_rules! handle_error {
     ($error:expr, $self:expr, $vm:expr) => {
         if $error.catchable {
-            let mut block = None;
-            for &try_block in &$vm.chunk.try_blocks {
-                if try_block.0 < $self.ip {
-                    block = Some(try_block);
-                }
-            }
-
-            match block {
+            match $vm.chunk.try_block_at($self.ip) {
                 Some((_, jump_at, slot)) => {
                     let value = $error.to_value($vm);
                     $self.ip = jump_at;
-                    $vm.add_local(slot as usize, value);
-                    Ok(())
+                    $vm.add_local(slot as usize, value)
                 },
                 None => return Err($error)
             }
@@ -120,16 +130,93 @@ pub struct Permissions {
     pub write: bool,
     pub memory: bool,
     pub child_process: bool,
+    pub crypto: bool,
+    pub signal: bool,
     pub unsafe_libs: bool
 }
 
+// The permissions which can be granted while the script runs with the
+// `--prompt-permissions` flag. The memory is not among them as `Memory` is a
+// global which only exists when the flag is given up front.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission {
+    Read,
+    Write,
+    ChildProcess,
+    Crypto,
+    Signal
+}
+
+impl Permission {
+    // The name used by the `--use-<name>` flags.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::ChildProcess => "child-process",
+            Self::Crypto => "crypto",
+            Self::Signal => "signal"
+        }
+    }
+}
+
+impl Permissions {
+    pub fn get(&self, permission: Permission) -> bool {
+        *self.field(permission)
+    }
+
+    pub fn grant(&mut self, permission: Permission) {
+        *self.field_mut(permission) = true;
+    }
+
+    fn field(&self, permission: Permission) -> &bool {
+        match permission {
+            Permission::Read => &self.read,
+            Permission::Write => &self.write,
+            Permission::ChildProcess => &self.child_process,
+            Permission::Crypto => &self.crypto,
+            Permission::Signal => &self.signal
+        }
+    }
+
+    fn field_mut(&mut self, permission: Permission) -> &mut bool {
+        match permission {
+            Permission::Read => &mut self.read,
+            Permission::Write => &mut self.write,
+            Permission::ChildProcess => &mut self.child_process,
+            Permission::Crypto => &mut self.crypto,
+            Permission::Signal => &mut self.signal
+        }
+    }
+}
+
+// A slot of the table used by `Memory.ref`. The generation is bumped whenever
+// the slot is released so that the refs to the old value become stale instead of
+// reading the value which reuses the slot.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RefSlot {
+    pub(crate) value: Option<Value>,
+    pub(crate) generation: u32
+}
+
+// A capability of the runtime which is reported by `window.features`. The version
+// is None for the features which are not supported yet.
+#[derive(Debug, Clone, Copy)]
+pub struct Feature {
+    pub name: &'static str,
+    pub enabled: bool,
+    pub since: Option<&'static str>
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct CallFrame {
-    name: TinyString,
+    pub(super) name: TinyString,
     upvalues: Vec<Upvalue>,
-    stack_start: usize,
-    max_slots: u8,
-    ip: usize // This would be 0 if the call frame belongs to a native function
+    pub(super) stack_start: usize,
+    pub(super) max_slots: u8,
+    pub(super) ip: usize, // This would be 0 if the call frame belongs to a native function
+    // The functions registered by `defer` which run when the frame exits.
+    deferred: Vec<Value>
 }
 
 impl CallFrame {
@@ -153,7 +240,28 @@ pub struct VmConstants {
     pub(super) cwd: Value,
     pub(super) cmd: Value,
     pub(super) env: Value,
-    pub(super) process_prototype: ValuePtr<Map>
+    pub(super) path: Value,
+    pub(super) __watcher: Value,
+    pub(super) __members: Value,
+    pub(super) __values: Value,
+    pub(super) __iter: Value,
+    pub(super) size: Value,
+    pub(super) __buffer: Value,
+    pub(super) length: Value,
+    pub(super) process_prototype: ValuePtr<Map>,
+    pub(super) watcher_prototype: ValuePtr<Map>,
+    pub(super) set_prototype: ValuePtr<Map>
+}
+
+impl VmConstants {
+    // The strings which are marked by the collector.
+    fn values(&self) -> Vec<Value> {
+        vec![
+            self.init, self.prototype, self.__listeners, self.__time, self.__date, self.rid,
+            self.pid, self.stdin, self.stdout, self.stderr, self.cwd, self.cmd, self.env, self.path,
+            self.__watcher, self.__members, self.__values, self.__iter, self.size, self.__buffer, self.length
+        ]
+    }
 }
 
 #[derive(Default)]
@@ -162,20 +270,362 @@ pub struct Vm {
     pub(crate) ip: usize,
     pub(crate) stack: Vec<Value>,
     pub(crate) globals: HashMap<u32, (Value, bool)>,
+    // The globals of the groups left out by `--global` with the name of their group.
+    pub(crate) uninstalled: HashMap<u32, (&'static str, &'static str)>,
     pub(crate) bytes_allocated: usize,
+    // The approximate bytes retained by the objects, which `--max-heap` limits.
+    pub(crate) approx_bytes: usize,
+    pub(crate) max_heap: Option<usize>,
     pub(crate) permissions: Permissions,
     pub(crate) next_gc: usize,
     pub(crate) objects: Vec<GcHandle>,
     pub(crate) path: PathBuf,
+    pub(crate) args: Vec<String>,
+    pub(crate) answers: Answers,
     pub(crate) iterator_methods: MethodMap<ValueIter>,
     pub(crate) string_methods: MethodMap<TinyString>,
     pub(crate) array_methods: MethodMap<Vec<Value>>,
+    pub(crate) bytes_methods: MethodMap<Vec<u8>>,
+    pub(crate) dict_methods: MethodMap<Map>,
     pub(super) call_stack: Vec<CallFrame>,
     pub(super) constants: VmConstants,
     pub(super) resource_table: BTreeMap<u32, Rc<dyn Resource>>,
     next_rid: u32,
+    pub(super) flags: HashMap<TinyString, TinyString>,
+    pub(crate) input: VmReader,
+    pub(crate) out: VmWriter,
+    pub(crate) err: VmWriter,
+    random_state: u64,
+    pub(crate) clock: Clock,
+    // The natives already noted as nondeterministic under `--seed`.
+    nondeterministic: Vec<String>,
+    pub(crate) interrupt_callback: Option<Value>,
+    pub(crate) panic_hook: Option<Value>,
+    pub(crate) exit_callbacks: Vec<Value>,
+    pub(crate) watchers: BTreeMap<u32, Watcher>,
+    // The temporary entries removed once the scripts have ended.
+    pub(crate) temp_paths: Vec<(PathBuf, TempKind)>,
+    pub(crate) next_watcher_id: u32,
+    pub(crate) pipelines: BTreeMap<u32, Pipeline>,
+    pub(crate) next_pipeline_id: u32,
+    // The slots of the top level of the script which stay after its execution.
+    top_level_len: usize,
+    pub(crate) ref_slots: Vec<RefSlot>,
+    // The builtin objects like `Boolean` which can be called as a function and
+    // the natives which they are called through.
+    pub(crate) converters: Vec<(ValuePtr<Map>, Value)>,
+    pub(crate) features: Vec<Feature>,
+    pub(crate) result: Value,
+    pub(crate) log_level: log::Level,
+    // The significant digits which `print` and `inspect` round the floats to.
+    pub(crate) display_digits: Option<usize>,
+    pub(crate) log: Value,
+    pub(crate) paranoid: bool,
+    pub(crate) prompt_permissions: bool,
+    pub(crate) cancellation: CancellationToken,
+    pub(super) integrity: IntegrityState,
+    pub(crate) profiler: Option<Profiler>,
+    pub(super) open_upvalues: Vec<Upvalue>
+}
+
+// A writer which the output of the scripts goes through. It defaults to the
+// stdout of the process but can be replaced by embedders with `VmBuilder`. The
+// stdout is buffered by the vm and written out every `FLUSH_LINES` lines or
+// `FLUSH_BYTES` bytes, or at every line when it is a terminal, and it is flushed
+// before anything is read from the input or a child process is spawned.
+pub struct VmWriter {
+    writer: Box<dyn Write>,
+    stream: Option<tty::Stream>,
+    // None when the writes go straight to the writer.
+    buffer: Option<OutputBuffer>
+}
+
+struct OutputBuffer {
+    bytes: Vec<u8>,
+    lines: usize,
+    max_lines: usize
+}
+
+impl VmWriter {
+    pub const FLUSH_LINES: usize = 1024;
+    pub const FLUSH_BYTES: usize = 64 * 1024;
+
+    pub fn new(writer: Box<dyn Write>) -> Self {
+        Self { writer, stream: None, buffer: None }
+    }
+
+    pub fn stdout() -> Self {
+        let max_lines = if tty::is_terminal(tty::Stream::Stdout) { 1 } else { Self::FLUSH_LINES };
+        Self {
+            writer: Box::new(io::stdout()),
+            stream: Some(tty::Stream::Stdout),
+            buffer: Some(OutputBuffer { bytes: Vec::with_capacity(Self::FLUSH_BYTES), lines: 0, max_lines })
+        }
+    }
+
+    pub fn stderr() -> Self {
+        Self { writer: Box::new(io::stderr()), stream: Some(tty::Stream::Stderr), buffer: None }
+    }
+
+    // The writers given by embedders are never treated as terminals.
+    pub fn is_terminal(&self) -> bool {
+        self.stream.is_some_and(tty::is_terminal)
+    }
+
+    fn write_buffer(&mut self) -> io::Result<()> {
+        if let Some(buffer) = &mut self.buffer {
+            if !buffer.bytes.is_empty() {
+                buffer.lines = 0;
+                let result = self.writer.write_all(&buffer.bytes);
+                buffer.bytes.clear();
+                return result;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for VmWriter {
+    fn default() -> Self {
+        Self::stdout()
+    }
+}
+
+// Flushes the buffered output of the scripts when the vm gets dropped even if
+// the execution failed.
+impl Drop for VmWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl Write for VmWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let buffer = match &mut self.buffer {
+            Some(buffer) => buffer,
+            None => return self.writer.write(buf)
+        };
+
+        buffer.bytes.extend_from_slice(buf);
+        buffer.lines += buf.iter().filter(|&&byte| byte == b'\n').count();
+        if buffer.lines >= buffer.max_lines || buffer.bytes.len() >= Self::FLUSH_BYTES {
+            self.write_buffer()?;
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.write_buffer()?;
+        self.writer.flush()
+    }
+}
+
+// A reader which the input of the scripts comes from. It defaults to the stdin of
+// the process and can be replaced by embedders to drive the prompts in tests.
+// The flags tell whether it is the stdin and whether it answers the permission
+// prompts even though it is not a terminal.
+pub struct VmReader(Box<dyn BufRead>, bool, bool);
+
+impl VmReader {
+    pub fn new(reader: Box<dyn BufRead>) -> Self {
+        Self(reader, false, false)
+    }
+
+    pub fn interactive(reader: Box<dyn BufRead>) -> Self {
+        Self(reader, false, true)
+    }
+
+    pub fn stdin() -> Self {
+        Self(Box::new(io::BufReader::new(io::stdin())), true, false)
+    }
+
+    // The readers given by embedders are never treated as terminals.
+    pub fn is_terminal(&self) -> bool {
+        self.1 && tty::is_terminal(tty::Stream::Stdin)
+    }
+
+    // Whether there is someone to ask for the permissions.
+    pub fn is_interactive(&self) -> bool {
+        self.2 || self.is_terminal()
+    }
+}
+
+impl Default for VmReader {
+    fn default() -> Self {
+        Self::stdin()
+    }
+}
+
+impl Read for VmReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl BufRead for VmReader {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.0.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.0.consume(amount)
+    }
+}
+
+// A flag which the embedders can set from another thread to stop the scripts.
+// The vm checks it between the instructions, so a native which blocks only
+// stops once it returns unless it checks the token on its own.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+pub struct VmBuilder {
+    chunk: Chunk,
+    preloads: Vec<(TinyString, Chunk)>,
     flags: HashMap<TinyString, TinyString>,
-    open_upvalues: Vec<Upvalue>
+    path: PathBuf,
+    args: Vec<String>,
+    answers: Answers,
+    input: VmReader,
+    out: VmWriter,
+    err: VmWriter,
+    cancellation: CancellationToken,
+    max_heap: Option<usize>,
+    profile_output: Option<PathBuf>,
+    natives: Vec<(TinyString, NativeFunctionHandler)>
+}
+
+impl VmBuilder {
+
+    pub fn stdin(mut self, reader: Box<dyn BufRead>) -> Self {
+        self.input = VmReader::new(reader);
+        self
+    }
+
+    // The same as `stdin` but the reader also answers the permission prompts of
+    // `--prompt-permissions` as if it was a terminal.
+    pub fn interactive_stdin(mut self, reader: Box<dyn BufRead>) -> Self {
+        self.input = VmReader::interactive(reader);
+        self
+    }
+
+    pub fn stdout(mut self, writer: Box<dyn Write>) -> Self {
+        self.out = VmWriter::new(writer);
+        self
+    }
+
+    pub fn stderr(mut self, writer: Box<dyn Write>) -> Self {
+        self.err = VmWriter::new(writer);
+        self
+    }
+
+    // The arguments which `script.args` of the main script exposes.
+    pub fn args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    // An answer which `window.confirm` and `window.prompt` serve to the prompts
+    // with the same key in their options instead of reading the input.
+    pub fn answer(mut self, key: &str, value: &str) -> Self {
+        self.answers.add(key, value);
+        self
+    }
+
+    // Executes the chunk before the main chunk in the same vm. The constant pool of
+    // the main chunk must be continued from the pool of the preloaded chunk.
+    pub fn preload(mut self, name: &str, chunk: Chunk) -> Self {
+        self.preloads.push((TinyString::new(name.as_bytes()), chunk));
+        self
+    }
+
+    // Stops the scripts once their objects retain about this many bytes. The
+    // `max-heap` flag takes precedence over it.
+    pub fn max_heap(mut self, bytes: usize) -> Self {
+        self.max_heap = Some(bytes);
+        self
+    }
+
+    // Starts the vm without the builtin globals such as `Math` and `print`, other
+    // than the groups added with `global`.
+    pub fn no_default_globals(mut self) -> Self {
+        self.flags.insert(TinyString::new(b"no-default-globals"), TinyString::default());
+        self
+    }
+
+    // Installs the group of builtins such as "math" or "window.fs" while leaving
+    // out the groups which are not added, the same as `--global`.
+    pub fn global(mut self, name: &str) -> Self {
+        let key = TinyString::new(b"global");
+        let names = match self.flags.get(&key) {
+            Some(names) => format!("{},{}", names, name),
+            None => name.to_owned()
+        };
+
+        self.flags.insert(key, TinyString::new(names.as_bytes()));
+        self
+    }
+
+    // Seeds the random numbers and makes the clock virtual so that the runs with
+    // the same seed give the same output, the same as `--seed`.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.flags.insert(TinyString::new(b"seed"), TinyString::new(seed.to_string().as_bytes()));
+        self
+    }
+
+    // Samples the call stacks while the scripts run and writes them to the file
+    // once they have ended, the same as `--profile-output`.
+    pub fn profile_output(mut self, path: PathBuf) -> Self {
+        self.profile_output = Some(path);
+        self
+    }
+
+    // Registers a global native function before the scripts are executed so that
+    // they can call into the embedder, which can call them back with `Vm::call`.
+    pub fn native(mut self, name: &str, func: NativeFunctionHandler) -> Self {
+        self.natives.push((TinyString::new(name.as_bytes()), func));
+        self
+    }
+
+    // The token which stops the vm once cancelled. It has to be taken before the
+    // build as the scripts are executed by it.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    pub fn build(self) -> RuntimeResult<Vm> {
+        Vm::start(Vm {
+            chunk: self.chunk,
+            flags: self.flags,
+            path: self.path,
+            args: self.args,
+            answers: self.answers,
+            input: self.input,
+            out: self.out,
+            err: self.err,
+            cancellation: self.cancellation,
+            max_heap: self.max_heap,
+            call_stack: vec![CallFrame { name: TinyString::new(b"runtime"), ..Default::default() }],
+            next_gc: u16::MAX as usize,
+            ..Default::default()
+        }, self.preloads, self.natives, self.profile_output)
+    }
+
 }
 
 impl Vm {
@@ -184,34 +634,261 @@ impl Vm {
     pub const NULL_POINTER: u32 = 0;
 
     pub fn new(chunk: Chunk, flags: HashMap<TinyString, TinyString>, path: PathBuf) -> RuntimeResult<Self> {
-        let mut vm = Self {
+        Self::builder(chunk, flags, path).build()
+    }
+
+    pub fn builder(chunk: Chunk, flags: HashMap<TinyString, TinyString>, path: PathBuf) -> VmBuilder {
+        VmBuilder {
             chunk,
             flags,
             path,
-            call_stack: vec![CallFrame { name: TinyString::new(b"runtime"), ..Default::default() }],
-            next_gc: u16::MAX as usize,
-            ..Default::default()
-        };
+            preloads: Vec::new(),
+            args: Vec::new(),
+            answers: Answers::default(),
+            input: VmReader::stdin(),
+            out: VmWriter::stdout(),
+            err: VmWriter::stderr(),
+            cancellation: CancellationToken::new(),
+            max_heap: None,
+            profile_output: None,
+            natives: Vec::new()
+        }
+    }
+
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    // The value of the script given by a return at the top level or by its last
+    // expression when it has been compiled with `BytecodeCompiler::new_with_result`.
+    pub fn result(&self) -> Value {
+        self.result
+    }
+
+    // A global or a top level declaration of the main script such as a function
+    // which the embedder wants to call once the script has been executed.
+    pub fn global(&mut self, name: &str) -> Option<Value> {
+        let constant_id = self.chunk.constants.add_string(TinyString::new(name.as_bytes()));
+        let declared = self.chunk.declarations.iter().find(|&&(declared, _)| declared == constant_id);
+
+        match declared {
+            Some(&(_, slot)) => self.stack.get(slot as usize).copied(),
+            None => self.globals.get(&constant_id).map(|&(value, _)| value)
+        }
+    }
+
+    // Calls a function of the scripts with the arguments. It can be called again
+    // and again after the script has been executed, where the calls share the top
+    // level of the script, and from the natives called by the scripts, where the
+    // function runs in frames above the ones of the scripts. The frames and the
+    // stack are as they were before the call once it returns, even if it fails.
+    pub fn call(&mut self, function: Value, args: &[Value]) -> RuntimeResult<Value> {
+        if args.len() > u8::MAX as usize {
+            return Err(RuntimeError::new(self, format!("[VM]: Cannot call a function with more than {} arguments.", u8::MAX)));
+        }
+
+        let (call_depth, stack_len) = (self.call_stack.len(), self.stack.len());
+        self.stack.extend_from_slice(args);
+
+        let result = self.call_function_with_returned_value(function, args.len() as u8);
+        if result.is_err() {
+            self.call_stack.truncate(call_depth);
+            self.close_upvalues(stack_len);
+            self.stack.truncate(stack_len);
+        }
+
+        result
+    }
+
+    // The number of frames, which is 1 for the top level of the scripts.
+    pub fn call_depth(&self) -> usize {
+        self.call_stack.len()
+    }
+
+    // Drops the frames and the values left on the stack by the calls, such as the
+    // ones of a native which has been given up on, while keeping the top level of
+    // the script and the globals.
+    pub fn reset_frames(&mut self) {
+        self.call_stack.truncate(1);
+        self.close_upvalues(self.top_level_len);
+        self.stack.truncate(self.top_level_len);
+        self.ip = self.chunk.bytes.len();
+    }
+
+    // Appends the feature to the registry which `window.features` is generated from.
+    pub(crate) fn register_feature(&mut self, name: &'static str, enabled: bool, since: Option<&'static str>) {
+        self.features.push(Feature { name, enabled, since });
+    }
+
+    fn start(
+        mut vm: Self,
+        preloads: Vec<(TinyString, Chunk)>,
+        natives: Vec<(TinyString, NativeFunctionHandler)>,
+        mut profile_output: Option<PathBuf>
+    ) -> RuntimeResult<Self> {
 
         macro_rules! vm_constants {
             ($($name:ident)+) => {
                 VmConstants {
                     $($name: Value::String(vm.allocate_static_str(stringify!($name))),)+
-                    process_prototype: ValuePtr::default()
+                    process_prototype: ValuePtr::default(),
+                    watcher_prototype: ValuePtr::default(),
+                    set_prototype: ValuePtr::default()
                 }
             };
         }
 
         vm.constants = vm_constants! { 
-            init prototype rid pid stdin stdout stderr cwd cmd env
-            __listeners __time __date 
+            init prototype rid pid stdin stdout stderr cwd cmd env path
+            __listeners __time __date __watcher __members __values __iter size
+            __buffer length
         };
 
+        // `--seed <n>` seeds the generator and makes the clock virtual so that the
+        // runs repeat each other.
+        match vm.flags.get(&TinyString::new(b"seed")) {
+            Some(seed) => match seed.to_string().parse::<u64>() {
+                Ok(seed) => {
+                    vm.random_state = mix_seed(seed);
+                    vm.clock = Clock::virtual_clock();
+                },
+                Err(_) => return Err(RuntimeError::new(&vm, format!("[VM]: Invalid seed \"{}\". Expected a positive integer.", seed)))
+            },
+            None => {
+                vm.random_state = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|duration| duration.as_nanos() as u64)
+                    .unwrap_or_default() | 1;
+                vm.clock = Clock::system();
+            }
+        }
+
+        if let Some(size) = vm.flags.get(&TinyString::new(b"max-heap")) {
+            match parse_byte_size(size) {
+                Some(bytes) => vm.max_heap = Some(bytes),
+                None => return Err(RuntimeError::new(&vm, format!("[VM]: Invalid heap limit \"{}\". Expected a size like 64mb.", size)))
+            }
+        }
+
+        // `js` is the only mode of `--compat` for now.
+        if let Some(mode) = vm.flags.get(&TinyString::new(b"compat")) {
+            if mode.to_bytes() != b"js" {
+                return Err(RuntimeError::new(&vm, format!("[VM]: Unknown compat mode \"{}\". Expected \"js\".", mode)));
+            }
+        }
+
+        if let Some(name) = Selection::of(&vm.flags).unknown() {
+            return Err(RuntimeError::new(&vm, format!("[VM]: Unknown global \"{}\". Expected one of {}.", name, installer::names().join(", "))));
+        }
+
         vm.init_permissions();
+        vm.paranoid = vm.flags.contains_key(&TinyString::new(b"paranoid"));
+        vm.prompt_permissions = vm.flags.contains_key(&TinyString::new(b"prompt-permissions"));
+        vm.answers.assume_yes = vm.flags.contains_key(&TinyString::new(b"assume-yes"));
+        vm.log_level = vm.flags.get(&TinyString::new(b"log-level"))
+            .and_then(|level| log::Level::from_name(level))
+            .unwrap_or_default();
         core::init(&mut vm);
-        vm.execute()?;
-        
-        Ok(vm)
+
+        for (name, func) in natives {
+            let constant_id = vm.chunk.constants.add_string(name.clone());
+            let native = vm.allocate_value_ptr(NativeFunction { name, func, bound: None });
+            vm.globals.insert(constant_id, (Value::NativeFn(native), true));
+        }
+
+        if let Some(path) = vm.flags.get(&TinyString::new(b"profile-output")) {
+            profile_output = Some(PathBuf::from(path.to_string()));
+        }
+
+        if profile_output.is_some() {
+            vm.profiler = Some(Profiler::default());
+        }
+
+        // The profile is written and the temporary entries are removed even when
+        // the scripts fail.
+        let result = vm.run_scripts(preloads);
+        vm.remove_temp_paths();
+        let written = match &profile_output {
+            Some(path) => vm.write_profile(path),
+            None => Ok(())
+        };
+
+        result.and(written).map(|_| vm)
+    }
+
+    fn run_scripts(&mut self, preloads: Vec<(TinyString, Chunk)>) -> RuntimeResult<()> {
+        let main_name = self.filename();
+        if preloads.is_empty() {
+            self.enter_script(&main_name, true);
+            self.execute_script()?;
+            self.run_exit_callbacks();
+            self.top_level_len = self.stack.len();
+            return Ok(());
+        }
+
+        // The preloaded scripts are executed one after another with the main script
+        // appended last so that the functions declared by them stay callable.
+        let main = mem::take(&mut self.chunk);
+        self.chunk.constants = main.constants.clone();
+
+        for (name, chunk) in preloads {
+            self.append_chunk(name.clone(), chunk, true);
+            self.enter_script(&name, false);
+            self.execute_script()?;
+            self.export_declarations();
+        }
+
+        self.append_chunk(TinyString::new(main_name.as_bytes()), main, false);
+        self.enter_script(&main_name, true);
+        self.execute_script()?;
+        self.run_exit_callbacks();
+        self.top_level_len = self.stack.len();
+
+        Ok(())
+    }
+
+    // The top level declarations of a preloaded script are locals of the script
+    // so they are turned into globals to be visible for the following scripts.
+    // The name of the main script given by the cli or the path of the vm otherwise.
+    pub(crate) fn filename(&self) -> String {
+        match self.flags.get(&TinyString::new(b"filename")) {
+            Some(filename) => filename.to_string(),
+            None => self.path.to_string_lossy().into_owned()
+        }
+    }
+
+    // Replaces the `script` global with the one of the script which is about to
+    // be executed. The globals are keyed by the pool of the appended chunk, so this
+    // has to be called after the chunk has been appended.
+    fn enter_script(&mut self, filename: &str, is_main: bool) {
+        let script = core::builtin::init_script(self, filename, is_main);
+        self.add_global("script", script);
+    }
+
+    fn export_declarations(&mut self) {
+        for upvalue in self.open_upvalues.drain(..) {
+            if let UpvalueState::Open(index) = upvalue.state() {
+                upvalue.close(self.stack[index]);
+            }
+        }
+
+        for &(name, slot) in &self.chunk.declarations {
+            let value = self.stack.get(slot as usize).copied().unwrap_or_default();
+            self.globals.insert(name, (value, false));
+        }
+    }
+
+    // The globals are keyed by the ids of their names in the constant pool. The
+    // names added to the pool at runtime are not known by the compiled chunk so
+    // the globals are moved to the ids of the names in the pool of the chunk.
+    fn append_chunk(&mut self, name: TinyString, chunk: Chunk, preloaded: bool) {
+        let constants = mem::take(&mut self.chunk.constants);
+        self.chunk.append(name, chunk, preloaded);
+
+        for (id, global) in mem::take(&mut self.globals) {
+            let id = self.chunk.constants.add_string(constants.get_string(id));
+            self.globals.insert(id, global);
+        }
     }
 
     pub fn init_permissions(&mut self) {
@@ -220,30 +897,308 @@ impl Vm {
             write: self.has_permission("write"),
             memory: self.has_permission("memory"),
             child_process: self.has_permission("child-process"),
+            crypto: self.has_permission("crypto"),
+            signal: self.has_permission("signal"),
             unsafe_libs: self.flags.contains_key(&TinyString::new(b"unsafe"))
         };
     }
 
+    // Jumps to the expect block of the innermost try block around the error. The
+    // frames are searched from the current one outwards through their return ips
+    // and the frames above the one which catches the error are dropped. A native
+    // frame ends the search as the error has to go through the native function.
     pub fn handle_error(&mut self, error: RuntimeError) -> RuntimeResult<()> {
-        handle_error!(error, self, self)
+        if !error.catchable {
+            return Err(error);
+        }
+
+        let mut ip = self.ip;
+        let mut native_depth = None;
+        for depth in (0..self.call_stack.len()).rev() {
+            let return_ip = self.call_stack[depth].ip;
+            if depth != 0 && return_ip == 0 {
+                native_depth = Some(depth);
+                break;
+            }
+
+            if let Some((_, jump_at, slot)) = self.chunk.try_block_at(ip) {
+                self.unwind_frames(depth + 1);
+
+                // The error slot is the first one of the try block, whose slots are
+                // given to the next locals, so the captures of them are closed.
+                let stack_start = self.call_stack[depth].stack_start;
+                self.close_upvalues(stack_start + slot as usize);
+
+                let value = error.to_value(self);
+                self.ip = jump_at;
+                return self.add_local(slot as usize, value);
+            }
+
+            ip = return_ip;
+        }
+
+        // The error leaves the frames up to the native it goes through or ends the
+        // script, in which case the top level deferred expressions run as well.
+        match native_depth {
+            Some(depth) => self.unwind_frames(depth + 1),
+            None => {
+                self.unwind_frames(1);
+                let deferred = mem::take(&mut self.call_stack[0].deferred);
+                self.report_deferred(deferred);
+            }
+        }
+
+        Err(error)
+    }
+
+    // Drops the frames above `len` which an error is thrown through. The deferred
+    // expressions of each frame run as it is dropped.
+    fn unwind_frames(&mut self, len: usize) {
+        while self.call_stack.len() > len {
+            let frame = self.call_stack.pop().unwrap();
+            self.close_upvalues(frame.stack_start);
+            self.stack.truncate(frame.stack_start);
+            self.report_deferred(frame.deferred);
+        }
+    }
+
+    // Runs the deferred expressions of a frame which has exited, the last one first.
+    // They are put above a frame like the one of a native so that their errors are
+    // not caught by the try blocks around them. All of them run even if one of them
+    // fails and the first error is returned.
+    fn run_deferred(&mut self, deferred: Vec<Value>) -> RuntimeResult<()> {
+        // The functions which are still to run stay on the stack so that they are
+        // not collected meanwhile.
+        let stack_len = self.stack.len();
+        self.stack.extend_from_slice(&deferred);
+
+        let mut result = Ok(());
+        for function in deferred.into_iter().rev() {
+            self.call_stack.push(CallFrame { name: TinyString::new(b"defer"), ..Default::default() });
+            let returned = self.call_function_with_returned_value(function, 0);
+            self.call_stack.pop();
+
+            if let Err(error) = returned {
+                match result {
+                    Ok(()) => result = Err(error),
+                    Err(_) => { self.report_error("", &error); }
+                }
+            }
+        }
+
+        self.stack.truncate(stack_len);
+        result
+    }
+
+    // The errors of the deferred expressions which run while another error is being
+    // thrown are only reported so that they do not replace it.
+    fn report_deferred(&mut self, deferred: Vec<Value>) {
+        if let Err(error) = self.run_deferred(deferred) {
+            self.report_error("", &error);
+        }
+    }
+
+    // Runs a chunk of the bytecode such as a callback called by a native function
+    // inside of the current instruction. If it fails the ip, the frames and the
+    // stack are given back to the caller as they were, dropping everything from
+    // `stack_start`, so that the error is handled at the position of the caller.
+    pub(crate) fn with_nested_execution<T>(
+        &mut self,
+        stack_start: usize,
+        execute: impl FnOnce(&mut Self) -> RuntimeResult<T>
+    ) -> RuntimeResult<T> {
+        let ip = self.ip;
+        let frames_len = self.call_stack.len();
+        let result = execute(self);
+
+        if result.is_err() {
+            self.ip = ip;
+            self.call_stack.truncate(frames_len);
+            self.close_upvalues(stack_start);
+            self.stack.truncate(stack_start);
+        }
+
+        result
+    }
+
+    // Closes the open upvalues which point to the slots from `stack_start` as
+    // these slots are about to be dropped.
+    // The upvalues are opened in the order of the captures rather than the one of
+    // the slots, so all of them are looked at.
+    fn close_upvalues(&mut self, stack_start: usize) {
+        let stack = &self.stack;
+        self.open_upvalues.retain(|upvalue| match upvalue.state() {
+            UpvalueState::Open(index) if stack_start <= index => {
+                upvalue.close(stack.get(index).copied().unwrap_or_default());
+                false
+            },
+            UpvalueState::Open(_) => true,
+            UpvalueState::Closed(_) => false
+        });
     }
 
     pub fn execute(&mut self) -> RuntimeResult<()> {
-        self.stack.resize_with(self.chunk.bytes[self.ip] as usize, Default::default);
+        let max_slots = match self.chunk.bytes.get(self.ip) {
+            Some(max_slots) => *max_slots,
+            None => return Err(self.truncated("the slots of the script"))
+        };
+
+        self.stack.resize_with(max_slots as usize, Default::default);
         self.ip += 1;
 
         while self.ip < self.chunk.bytes.len() {
-            match self.execute_byte(self.chunk.bytes[self.ip]) {
+            if signal::interrupted() {
+                self.interrupt();
+            }
+
+            if self.cancellation.is_cancelled() {
+                return Err(RuntimeError::new_cancelled(self));
+            }
+
+            if let Some(max_heap) = self.heap_exceeded() {
+                return Err(RuntimeError::new_exhausted(self, max_heap));
+            }
+
+            let byte = self.chunk.bytes[self.ip];
+            match self.execute_byte(byte) {
                 Ok(_) => (),
                 Err(error) => self.handle_error(error)?
             }
+
+            if self.paranoid {
+                self.check_integrity(byte)?;
+            }
         }
 
-        Ok(())
+        // The expressions deferred at the top level run at the end of the script.
+        let deferred = mem::take(&mut self.call_stack[0].deferred);
+        self.run_deferred(deferred)
+    }
+
+    // The limit of the heap once the objects retain more than it allows. Garbage
+    // which has not been collected yet is counted as well.
+    fn heap_exceeded(&self) -> Option<usize> {
+        self.max_heap.filter(|&max_heap| self.approx_bytes > max_heap)
+    }
+
+    // The error of an instruction whose operands are cut off by the end of the chunk.
+    pub(crate) fn truncated(&self, decoding: &str) -> RuntimeError {
+        RuntimeError::new_uncatchable(self, format!("[BytecodeReader]: Corrupted Bytecode. Unexpected end of the chunk at offset {} while decoding {}.", self.ip, decoding))
+    }
+
+    // Runs the callback registered by `window.signal.onInterrupt` and exits the
+    // process. The callback can only execute a limited number of instructions and
+    // gets cut off if the signal is received once more.
+    pub fn interrupt(&mut self) -> ! {
+        const INSTRUCTION_BUDGET: usize = 1_000_000;
+        signal::reset();
+
+        if let Some(callback) = self.interrupt_callback.take() {
+            self.call_bounded(callback, &[], INSTRUCTION_BUDGET);
+        }
+
+        // The destructor of the vm is skipped by `process::exit`.
+        let _ = self.out.flush();
+        let _ = self.err.flush();
+        process::exit(130)
+    }
+
+    // Calls a callback which may only execute `budget` instructions, after which it
+    // is cut off like when an interrupt is received. Its errors are only reported.
+    fn call_bounded(&mut self, callback: Value, args: &[Value], budget: usize) {
+        let (call_depth, stack_len) = (self.call_stack.len(), self.stack.len());
+
+        match callback {
+            Value::Function(ptr) => {
+                let Function { name, max_slots, start, upvalues, .. } = ptr.unwrap();
+                let stack_start = self.stack.len();
+                let return_ip = self.ip;
+
+                self.stack.extend_from_slice(args);
+                self.call_stack.push(CallFrame { ip: return_ip, stack_start, name, upvalues: upvalues.to_vec(), max_slots, deferred: Vec::new() });
+                self.stack.resize(stack_start + (max_slots as usize).max(args.len()), Value::Null);
+                self.ip = start;
+
+                let mut budget = budget;
+                while self.ip != return_ip && self.ip < self.chunk.bytes.len() && budget != 0 && !signal::interrupted() {
+                    if let Err(error) = self.execute_byte(self.chunk.bytes[self.ip]) {
+                        self.report_error("", &error);
+                        break;
+                    }
+
+                    budget -= 1;
+                }
+
+                self.ip = return_ip;
+            },
+            callback => {
+                self.stack.extend_from_slice(args);
+                if let Err(error) = self.call_function_with_returned_value(callback, args.len() as u8) {
+                    self.report_error("", &error);
+                }
+            }
+        }
+
+        // The frames of a callback which has been cut off are dropped.
+        self.call_stack.truncate(call_depth);
+        self.stack.truncate(stack_len);
+    }
+
+    // Runs a script and gives an uncaught panic to the hook of `window.atPanic`
+    // before its trace is printed. The hook runs once even if it panics itself.
+    fn execute_script(&mut self) -> RuntimeResult<()> {
+        const PANIC_HOOK_BUDGET: usize = 100_000;
+
+        let error = match self.execute() {
+            Err(error) if error.kind() == RuntimeErrorKind::Panic => error,
+            result => return result
+        };
+
+        if let Some(hook) = self.panic_hook.take() {
+            let value = error.clone().to_value(self);
+            self.call_bounded(hook, &[value], PANIC_HOOK_BUDGET);
+        }
+
+        let _ = self.out.flush();
+        self.report_error("panic: ", &error);
+        let _ = self.err.flush();
+        Err(error)
+    }
+
+    // Writes an error which is only reported to the stderr, in red if the stderr
+    // is a terminal.
+    fn report_error(&mut self, prefix: &str, error: &RuntimeError) {
+        let text = tty::paint(&format!("{}{}", prefix, error), tty::Color::Red, self.err.is_terminal());
+        let _ = writeln!(self.err, "{}", text);
+    }
+
+    // Runs the callbacks registered by `window.onExit` once, the last one first.
+    // Their errors are only reported so that all of them run.
+    pub(crate) fn run_exit_callbacks(&mut self) {
+        for callback in mem::take(&mut self.exit_callbacks).into_iter().rev() {
+            if let Err(error) = self.call_function_with_returned_value(callback, 0) {
+                self.report_error("", &error);
+            }
+        }
     }
 
     pub fn execute_byte(&mut self, byte: u8) -> RuntimeResult<()> {
         use opcode::*;
+        // The garbage is only collected between the instructions, where every value
+        // in use is reachable from the stack or the globals. The natives which call
+        // back into the vm root the values they build on the stack.
+        #[cfg(feature = "stress_gc")]
+        self.collect_garbage();
+
+        #[cfg(not(feature = "stress_gc"))]
+        if self.bytes_allocated >= self.next_gc {
+            self.collect_garbage();
+        }
+
+        if self.profiler.is_some() {
+            self.sample_profile();
+        }
+
         self.ip += 1;
 
         match byte {
@@ -251,7 +1206,7 @@ impl Vm {
             FALSE => self.stack.push(Value::Bool(false)),
             NULL => self.stack.push(Value::Null),
             STRING => {
-                let bytes = match self.chunk.constants.strings.get(read_u8!(self) as usize) {
+                let bytes = match self.chunk.constants.strings.get(read_u8!(self, byte) as usize) {
                     Some(string) => string.as_bytes(),
                     None => &[]
                 };
@@ -261,7 +1216,7 @@ impl Vm {
                 self.stack.push(Value::String(constant));
             },
             STRING_LONG => {
-                let bytes = match self.chunk.constants.strings.get(read_u32!(self) as usize) {
+                let bytes = match self.chunk.constants.strings.get(read_u32!(self, byte) as usize) {
                     Some(string) => string.as_bytes(),
                     None => &[]
                 };
@@ -271,19 +1226,19 @@ impl Vm {
                 self.stack.push(Value::String(constant));
             },
             INT => {
-                let constant = self.chunk.constants.ints[read_u8!(self) as usize];
+                let constant = read_constant!(self, ints, read_u8!(self, byte), byte);
                 self.stack.push(Value::Int(constant))
             },
             INT_LONG => {
-                let constant = self.chunk.constants.ints[read_u32!(self) as usize];
+                let constant = read_constant!(self, ints, read_u32!(self, byte), byte);
                 self.stack.push(Value::Int(constant))
             },
             FLOAT => {
-                let constant = self.chunk.constants.floats[read_u8!(self) as usize];
+                let constant = read_constant!(self, floats, read_u8!(self, byte), byte);
                 self.stack.push(Value::Float(constant))
             },
             FLOAT_LONG => {
-                let constant = self.chunk.constants.floats[read_u32!(self) as usize];
+                let constant = read_constant!(self, floats, read_u32!(self, byte), byte);
                 self.stack.push(Value::Float(constant))
             },
             POP => {
@@ -311,19 +1266,28 @@ impl Vm {
                 self.stack.push(lhs % rhs);
             },
             SET_LOCAL => {
-                let slot = read_u8!(self);
+                let slot = read_u8!(self, byte);
                 let value = self.stack.pop().unwrap_or_default();
-                self.add_local(slot as usize, value);
+                self.add_local(slot as usize, value)?;
             },
             SET_GLOBAL => {
-                let id = read_auto!(self);
+                let id = read_auto!(self, byte);
+                if let Some((_, true)) = self.globals.get(&id) {
+                    return Err(RuntimeError::new(self, format!("Cannot redeclare built-in '{}'.", self.chunk.constants.get_string(id))));
+                }
+
                 self.globals.insert(id, (self.stack.pop().unwrap_or_default(), false));
             },
             SET_UPVALUE => {
-                let upval = read_u8!(self);
-                let value = self.stack.pop().unwrap();
-                let frame = self.call_stack.last_mut().unwrap();
-                frame.upvalues[upval as usize].close(value);
+                let upval = read_u8!(self, byte);
+                let value = pop!(self);
+                let upvalue = self.upvalue_at(upval)?;
+
+                // An open upvalue still lives in the slot of the local it captures.
+                match upvalue.state() {
+                    UpvalueState::Open(index) => self.stack[index] = value,
+                    UpvalueState::Closed(_) => upvalue.close(value)
+                }
             },
             SET_ATTR => {
                 return match (self.stack.pop(), self.stack.pop(), self.stack.pop()) {
@@ -331,14 +1295,37 @@ impl Vm {
                     _ => return Err(RuntimeError::new_uncatchable(self, "[VM]: Stack Manipulation Failed. Expected stack length with minimum size as 3."))
                 }
             },
+            ASSIGN_ATTR => {
+                let op = read_u8!(self, byte);
+                return match (self.stack.pop(), self.stack.pop(), self.stack.pop()) {
+                    (Some(value), Some(attr), Some(target)) => {
+                        let value = match op {
+                            ASSIGN_OP => value,
+                            op => {
+                                let old = self.resolve_attr(target, attr)?;
+                                self.apply_compound_op(old, op, value)?
+                            }
+                        };
+
+                        self.set_attr(target, attr, value, false)
+                    },
+                    _ => return Err(RuntimeError::new_uncatchable(self, "[VM]: Stack Manipulation Failed. Expected stack length with minimum size as 3."))
+                }
+            },
+            COMPOUND_ASSIGN => {
+                let op = read_u8!(self, byte);
+                let (old, value) = pop_two!(self);
+                let value = self.apply_compound_op(old, op, value)?;
+                self.stack.push(value);
+            },
             GET_LOCAL => {
-                let frame = self.call_stack.last().unwrap();
-                let local = self.stack[frame.stack_start + read_u8!(self) as usize];
-                self.stack.push(local);
+                let slot = read_u8!(self, byte);
+                let index = self.local_index(slot as usize)?;
+                self.stack.push(self.stack[index]);
             },
             GET_UPVALUE => {
-                let slot = read_u8!(self);
-                let value = match self.call_stack.last().unwrap().upvalues[slot as usize].state() {
+                let slot = read_u8!(self, byte);
+                let value = match self.upvalue_at(slot)?.state() {
                     UpvalueState::Closed(value) => value,
                     UpvalueState::Open(index) => self.stack[index]
                 };
@@ -346,47 +1333,80 @@ impl Vm {
                 self.stack.push(value)
             },
             GET_GLOBAL => {
-                match self.globals.get(&read_auto!(self)) {
+                let constant_id = read_auto!(self, byte);
+                match self.globals.get(&constant_id) {
                     Some((global, _)) => self.stack.push(*global),
-                    None => self.stack.push(Value::Null)
+                    None => match self.uninstalled.get(&constant_id) {
+                        Some(&(global, group)) => return Err(RuntimeError::new(self, format!(
+                            "ReferenceError: The global \"{}\" is not installed. It is installed with --global {}.", global, group
+                        ))),
+                        None => self.stack.push(Value::Null)
+                    }
                 }
             },
             GET_ATTR => {
                 let (target, attr) = pop_two!(self);
-                let result = self.resolve_attr(target, attr);
+                let result = self.resolve_attr(target, attr)?;
+                self.stack.push(result);
+            },
+            SLICE => {
+                let (end, step) = pop_two!(self);
+                let (target, start) = pop_two!(self);
+                let result = self.slice(target, start, end, step)?;
                 self.stack.push(result);
             },
             CLOSE_UPVALUE => {
-                let closed_index = self.stack.len() - 1;
-                let closed = self.stack.pop().unwrap();
-                let mut retain = self.open_upvalues.len();
-
-                for upvalue in self.open_upvalues.iter().rev() {
-                    match upvalue.state() {
-                        UpvalueState::Open(index) if index == closed_index => upvalue.close(closed),
-                        UpvalueState::Closed(_) => (),
-                        _ => break
-                    }
-
-                    retain -= 1;
-                }
-
-                self.open_upvalues.truncate(retain);
+                // The captured locals of a block which has been left, from the given
+                // slot onwards. Their upvalues keep the values while the slots are
+                // reused by the next iteration of a loop.
+                let slot = read_u8!(self, byte) as usize;
+                let stack_start = self.call_stack.last().unwrap().stack_start;
+                self.close_upvalues(stack_start + slot);
             },
             ITER => {
-                let value = self.stack.pop().unwrap();
+                let value = pop!(self);
+                let value = self.iterable_of(value)?;
                 let ptr = self.allocate_value_ptr(value.into_iter());
                 self.stack.push(Value::Iterator(ptr))
             },
             ITER_NEXT => {
-                let slot = read_u8!(self);
-                let jump_index = read_u16!(self);
+                let slot = read_u8!(self, byte);
+                let jump_index = read_u16!(self, byte);
 
-                match self.stack.last().unwrap().iter_next() {
-                    Some(value) => self.stack[self.call_stack.last().unwrap().stack_start + slot as usize] = value,
+                let next = match self.stack.last().copied().unwrap_or_default() {
+                    Value::Iterator(ptr) => self.iter_next(ptr)?,
+                    value => value.iter_next()
+                };
+
+                match next {
+                    Some(value) => self.add_local(slot as usize, value)?,
                     None => self.ip += jump_index as usize
                 }
             },
+            ITER_ENTRIES => {
+                let value = match pop!(self) {
+                    Value::Iterator(ptr) if ptr.unwrap_ref().pipeline().is_some() => self.collect_iter(ptr)?,
+                    value => value
+                };
+
+                let entries = self.entries_of(value);
+                let ptr = self.allocate_value_ptr(ValueIter::new(&entries));
+                self.stack.push(Value::Iterator(ptr))
+            },
+            ITER_NEXT_ENTRY => {
+                let key_slot = read_u8!(self, byte);
+                let value_slot = read_u8!(self, byte);
+                let jump_index = read_u16!(self, byte);
+                let iterator = self.stack.last().copied().unwrap_or_default();
+
+                match (iterator.iter_next(), iterator.iter_next()) {
+                    (Some(key), Some(value)) => {
+                        self.add_local(key_slot as usize, key)?;
+                        self.add_local(value_slot as usize, value)?;
+                    },
+                    _ => self.ip += jump_index as usize
+                }
+            },
             EQ => {
                 let (lhs, rhs) = pop_two!(self);
                 self.stack.push(Value::Bool(lhs == rhs));
@@ -412,41 +1432,50 @@ impl Vm {
                 self.stack.push(Value::Bool(lhs <= rhs));
             },
             JUMP => {
-                self.ip += read_u16!(self) as usize
+                self.ip += read_u16!(self, byte) as usize
             },
             JUMP_BACK => {
-                self.ip -= read_u16!(self) as usize - 2;
+                let offset = read_u16!(self, byte) as usize;
+                self.ip = match (self.ip + 2).checked_sub(offset) {
+                    Some(ip) => ip,
+                    None => return Err(RuntimeError::new_uncatchable(self, format!("[BytecodeReader]: Corrupted Bytecode. Cannot jump back by {} from the offset {}.", offset, self.ip)))
+                };
             },
             JUMP_IF => {
+                // The offset is read either way so that a truncated one is reported.
+                let offset = read_u16!(self, byte) as usize;
                 if self.stack.last().unwrap_or_default().to_bool() {
-                    self.ip += read_u16!(self) as usize;
-                } else {
-                    self.ip += 2;
+                    self.ip += offset;
+                }
+            },
+            JUMP_IF_NOT_NULL => {
+                let offset = read_u16!(self, byte) as usize;
+                if !matches!(self.stack.last(), None | Some(Value::Null)) {
+                    self.ip += offset;
                 }
             },
             JUMP_NOT_IF => {
+                let offset = read_u16!(self, byte) as usize;
                 if !self.stack.pop().unwrap_or_default().to_bool() {
-                    self.ip += read_u16!(self) as usize;
-                } else {
-                    self.ip += 2;
+                    self.ip += offset;
                 }
             },
             CALL => {
-                let args_len = read_u8!(self);
-                let target = self.stack.pop().unwrap();
+                let args_len = read_u8!(self, byte);
+                let target = pop!(self);
+                self.expect_args(args_len)?;
                 return self.call_function(target, args_len);
             },
             CALL_CHILD => {
-                let args_len = read_u8!(self);
+                let args_len = read_u8!(self, byte);
                 let (parent, attr) = pop_two!(self);
+                self.expect_args(args_len)?;
                 return self.call_inst_function(parent, attr, args_len);
             },
             ARRAY => {
-                let array_len = read_auto!(self) as usize;
-                let len = self.stack.len();
-                let offset_ip = len - array_len;
-
+                let array_len = read_auto!(self, byte) as usize;
                 if self.stack.len() >= array_len {
+                    let offset_ip = self.stack.len() - array_len;
                     let array = self.stack[offset_ip..].to_vec();
                     self.stack.truncate(offset_ip);
 
@@ -457,17 +1486,15 @@ impl Vm {
                 }
             },
             DICT => {
-                let actual_len = read_auto!(self) as usize;
+                let actual_len = read_auto!(self, byte) as usize;
                 let entries_len = actual_len * 2;
-                let len = self.stack.len();
-                let offset_ip = len - entries_len;
-
                 if self.stack.len() >= entries_len {
-                    let mut entries = HashMap::new();
+                    let offset_ip = self.stack.len() - entries_len;
+                    let mut entries = Map::with_capacity(actual_len);
 
-                    for _ in 0..actual_len {
-                        let value = self.stack.pop().unwrap();
-                        entries.insert(self.stack.pop().unwrap(), (value, false));
+                    // The first of the entries with the same key is kept.
+                    for pair in self.stack[offset_ip..].chunks_exact(2) {
+                        entries.entry(pair[0]).or_insert((pair[1], false));
                     }
 
                     self.stack.truncate(offset_ip);
@@ -479,14 +1506,14 @@ impl Vm {
             },
             FUNC => {
                 let start = self.ip + 2;
-                self.ip += read_u16!(self) as usize;
+                self.ip += read_u16!(self, byte) as usize;
                 
                 let (max_slots, upvalue_len, is_async) = match self.chunk.bytes.get(self.ip..self.ip + 3) {
                     Some(bytes) => {
                         self.ip += 3;
                         (bytes[0], bytes[1], bytes[2] != 0)
                     },
-                    None => return Err(RuntimeError::new_uncatchable(self, "[BytecodeReader]: Corrupted Bytecode. Expected 2 more bytes to get [MAX_SLOTS, UPVALUES_COUNT, IS_ASYNC[bool]]."))
+                    None => return Err(self.truncated("FUNC"))
                 };
 
                 let mut upvalues = Vec::with_capacity(upvalue_len as usize);
@@ -495,22 +1522,34 @@ impl Vm {
                 for _ in 0..upvalue_len {
                     match self.chunk.bytes.get(self.ip..self.ip + 2) {
                         Some(bytes) => {
+                            // The closures capturing the same local share its upvalue.
                             let upvalue = if bytes[0] != 0 {
-                                let upvalue = Upvalue::new_open(current_frame.stack_start + bytes[1] as usize);
-                                self.open_upvalues.push(upvalue);
-                                upvalue
+                                let index = current_frame.stack_start + bytes[1] as usize;
+                                let open = self.open_upvalues.iter().rev().find(|upvalue| matches!(upvalue.state(), UpvalueState::Open(open) if open == index));
+
+                                match open {
+                                    Some(&upvalue) => upvalue,
+                                    None => {
+                                        let upvalue = Upvalue::new_open(index);
+                                        self.open_upvalues.push(upvalue);
+                                        upvalue
+                                    }
+                                }
                             } else {
-                                current_frame.upvalues[bytes[1] as usize]
+                                match current_frame.upvalues.get(bytes[1] as usize) {
+                                    Some(&upvalue) => upvalue,
+                                    None => return Err(self.unknown_upvalue(bytes[1]))
+                                }
                             };
 
                             upvalues.push(upvalue);
                             self.ip += 2;
                         },
-                        None => return Err(RuntimeError::new_uncatchable(self, "[BytecodeReader]: Corrupted Bytecode. Expected 2 more bytes to get [IS_UPVALUE, UPVALUE_SLOT]."))
+                        None => return Err(self.truncated("FUNC"))
                     };
                 }
 
-                let name = read_auto!(self);
+                let name = read_auto!(self, byte);
                 let ptr = self.allocate_value_ptr(
                     Function {
                         name: self.chunk.constants.get_string(name),
@@ -523,33 +1562,82 @@ impl Vm {
 
                 self.stack.push(Value::Function(ptr))
             },
-            RETURN => {
-                let frame = self.call_stack.pop().unwrap();
-                let mut retain = self.open_upvalues.len();
-
-                for upvalue in self.open_upvalues.iter().rev() {
-                    match upvalue.state() {
-                        UpvalueState::Open(index) => {
-                            if frame.stack_start <= index {
-                                upvalue.close(self.stack[index])
-                            } else { break }
-                        },
-                        UpvalueState::Closed(_) => ()
-                    }
+            CHECK_TYPE => {
+                let slot = read_u8!(self, byte);
+                let type_ = TypeAnnotation::from_u8(read_u8!(self, byte));
+                let name = read_auto!(self, byte);
+                let value_type = self.stack[self.local_index(slot as usize)?].get_type();
+                let frame = self.call_stack.last().unwrap();
+
+                if value_type.to_bytes() != type_.name().as_bytes() {
+                    // The error is reported at the call instead of the declaration
+                    // of the function as the caller is the one who is wrong.
+                    let ip = mem::replace(&mut self.ip, frame.ip);
+                    let error = RuntimeError::new(
+                        self,
+                        format!("TypeError: argument '{}' expected {}, got {}", self.chunk.constants.get_string(name), type_.name(), value_type)
+                    );
+
+                    self.ip = ip;
+                    return Err(error);
+                }
+            },
+            SET_RESULT => {
+                self.result = self.stack.pop().unwrap_or_default();
+            },
+            CHECK_RETURN => {
+                let type_ = TypeAnnotation::from_u8(read_u8!(self, byte));
+                let value_type = self.stack.last().unwrap().get_type();
 
-                    retain -= 1;
+                if value_type.to_bytes() != type_.name().as_bytes() {
+                    return Err(RuntimeError::new(
+                        self,
+                        format!("TypeError: return value of '{}' expected {}, got {}", self.call_stack.last().unwrap().name, type_.name(), value_type)
+                    ));
+                }
+            },
+            RETURN => {
+                // A return at the top level ends the script with the value as its result,
+                // which the compiler only allows for the code given to `--eval`.
+                if self.call_stack.len() == 1 {
+                    self.result = self.stack.pop().unwrap_or_default();
+                    self.ip = self.chunk.bytes.len();
+                    return Ok(());
                 }
 
-                self.stack.drain(frame.stack_start..frame.stack_start + frame.max_slots as usize);
-                self.open_upvalues.truncate(retain);
+                let frame = self.call_stack.pop().unwrap();
+                self.close_upvalues(frame.stack_start);
+
+                // The temporaries such as the iterators of the loops being returned from
+                // are dropped along with the slots.
+                let value = self.stack.pop().unwrap_or_default();
+                self.stack.truncate(frame.stack_start);
+                self.stack.push(value);
                 self.ip = frame.ip;
+
+                // The deferred expressions run once the frame is gone so that their
+                // errors are thrown at the call.
+                self.run_deferred(frame.deferred)?;
+            },
+            DEFER => {
+                let function = self.stack.pop().unwrap_or_default();
+                self.call_stack.last_mut().unwrap().deferred.push(function);
             },
             AND => {
                 let (lhs, rhs) = pop_two!(self); 
                 self.stack.push(Value::Bool(lhs.to_bool() && rhs.to_bool()));
             },
+            NEG => {
+                let value = match self.stack.pop().unwrap_or_default() {
+                    Value::Int(int) => int.checked_neg().map_or(Value::Float(-(int as f64)), Value::Int),
+                    Value::Float(float) => Value::Float(-float),
+                    value => return Err(RuntimeError::new(self, format!("TypeError: Cannot negate a value of type {}.", value.get_type())))
+                };
+
+                self.stack.push(value);
+            },
             NOT => {
-                let boolean = !self.stack.last().unwrap_or_default().to_bool();
+                let boolean = !self.stack.pop().unwrap_or_default().to_bool();
                 self.stack.push(Value::Bool(boolean));
             },
             POW => {
@@ -584,20 +1672,27 @@ impl Vm {
 
     pub fn call_function(&mut self, target: Value, args_len: u8) -> RuntimeResult<()> {
         match target {
-            Value::NativeFn(ptr) => unsafe {
+            Value::NativeFn(ptr) if ptr.unwrap_ref().bound.is_some() => {
+                let (receiver, attr) = ptr.unwrap_ref().bound.unwrap();
+                self.call_inst_function(receiver, attr, args_len)
+            },
+            Value::NativeFn(ptr) => {
                 let nf = ptr.unwrap_ref();
                 let stack_offset_index = self.stack.len() - args_len as usize;
-                let args = ptr::slice_from_raw_parts(self.stack.as_mut_ptr().add(stack_offset_index), args_len as usize);
+                let args = self.native_args(stack_offset_index);
 
                 self.call_stack.push(CallFrame { name: nf.name.clone(), ..Default::default() });
-                match (nf.func)(self, &*args) {
+                match (nf.func)(self, &args) {
                     Ok(value) => {
                         self.call_stack.pop();
-                        self.stack.set_len(stack_offset_index);
-                        ptr::drop_in_place(args as *mut [Value]);
+                        self.stack.truncate(stack_offset_index);
                         self.stack.push(value);
                     },
-                    Err(error) => return Err(error)
+                    Err(error) => {
+                        self.call_stack.pop();
+                        self.stack.truncate(stack_offset_index);
+                        return Err(error);
+                    }
                 }
 
                 Ok(())
@@ -616,7 +1711,8 @@ impl Vm {
                     stack_start, 
                     name: name.clone(), 
                     upvalues: upvalues.to_vec(), 
-                    max_slots: *max_slots 
+                    max_slots: *max_slots,
+                    deferred: Vec::new()
                 });
 
                 self.stack.resize(stack_start + *max_slots as usize, Value::Null);
@@ -626,6 +1722,11 @@ impl Vm {
             },
             Value::Dict(ptr) => {
                 let map = ptr.unwrap_ref();
+
+                if let Some(converter) = self.converter_of(ptr) {
+                    return self.call_function(converter, args_len);
+                }
+
                 let self_ = match map.get(&self.constants.prototype) {
                     Some(&(Value::Dict(ptr), _)) => {
                         let instance = Instance {
@@ -662,21 +1763,37 @@ impl Vm {
 
     pub fn call_function_with_returned_value(&mut self, target: Value, args_len: u8) -> RuntimeResult<Value> {
         match target {
-            Value::NativeFn(ptr) => unsafe {
+            // The methods of the instances run in frames of their own, whereas the
+            // native methods leave their value on the stack at once.
+            Value::NativeFn(ptr) if ptr.unwrap_ref().bound.is_some() => match ptr.unwrap_ref().bound.unwrap() {
+                (receiver @ Value::Instance(instance), attr) => {
+                    let method = instance.unwrap_ref().methods.unwrap_ref().get(&attr).map_or(Value::Null, |&(method, _)| method);
+                    self.stack.insert(self.stack.len() - args_len as usize, receiver);
+                    self.call_function_with_returned_value(method, args_len + 1)
+                },
+                (receiver, attr) => {
+                    self.call_inst_function(receiver, attr, args_len)?;
+                    Ok(self.stack.pop().unwrap_or_default())
+                }
+            },
+            Value::NativeFn(ptr) => {
                 let nf = ptr.unwrap_ref();
                 let stack_offset_index = self.stack.len() - args_len as usize;
-                let args = ptr::slice_from_raw_parts(self.stack.as_ptr().add(stack_offset_index), args_len as usize);
+                let args = self.native_args(stack_offset_index);
 
                 self.call_stack.push(CallFrame { name: nf.name.clone(), ..Default::default() });
 
-                match (nf.func)(self, &*args) {
+                match (nf.func)(self, &args) {
                     Ok(value) => {
                         self.call_stack.pop();
-                        self.stack.set_len(stack_offset_index);
-                        ptr::drop_in_place(args as *mut [Value]);
+                        self.stack.truncate(stack_offset_index);
                         Ok(value)
                     },
-                    Err(error) => Err(error)
+                    Err(error) => {
+                        self.call_stack.pop();
+                        self.stack.truncate(stack_offset_index);
+                        Err(error)
+                    }
                 }
             },
             Value::Function(ptr) => {
@@ -691,26 +1808,55 @@ impl Vm {
                 let stack_start = self.stack.len() - args_len as usize;
                 let current_ip = self.ip;
 
-                self.call_stack.push(CallFrame { ip: self.ip, stack_start, name, upvalues: upvalues.to_vec(), max_slots });
-                self.stack.resize(stack_start + max_slots as usize, Value::Null);
-                self.ip = start;
+                self.with_nested_execution(stack_start, |vm| {
+                    vm.call_stack.push(CallFrame { ip: vm.ip, stack_start, name, upvalues: upvalues.to_vec(), max_slots, deferred: Vec::new() });
+                    vm.stack.resize(stack_start + max_slots as usize, Value::Null);
+                    vm.ip = start;
 
-                while self.ip < self.chunk.bytes.len() {
-                    // Ip would reach current ip if the RETURN opcode appeared
-                    if self.ip == current_ip {
-                        return Ok(self.stack.pop().unwrap_or(Value::Null));
-                    }
+                    while vm.ip < vm.chunk.bytes.len() {
+                        // Ip would reach current ip if the RETURN opcode appeared
+                        if vm.ip == current_ip {
+                            return Ok(vm.stack.pop().unwrap_or(Value::Null));
+                        }
+
+                        if signal::interrupted() {
+                            vm.interrupt();
+                        }
+
+                        if vm.cancellation.is_cancelled() {
+                            return Err(RuntimeError::new_cancelled(vm));
+                        }
 
-                    match self.execute_byte(self.chunk.bytes[self.ip]) {
-                        Ok(_) => (),
-                        Err(error) => self.handle_error(error)?
+                        if let Some(max_heap) = vm.heap_exceeded() {
+                            return Err(RuntimeError::new_exhausted(vm, max_heap));
+                        }
+
+                        let byte = vm.chunk.bytes[vm.ip];
+                        match vm.execute_byte(byte) {
+                            Ok(_) => (),
+                            Err(error) => vm.handle_error(error)?
+                        }
+
+                        if vm.paranoid {
+                            vm.check_integrity(byte)?;
+                        }
                     }
-                }
 
-                Ok(Value::Null)
+                    // The call returns to the end of the chunk once the script has
+                    // been executed.
+                    match vm.ip == current_ip {
+                        true => Ok(vm.stack.pop().unwrap_or(Value::Null)),
+                        false => Ok(Value::Null)
+                    }
+                })
             },
             Value::Dict(ptr) => {
                 let map = ptr.unwrap_ref();
+
+                if let Some(converter) = self.converter_of(ptr) {
+                    return self.call_function_with_returned_value(converter, args_len);
+                }
+
                 let self_ = match map.get(&self.constants.prototype) {
                     Some(&(Value::Dict(ptr), _)) => {
                         let instance = Instance {
@@ -742,6 +1888,7 @@ impl Vm {
     }
 
     fn call_inst_function(&mut self, self_: Value, attr: Value, args_len: u8) -> RuntimeResult<()> {
+        let attr = self.map_key(attr)?;
         macro_rules! inst_method {
             ($ptr:expr, $attr:ident) => {
                 match attr {
@@ -750,7 +1897,7 @@ impl Vm {
 
                         match self.$attr.get(name) {
                             Some(&function) => {
-                                match self.call_native_method(name.clone(), $ptr, args_len as usize, function) {
+                                match self.call_native_method(name.clone(), self_, $ptr, args_len as usize, function) {
                                     Ok(value) => {
                                         self.stack.push(value);
                                         Ok(())
@@ -767,10 +1914,11 @@ impl Vm {
         }
 
         match self_ {
+            // The properties of a dict shadow its methods.
             Value::Dict(ptr) => {
                 match ptr.unwrap_ref().get(&attr) {
                     Some((value, _)) => self.call_function(*value, args_len),
-                    None => Err(RuntimeError::new(self, "You cannot call a null"))
+                    None => inst_method!(ptr, dict_methods)
                 }
             },
             Value::Array(ptr) => {
@@ -788,7 +1936,7 @@ impl Vm {
 
                         match self.array_methods.get(string) {
                             Some(&function) => {
-                                match self.call_native_method(string.clone(), ptr, args_len as usize, function) {
+                                match self.call_native_method(string.clone(), self_, ptr, args_len as usize, function) {
                                     Ok(value) => {
                                         self.stack.push(value);
                                         Ok(())
@@ -815,6 +1963,7 @@ impl Vm {
             },
             Value::Iterator(ptr) => inst_method!(ptr, iterator_methods),
             Value::String(ptr) => inst_method!(ptr, string_methods),
+            Value::Bytes(ptr) => inst_method!(ptr, bytes_methods),
             _ => Err(RuntimeError::new(self, format!("Cannot call a {}.", self_.get_type())))
         }
     }
@@ -822,30 +1971,39 @@ impl Vm {
     fn call_native_method<T>(
         &mut self, 
         name: TinyString, 
+        receiver: Value,
         ptr: ValuePtr<T>, 
         args_len: usize, 
         method: MethodFn<T>
     ) -> RuntimeResult<Value> {
-        unsafe {
-            let stack_offset_index = self.stack.len() - args_len;
-            let args = ptr::slice_from_raw_parts(self.stack.as_ptr().add(stack_offset_index), args_len as usize);
-
-            self.call_stack.push(CallFrame { name, ..Default::default() });
-            let result = method(self, ptr.unwrap_mut(), ptr.0, &*args);
+        // The receiver stays on the stack above the arguments so that it is not
+        // collected while the method calls back into the vm.
+        let stack_offset_index = self.stack.len() - args_len;
+        let args = self.native_args(stack_offset_index);
+        self.stack.push(receiver);
+
+        self.call_stack.push(CallFrame { name, ..Default::default() });
+        let result = method(self, ptr.unwrap_mut(), ptr.0, &args);
+
+        self.stack.truncate(stack_offset_index);
+        self.call_stack.pop();
+        result
+    }
 
-            self.stack.set_len(stack_offset_index);
-            self.call_stack.pop();
-            ptr::drop_in_place(args as *mut [Value]);
-            result
-        }
+    // The arguments of a native are copied off the stack, which grows when the
+    // native calls back into the vm. The originals stay on the stack to be marked
+    // by the collector until the native returns.
+    fn native_args(&self, stack_offset_index: usize) -> Vec<Value> {
+        self.stack[stack_offset_index..].to_vec()
     }
 
-    fn resolve_attr(&mut self, target: Value, attr: Value) -> Value {
-        match target {
+    fn resolve_attr(&mut self, target: Value, attr: Value) -> RuntimeResult<Value> {
+        let attr = self.map_key(attr)?;
+        Ok(match target {
             Value::Dict(ptr) => {
                 match ptr.unwrap_ref().get(&attr) {
                     Some((value, _)) => *value,
-                    None => Value::Null
+                    None => self.bind_method(target, attr)
                 }
             },
             Value::Array(ptr) => {
@@ -856,28 +2014,150 @@ impl Vm {
                             None => Value::Null
                         }
                     },
-                    _ => Value::Null
+                    _ => self.bind_method(target, attr)
+                }
+            },
+            Value::Bytes(ptr) => {
+                match (attr, ptr.unwrap_ref()) {
+                    (Value::Int(int), bytes) if int >= 0 && (int as usize) < bytes.len() => Value::Int(bytes[int as usize] as isize),
+                    _ => self.bind_method(target, attr)
                 }
             },
             Value::Instance(ptr) => {
-                let instance = ptr.unwrap_ref();
-                
-                match instance.properties
-                        .get(&attr)
-                        .or_else(|| instance.methods.unwrap_ref().get(&attr)) {
+                match ptr.unwrap_ref().properties.get(&attr) {
                     Some((value, _)) => *value,
-                    None => Value::Null
+                    None => self.bind_method(target, attr)
                 }
             },
+            Value::String(_) | Value::Iterator(_) => self.bind_method(target, attr),
             _ => Value::Null
+        })
+    }
+
+    // A method read off a value instead of being called on it, such as
+    // `let upper = name.toUpperCase`, is bound to the value it was read from. The
+    // value is the same one and not a copy, so the method of an array sees the
+    // items pushed later and `this` of the method of an instance is the instance.
+    // The functions kept in the entries of a dict are values of their own and are
+    // never bound, and a name which is not a method gives null.
+    fn bind_method(&mut self, receiver: Value, attr: Value) -> Value {
+        let name = match attr {
+            Value::String(name) => name.unwrap_ref().clone(),
+            _ => return Value::Null
+        };
+
+        let is_method = match receiver {
+            Value::Dict(_) => self.dict_methods.contains_key(&name),
+            Value::Array(_) => self.array_methods.contains_key(&name),
+            Value::Bytes(_) => self.bytes_methods.contains_key(&name),
+            Value::String(_) => self.string_methods.contains_key(&name),
+            Value::Iterator(_) => self.iterator_methods.contains_key(&name),
+            Value::Instance(ptr) => ptr.unwrap_ref().methods.unwrap_ref().get(&attr).is_some_and(|(method, _)| method.is_function()),
+            _ => false
+        };
+
+        match is_method {
+            true => Value::NativeFn(self.allocate_value_ptr(NativeFunction { name, bound: Some((receiver, attr)), ..Default::default() })),
+            false => Value::Null
+        }
+    }
+
+    // The slices of arrays share the values of the elements, the slices of strings
+    // are indexed by their chars and the slices of bytes are copied.
+    fn slice(&mut self, target: Value, start: Value, end: Value, step: Value) -> RuntimeResult<Value> {
+        let mut bounds = [None; 3];
+        for (bound, value) in bounds.iter_mut().zip([start, end, step]) {
+            *bound = match value {
+                Value::Null => None,
+                Value::Int(int) => Some(int),
+                _ => return Err(RuntimeError::new(self, format!("Expected the bounds of a slice to be integers but found {}.", value.get_type())))
+            };
+        }
+
+        let [start, end, step] = bounds;
+        let step = match step {
+            Some(0) => return Err(RuntimeError::new(self, "Cannot slice with a step of 0.")),
+            step => step.unwrap_or(1)
+        };
+
+        Ok(match target {
+            Value::Array(ptr) => {
+                let array = ptr.unwrap_ref();
+                let items = slice_indices(array.len(), start, end, step).map(|index| array[index]).collect();
+                Value::Array(self.allocate_value_ptr(items))
+            },
+            Value::String(ptr) => {
+                let chars: Vec<char> = (ptr.unwrap_ref() as &str).chars().collect();
+                let string = slice_indices(chars.len(), start, end, step).map(|index| chars[index]).collect();
+                Value::String(self.allocate_string(string))
+            },
+            Value::Bytes(ptr) => {
+                let bytes = ptr.unwrap_ref();
+                let bytes = slice_indices(bytes.len(), start, end, step).map(|index| bytes[index]).collect();
+                Value::Bytes(self.allocate_value_ptr(bytes))
+            },
+            _ => return Err(RuntimeError::new(self, format!("Cannot slice {}. Expected an array, a string or bytes.", target.get_type())))
+        })
+    }
+
+    // The compound assignments mutate the containers in place so that the change
+    // is visible through the other references. Arrays are extended by arrays and
+    // get any other value pushed, dicts are merged with the right side winning and
+    // the rest falls back to the binary operators.
+    fn apply_compound_op(&mut self, old: Value, op: u8, value: Value) -> RuntimeResult<Value> {
+        match (op, old, value) {
+            (opcode::ASSIGN_OP_ADD, Value::Array(array), Value::Array(items)) => {
+                let items = items.unwrap_ref().clone();
+                array.unwrap_mut().extend(items);
+            },
+            (opcode::ASSIGN_OP_ADD, Value::Array(array), value) => array.unwrap_mut().push(value),
+            (opcode::ASSIGN_OP_ADD, Value::Dict(_), Value::Dict(entries)) => {
+                let entries: Vec<(Value, Value)> = entries.unwrap_ref().iter().map(|(key, (value, _))| (*key, *value)).collect();
+                for (key, value) in entries {
+                    self.set_attr(old, key, value, false)?;
+                }
+            },
+            (opcode::ASSIGN_OP_ADD, old, value) => return Ok(old.add(self, value)),
+            (opcode::ASSIGN_OP_SUB, old, value) => return Ok(old - value),
+            (opcode::ASSIGN_OP_REM, old, value) => return Ok(old % value),
+            (opcode::ASSIGN_OP_MERGE, Value::Dict(target), Value::Dict(entries)) => self.merge_dict(target, entries)?,
+            // The value has been evaluated even when the old one is kept.
+            (opcode::ASSIGN_OP_NULLISH, Value::Null, value) => return Ok(value),
+            (opcode::ASSIGN_OP_NULLISH, old, _) => return Ok(old),
+            (opcode::ASSIGN_OP_MERGE, old, value) => {
+                return Err(RuntimeError::new(self, format!("Expected objects on both sides of |= but found {} and {}.", old.get_type(), value.get_type())))
+            },
+            (_, _, value) => return Ok(value)
+        }
+
+        Ok(old)
+    }
+
+    // Used by `|=` and `update`. The readonly properties are checked before any
+    // entry is written so that a failed merge leaves the target untouched.
+    pub(crate) fn merge_dict(&mut self, target: ValuePtr<Map>, entries: ValuePtr<Map>) -> RuntimeResult<()> {
+        let entries: Vec<(Value, Value)> = entries.unwrap_ref().iter().map(|(key, (value, _))| (*key, *value)).collect();
+        let map = target.unwrap_mut();
+
+        if let Some((key, _)) = entries.iter().find(|(key, _)| matches!(map.get(key), Some((_, true)))) {
+            return Err(RuntimeError::new(self, format!("Cannot assign value to property {} which is a readonly property.", key)))
         }
+
+        for (key, value) in entries {
+            map.insert(key, (value, false));
+        }
+
+        Ok(())
     }
 
     pub(super) fn set_attr(&mut self, target: Value, attr: Value, value: Value, readonly: bool) -> RuntimeResult<()> {
+        let attr = self.map_key(attr)?;
         match target {
             Value::Dict(ptr) => {
-                if let Some((_, true)) = ptr.unwrap_mut().insert(attr, (value, readonly)) {
-                    return Err(RuntimeError::new(self, format!("Cannot assign value to property {} which is a readonly property.", attr)))
+                match ptr.unwrap_mut().insert(attr, (value, readonly)) {
+                    Some((_, true)) => return Err(RuntimeError::new(self, format!("Cannot assign value to property {} which is a readonly property.", attr))),
+                    Some(_) => (),
+                    None => self.approx_bytes += ENTRY_BYTES
                 }
             },
             Value::Array(ptr) => {
@@ -889,7 +2169,8 @@ impl Vm {
                         }
 
                         let index = int as usize;
-                        if index > array.len() {
+                        if index >= array.len() {
+                            self.approx_bytes += (index + 1 - array.len()) * VALUE_BYTES;
                             array.resize_with(index + 1, || Value::Null);
                         }
 
@@ -899,8 +2180,10 @@ impl Vm {
                 }
             },
             Value::Instance(ptr) => {
-                if let Some((_, true)) = ptr.unwrap_mut().properties.insert(attr, (value, readonly)) {
-                    return Err(RuntimeError::new(self, format!("Cannot assign value to property {} which is a readonly property.", attr)))
+                match ptr.unwrap_mut().properties.insert(attr, (value, readonly)) {
+                    Some((_, true)) => return Err(RuntimeError::new(self, format!("Cannot assign value to property {} which is a readonly property.", attr))),
+                    Some(_) => (),
+                    None => self.approx_bytes += ENTRY_BYTES
                 }
             },
             _ => return Err(RuntimeError::new(self, format!("Cannot set property {} to {}.", attr, target)))
@@ -909,18 +2192,193 @@ impl Vm {
         Ok(())
     }
 
+    // Canonicalizes the key used to index an object or an array. See [`Value::to_map_key`].
+    pub(crate) fn map_key(&self, key: Value) -> RuntimeResult<Value> {
+        match key.to_map_key() {
+            Some(key) => Ok(key),
+            None => Err(RuntimeError::new(self, "Cannot use NaN as a key of an object."))
+        }
+    }
+
+    // A xorshift64* generator which is seeded once when the vm starts.
+    pub(crate) fn next_random(&mut self) -> u64 {
+        self.random_state ^= self.random_state >> 12;
+        self.random_state ^= self.random_state << 25;
+        self.random_state ^= self.random_state >> 27;
+        self.random_state.wrapping_mul(0x2545F4914F6CDD1D)
+    }
+
+    // Tells once per native on the stderr that it can make a run under `--seed`
+    // differ from the other ones, such as one reading a file.
+    pub(crate) fn note_nondeterministic(&mut self, native: &str) {
+        if !self.clock.is_virtual() || self.nondeterministic.iter().any(|noted| noted == native) {
+            return;
+        }
+
+        self.nondeterministic.push(native.to_owned());
+        let _ = self.out.flush();
+        let _ = writeln!(self.err, "[{}]: Depends on the world outside of the vm, which --seed does not make deterministic.", native);
+    }
+
     pub fn has_permission(&self, string: &str) -> bool {
         self.flags.contains_key(&TinyString::new(&[b"use-", string.as_bytes()].concat()))
     }
 
+    // Checks the permission of a gated native before it acts. Without the permission
+    // the user is asked with `--prompt-permissions` if the input is interactive, and
+    // otherwise the native fails. The action completes "Script wants <name> access
+    // to ..." such as `read "notes.txt"`.
+    pub(crate) fn require_permission(&mut self, permission: Permission, native: &str, action: &str) -> RuntimeResult<()> {
+        if permission != Permission::Crypto {
+            self.note_nondeterministic(native);
+        }
+
+        if self.permissions.get(permission) {
+            return Ok(());
+        }
+
+        let denied = format!("[{}]: The {} access requires the `--use-{}` flag.", native, permission.name(), permission.name());
+        if !self.prompt_permissions || !self.input.is_interactive() {
+            return Err(RuntimeError::new(self, denied));
+        }
+
+        let message = format!("Script wants {} access to {}.", permission.name(), action);
+        match prompt::permission(self, &message) {
+            Ok(Some(Grant::Once)) => Ok(()),
+            Ok(Some(Grant::Always)) => {
+                self.permissions.grant(permission);
+                Ok(())
+            },
+            Ok(Some(Grant::Deny)) | Ok(None) => Err(RuntimeError::new(self, denied)),
+            Err(error) => Err(RuntimeError::new_io(self, error))
+        }
+    }
+
+    // The names of the frames in the call stack. The frames executing the code of
+    // a preloaded script are labeled with the name of the script.
+    pub(crate) fn trace(&self) -> Vec<TinyString> {
+        let mut trace = Vec::with_capacity(self.call_stack.len());
+
+        for (index, frame) in self.call_stack.iter().enumerate() {
+            // The ip of a frame is saved as the return ip of the frame called by it
+            // whereas the native functions do not move the ip at all.
+            let ip = match self.call_stack.get(index + 1) {
+                Some(callee) if callee.ip != 0 => callee.ip,
+                _ => self.ip
+            };
+
+            match self.chunk.get_source(ip) {
+                Some(source) if source.preloaded && (index == 0 || frame.ip != 0) => {
+                    trace.push(TinyString::from(format!("{} ({})", frame.name, source.name)));
+                },
+                _ => trace.push(frame.name())
+            }
+        }
+
+        trace
+    }
+
     pub fn add_global(&mut self, name: &str, value: Value) {
         let constant_id = self.chunk.constants.add_string(TinyString::new(name.as_bytes()));
         self.globals.insert(constant_id, (value, true));
     }
 
-    fn add_local(&mut self, slot: usize, value: Value) {
-        let frame = self.call_stack.last().unwrap();
-        self.stack[frame.stack_start + slot] = value;
+    // Makes the builtin object callable as a function such as `Boolean(value)`.
+    // Only the natives can do so, the objects of the scripts are never callable.
+    pub(crate) fn add_converter(&mut self, object: ValuePtr<Map>, name: &str, func: NativeFunctionHandler) {
+        let native = NativeFunction { name: TinyString::new(name.as_bytes()), func, bound: None };
+        let converter = Value::NativeFn(self.allocate_value_ptr(native));
+        self.converters.push((object, converter));
+    }
+
+    fn converter_of(&self, object: ValuePtr<Map>) -> Option<Value> {
+        self.converters.iter().find(|&&(converter_object, _)| converter_object == object).map(|&(_, converter)| converter)
+    }
+
+    // A snapshot of the keys and values of the object flattened as `[key, value, ...]`
+    // so that mutating the object while iterating does not affect the loop.
+    // The instances whose class has an `__iter` method are iterated over the
+    // array or the iterator returned by it.
+    fn iterable_of(&mut self, value: Value) -> RuntimeResult<Value> {
+        let method = match value {
+            Value::Instance(ptr) => ptr.unwrap_ref().methods.unwrap_ref().get(&self.constants.__iter).map(|&(method, _)| method),
+            _ => None
+        };
+
+        match method {
+            Some(method) => {
+                self.stack.push(value);
+                self.call_function_with_returned_value(method, 1)
+            },
+            None => Ok(value)
+        }
+    }
+
+    fn entries_of(&mut self, value: Value) -> Vec<Value> {
+        let mut entries = Vec::new();
+
+        match value {
+            Value::Dict(ptr) => {
+                for (key, (value, _)) in ptr.unwrap_ref().iter() {
+                    entries.extend_from_slice(&[*key, *value]);
+                }
+            },
+            Value::Array(ptr) => {
+                for (index, value) in ptr.unwrap_ref().iter().enumerate() {
+                    entries.extend_from_slice(&[Value::Int(index as isize), *value]);
+                }
+            },
+            Value::String(ptr) => {
+                let string = ptr.unwrap_ref().to_string();
+                for (index, character) in string.chars().enumerate() {
+                    let character = self.allocate_static_str(character.encode_utf8(&mut [0; 4]));
+                    entries.extend_from_slice(&[Value::Int(index as isize), Value::String(character)]);
+                }
+            },
+            Value::Iterator(ptr) => {
+                for (index, value) in ptr.unwrap().enumerate() {
+                    entries.extend_from_slice(&[Value::Int(index as isize), value]);
+                }
+            },
+            _ => ()
+        }
+
+        entries
+    }
+
+    // The index of a slot of the current frame on the stack, which is only beyond
+    // the stack when the bytecode has been corrupted.
+    fn local_index(&self, slot: usize) -> RuntimeResult<usize> {
+        let index = self.call_stack.last().map_or(0, |frame| frame.stack_start) + slot;
+        match index < self.stack.len() {
+            true => Ok(index),
+            false => Err(RuntimeError::new_uncatchable(self, format!("[BytecodeReader]: Corrupted Bytecode. The slot {} is out of the stack of length {}.", slot, self.stack.len())))
+        }
+    }
+
+    // The arguments of a call are on the stack unless the bytecode has been corrupted.
+    fn expect_args(&self, args_len: u8) -> RuntimeResult<()> {
+        match self.stack.len() >= args_len as usize {
+            true => Ok(()),
+            false => Err(RuntimeError::new_uncatchable(self, format!("[VM]: Stack Manipulation Failed. Expected stack length with minimum size as {} for the arguments.", args_len)))
+        }
+    }
+
+    fn upvalue_at(&self, slot: u8) -> RuntimeResult<Upvalue> {
+        match self.call_stack.last().and_then(|frame| frame.upvalues.get(slot as usize)) {
+            Some(&upvalue) => Ok(upvalue),
+            None => Err(self.unknown_upvalue(slot))
+        }
+    }
+
+    fn unknown_upvalue(&self, slot: u8) -> RuntimeError {
+        RuntimeError::new_uncatchable(self, format!("[BytecodeReader]: Corrupted Bytecode. The upvalue {} is not captured by the function.", slot))
+    }
+
+    fn add_local(&mut self, slot: usize, value: Value) -> RuntimeResult<()> {
+        let index = self.local_index(slot)?;
+        self.stack[index] = value;
+        Ok(())
     }
 
     pub fn allocate<O: ObjectTrait>(&mut self, object: O) -> *mut u8 {
@@ -935,16 +2393,9 @@ impl Vm {
 
             ptr::write(pointer as *mut GcHeader, GcHeader(false));
             ptr::write(pointer.add(offset) as *mut O, object);
-            self.objects.push(GcHandle(pointer as *const GcHeader, O::KIND));
-
-            #[cfg(feature = "stress_gc")]
-            self.collect_garbage();
-
-            #[cfg(not(feature = "stress_gc"))]
-            if self.bytes_allocated >= self.next_gc {
-                self.collect_garbage();
-            }
-
+            let handle = GcHandle(pointer as *const GcHeader, O::KIND);
+            self.approx_bytes += handle.approx_bytes();
+            self.objects.push(handle);
             pointer
         }
     }
@@ -975,71 +2426,171 @@ impl Vm {
             })
     }
 
-    pub(crate) fn get_io_resource(&self, resource_id: u32) -> Option<Rc<dyn IoResource>> {
-        self.resource_table.get(&resource_id)
-            .and_then(|rc| unsafe {
-                if rc.kind() == ResourceKind::Io {
-                    Some((*(rc as *const Rc<_> as *const Rc<dyn IoResource>)).clone())
-                } else { None }
-            })
+    pub(crate) fn get_io_resource(&self, resource_id: u32) -> Option<&dyn IoResource> {
+        self.resource_table.get(&resource_id).and_then(|resource| resource.as_io())
     }
 
-    pub(crate) fn add_resource<T: Resource>(&mut self, resource: T) -> u32 {
+    // Returns the handle of a new resource. The rids are never reused so the handle
+    // of a closed resource stays stale instead of reaching a newer one.
+    pub(crate) fn add_resource<T: Resource>(&mut self, resource: T) -> Value {
         let rid = self.next_rid;
+        let kind = resource.kind();
         assert!(self.resource_table.insert(self.next_rid, Rc::new(resource)).is_none());
         self.next_rid += 1;
-        rid
+        Value::Resource(rid, kind)
+    }
+
+    // Validates a handle given to a native which consumes resources. The dicts and
+    // numbers are rejected even if they hold the rid of a live resource.
+    pub(crate) fn resource_of(&self, native: &str, value: Option<&Value>) -> RuntimeResult<u32> {
+        match value {
+            Some(&Value::Resource(rid, _)) if self.resource_table.contains_key(&rid) => Ok(rid),
+            Some(resource @ Value::Resource(..)) => Err(RuntimeError::new(self, format!("[{}]: The resource {} has already been closed.", native, resource))),
+            value => Err(RuntimeError::new_argument(self, native, 0, "resource", value))
+        }
+    }
+
+    pub(crate) fn io_resource_of(&self, native: &str, value: Option<&Value>) -> RuntimeResult<&dyn IoResource> {
+        let rid = self.resource_of(native, value)?;
+        match self.get_io_resource(rid) {
+            Some(resource) => Ok(resource),
+            None => Err(RuntimeError::new(self, format!("[{}]: Expected an io resource but found {}.", native, value.unwrap_or_default())))
+        }
     }
 
     pub fn collect_garbage(&mut self) {
+        // Marks an object and everything reachable from it. The objects which are
+        // already marked are skipped so that the cycles end.
         unsafe fn mark_value(value: &Value) {
-            match value {
-                Value::Array(ptr) => {
-                    let pointer = ptr.as_ptr();
-                    if pointer.is_null() {
-                        panic!("Could not mark the pointer while sweeping garbage.");
-                    }
-
-                    let items = ptr::read(pointer.add(next_alignment(GcHeader::SIZE, mem::align_of::<Vec<Value>>())) as *const Vec<Value>);
-                    for item in &items {
-                        mark_value(item)
-                    }
+            let pointer = match value.heap_pointer() {
+                Some(pointer) if !(*(pointer as *const GcHeader)).0 => pointer,
+                _ => return
+            };
 
-                    ptr::write(pointer as *mut bool, true);
+            GcHeader::mark(pointer);
+            match value {
+                Value::Array(ptr) => for item in ptr.unwrap_ref() {
+                    mark_value(item);
+                },
+                Value::Dict(ptr) => mark_map(ptr.unwrap_ref()),
+                Value::Instance(ptr) => {
+                    let instance = ptr.unwrap_ref();
+                    mark_map(&instance.properties);
+                    mark_value(&Value::Dict(instance.methods));
                 },
-                Value::String(ptr) => {
-                    ptr::write(ptr.as_ptr() as *mut bool, true);
+                Value::Function(ptr) => mark_upvalues(&ptr.unwrap_ref().upvalues),
+                Value::Iterator(ptr) => for item in ptr.unwrap() {
+                    mark_value(&item);
+                },
+                Value::NativeFn(ptr) => if let Some((receiver, attr)) = &ptr.unwrap_ref().bound {
+                    mark_value(receiver);
+                    mark_value(attr);
                 },
                 _ => ()
             }
         }
 
-        unsafe {
-            for value in &self.stack {
+        unsafe fn mark_map(map: &Map) {
+            for (key, (value, _)) in map {
+                mark_value(key);
                 mark_value(value);
             }
+        }
+
+        // The open upvalues point to the stack which is marked on its own.
+        unsafe fn mark_upvalues(upvalues: &[Upvalue]) {
+            for upvalue in upvalues {
+                if let UpvalueState::Closed(value) = upvalue.state() {
+                    mark_value(&value);
+                }
+            }
+        }
 
+        unsafe {
             for value in &self.stack {
                 mark_value(value);
             }
 
             for (_, (value, _)) in &self.globals {
+                mark_value(value);
+            }
+
+            let VmConstants { process_prototype, watcher_prototype, set_prototype, .. } = self.constants;
+            for value in self.constants.values() {
                 mark_value(&value);
             }
 
-            // Clear all unreacable objects
-            let mut index = 0;
+            // The prototypes point to a static until their modules are installed.
+            for prototype in [process_prototype, watcher_prototype, set_prototype].iter() {
+                if *prototype != ValuePtr::default() {
+                    mark_value(&Value::Dict(*prototype));
+                }
+            }
+
+            if let Some(callback) = &self.interrupt_callback {
+                mark_value(callback);
+            }
+
+            if let Some(hook) = &self.panic_hook {
+                mark_value(hook);
+            }
+
+            for callback in &self.exit_callbacks {
+                mark_value(callback);
+            }
 
-            for handle in self.objects.clone() {
-                if handle.dealloc_if_unreachable() {
-                    self.objects.remove(index);
+            for watcher in self.watchers.values() {
+                mark_value(&watcher.callback);
+            }
+
+            for pipeline in self.pipelines.values() {
+                mark_value(&Value::Iterator(pipeline.source));
+                if let Some(function) = pipeline.stage.function() {
+                    mark_value(&function);
+                }
+            }
+
+            for frame in &self.call_stack {
+                mark_upvalues(&frame.upvalues);
+                for function in &frame.deferred {
+                    mark_value(function);
                 }
+            }
+
+            mark_upvalues(&self.open_upvalues);
+            mark_value(&self.result);
+            mark_value(&self.log);
+
+            for (object, converter) in &self.converters {
+                mark_value(&Value::Dict(*object));
+                mark_value(converter);
+            }
 
-                index += 1;
+            for slot in &self.ref_slots {
+                if let Some(value) = &slot.value {
+                    mark_value(value);
+                }
             }
+
+            // Clear all unreacable objects. The accounting of the heap starts over
+            // from the objects which are left.
+            let mut approx_bytes = 0;
+            self.objects.retain(|handle| {
+                let bytes = handle.approx_bytes();
+                let freed = handle.dealloc_if_unreachable();
+                if !freed {
+                    approx_bytes += bytes;
+                }
+
+                !freed
+            });
+
+            self.approx_bytes = approx_bytes;
         }
 
-        self.next_gc = self.bytes_allocated * 2;
+        // The next collection comes once as much as the live objects retain has been
+        // allocated again, with the initial threshold as the least.
+        self.next_gc = self.bytes_allocated + self.approx_bytes.max(u16::MAX as usize);
     }
 
 }
@@ -1064,4 +2615,35 @@ impl<'a> VmAsyncExecution<'a> {
     pub async fn handle_error(&mut self, error: RuntimeError) -> RuntimeResult<()> {
         handle_error!(error, self, self.vm)
     }
-}
\ No newline at end of file
+}
+
+// The splitmix64 step which spreads the bits of the seeds such as 0 and 1 apart,
+// as the xorshift generator needs a state other than 0.
+fn mix_seed(seed: u64) -> u64 {
+    let mut mixed = seed.wrapping_add(0x9E3779B97F4A7C15);
+    mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94D049BB133111EB);
+    (mixed ^ (mixed >> 31)) | 1
+}
+
+// The indices selected by a slice. The negative bounds count from the end and the
+// bounds out of the range are clamped like in python.
+fn slice_indices(len: usize, start: Option<isize>, end: Option<isize>, step: isize) -> impl Iterator<Item = usize> {
+    let len = len as isize;
+    let resolve = |bound: isize, min: isize, max: isize| {
+        if bound < 0 { (bound + len).max(min) } else { bound.min(max) }
+    };
+
+    let (start, end) = match step > 0 {
+        true => (start.map_or(0, |start| resolve(start, 0, len)), end.map_or(len, |end| resolve(end, 0, len))),
+        // -1 stands for the position before the first element.
+        false => (start.map_or(len - 1, |start| resolve(start, -1, len - 1)), end.map_or(-1, |end| resolve(end, -1, len - 1)))
+    };
+
+    let count = match step > 0 {
+        true => (end - start + step - 1) / step,
+        false => (start - end - step - 1) / -step
+    }.max(0);
+
+    (0..count).map(move |index| (start + index * step) as usize)
+}
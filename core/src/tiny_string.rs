@@ -2,12 +2,19 @@ extern crate alloc;
 
 use alloc::alloc::Layout;
 use std::hash::{Hash, Hasher};
+use std::collections::hash_map::DefaultHasher;
 use std::ptr::{self, NonNull};
 use std::ops::{Deref, DerefMut, Add};
 use std::fmt::{self, Display, Formatter, Debug};
 use std::slice::Iter;
 use crate::runtime::memory::{USIZE_SIZE, USIZE_ALIGN};
 
+// The string is stored as [LENGTH, HASH, BYTES...] where the hash is computed the
+// first time the string is hashed so that the long keys of the dicts are not hashed
+// again on every lookup. A hash of 0 stands for one which is not computed yet.
+const HASH_OFFSET: usize = USIZE_SIZE;
+const BYTES_OFFSET: usize = USIZE_SIZE + 8;
+
 pub struct TinyString(NonNull<u8>);
 
 impl TinyString {
@@ -15,16 +22,48 @@ impl TinyString {
     pub fn new(bytes: &[u8]) -> Self {
         unsafe {
             let length = bytes.len();
-            let str_ptr = alloc::alloc::alloc(Layout::from_size_align(USIZE_SIZE + length, USIZE_ALIGN).unwrap());
+            let str_ptr = alloc::alloc::alloc(Self::layout(length));
             let non_null = NonNull::new(str_ptr).expect("Could not allocate a tiny string.");
 
             ptr::write(str_ptr as *mut usize, length);
-            ptr::copy_nonoverlapping(bytes.as_ptr(), str_ptr.add(USIZE_SIZE), length);
+            ptr::write_unaligned(str_ptr.add(HASH_OFFSET) as *mut u64, 0);
+            ptr::copy_nonoverlapping(bytes.as_ptr(), str_ptr.add(BYTES_OFFSET), length);
 
             Self(non_null)
         }
     }
 
+    // A string whose cached hash is forced so that the tests can make keys collide.
+    #[cfg(test)]
+    fn with_hash(bytes: &[u8], hash: u64) -> Self {
+        let string = Self::new(bytes);
+        unsafe { ptr::write_unaligned(string.0.as_ptr().add(HASH_OFFSET) as *mut u64, hash) };
+        string
+    }
+
+    fn layout(length: usize) -> Layout {
+        Layout::from_size_align(BYTES_OFFSET + length, USIZE_ALIGN).unwrap()
+    }
+
+    // The hash of the bytes which is used when the string is hashed. It is not
+    // cryptographic and only stays the same between the runs of the same build.
+    pub fn hash_u64(&self) -> u64 {
+        unsafe {
+            let hash_ptr = self.0.as_ptr().add(HASH_OFFSET) as *mut u64;
+            match ptr::read_unaligned(hash_ptr) {
+                0 => {
+                    let mut hasher = DefaultHasher::new();
+                    hasher.write(self.to_bytes());
+                    let hash = hasher.finish().max(1);
+
+                    ptr::write_unaligned(hash_ptr, hash);
+                    hash
+                },
+                hash => hash
+            }
+        }
+    }
+
     pub fn len(&self) -> usize {
         unsafe { *(self.0.as_ptr() as *const usize) }
     }
@@ -32,7 +71,7 @@ impl TinyString {
     pub fn to_bytes(&self) -> &[u8] {
         let ptr = self.0.as_ptr();
         unsafe {
-            std::slice::from_raw_parts(ptr.add(USIZE_SIZE), *(ptr as *const usize))
+            std::slice::from_raw_parts(ptr.add(BYTES_OFFSET), *(ptr as *const usize))
         }
     }
 
@@ -91,16 +130,18 @@ impl Deref for TinyString {
     fn deref(&self) -> &str {
         unsafe {
             let ptr = self.0.as_ptr();
-            std::str::from_utf8_unchecked(std::slice::from_raw_parts(ptr.add(USIZE_SIZE), *(ptr as *const usize)))
+            std::str::from_utf8_unchecked(std::slice::from_raw_parts(ptr.add(BYTES_OFFSET), *(ptr as *const usize)))
         }
     }
 }
 
 impl DerefMut for TinyString {
+    // The cached hash is dropped as the bytes may be changed.
     fn deref_mut(&mut self) -> &mut str {
         unsafe {
             let ptr = self.0.as_ptr();
-            std::str::from_utf8_unchecked_mut(std::slice::from_raw_parts_mut(ptr.add(USIZE_SIZE), *(ptr as *const usize)))
+            ptr::write_unaligned(ptr.add(HASH_OFFSET) as *mut u64, 0);
+            std::str::from_utf8_unchecked_mut(std::slice::from_raw_parts_mut(ptr.add(BYTES_OFFSET), *(ptr as *const usize)))
         }
     }
 }
@@ -127,24 +168,23 @@ impl Drop for TinyString {
 
             let len = *(str_ptr as *const usize);
             
-            // Deallocate the length and the bytes
+            // Deallocate the length, the hash and the bytes
             ptr::drop_in_place(self.deref_mut());
-            alloc::alloc::dealloc(str_ptr as _, Layout::from_size_align(USIZE_SIZE + len, USIZE_ALIGN).unwrap());
+            alloc::alloc::dealloc(str_ptr as _, Self::layout(len));
         }
     }
 }
 
 impl Clone for TinyString {
+    // The cached hash is copied along with the bytes.
     fn clone(&self) -> Self {
         unsafe {
             let old_ptr = self.0.as_ptr();
             let length = *(old_ptr as *const usize);
-            let str_ptr = alloc::alloc::alloc(Layout::from_size_align(USIZE_SIZE + length, USIZE_ALIGN).unwrap());
+            let str_ptr = alloc::alloc::alloc(Self::layout(length));
             let non_null = NonNull::new(str_ptr).expect("Could not allocate a tiny string.");
 
-            ptr::write(str_ptr as *mut usize, length);
-            ptr::copy_nonoverlapping(old_ptr.add(USIZE_SIZE), str_ptr.add(USIZE_SIZE), length);
-
+            ptr::copy_nonoverlapping(old_ptr, str_ptr, BYTES_OFFSET + length);
             Self(non_null)
         }
     }
@@ -156,17 +196,7 @@ impl Add for TinyString {
     // A new tiny string gets created here instead of updating the
     // original one.
     fn add(self, rhs: Self) -> Self {
-        unsafe {
-            let bytes = [self.to_bytes(), rhs.to_bytes()].concat();
-            let length = bytes.len();
-            let str_ptr = alloc::alloc::alloc(Layout::from_size_align(USIZE_SIZE + length, USIZE_ALIGN).unwrap());
-            let non_null = NonNull::new(str_ptr).expect("Could not allocate a tiny string.");
-
-            ptr::write(str_ptr as *mut usize, length);
-            ptr::copy_nonoverlapping(bytes.as_ptr(), str_ptr.add(USIZE_SIZE), length);
-
-            Self(non_null)
-        }
+        Self::new(&[self.to_bytes(), rhs.to_bytes()].concat())
     }
 }
 
@@ -184,9 +214,11 @@ impl Default for TinyString {
     }
 }
 
+// The strings which are equal have the same cached hash, and the strings with the
+// same hash are still told apart by comparing their bytes.
 impl Hash for TinyString {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.to_bytes().hash(state);
+        state.write_u64(self.hash_u64());
     }
 }
 
@@ -249,4 +281,43 @@ impl<'a, I: Iterator<Item = &'a u8>> Iterator for TinyChars<'a, I> {
 
         Some(ch)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+
+    use std::collections::HashMap;
+    use super::TinyString;
+
+    #[test]
+    fn keys_with_the_same_hash_are_told_apart_by_their_bytes() {
+        let mut map = HashMap::new();
+        map.insert(TinyString::with_hash(b"first", 7), 1);
+        map.insert(TinyString::with_hash(b"second", 7), 2);
+        map.insert(TinyString::with_hash(b"first", 7), 3);
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&TinyString::with_hash(b"first", 7)), Some(&3));
+        assert_eq!(map.get(&TinyString::with_hash(b"second", 7)), Some(&2));
+        assert_eq!(map.get(&TinyString::with_hash(b"third", 7)), None);
+    }
+
+    #[test]
+    fn equal_strings_have_the_same_hash_however_they_are_made() {
+        let long = "k".repeat(1000);
+        let made = TinyString::from(&long[..500]) + TinyString::from(&long[500..]);
+
+        assert_eq!(made.hash_u64(), TinyString::from(long.as_str()).hash_u64());
+        assert_eq!(made.clone().hash_u64(), made.hash_u64());
+        assert_ne!(TinyString::from("a").hash_u64(), TinyString::from("b").hash_u64());
+    }
+
+    #[test]
+    fn changing_the_bytes_drops_the_cached_hash() {
+        let mut string = TinyString::from("abc");
+        let before = string.hash_u64();
+        string.make_ascii_uppercase();
+
+        assert_ne!(string.hash_u64(), before);
+        assert_eq!(string.hash_u64(), TinyString::from("ABC").hash_u64());
+    }
+}
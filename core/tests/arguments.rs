@@ -0,0 +1,64 @@
+mod common;
+
+use dashscript_core::RuntimeErrorKind;
+use common::run_with;
+
+// The calls with the wrong arguments and the messages they throw, which name the
+// argument and the type it has been given.
+const CASES: &[(&str, &str)] = &[
+    ("Math.gcd(1, \"a\");", "[Math.gcd]: expected int at argument 2, got string."),
+    ("Math.factorial(\"3\");", "[Math.factorial]: expected int at argument 1, got string."),
+    ("Math.roundTo(\"1\", 2);", "[Math.roundTo]: expected number at argument 1, got string."),
+    ("Math.divmod(1);", "[Math.divmod]: expected number at argument 2, got nothing."),
+    ("Math.approxEqual(1, 2, \"x\");", "[Math.approxEqual]: expected number at argument 3, got string."),
+    ("Date(1, 2);", "[Date.init]: expected int at argument 3, got nothing."),
+    ("Date.toISOString(\"x\");", "[Date.toISOString]: expected timestamp in milliseconds at argument 1, got string."),
+    ("Date.diff(1, 2.5);", "[Date.diff]: expected timestamp in milliseconds at argument 2, got float."),
+    ("Date.add(1, 2);", "[Date.add]: expected dict at argument 2, got int."),
+    ("Date.startOf(1, 2);", "[Date.startOf]: expected string at argument 2, got int."),
+    ("Set([1]).add();", "[Set.add]: expected any at argument 1, got nothing."),
+    ("Set(1);", "[Set.init]: expected array at argument 1, got int."),
+    ("Set([1]).union(1);", "[Set.union]: expected Set at argument 1, got int."),
+    ("StringBuilder().append();", "[StringBuilder.append]: expected any at argument 1, got nothing."),
+    ("EventEmitter().on(\"x\", 1);", "[EventEmitter.on]: expected function at argument 2, got int."),
+    ("[1].fill(0, \"a\");", "[Array.fill]: expected int at argument 2, got string."),
+    ("[1].copyWithin();", "[Array.copyWithin]: expected int at argument 1, got nothing."),
+    ("[1].reduce();", "[Array.reduce]: expected function at argument 1, got nothing."),
+    ("[1].groupBy(1);", "[Array.groupBy]: expected function at argument 1, got int."),
+    ("[1].zip(1);", "[Array.zip]: expected array at argument 1, got int."),
+    ("Array.withCapacity(\"x\");", "[Array.withCapacity]: expected non-negative int at argument 1, got string."),
+    ("Bytes.from(1);", "[Bytes.from]: expected string or array or Bytes at argument 1, got int."),
+    ("Bytes.from(\"a\").toString(1);", "[Bytes.toString]: expected string at argument 1, got int."),
+    ("Bytes.from(\"abc\").slice(\"x\");", "[Bytes.slice]: expected int at argument 1, got string."),
+    ("Object.update(1);", "[Object.update]: expected object at argument 1, got int."),
+    ("Object.fromEntries(1);", "[Object.fromEntries]: expected array at argument 1, got int."),
+    ("range(0, 3).take(\"x\");", "[Iterator.take]: expected positive int at argument 1, got string."),
+    ("window.format.percent(\"x\");", "[format.percent]: expected number at argument 1, got string."),
+    ("window.close(1);", "[window.close]: expected resource at argument 1, got int."),
+    ("window.write();", "[window.write]: expected resource at argument 1, got nothing."),
+    ("window.inspect();", "[window.inspect]: expected any at argument 1, got nothing."),
+    ("window.tty.stripColors(1);", "[tty.stripColors]: expected string at argument 1, got int."),
+    ("window.log.info();", "[log.info]: expected any at argument 1, got nothing."),
+    ("window.crypto.hash(1, \"x\");", "[crypto.hash]: expected string at argument 1, got int."),
+    ("window.atPanic(1);", "[window.atPanic]: expected function at argument 1, got int."),
+    ("window.onExit(null);", "[window.onExit]: expected function at argument 1, got null."),
+    ("window.fs.readBytes(1);", "[fs.readBytes]: expected string at argument 1, got int."),
+    ("window.fs.writeBytes(\"x\", 1);", "[fs.writeBytes]: expected Bytes at argument 2, got int."),
+    ("window.requireFeature();", "[window.requireFeature]: expected string at argument 1, got nothing.")
+];
+
+#[test]
+fn wrong_arguments_name_the_argument_and_its_type() {
+    for (source, message) in CASES {
+        let result = run_with(source, &["use-read", "use-write", "use-crypto"]);
+        let error = result.error();
+        assert_eq!(error.message(), *message, "{}", source);
+        assert_eq!(error.kind(), RuntimeErrorKind::ArgumentError, "{}", source);
+    }
+}
+
+#[test]
+fn wrong_arguments_can_be_caught() {
+    let result = run_with("try { Math.gcd(\"a\", 1); } expect { println(error); }", &[]);
+    assert_eq!(result.out, "[Math.gcd]: expected int at argument 1, got string.\n");
+}
@@ -0,0 +1,217 @@
+mod common;
+
+use common::{output, run};
+
+// The comparators which contradict themselves must neither panic nor lose items.
+#[test]
+fn sort_with_a_random_comparator() {
+    let out = output(r#"
+        let items = [];
+        let i = 0;
+        while i < 2000 {
+            items.push(i);
+            i += 1;
+        }
+
+        items.sort(func (a, b) { return Math.random() - 0.5; });
+        println(items.len());
+
+        let sorted = items.sort(func (a, b) { return a - b; });
+        println(sorted[0], sorted[1999]);
+    "#);
+
+    assert_eq!(out, "2000\n0\n1999\n");
+}
+
+#[test]
+fn sort_stops_at_the_first_error_of_the_comparator() {
+    let out = output(r#"
+        let words = ["b", "a", "c"];
+        let calls = 0;
+        try {
+            words.sort(func (a, b) {
+                calls += 1;
+                if calls == 2 {
+                    throw("stop");
+                }
+                return 0 - 1;
+            });
+        } expect {
+            println(calls, words[0] + words[1] + words[2]);
+        }
+    "#);
+
+    assert_eq!(out, "2\nbac\n");
+}
+
+#[test]
+fn sort_is_stable() {
+    let out = output(r#"
+        let pairs = [[1, "a"], [0, "b"], [1, "c"], [0, "d"]];
+        pairs.sort(func (x, y) { return x[0] - y[0]; });
+        println(pairs[0][1] + pairs[1][1] + pairs[2][1] + pairs[3][1]);
+
+        let numbers = [3, 1, 2].sort();
+        println(numbers[0], numbers[1], numbers[2]);
+    "#);
+
+    assert_eq!(out, "bdac\n1\n2\n3\n");
+}
+
+#[test]
+fn reduce_starts_from_the_first_item_without_an_initial_value() {
+    let out = output(r#"
+        let add = func (total, item) { return total + item; };
+        println([1, 2, 3].reduce(add), [1, 2, 3].reduce(add, 10), [].reduce(add, 5));
+        println(["a", "b"].reduce(func (total, item, index) { return total + item + index; }, ""));
+    "#);
+
+    assert_eq!(out, "6\n16\n5\na0b1\n");
+    assert_eq!(run("[].reduce(func (a, b) { return a; });").error().message(), "[Array.reduce]: Cannot reduce an empty array without an initial value.");
+}
+
+#[test]
+fn find_and_find_index_return_the_first_match() {
+    let out = output(r#"
+        let items = [5, 12, 8, 130];
+        let big = func (item) { return item > 10; };
+        println(items.find(big), items.findIndex(big));
+        println(items.find(func (item) { return item > 500; }), items.findIndex(func (item) { return item > 500; }));
+    "#);
+
+    assert_eq!(out, "12\n1\nnull\n-1\n");
+}
+
+// The callbacks are not called once the answer is known.
+#[test]
+fn some_and_every_short_circuit() {
+    let out = output(r#"
+        let calls = 0;
+        let big = func (item) { calls += 1; return item > 3; };
+
+        println([1, 5, 2, 7].some(big), calls);
+        calls = 0;
+        println([5, 6, 1, 7].every(big), calls);
+        calls = 0;
+        println([1, 2].some(big), [5, 6].every(big), calls);
+        println([].some(big), [].every(big));
+    "#);
+
+    assert_eq!(out, "true\n2\nfalse\n3\nfalse\ntrue\n4\nfalse\ntrue\n");
+}
+
+#[test]
+fn flat_resolves_the_nested_arrays_up_to_the_depth() {
+    let out = output(r#"
+        let nested = [1, [2, [3, [4]]]];
+        println(nested.flat().len(), nested.flat(2).len(), nested.flat(3).len(), nested.flat(0).len());
+        println(nested.flat(3)[3]);
+        let pairs = [1, 2].flatMap(func (item) { return [item, item * 10]; });
+        println(pairs.len(), pairs[3], [1, 2].flatMap(func (item) { return item; }).len());
+    "#);
+
+    assert_eq!(out, "3\n4\n4\n2\n4\n4\n20\n2\n");
+}
+
+#[test]
+fn errors_of_the_callbacks_are_propagated() {
+    for method in ["reduce", "find", "findIndex", "some", "every", "flatMap"].iter() {
+        let result = run(&format!("[1, 2].{}(func (a) {{ throw(\"boom\"); }}, 0);", method));
+        assert_eq!(result.error().message(), format!("[Array.{}]: The callback threw an error.", method));
+    }
+}
+
+#[test]
+fn fill_clamps_its_bounds_and_counts_negative_ones_from_the_end() {
+    let out = output(r#"
+        println([1, 2, 3, 4, 5].fill(0, 1, 3).join(" "));
+        println([1, 2, 3, 4, 5].fill(9, -2).join(" "));
+        println([1, 2, 3].fill(7).join(" "));
+        println([1, 2, 3].fill(7, 5, 10).join(" "));
+        println([1, 2, 3].fill(7, 2, 1).join(" "));
+        println([1, 2, 3].fill(7, -10, 1).join(" "));
+    "#);
+
+    assert_eq!(out, "1 0 0 4 5 \n1 2 3 9 9 \n7 7 7 \n1 2 3 \n1 2 3 \n7 2 3 \n");
+}
+
+#[test]
+fn copy_within_handles_overlapping_ranges() {
+    let out = output(r#"
+        println([1, 2, 3, 4, 5].copyWithin(0, 3).join(" "));
+        println([1, 2, 3, 4, 5].copyWithin(1, 0, 3).join(" "));
+        println([1, 2, 3, 4, 5].copyWithin(-2, 0).join(" "));
+    "#);
+
+    assert_eq!(out, "4 5 3 4 5 \n1 1 2 3 5 \n1 2 3 1 2 \n");
+}
+
+#[test]
+fn push_all_resize_and_with_capacity_grow_the_arrays() {
+    let out = output(r#"
+        let items = Array.withCapacity(10);
+        println(items.len());
+        items.pushAll([1, 2]);
+        items.pushAll(items);
+        println(items.join(" "));
+        println(items.resize(6, 0).join(" "), items.resize(2).join(" "));
+
+        let many = Array.withCapacity(100000);
+        let chunk = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];
+        let i = 0;
+        while i < 10000 {
+            many.pushAll(chunk);
+            i += 1;
+        }
+        println(many.len(), many[99999]);
+    "#);
+
+    assert_eq!(out, "0\n1 2 1 2 \n1 2 1 2 0 0 \n1 2 \n100000\n10\n");
+    assert_eq!(run("[1].pushAll(1);").error().message(), "[Array.pushAll]: expected array at argument 1, got int.");
+    assert_eq!(run("Array.withCapacity(-1);").error().message(), "[Array.withCapacity]: expected non-negative int at argument 1, got int.");
+}
+
+#[test]
+fn binary_searches_give_the_leftmost_match_or_the_insertion_point() {
+    let out = output(r#"
+        func result(search) { return "" + search.found + ":" + search.index; }
+        let sorted = [1, 3, 3, 3, 5];
+        println(result(sorted.binarySearch(3)), result(sorted.binarySearch(4)));
+        println(result(sorted.binarySearch(0)), result(sorted.binarySearch(6)), result([].binarySearch(1)));
+        println(result(sorted.binarySearchBy(func (item) { return item - 5; })));
+        println(result(sorted.binarySearchBy(func (item) { return item - 2; })));
+    "#);
+
+    assert_eq!(out, "true:1\nfalse:4\nfalse:0\nfalse:5\nfalse:0\ntrue:4\nfalse:1\n");
+}
+
+#[test]
+fn insert_sorted_keeps_the_order_and_goes_after_the_equal_items() {
+    let out = output(r#"
+        let sorted = [1, 5, 5];
+        println(sorted.insertSorted(5), sorted.insertSorted(0), sorted.insertSorted(9), sorted.insertSorted(3));
+        println(sorted.join(" "), sorted.isSorted());
+        println([2, 1].isSorted(), [].isSorted(), ["a", "b"].isSorted());
+    "#);
+
+    assert_eq!(out, "3\n0\n5\n2\n0 1 3 5 5 5 9 \ntrue\nfalse\ntrue\ntrue\n");
+}
+
+// The result of a search in an unsorted array is unspecified but is still an
+// index of the array or its end.
+#[test]
+fn searches_in_unsorted_arrays_end() {
+    let out = output(r#"
+        let unsorted = [5, 1, 4, 2, 3, 9, 0, 7];
+        let i = 0;
+        let inRange = true;
+        while i < 10 {
+            let index = unsorted.binarySearch(i).index;
+            inRange = inRange && index >= 0 && index <= unsorted.len();
+            i += 1;
+        }
+        println(inRange);
+    "#);
+
+    assert_eq!(out, "true\n");
+}
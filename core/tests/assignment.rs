@@ -0,0 +1,147 @@
+mod common;
+
+use common::output;
+
+// The value of `??=` is only evaluated when the target is null.
+#[test]
+fn nullish_assignment_is_lazy() {
+    let out = output(r#"
+        let calls = 0;
+        func make() {
+            calls += 1;
+            return "made";
+        }
+
+        let a = null;
+        a ??= make();
+        a ??= make();
+        println(a, calls);
+
+        let l = [null, 2];
+        l[0] ??= 5;
+        l[1] ??= make();
+        println(l[0], l[1], calls);
+    "#);
+
+    assert_eq!(out, "made\n1\n5\n2\n1\n");
+}
+
+// The target of an attribute is evaluated once for both the check and the store.
+#[test]
+fn nullish_assignment_evaluates_the_target_once() {
+    let out = output(r#"
+        let d = { x: 1 };
+        let reads = 0;
+        func target() {
+            reads += 1;
+            return d;
+        }
+
+        target().x ??= 2;
+        target().y ??= 3;
+        println(d.x, d.y, reads);
+
+        func outer() {
+            let v = null;
+            let set = func () {
+                v ??= "first";
+                v ??= "second";
+            };
+            set();
+            return v;
+        }
+
+        println(outer());
+    "#);
+
+    assert_eq!(out, "1\n3\n2\nfirst\n");
+}
+
+// The old value of an attribute is read before the value is evaluated, like the
+// one of a name, and the receiver is evaluated only once.
+#[test]
+fn compound_attribute_assignment_reads_the_old_value_first() {
+    let out = output(r#"
+        let o = {"x": 1};
+        func bumpAttribute() {
+            o.x = 100;
+            return 5;
+        }
+
+        let x = 1;
+        func bumpName() {
+            x = 100;
+            return 5;
+        }
+
+        o.x += bumpAttribute();
+        x += bumpName();
+        println(o.x, x);
+
+        let calls = 0;
+        let items = [10, 20];
+        func list() {
+            calls += 1;
+            return items;
+        }
+
+        list()[1] -= 5;
+        println(items[1], calls);
+    "#);
+
+    assert_eq!(out, "6\n6\n15\n1\n");
+}
+
+// The compound assignments write through the whole path to the last container,
+// including the arrays inside of the dicts.
+#[test]
+fn nested_paths_are_assigned_at_each_depth() {
+    let out = output(r#"
+        let config = {"server": {"limits": {"max": 1, "list": [1, 2]}}};
+        config.server.limits.max += 1;
+        config.server.limits.max -= 5;
+        config.server.limits["list"][1] += 10;
+        config.server.limits.list[2] = 7;
+        println(config.server.limits.max, config.server.limits.list.len(), config.server.limits.list[1], config.server.limits.list[2]);
+
+        let deep = {"a": {"b": {"c": {"d": 1}}}};
+        deep.a.b.c.d += 41;
+        deep.a.b.c["e"] ??= 3;
+        deep.a.x = {"y": "p"};
+        deep.a.x.y += "q";
+        println(deep.a.b.c.d, deep.a.b.c.e, deep.a.x.y);
+    "#);
+
+    assert_eq!(out, "-3\n3\n12\n7\n42\n3\npq\n");
+}
+
+// `+=` changes the arrays and the dicts in place, so their aliases see the change,
+// whether the target is a name or an attribute.
+#[test]
+fn add_assignment_appends_to_arrays_and_merges_dicts() {
+    let out = output(r#"
+        let a = [1, 2];
+        let alias = a;
+        a += [3, 4];
+        a += 5;
+        println(alias.len(), alias[4]);
+
+        let d = {"x": 1, "y": 2};
+        let dictAlias = d;
+        d += {"y": 20, "z": 30};
+        println(dictAlias.y, dictAlias.z, Object.keys(dictAlias).len());
+
+        let o = {"list": [1]};
+        let list = o.list;
+        o.list += [2];
+        o["list"] += 3;
+        println(list.len(), list[2]);
+
+        let s = "n";
+        s += 5;
+        s += 1.5;
+        println(s);
+    "#);
+
+    assert_eq!(out, "5\n5\n20\n30\n3\n3\n3\nn51.5\n");
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::{output, run};
+
+#[test]
+fn sleeping_functions_are_timed_one_by_one() {
+    let out = output(r#"
+        let report = window.benchmark(func () { window.sleep(2); }, {"iterations": 5, "warmup": 1});
+        println(report.iterations, report.batch, report.min, report.mean, report.totalMs);
+    "#);
+
+    let values: Vec<f64> = out.lines().map(|line| line.parse().unwrap()).collect();
+    assert_eq!(values[..2], [5.0, 1.0]);
+    assert!(values[2] >= 2.0 && values[3] >= values[2] && values[3] < 50.0, "{}", out);
+    assert!(values[4] >= 10.0, "{}", out);
+}
+
+// The functions too fast to be timed alone are run in batches.
+#[test]
+fn fast_functions_are_batched() {
+    let out = output(r#"
+        let report = window.benchmark(func () { return 1; }, {"iterations": 3});
+        println(report.iterations, report.batch > 1, report.min <= report.median, report.median <= report.max);
+    "#);
+
+    assert_eq!(out, "3\ntrue\ntrue\ntrue\n");
+}
+
+#[test]
+fn errors_stop_the_benchmark_at_once() {
+    let output = run(r#"
+        let calls = 0;
+        try {
+            window.benchmark(func () { calls += 1; null(); });
+        } expect {
+            println(calls);
+        }
+        window.benchmark(func () { null(); }, {"warmup": 0});
+    "#);
+
+    assert_eq!(output.out, "1\n");
+    let error = output.error().to_string();
+    assert!(error.contains("[window.benchmark]: The function threw an error.") && error.contains("You cannot call a null."), "{}", error);
+}
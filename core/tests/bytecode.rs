@@ -0,0 +1,110 @@
+use dashscript_core::opcode::{self, *};
+use dashscript_core::runtime::fuzz::execute_bytes;
+use dashscript_core::{AST, BytecodeCompiler};
+use dashscript_core::ast::constant_pool;
+
+// The instructions cut off in their operands and the offset of the operand which
+// could not be read. The chunks start with the number of slots of the script.
+const TRUNCATED: [(u8, &[u8], usize); 44] = [
+    (INT, &[], 2),
+    (INT_LONG, &[], 2),
+    (INT_LONG, &[1, 0, 0], 2),
+    (FLOAT, &[], 2),
+    (FLOAT_LONG, &[1], 2),
+    (STRING, &[], 2),
+    (STRING_LONG, &[], 2),
+    (STRING_LONG, &[1, 0], 2),
+    (GET_LOCAL, &[], 2),
+    (SET_LOCAL, &[], 2),
+    (GET_UPVALUE, &[], 2),
+    (SET_UPVALUE, &[], 2),
+    (CLOSE_UPVALUE, &[], 2),
+    (CALL, &[], 2),
+    (CALL_CHILD, &[], 2),
+    (COMPOUND_ASSIGN, &[], 2),
+    (ASSIGN_ATTR, &[], 2),
+    (CHECK_RETURN, &[], 2),
+    // The constant ids are a byte after 0 or four bytes after 1.
+    (GET_GLOBAL, &[], 2),
+    (GET_GLOBAL, &[0], 3),
+    (GET_GLOBAL, &[1, 0, 0, 0], 3),
+    (SET_GLOBAL, &[1], 3),
+    (ARRAY, &[], 2),
+    (ARRAY, &[0], 3),
+    (DICT, &[1, 0], 3),
+    // The jumps read their offsets whether they jump or not.
+    (JUMP, &[], 2),
+    (JUMP, &[1], 2),
+    (JUMP_BACK, &[1], 2),
+    (JUMP_IF, &[], 2),
+    (JUMP_IF, &[1], 2),
+    (JUMP_NOT_IF, &[1], 2),
+    (JUMP_IF_NOT_NULL, &[], 2),
+    (JUMP_IF_NOT_NULL, &[1], 2),
+    (ITER_NEXT, &[], 2),
+    (ITER_NEXT, &[0], 3),
+    (ITER_NEXT, &[0, 1], 3),
+    (ITER_NEXT_ENTRY, &[0, 1], 4),
+    (ITER_NEXT_ENTRY, &[0, 1, 0], 4),
+    // A function is followed by the length of its body, which is followed by its slots.
+    (FUNC, &[], 2),
+    (FUNC, &[0], 2),
+    (FUNC, &[0, 0], 4),
+    (CHECK_TYPE, &[0], 3),
+    (CHECK_TYPE, &[0, 0], 4),
+    (CHECK_TYPE, &[0, 0, 0], 5)
+];
+
+#[test]
+fn truncated_operands_are_corrupted_bytecode() {
+    for &(op, operands, offset) in TRUNCATED.iter() {
+        let mut bytes = vec![0, op];
+        bytes.extend_from_slice(operands);
+
+        let name = opcode::to_string(op);
+        let expected = format!("[BytecodeReader]: Corrupted Bytecode. Unexpected end of the chunk at offset {} while decoding {}.", offset, name);
+        match execute_bytes(&bytes) {
+            Ok(()) => panic!("{} {:?} has been executed.", name, operands),
+            Err(error) => assert_eq!(error.message(), expected, "{} {:?}", name, operands)
+        }
+    }
+}
+
+#[test]
+fn an_empty_chunk_has_no_slots() {
+    let error = execute_bytes(&[]).unwrap_err();
+    assert_eq!(error.message(), "[BytecodeReader]: Corrupted Bytecode. Unexpected end of the chunk at offset 0 while decoding the slots of the script.");
+}
+
+fn compiler(source: &str) -> BytecodeCompiler {
+    let build = AST::compile(&String::from("test.ds"), &source.to_string()).expect("The script parses.");
+    BytecodeCompiler::new(build).expect("The script compiles.")
+}
+
+// Only the names which the compiler refers to by their ids are in the pool before
+// the ones of the script.
+#[test]
+fn the_pool_starts_with_the_names_of_the_compiler() {
+    let compiler = compiler("1;");
+    let names: Vec<String> = compiler.constants().iter().map(|name| name.to_string()).collect();
+
+    assert_eq!(names, ["anonymous", "error"]);
+    assert_eq!(compiler.constant_at(constant_pool::ERROR_CONSTANT).map(|name| name.to_string()), Some("error".to_owned()));
+    assert!(compiler.constant_at(2).is_none());
+}
+
+// The words and the strings with the same text share one entry.
+#[test]
+fn intern_shares_the_entries_of_the_same_text() {
+    let mut compiler = compiler("let print2 = \"print2\";\nprintln(print2);");
+    let len = compiler.constants().len();
+    let id = compiler.intern("print2");
+
+    assert_eq!(compiler.constants().iter().filter(|name| name.to_string() == "print2").count(), 1);
+    assert_eq!(compiler.intern("print2"), id);
+    assert_eq!(compiler.constants().len(), len);
+
+    let new = compiler.intern("fresh");
+    assert_eq!(new as usize, len);
+    assert_eq!(compiler.constant_at(new).map(|name| name.to_string()), Some("fresh".to_owned()));
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+use dashscript_core::{AST, BytecodeCompiler, CancellationToken, RuntimeError, RuntimeErrorKind, Vm, VmBuilder};
+use common::Capture;
+
+fn builder(source: &str) -> VmBuilder {
+    let build = AST::compile(&String::from("cancel.ds"), &source.to_string()).unwrap();
+    let compiler = BytecodeCompiler::new(build).unwrap();
+    Vm::builder(compiler.into(), HashMap::new(), PathBuf::from("cancel.ds")).stdout(Box::new(Capture::default()))
+}
+
+// Cancels the token from another thread after 50ms.
+fn cancel_later(token: CancellationToken) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        token.cancel();
+    })
+}
+
+// Builds the vm, which runs the script, and returns its error and how long it
+// has taken.
+fn cancelled_run(source: &str) -> (RuntimeError, Duration) {
+    let builder = builder(source);
+    let canceller = cancel_later(builder.cancellation_token());
+
+    let start = Instant::now();
+    let error = builder.build().err().expect("The script has been cancelled.");
+    let elapsed = start.elapsed();
+    canceller.join().unwrap();
+
+    (error, elapsed)
+}
+
+#[test]
+fn infinite_loops_stop_with_the_cancelled_kind() {
+    let (error, elapsed) = cancelled_run("while true {}");
+
+    assert_eq!(error.kind(), RuntimeErrorKind::Cancelled);
+    assert_eq!(error.message(), "[VM]: The execution has been cancelled.");
+    assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+}
+
+#[test]
+fn cancellations_cannot_be_caught_by_the_script() {
+    let (error, _) = cancelled_run("while true { try { [1].map(func (x) { while true {} }); } expect { error } }");
+    assert_eq!(error.kind(), RuntimeErrorKind::Cancelled);
+}
+
+#[test]
+fn sleeps_return_soon_after_a_cancellation() {
+    let (error, elapsed) = cancelled_run("window.sleep(100000);");
+
+    assert_eq!(error.kind(), RuntimeErrorKind::Cancelled);
+    assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+}
+
+#[test]
+fn tokens_of_built_vms_stop_the_later_calls() {
+    let mut vm = builder("func spin() { while true {} }").build().unwrap();
+    let spin = vm.global("spin").unwrap();
+    let canceller = cancel_later(vm.cancellation_token());
+
+    let error = vm.call(spin, &[]).err().expect("The call has been cancelled.");
+    canceller.join().unwrap();
+    assert_eq!(error.kind(), RuntimeErrorKind::Cancelled);
+}
@@ -0,0 +1,193 @@
+mod common;
+
+use common::output;
+
+// Every iteration of a loop has its own block locals for the closures to capture.
+#[test]
+fn closures_capture_the_locals_of_each_iteration() {
+    let out = output(r#"
+        let fs = [];
+        let k = 0;
+        while k < 3 {
+            let j = k * 10;
+            fs.push(func () { return j; });
+            j += 1;
+            k += 1;
+        }
+
+        println(fs[0](), fs[1](), fs[2]());
+    "#);
+
+    assert_eq!(out, "1\n11\n21\n");
+}
+
+#[test]
+fn continue_closes_the_locals_of_the_iteration() {
+    let out = output(r#"
+        let fs = [];
+        for i in [1, 2, 3] {
+            let v = i * 2;
+            fs.push(func () { return v; });
+            if i == 2 {
+                continue;
+            }
+            let w = v + 1;
+            fs.push(func () { return w + v; });
+        }
+
+        for f in fs {
+            println(f());
+        }
+    "#);
+
+    assert_eq!(out, "2\n5\n4\n6\n13\n");
+}
+
+#[test]
+fn closures_share_the_locals_of_enclosing_blocks() {
+    let out = output(r#"
+        let outer = 5;
+        let gs = [];
+        let n = 0;
+        while n < 2 {
+            let inner = n;
+            gs.push(func () { return outer + inner; });
+            n += 1;
+        }
+
+        outer = 100;
+        println(gs[0](), gs[1]());
+
+        func counter() {
+            let count = 0;
+            return func () {
+                count += 1;
+                return count;
+            };
+        }
+
+        let next = counter();
+        next();
+        println(next());
+    "#);
+
+    assert_eq!(out, "100\n101\n2\n");
+}
+
+#[test]
+fn closures_capturing_the_same_local_share_it() {
+    let out = output(r#"
+        func pair() {
+            let value = 0;
+            let set = func (x) { value = x; return null; };
+            let get = func () { return value; };
+            return [set, get];
+        }
+
+        let accessors = pair();
+        accessors[0](7);
+        println(accessors[1]());
+        accessors[0](9);
+        println(accessors[1]());
+    "#);
+
+    assert_eq!(out, "7\n9\n");
+}
+
+#[test]
+fn writes_to_an_open_upvalue_reach_the_local() {
+    let out = output(r#"
+        func f() {
+            let local = 1;
+            let set = func () { local = 2; return null; };
+            set();
+            println(local);
+            local = 3;
+            let get = func () { return local; };
+            return get();
+        }
+
+        println(f());
+    "#);
+
+    assert_eq!(out, "2\n3\n");
+}
+
+#[test]
+fn a_name_captured_twice_by_a_closure_is_one_upvalue() {
+    let out = output(r#"
+        func f() {
+            let x = 1;
+            let g = func () {
+                x += 1;
+                x += 1;
+                return x;
+            };
+
+            g();
+            return g() + x;
+        }
+
+        println(f());
+    "#);
+
+    assert_eq!(out, "10\n");
+}
+
+// The slots of a loop and of a try block are taken by the next locals once they
+// end, which must not change the values captured by the closures made in them.
+#[test]
+fn captured_locals_keep_their_values_when_their_slots_are_reused() {
+    let out = output(r#"
+        let fs = [];
+        for i in [1, 2] {
+            if (true) {
+                let v = i * 100;
+                fs.push(func () { return v; });
+                break;
+            }
+        }
+        try {
+            let w = 7;
+            fs.push(func () { return w; });
+            null();
+        } expect {
+            let x = 8;
+        }
+        if (true) {
+            let a = 1;
+            let b = 2;
+            let c = 3;
+        }
+
+        for f in fs {
+            println(f());
+        }
+    "#);
+
+    assert_eq!(out, "100\n7\n");
+}
+
+// The name of a function is declared before its body, so the functions declared
+// in blocks and in other functions call themselves through an upvalue.
+#[test]
+fn local_functions_call_themselves() {
+    let out = output(r#"
+        func outer(n) {
+            func fib(k) {
+                return k < 2 ? k : fib(k - 1) + fib(k - 2);
+            }
+            return fib(n);
+        }
+
+        if (true) {
+            func fact(n) {
+                return n < 2 ? 1 : n * fact(n - 1);
+            }
+            let saved = fact;
+            println(outer(20), saved(10));
+        }
+    "#);
+
+    assert_eq!(out, "6765\n3628800\n");
+}
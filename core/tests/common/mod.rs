@@ -0,0 +1,131 @@
+// The helpers shared by the tests which run the scripts through the vm and check
+// what they have written.
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Cursor, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use dashscript_core::{AST, BytecodeCompiler, CompileOptions, Vm, VmBuilder, RuntimeError, TinyString};
+use dashscript_core::core::installer::Selection;
+
+// A writer whose bytes are read back after the vm has been dropped.
+#[derive(Clone, Default)]
+pub struct Capture(Rc<RefCell<Vec<u8>>>);
+
+impl Capture {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+}
+
+impl Write for Capture {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct Output {
+    pub out: String,
+    pub err: String,
+    pub error: Option<RuntimeError>
+}
+
+impl Output {
+    // The error the script has ended with, which fails the test if there is none.
+    pub fn error(&self) -> &RuntimeError {
+        match &self.error {
+            Some(error) => error,
+            None => panic!("The script has not thrown. Its output was:\n{}", self.out)
+        }
+    }
+}
+
+// The compiler errors of a script which is expected not to compile.
+pub fn compile_errors(source: &str) -> Vec<String> {
+    let filename = String::from("test.ds");
+    match AST::compile(&filename, &source.to_string()) {
+        Ok(build) => match BytecodeCompiler::new(build) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.iter().map(|error| error.kind.to_string()).collect()
+        },
+        Err(errors) => errors.iter().map(|error| error.to_string()).collect()
+    }
+}
+
+// Runs a script with the flags given as `name` or `name=value`.
+pub fn run_with(source: &str, flags: &[&str]) -> Output {
+    let (out, err) = (Capture::default(), Capture::default());
+
+    // The vm flushes its output once it is dropped.
+    let error = run_into(source, flags, Box::new(out.clone()), Box::new(err.clone()));
+    Output { out: out.text(), err: err.text(), error }
+}
+
+// Runs a script which writes into the given writers and returns its error.
+pub fn run_into(source: &str, flags: &[&str], out: Box<dyn Write>, err: Box<dyn Write>) -> Option<RuntimeError> {
+    builder(source, flags).stdout(out).stderr(err).build().err()
+}
+
+// Runs a script which reads the input from the text rather than the stdin.
+pub fn run_with_input(source: &str, flags: &[&str], input: &str) -> Output {
+    capture(builder(source, flags).stdin(Box::new(Cursor::new(input.to_owned()))))
+}
+
+// The same as `run_with_input` but the input also answers the permission prompts.
+pub fn run_interactive(source: &str, flags: &[&str], input: &str) -> Output {
+    capture(builder(source, flags).interactive_stdin(Box::new(Cursor::new(input.to_owned()))))
+}
+
+fn capture(builder: VmBuilder) -> Output {
+    let (out, err) = (Capture::default(), Capture::default());
+    let error = builder.stdout(Box::new(out.clone())).stderr(Box::new(err.clone())).build().err();
+    Output { out: out.text(), err: err.text(), error }
+}
+
+fn builder(source: &str, flags: &[&str]) -> VmBuilder {
+    let filename = String::from("test.ds");
+    let build = match AST::compile(&filename, &source.to_string()) {
+        Ok(build) => build,
+        Err(errors) => panic!("The script does not parse: {:?}", errors.iter().map(|error| error.to_string()).collect::<Vec<_>>())
+    };
+
+    let mut map = HashMap::new();
+    for flag in flags {
+        let (name, value) = match flag.find('=') {
+            Some(index) => (&flag[..index], &flag[index + 1..]),
+            None => (*flag, "")
+        };
+
+        map.insert(TinyString::new(name.as_bytes()), TinyString::new(value.as_bytes()));
+    }
+
+    // The groups of `--global` are given to the compiler as well like the cli does.
+    let options = CompileOptions { globals: Selection::of(&map), ..Default::default() };
+    let compiler = match BytecodeCompiler::with_options(build, options) {
+        Ok(compiler) => compiler,
+        Err(errors) => panic!("The script does not compile: {:?}", errors.iter().map(|error| error.kind.to_string()).collect::<Vec<_>>())
+    };
+
+    Vm::builder(compiler.into(), map, PathBuf::from(filename))
+}
+
+pub fn run(source: &str) -> Output {
+    run_with(source, &[])
+}
+
+// The output of a script which must not throw.
+pub fn output(source: &str) -> String {
+    let output = run(source);
+    if let Some(error) = output.error {
+        panic!("The script has thrown {}. Its output was:\n{}", error, output.out);
+    }
+
+    output.out
+}
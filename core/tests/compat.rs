@@ -0,0 +1,34 @@
+mod common;
+
+use std::io::BufWriter;
+use common::{run_with, run_into, Capture};
+
+#[test]
+fn console_writes_its_arguments_on_one_line() {
+    let result = run_with("console.log(1, \"a\", true); console.warn(\"w\", 2);", &["compat=js"]);
+    assert_eq!(result.out, "1 a true\n");
+    assert_eq!(result.err, "w 2\n");
+}
+
+#[test]
+fn console_error_comes_after_the_output_written_before_it() {
+    // Both streams go to the same capture but only the output is buffered.
+    let capture = Capture::default();
+    let source = "console.log(\"first\"); console.error(\"second\"); console.log(\"third\");";
+    let error = run_into(source, &["compat=js"], Box::new(BufWriter::new(capture.clone())), Box::new(capture.clone()));
+
+    assert!(error.is_none());
+    assert_eq!(capture.text(), "first\nsecond\nthird\n");
+}
+
+#[test]
+fn string_and_number_convert_their_argument() {
+    let result = run_with("println(String(12) + \"!\"); println(Number(\" 7 \")); println(Number(\"\")); println(Number(\"x\")); println(Number(true));", &["compat=js"]);
+    assert_eq!(result.out, "12!\n7\n0\nNaN\n1\n");
+}
+
+#[test]
+fn string_and_number_are_not_callable_without_the_flag() {
+    let result = run_with("String(12);", &[]);
+    assert!(result.error.is_some());
+}
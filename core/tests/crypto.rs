@@ -0,0 +1,59 @@
+mod common;
+
+use common::run_with;
+
+fn crypto(source: &str, flags: &[&str]) -> String {
+    let result = run_with(source, flags);
+    if let Some(error) = result.error {
+        panic!("The script has thrown {}. Its output was:\n{}", error, result.out);
+    }
+
+    result.out
+}
+
+#[test]
+fn hashes_of_the_scripts_match_the_known_answers() {
+    let out = crypto(r#"
+        println(window.crypto.hash("sha256", "abc"));
+        println(window.crypto.hmac("sha256", "Jefe", "what do ya want for nothing?"));
+        println(window.crypto.hash("fnv1a64", "a"));
+    "#, &["use-crypto"]);
+
+    assert_eq!(out, concat!(
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad\n",
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843\n",
+        "af63dc4c8601ec8c\n"
+    ));
+}
+
+// The bytes come from the os so that the same seed does not repeat them.
+#[test]
+fn random_bytes_and_uuids_are_not_repeated_by_the_seed() {
+    let source = r#"
+        let bytes = window.crypto.randomBytes(16);
+        let text = "";
+        for byte in bytes {
+            text += byte + ",";
+        }
+        println(bytes.len(), text, window.uuid());
+    "#;
+
+    let first = run_with(source, &["use-crypto", "seed=7"]);
+    let second = run_with(source, &["use-crypto", "seed=7"]);
+
+    assert!(first.error.is_none() && second.error.is_none());
+    assert_ne!(first.out, second.out);
+    assert!(first.err.contains("[crypto.randomBytes]: Depends on the world outside of the vm"), "{}", first.err);
+    assert!(first.err.contains("[window.uuid]: Depends on the world outside of the vm"), "{}", first.err);
+}
+
+#[test]
+fn uuids_are_version_4() {
+    let out = crypto("println(window.uuid());", &[]);
+    let uuid = out.trim_end();
+    let groups: Vec<usize> = uuid.split('-').map(str::len).collect();
+
+    assert_eq!(groups, [8, 4, 4, 4, 12], "{}", uuid);
+    assert_eq!(&uuid[14..15], "4");
+    assert!("89ab".contains(&uuid[19..20]), "{}", uuid);
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use common::{output, run};
+
+// Prints the dates given by the timestamps on one line each.
+fn iso(timestamps: &str) -> String {
+    output(&format!("let timestamps = [{}];\nfor ms in timestamps {{ println(Date.toISOString(ms)); }}", timestamps))
+}
+
+#[test]
+fn iso_strings_cover_leap_days_and_timestamps_before_1970() {
+    assert_eq!(iso("0, -1, 951782400000, -62135596800000"), concat!(
+        "1970-01-01T00:00:00.000Z\n",
+        "1969-12-31T23:59:59.999Z\n",
+        "2000-02-29T00:00:00.000Z\n",
+        "0001-01-01T00:00:00.000Z\n"
+    ));
+}
+
+// 1706659200000 is 2024-01-31 and 1675123200000 is 2023-01-31.
+#[test]
+fn adding_months_clamps_the_day_to_the_end_of_the_month() {
+    let out = iso(r#"
+        Date.add(1706659200000, { months: 1 }),
+        Date.add(1675123200000, { months: 1 }),
+        Date.add(951782400000, { years: 1 }),
+        Date.add(0, { years: -1, days: 1, hours: 2, minutes: 3, seconds: 4, milliseconds: 5 })
+    "#);
+
+    assert_eq!(out, concat!(
+        "2024-02-29T00:00:00.000Z\n",
+        "2023-02-28T00:00:00.000Z\n",
+        "2001-02-28T00:00:00.000Z\n",
+        "1969-01-02T02:03:04.005Z\n"
+    ));
+}
+
+#[test]
+fn start_of_truncates_to_the_unit() {
+    let out = iso(r#"
+        Date.startOf(-1, "year"),
+        Date.startOf(-1, "month"),
+        Date.startOf(951825000123, "day"),
+        Date.startOf(951825000123, "hour"),
+        Date.startOf(1706659261999, "minute"),
+        Date.startOf(1706659261999, "second")
+    "#);
+
+    assert_eq!(out, concat!(
+        "1969-01-01T00:00:00.000Z\n",
+        "1969-12-01T00:00:00.000Z\n",
+        "2000-02-29T00:00:00.000Z\n",
+        "2000-02-29T11:00:00.000Z\n",
+        "2024-01-31T00:01:00.000Z\n",
+        "2024-01-31T00:01:01.000Z\n"
+    ));
+}
+
+#[test]
+fn diff_splits_the_duration_and_keeps_its_sign() {
+    let out = output("
+        let later = Date.diff(90061001, 0);
+        println(later.total, later.days, later.hours, later.minutes, later.seconds, later.milliseconds);
+        let earlier = Date.diff(0, 90061001);
+        println(earlier.total, earlier.days, earlier.milliseconds);
+    ");
+
+    assert_eq!(out, "90061001\n1\n1\n1\n1\n1\n-90061001\n-1\n-1\n");
+}
+
+#[test]
+fn unknown_units_and_other_values_are_errors() {
+    assert_eq!(run("Date.startOf(0, \"week\");").error().message(), "[Date.startOf]: Unknown unit \"week\".");
+    assert_eq!(run("Date.add(0, { weeks: 1 });").error().message(), "[Date.add]: Unknown unit \"weeks\".");
+    assert_eq!(
+        run("Date.toISOString(\"x\");").error().message(),
+        "[Date.toISOString]: expected timestamp in milliseconds at argument 1, got string."
+    );
+}
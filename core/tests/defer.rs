@@ -0,0 +1,75 @@
+mod common;
+
+use common::{output, run};
+
+#[test]
+fn deferred_calls_run_in_reverse_when_the_function_exits() {
+    let out = output(r#"
+        func f() {
+            defer println("first");
+            defer println("second");
+            println("body");
+            return 1;
+        }
+
+        println(f());
+        defer println("top level");
+        println("end");
+    "#);
+
+    assert_eq!(out, "body\nsecond\nfirst\n1\nend\ntop level\n");
+}
+
+#[test]
+fn deferred_calls_run_while_an_error_unwinds() {
+    let out = output(r#"
+        func g() {
+            defer println("cleanup");
+            throw("failed");
+        }
+
+        try { g(); } expect { println("caught"); }
+    "#);
+
+    assert_eq!(out, "cleanup\ncaught\n");
+}
+
+// The callee and the arguments are evaluated when the defer is registered, even
+// in a loop whose locals are gone by the time the frame exits.
+#[test]
+fn deferred_calls_keep_the_values_of_their_registration() {
+    let output = run(r#"
+        func loopy() {
+            for i in [1, 2] {
+                let label = "loop" + i;
+                defer println(label, i);
+            }
+
+            let value = "before";
+            defer println(value);
+            value = "after";
+        }
+
+        loopy();
+    "#);
+
+    assert!(output.error.is_none());
+    assert_eq!(output.out, "before\nloop2\n2\nloop1\n1\n");
+}
+
+// An error of a deferred call is only reported when another one is being thrown.
+#[test]
+fn deferred_errors_do_not_mask_the_original_error() {
+    let output = run(r#"
+        func h() {
+            defer throw("in defer");
+            throw("original");
+        }
+
+        try { h(); } expect { println("caught"); }
+    "#);
+
+    assert_eq!(output.out, "caught\n");
+    assert!(!output.err.contains("RuntimeError {"), "{}", output.err);
+    assert!(output.err.contains("(line 3)"), "{}", output.err);
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use common::output;
+
+// The integral floats are the same keys as the ints while the other floats keep
+// their exact bits.
+#[test]
+fn numeric_keys_are_canonicalized() {
+    let out = output(r#"
+        let d = {};
+        d[1] = "one";
+        d[-0.0] = "zero";
+        d[0.3] = "third";
+        println(d[1.0], d[0], d[0.0], d[0.3], d[0.1 + 0.2]);
+
+        Object.defineProperty(d, 2.0, "two");
+        println(d[2]);
+        Object.remove(d, 2.0, 1.0);
+        println(Object.keys(d));
+    "#);
+
+    assert_eq!(out, "one\nzero\nzero\nthird\nnull\ntwo\n[\n    0,\n    0.3,\n]\n");
+}
+
+#[test]
+fn nan_is_not_a_key() {
+    let out = output(r#"
+        let d = {};
+        try {
+            d[0.0 / 0.0] = 1;
+        } expect {
+            println(error);
+        }
+        try {
+            println(d[0.0 / 0.0]);
+        } expect {
+            println(error);
+        }
+        println(Object.keys(d).len());
+    "#);
+
+    assert_eq!(out, "Cannot use NaN as a key of an object.\nCannot use NaN as a key of an object.\n0\n");
+}
+
+// The entries filtered and mapped as arrays are built back into a dict.
+#[test]
+fn from_entries_rebuilds_the_filtered_entries() {
+    let out = output(r#"
+        let prices = {"apple": 3, "melon": 12, "pear": 4, "fig": 20};
+        let cheap = Object.fromEntries(Object.entries(prices)
+            .filter(func (entry) { return entry[1] < 10; })
+            .map(func (entry) { return [entry[0], entry[1] * 2]; }));
+
+        println(window.diff(cheap, {"apple": 6, "pear": 8}).len(), Object.keys(cheap));
+
+        let keys = Object.fromEntries([["x", 1], [2, "two"], [2.0, "again"], {"key": true, "value": 3}]);
+        println(keys.x, keys[2], keys[true], Object.keys(keys).len());
+    "#);
+
+    assert_eq!(out, "0\n[\n    \"apple\",\n    \"pear\",\n]\n1\nagain\n3\n3\n");
+}
+
+#[test]
+fn from_entries_names_the_index_of_a_bad_pair() {
+    let out = output(r#"
+        let calls = [
+            func () { return Object.fromEntries([["a", 1], ["b"]]); },
+            func () { return Object.fromEntries([1]); },
+            func () { return Object.fromEntries("x"); }
+        ];
+
+        for call in calls {
+            try {
+                call();
+            } expect {
+                println(error);
+            }
+        }
+    "#);
+
+    assert_eq!(out, concat!(
+        "[Object.fromEntries]: Expected the entry at index 1 to be a [key, value] pair but found array.\n",
+        "[Object.fromEntries]: Expected the entry at index 0 to be a [key, value] pair but found number.\n",
+        "[Object.fromEntries]: expected array at argument 1, got string.\n"
+    ));
+}
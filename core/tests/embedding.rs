@@ -0,0 +1,86 @@
+mod common;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use dashscript_core::{AST, BytecodeCompiler, Vm, Value, RuntimeError};
+use common::Capture;
+
+fn start(source: &str) -> Vm {
+    let build = AST::compile(&String::from("embed.ds"), &source.to_string()).unwrap();
+    let compiler = BytecodeCompiler::new(build).unwrap();
+
+    // Calls the function given as the first argument with the rest of them, which
+    // goes from the script back into the host and then into the script again.
+    Vm::builder(compiler.into(), HashMap::new(), PathBuf::from("embed.ds"))
+        .stdout(Box::new(Capture::default()))
+        .native("host", |vm, args| match args {
+            [function, rest @ ..] => vm.call(*function, rest),
+            [] => Ok(Value::Null)
+        })
+        .build()
+        .unwrap()
+}
+
+fn int(value: Result<Value, RuntimeError>) -> isize {
+    match value {
+        Ok(Value::Int(int)) => int,
+        other => panic!("Expected an int but got {:?}", other.map(|value| value.to_string()))
+    }
+}
+
+#[test]
+fn calls_see_the_state_left_by_earlier_calls() {
+    let mut vm = start(r#"
+        let count = 0;
+        func increment(by) { count += by; return count; }
+    "#);
+
+    let increment = vm.global("increment").unwrap();
+    assert_eq!(int(vm.call(increment, &[Value::Int(1)])), 1);
+    assert_eq!(int(vm.call(increment, &[Value::Int(2)])), 3);
+    assert_eq!(vm.call_depth(), 1);
+}
+
+#[test]
+fn natives_can_call_back_into_the_script() {
+    let mut vm = start(r#"
+        func double(x) { return x * 2; }
+        func outer(x) { return host(double, x) + 1; }
+    "#);
+
+    // Host, script, host and script again.
+    let outer = vm.global("outer").unwrap();
+    assert_eq!(int(vm.call(outer, &[Value::Int(20)])), 41);
+    assert_eq!(vm.call_depth(), 1);
+}
+
+#[test]
+fn failed_calls_leave_the_frames_as_they_were() {
+    let mut vm = start(r#"
+        func fail(x) { throw("failed"); return x; }
+        func outer(x) { return host(fail, x); }
+        func ok(x) { return x; }
+    "#);
+
+    let outer = vm.global("outer").unwrap();
+    assert!(vm.call(outer, &[Value::Int(1)]).is_err());
+    assert_eq!(vm.call_depth(), 1);
+
+    let ok = vm.global("ok").unwrap();
+    assert_eq!(int(vm.call(ok, &[Value::Int(7)])), 7);
+}
+
+#[test]
+fn reset_frames_keeps_the_globals() {
+    let mut vm = start(r#"
+        let total = 10;
+        func add(x) { total += x; return total; }
+    "#);
+
+    let add = vm.global("add").unwrap();
+    assert_eq!(int(vm.call(add, &[Value::Int(5)])), 15);
+
+    vm.reset_frames();
+    assert_eq!(vm.call_depth(), 1);
+    assert_eq!(int(vm.call(add, &[Value::Int(5)])), 20);
+}
@@ -0,0 +1,54 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+use common::{run_with, output};
+
+// A dotenv file in a directory of its own. The variables are set on the process
+// which runs every test, so each test uses names of its own.
+fn dotenv(test: &str, source: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(test).join(".env");
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, source).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+#[test]
+fn load_sets_the_missing_variables_and_returns_them() {
+    let path = dotenv("env-load", "export LOAD_NEW=\"a\\nb\" # comment\nLOAD_KEPT=file\n");
+    let result = run_with(&format!(r#"
+        window.env.set("LOAD_KEPT", "before");
+        let loaded = window.env.load("{}");
+        println(loaded);
+        println(window.env.get("LOAD_NEW") == "a\nb", window.env.get("LOAD_KEPT"));
+    "#, path), &["use-read"]);
+
+    assert!(result.error.is_none(), "{}", result.err);
+    assert_eq!(result.out, "{\n    \"LOAD_NEW\": \"a\nb\",\n}\ntrue\nbefore\n");
+}
+
+#[test]
+fn override_replaces_the_variables_which_are_set() {
+    let path = dotenv("env-override", "OVERRIDE_KEPT=file\nOVERRIDE_KEPT=last\n");
+    let result = run_with(&format!(r#"
+        window.env.set("OVERRIDE_KEPT", "before");
+        println(window.env.load("{}", {{ override: true }})["OVERRIDE_KEPT"]);
+        println(window.env.get("OVERRIDE_KEPT"));
+    "#, path), &["use-read"]);
+
+    assert!(result.error.is_none(), "{}", result.err);
+    assert_eq!(result.out, "last\nlast\n");
+}
+
+#[test]
+fn malformed_files_throw_with_the_line() {
+    let path = dotenv("env-malformed", "MALFORMED_OK=1\nno equals\n");
+    let result = run_with(&format!("window.env.load(\"{}\");", path), &["use-read"]);
+
+    assert_eq!(result.error().message(), format!("[window.env.load]: Expected KEY=VALUE at line 2 of \"{}\".", path));
+}
+
+#[test]
+fn load_needs_the_read_permission() {
+    assert_eq!(output("println(window.env.load);"), "null\n");
+}
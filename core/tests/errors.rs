@@ -0,0 +1,90 @@
+mod common;
+
+use std::error::Error;
+use dashscript_core::{AST, RuntimeError, RuntimeErrorKind};
+use common::run;
+
+fn fails() -> Result<(), Box<dyn Error>> {
+    let result = run("let x = 1; x();");
+    Err(result.error().clone())?;
+    Ok(())
+}
+
+#[test]
+fn errors_convert_into_a_boxed_error() {
+    let error = fails().unwrap_err();
+    assert!(error.to_string().starts_with("You cannot call a number."));
+    assert!(error.downcast_ref::<RuntimeError>().is_some());
+}
+
+#[test]
+fn kinds_are_derived_from_the_messages() {
+    let cases = [
+        ("TypeError: Expected a string.", RuntimeErrorKind::TypeError),
+        ("You cannot call a number.", RuntimeErrorKind::TypeError),
+        ("Cannot call a null.", RuntimeErrorKind::TypeError),
+        ("ReferenceError: The global \"Math\" is not installed.", RuntimeErrorKind::ReferenceError),
+        ("Cannot assign to a constant.", RuntimeErrorKind::AssignmentError),
+        ("Cannot redeclare the global \"x\".", RuntimeErrorKind::AssignmentError),
+        ("[VM]: The stack is empty.", RuntimeErrorKind::InternalError),
+        ("[Array.map]: expected function at argument 1, got int.", RuntimeErrorKind::ArgumentError),
+        ("[window.fs.readText]: Reading requires the `--use-read` flag.", RuntimeErrorKind::PermissionDenied),
+        ("Something else went wrong.", RuntimeErrorKind::Error)
+    ];
+
+    for (message, kind) in cases.iter() {
+        assert_eq!(RuntimeErrorKind::of(message), *kind, "{}", message);
+    }
+}
+
+#[test]
+fn errors_of_the_scripts_carry_their_kind() {
+    assert_eq!(run("let x = 1; x();").error().kind(), RuntimeErrorKind::TypeError);
+    assert_eq!(run("window.fs.readBytes(\"/tmp/x\");").error().kind(), RuntimeErrorKind::TypeError);
+    assert_eq!(run("Math.PI = 3;").error().kind(), RuntimeErrorKind::AssignmentError);
+}
+
+#[test]
+fn errors_in_callbacks_are_the_cause() {
+    let result = run("[1, 2].map(func (x) { throw(\"boom\"); return x; });");
+    let error = result.error();
+    let cause = error.cause().expect("The error of the callback is the cause.");
+
+    assert!(error.message().starts_with("[Array.map]:"));
+    assert!(error.source().is_some());
+    assert!(error.to_string().contains("\ncaused by: "));
+    assert_eq!(cause.cause().map(RuntimeError::message), None);
+}
+
+// Each condition of a chain reports its own line instead of the one of the `if`.
+#[test]
+fn errors_in_elif_chains_point_at_their_branch() {
+    let source = |x: usize| format!(r#"
+        let x = {};
+        if (x == 1 ? missing() : false) {{
+            println("one");
+        }} elif (x == 2) {{
+            println("two");
+        }} elif (x == 3 ? missing() : false) {{
+            println("three");
+        }} else {{
+            missing();
+        }}
+    "#, x);
+
+    let lines: Vec<_> = [1, 3, 4].iter().map(|&x| run(&source(x)).error().line()).collect();
+    assert_eq!(lines, vec![Some(3), Some(7), Some(10)]);
+    assert_eq!(run(&source(2)).out, "two\n");
+}
+
+// The columns count chars and the lines of both endings are the same.
+#[test]
+fn positions_count_chars_across_line_endings() {
+    let source = String::from("let a = \"\u{e9}\u{e9}\";\r\nlet b = ;\r\n");
+    let errors = AST::compile(&String::from("test.ds"), &source).err().expect("The script does not parse.");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].position(), (String::from("test.ds"), 2, 9));
+    assert_eq!(errors[0].body(), ";");
+
+    assert_eq!(run("let a = \"\u{e9}\";\r\n\r\nlet b = null;\r\nb();\r\n").error().to_string().lines().next(), Some("You cannot call a null. (line 4)"));
+}
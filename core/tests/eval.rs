@@ -0,0 +1,55 @@
+mod common;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use dashscript_core::{AST, BytecodeCompiler, Vm, Value};
+use dashscript_core::core::window;
+use common::{compile_errors, Capture};
+
+// The result of the code compiled like the one given to `--eval`, as shown by the
+// cli which also ends the code with a semicolon.
+fn eval(source: &str) -> Option<String> {
+    let build = AST::compile(&String::from("eval.ds"), &format!("{};", source)).unwrap();
+    let compiler = BytecodeCompiler::new_with_result(build).unwrap();
+    let vm = Vm::builder(compiler.into(), HashMap::new(), PathBuf::from("eval.ds"))
+        .stdout(Box::new(Capture::default()))
+        .build()
+        .unwrap();
+
+    match vm.result() {
+        Value::Null => None,
+        result => Some(window::inspect(&vm, &result))
+    }
+}
+
+#[test]
+fn the_last_expression_is_the_result() {
+    assert_eq!(eval("2 + 2").as_deref(), Some("4"));
+    assert_eq!(eval("let x = 2; x * 1.5").as_deref(), Some("3"));
+    assert_eq!(eval("\"a\" + \"b\"").as_deref(), Some("ab"));
+}
+
+#[test]
+fn statements_without_a_value_have_no_result() {
+    assert_eq!(eval("let x = 2;"), None);
+    assert_eq!(eval("println(1);"), None);
+}
+
+#[test]
+fn results_are_shown_like_window_inspect() {
+    assert_eq!(eval("func f() { return 1; } f").as_deref(), Some("[Function f]"));
+}
+
+#[test]
+fn a_top_level_return_overrides_the_last_expression() {
+    assert_eq!(eval("if true { return 3; } 4").as_deref(), Some("3"));
+    assert_eq!(eval("if false { return 3; } 4").as_deref(), Some("4"));
+}
+
+#[test]
+fn a_top_level_return_is_only_allowed_in_eval() {
+    let errors = compile_errors("return 1;");
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("outside of a function"), "{}", errors[0]);
+    assert!(compile_errors("func f() { return 1; }").is_empty());
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{output, run, run_with};
+
+const GATED: [&str; 6] = ["fs", "fsWrite", "childProcess", "crypto", "signal", "memory"];
+
+#[test]
+fn permission_gated_modules_report_false_without_their_permission() {
+    for name in GATED.iter() {
+        let out = output(&format!("println(window.features.{});", name));
+        assert_eq!(out, "false\n", "window.features.{}", name);
+    }
+}
+
+#[test]
+fn permission_gated_modules_report_true_with_their_permission() {
+    let cases = [
+        ("fs", "use-read"),
+        ("fsWrite", "use-write"),
+        ("childProcess", "use-child-process"),
+        ("crypto", "use-crypto"),
+        ("signal", "use-signal"),
+        ("memory", "use-memory")
+    ];
+
+    for (name, flag) in cases.iter() {
+        let source = format!("println(window.features.{});", name);
+        let result = run_with(&source, &[flag]);
+        assert_eq!(result.out, "true\n", "window.features.{} with --{}", name, flag);
+    }
+}
+
+#[test]
+fn unsupported_features_report_false() {
+    assert_eq!(output("println(window.features.asyncAwait);"), "false\n");
+    assert_eq!(output("println(window.features.optionalChaining);"), "false\n");
+    assert_eq!(output("println(window.features.forLoops);"), "true\n");
+}
+
+#[test]
+fn version_follows_the_crate() {
+    assert_eq!(output("println(window.version);"), format!("{}\n", env!("CARGO_PKG_VERSION")));
+}
+
+#[test]
+fn require_feature_names_the_missing_feature() {
+    let result = run("window.requireFeature(\"crypto\");");
+    let message = result.error().to_string();
+    assert!(message.contains("\"crypto\""), "{}", message);
+    assert!(message.contains(env!("CARGO_PKG_VERSION")), "{}", message);
+
+    let result = run("window.requireFeature(\"asyncAwait\");");
+    assert!(result.error().to_string().contains("is not supported"));
+
+    assert_eq!(output("window.requireFeature(\"json\"); println(\"ok\");"), "ok\n");
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use dashscript_core::core::format;
+use common::output;
+
+#[test]
+fn numbers() {
+    let rows: &[(f64, usize, &str, &str, &str)] = &[
+        (1234567.891, 2, ",", ".", "1,234,567.89"),
+        (1234567.891, 0, ",", ".", "1,234,568"),
+        (1234567.891, 1, ".", ",", "1.234.567,9"),
+        (1234567.891, 2, "", ".", "1234567.89"),
+        (1234567.891, 2, " ", ",", "1 234 567,89"),
+        (0.0, 2, ",", ".", "0.00"),
+        (999.0, 0, ",", ".", "999"),
+        (1000.0, 0, ",", ".", "1,000"),
+        (999.995, 2, ",", ".", "1,000.00"),
+        (1.005, 2, ",", ".", "1.01"),
+        (2.5, 0, ",", ".", "3"),
+        (-2.5, 0, ",", ".", "-3"),
+        (0.125, 2, ",", ".", "0.13"),
+        (-1234.5, 1, ",", ".", "-1,234.5"),
+        (-0.001, 2, ",", ".", "0.00"),
+        (0.5, 3, ",", ".", "0.500"),
+        (1e21, 2, ",", ".", "1.00e21"),
+        (-1.5e22, 1, ",", ".", "-1.5e22"),
+        (1.5e22, 1, ",", ",", "1,5e22"),
+        (f64::NAN, 2, ",", ".", "NaN"),
+        (f64::INFINITY, 2, ",", ".", "inf"),
+        (f64::NEG_INFINITY, 2, ",", ".", "-inf")
+    ];
+
+    for &(number, decimals, thousands_sep, decimal_sep, expected) in rows {
+        assert_eq!(format::number(number, decimals, thousands_sep, decimal_sep), expected, "{} with {} decimals", number, decimals);
+    }
+}
+
+#[test]
+fn percents() {
+    let rows: &[(f64, usize, &str)] = &[
+        (0.5, 0, "50%"),
+        (0.1234, 1, "12.3%"),
+        (0.12345, 2, "12.35%"),
+        (1.0, 0, "100%"),
+        (12.5, 0, "1,250%"),
+        (-0.25, 0, "-25%"),
+        (0.0, 1, "0.0%")
+    ];
+
+    for &(number, decimals, expected) in rows {
+        assert_eq!(format::percent(number, decimals), expected, "{} with {} decimals", number, decimals);
+    }
+}
+
+#[test]
+fn byte_sizes() {
+    let rows: &[(f64, &str)] = &[
+        (0.0, "0 B"),
+        (1023.0, "1023 B"),
+        (1024.0, "1.0 KiB"),
+        (1536.0, "1.5 KiB"),
+        (1258291.0, "1.2 MiB"),
+        (1048575.0, "1.0 MiB"),
+        (1073741824.0, "1.0 GiB"),
+        (-2048.0, "-2.0 KiB")
+    ];
+
+    for &(bytes, expected) in rows {
+        assert_eq!(format::bytes(bytes), expected, "{} bytes", bytes);
+    }
+}
+
+#[test]
+fn durations() {
+    let rows: &[(f64, &str)] = &[
+        (0.0, "0ms"),
+        (999.0, "999ms"),
+        (999.6, "1s"),
+        (1000.0, "1s"),
+        (1500.0, "1.5s"),
+        (59960.0, "1m"),
+        (123500.0, "2m 3.5s"),
+        (3600000.0, "1h"),
+        (3723000.0, "1h 2m 3s"),
+        (90000000.0, "1d 1h"),
+        (-1500.0, "-1.5s")
+    ];
+
+    for &(milliseconds, expected) in rows {
+        assert_eq!(format::duration(milliseconds), expected, "{}ms", milliseconds);
+    }
+}
+
+#[test]
+fn options_of_the_natives() {
+    let out = output(r#"
+        println(window.format.number(1234567.891));
+        println(window.format.number(1234567.891, { decimals: 0, thousandsSep: "." }));
+        println(window.format.number(-1234.5, { decimals: 1, thousandsSep: " ", decimalSep: "," }));
+        println(window.format.percent(0.256, 1));
+        println(window.format.bytes(1536));
+        println(window.format.duration(123500));
+    "#);
+
+    assert_eq!(out, "1,234,567.89\n1.234.568\n-1 234,5\n25.6%\n1.5 KiB\n2m 3.5s\n");
+}
@@ -0,0 +1,129 @@
+mod common;
+
+use common::output;
+
+// The scripts allocate well past the first threshold of the collector, so the
+// objects they keep must survive the collections in between.
+#[test]
+fn reachable_objects_survive_the_collections() {
+    let out = output(r#"
+        let kept = { list: [], names: {} };
+        let numbers = range(0, 3);
+        for i in range(0, 3000) {
+            let name = "item" + i;
+            let index = i;
+            kept.list.push([name, { index: index }]);
+            kept.names[name] = func () { return index; };
+        }
+
+        println(kept.list.len(), kept.list[2999][0], kept.list[1234][1].index);
+        println(kept.names["item42"]());
+
+        for n in numbers {
+            println(n);
+        }
+    "#);
+
+    assert_eq!(out, "3000\nitem2999\n1234\n42\n0\n1\n2\n");
+}
+
+#[test]
+fn deferred_functions_survive_the_collections() {
+    let out = output(r#"
+        func work() {
+            defer println("first");
+            defer println("second");
+            let garbage = [];
+            for i in range(0, 3000) {
+                garbage.push("x" + i);
+            }
+            return garbage.len();
+        }
+
+        println(work());
+    "#);
+
+    assert_eq!(out, "second\nfirst\n3000\n");
+}
+
+#[test]
+fn built_arrays_survive_the_collections_of_their_callbacks() {
+    let out = output(r#"
+        let items = [];
+        for i in range(0, 300) {
+            items.push([i % 3, "v" + i]);
+        }
+
+        let groups = items.groupBy(func (item) {
+            let garbage = [];
+            for i in range(0, 20) {
+                garbage.push({ key: "k" + i });
+            }
+            return "g" + item[0];
+        });
+
+        let garbage = [];
+        for i in range(0, 3000) {
+            garbage.push(["x" + i]);
+        }
+
+        println(groups.g0.len(), groups.g1[0][1], groups.g2[99][1]);
+    "#);
+
+    assert_eq!(out, "100\nv1\nv299\n");
+}
+
+#[test]
+fn unique_chunk_and_zip_results_survive_the_collections() {
+    let out = output(r#"
+        let values = [];
+        for i in range(0, 600) {
+            values.push(["v" + (i % 200)]);
+        }
+
+        let unique = values.unique();
+        let chunks = values.chunk(7);
+        let pairs = values.zip(values.map(func (value) { return value[0] + "!"; }));
+
+        let garbage = [];
+        for i in range(0, 3000) {
+            garbage.push({ value: "x" + i });
+        }
+
+        println(unique.len(), unique[199][0], chunks.len(), chunks[85][4][0]);
+        println(pairs.len(), pairs[599][0][0], pairs[599][1]);
+    "#);
+
+    assert_eq!(out, "200\nv199\n86\nv199\n600\nv199\nv199!\n");
+}
+
+// The results of the callbacks are kept on the stack while the callbacks of the
+// next items run. The collections happen before each instruction with the
+// `stress_gc` feature, which makes these fail at once when a value is lost.
+#[test]
+fn callback_results_survive_the_collections_of_the_next_callbacks() {
+    let out = output(r#"
+        let xs = [];
+        for i in range(0, 30) {
+            xs.push(i);
+        }
+
+        let strings = xs.map(func (x) { return x + ""; });
+        let pairs = xs.flatMap(func (x) { return ["a" + x, { value: "b" + x }]; });
+        let collected = Iterator.collect(xs, func (x) { return [x + "!"]; });
+        let sorted = xs.map(func (x) { return "s" + (29 - x); }).sort(func (a, b) {
+            let garbage = [a + b, { key: b + a }];
+            return a.len() - b.len();
+        });
+
+        let garbage = [];
+        for i in range(0, 2000) {
+            garbage.push({ value: "x" + i });
+        }
+
+        println(strings[0] + strings[29], pairs.len(), pairs[58] + pairs[59].value, collected[29][0]);
+        println(sorted.len(), sorted[0], sorted[29]);
+    "#);
+
+    assert_eq!(out, "029\n60\na29b29\n29!\n30\ns9\ns10\n");
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use dashscript_core::{AST, BytecodeCompiler, CompileOptions, RuntimeErrorKind, TinyString};
+use dashscript_core::core::installer::{self, Selection, NAMESPACES};
+use std::collections::HashMap;
+use common::{compile_errors, run_with};
+
+fn compiles_with_globals(source: &str, globals: &str) -> bool {
+    let mut flags = HashMap::new();
+    flags.insert(TinyString::new(b"global"), TinyString::new(globals.as_bytes()));
+
+    let build = AST::compile(&String::from("test.ds"), &source.to_string()).unwrap();
+    let options = CompileOptions { globals: Selection::of(&flags), ..Default::default() };
+    BytecodeCompiler::with_options(build, options).is_ok()
+}
+
+#[test]
+fn globals_which_are_not_installed_are_reference_errors() {
+    let result = run_with("println(1); Math.abs(1);", &["global=print"]);
+    let error = result.error();
+
+    assert_eq!(result.out, "1\n");
+    assert_eq!(error.kind(), RuntimeErrorKind::ReferenceError);
+    assert!(error.message().starts_with("ReferenceError: The global \"Math\" is not installed."), "{}", error);
+    assert!(error.message().contains("--global math"), "{}", error);
+}
+
+#[test]
+fn only_the_installed_globals_cannot_be_redeclared() {
+    assert!(compiles_with_globals("let Math = 1;", "print"));
+    assert!(!compiles_with_globals("let Math = 1;", "math"));
+    assert!(!compiles_with_globals("let println = 1;", "print"));
+    assert!(compile_errors("let Math = 1;").len() == 1);
+}
+
+#[test]
+fn globals_left_out_can_be_declared_by_the_scripts() {
+    let result = run_with("let Math = 2; println(Math);", &["global=print"]);
+    assert!(result.error.is_none());
+    assert_eq!(result.out, "2\n");
+}
+
+// The names blocked at the top level come from the registry, so each of them has
+// to be a global which a vm with every group actually has.
+#[test]
+fn every_blocked_name_is_a_global() {
+    let names = installer::globals(&Selection::default());
+    for name in names.iter() {
+        let result = run_with(&format!("println({} == null);", name), &["use-memory"]);
+        assert_eq!(result.out, "false\n", "{} is blocked but is not a global", name);
+    }
+}
+
+#[test]
+fn every_registered_global_is_blocked_at_the_top_level() {
+    let groups = installer::INSTALLERS.iter().flat_map(|installer| installer.globals.iter());
+    for name in NAMESPACES.iter().chain(groups) {
+        let errors = compile_errors(&format!("let {} = 1;", name));
+        assert_eq!(errors.len(), 1, "{} can be redeclared", name);
+
+        let errors = compile_errors(&format!("if (true) {{ let {} = 1; }}", name));
+        assert!(errors.is_empty(), "{} cannot be shadowed inside a block", name);
+    }
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{output, run};
+
+// Only the values needed by `take` go through the stages, however long the
+// array is.
+#[test]
+fn lazy_chains_call_the_stages_only_for_the_taken_values() {
+    let out = output("
+        let big = [].resize(1000000, 1);
+        let calls = 0;
+        let taken = big.iter()
+            .enumerate()
+            .map(func (pair) { calls += 1; return pair[0]; })
+            .filter(func (n) { calls += 1; return n >= 5; })
+            .map(func (n) { calls += 1; return n * 10; })
+            .take(10)
+            .collect();
+        println(taken.join(\" \"));
+        println(calls);
+    ");
+
+    assert_eq!(out, "50 60 70 80 90 100 110 120 130 140 \n40\n");
+}
+
+#[test]
+fn terminal_operations_consume_the_chain() {
+    let out = output("
+        println([1, 2, 3, 4, 5].iter().skip(2).count());
+        println([1, 2, 3].iter().reduce(func (sum, n) { return sum + n; }, 10));
+        [7, 8].iter().forEach(func (value, index) { println(value, index); });
+        println(Iterator.collect([1, 2, 3], func (n) { return n * 2; }).join(\" \"));
+    ");
+
+    assert_eq!(out, "3\n16\n7\n0\n8\n1\n2 4 6 \n");
+}
+
+#[test]
+fn adapters_are_iterated_by_for_loops_once() {
+    let out = output("
+        let plusOne = [1, 2, 3].iter().map(func (n) { return n + 1; });
+        for value in plusOne {
+            print(value);
+        }
+        println(\"\");
+        println(plusOne.next());
+    ");
+
+    assert_eq!(out, "234\nnull\n");
+}
+
+#[test]
+fn adapters_check_their_arguments() {
+    assert_eq!(run("[1].iter().take(-1);").error().message(), "[Iterator.take]: expected positive int at argument 1, got int.");
+    assert_eq!(run("[1].iter().map(1);").error().message(), "[Iterator.map]: expected function at argument 1, got int.");
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use common::run_with;
+
+// The seed gives the virtual clock, so the timestamps are always the same.
+const SOURCE: &str = r#"
+    println(window.log.level);
+    window.log.debug("hidden", 1);
+    window.log.info("started", 1, [2]);
+    window.log.warn("careful");
+    window.log.level = "error";
+    window.log.warn("hidden");
+    window.log.error("failed", true);
+"#;
+
+#[test]
+fn lines_have_the_time_the_level_and_the_values() {
+    let output = run_with(SOURCE, &["seed=1"]);
+    assert!(output.error.is_none(), "{}", output.err);
+
+    assert_eq!(output.out, "info\n");
+    assert_eq!(output.err, concat!(
+        "2000-01-01T00:00:00.000Z INFO  started 1 [\n    2,\n]\n",
+        "2000-01-01T00:00:00.000Z WARN  careful\n",
+        "2000-01-01T00:00:00.000Z ERROR failed true\n"
+    ));
+}
+
+#[test]
+fn the_level_flag_filters_the_lines() {
+    let output = run_with(SOURCE, &["seed=1", "log-level=debug"]);
+    assert_eq!(output.out, "debug\n");
+    assert!(output.err.starts_with("2000-01-01T00:00:00.000Z DEBUG hidden 1\n"), "{}", output.err);
+
+    let output = run_with(SOURCE, &["seed=1", "log-level=error"]);
+    assert_eq!(output.out, "error\n");
+    assert_eq!(output.err, "2000-01-01T00:00:00.000Z ERROR failed true\n");
+}
+
+#[test]
+fn unknown_levels_are_errors() {
+    let output = run_with("window.log.level = \"loud\";\nwindow.log.info(1);", &[]);
+    assert_eq!(output.error().to_string().lines().next(), Some("[log.info]: Unknown level \"loud\". Expected one of debug, info, warn, error. (line 2)"));
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use common::output;
+
+#[test]
+fn break_gives_the_value_of_a_while_loop() {
+    let out = output(r#"
+        let items = [3, 8, 5];
+        let i = 0;
+        let found = while i < items.len() {
+            if (items[i] > 4) {
+                break items[i];
+            }
+            i += 1;
+        } else {
+            println("not found");
+        };
+
+        println(found, i);
+    "#);
+
+    assert_eq!(out, "8\n1\n");
+}
+
+#[test]
+fn else_runs_when_the_loop_ends_without_a_break() {
+    let out = output(r#"
+        let i = 0;
+        let value = while i < 3 {
+            i += 1;
+        } else {
+            println("while completed");
+        };
+        println(value);
+
+        for x in [1, 2] {
+            if (x == 5) {
+                break;
+            }
+        } else {
+            println("for completed");
+        }
+
+        for x in [1, 2] {
+            break;
+        } else {
+            println("unreachable");
+        }
+
+        println(while false {});
+    "#);
+
+    assert_eq!(out, "while completed\nnull\nfor completed\nnull\n");
+}
+
+#[test]
+fn nested_loops_break_with_their_own_values() {
+    let out = output(r#"
+        let outer = for a in [1, 2, 3] {
+            let inner = for b in [1, 2, 3] {
+                if (b == a) {
+                    break b * 10;
+                }
+            };
+
+            if (a == 2) {
+                break a * 100 + inner;
+            }
+        } else {
+            println("unreachable");
+        };
+
+        println(outer);
+    "#);
+
+    assert_eq!(out, "220\n");
+}
+
+// The iterators of the loops are not left on the stack by a return.
+#[test]
+fn returns_from_loops_keep_the_stack_of_the_caller() {
+    let out = output(r#"
+        func first(items) {
+            for item in items {
+                if (item > 1) {
+                    return item;
+                }
+            }
+            return null;
+        }
+
+        let a = 1;
+        let results = [first([1, 2, 3]), first([5]), first([])];
+        println(a, results[0], results[1], results[2]);
+    "#);
+
+    assert_eq!(out, "1\n2\n5\nnull\n");
+}
+
+#[test]
+fn two_bindings_give_the_entries_in_insertion_order() {
+    let out = output(r#"
+        let d = {"zeta": 1, "alpha": 2, "mid": 3, "beta": 4};
+        d["first"] = 5;
+        Object.remove(d, "alpha");
+        for key, value in d {
+            print(key, "=", value, " ");
+        }
+        for index, item in ["x", "y"] {
+            print(index, item, " ");
+        }
+        for index, character in "ab" {
+            print(index, character, " ");
+        }
+        println("");
+    "#);
+
+    assert_eq!(out, "zeta=1 mid=3 beta=4 first=5 0x 1y 0a 1b \n");
+}
+
+// The entries are read when the loop starts, so the changes made by the body
+// are not seen by the loop.
+#[test]
+fn dicts_changed_in_the_loop_are_iterated_as_a_snapshot() {
+    let out = output(r#"
+        let d = {"a": 1, "b": 2};
+        for key, value in d {
+            d["c" + key] = value * 10;
+            d["b"] = 20;
+            print(key, value, " ");
+        }
+        println("");
+        println(Object.keys(d).len(), d["ca"], d["cb"]);
+    "#);
+
+    assert_eq!(out, "a1 b2 \n4\n10\n20\n");
+}
+
+// The slots of the iterator and of the bindings are given back when a loop
+// ends, so a function is not limited by the count of the loops in it.
+#[test]
+fn loops_give_their_slots_back_when_they_end() {
+    let loops = "for key, value in {\"a\": 1} { total += value; }\n".repeat(200);
+    let out = output(&format!("func count() {{\nlet total = 0;\n{}return total;\n}}\nprintln(count());", loops));
+
+    assert_eq!(out, "200\n");
+}
@@ -0,0 +1,139 @@
+mod common;
+
+use common::{output, run};
+
+#[test]
+fn statistics_of_arrays() {
+    let out = output(r#"
+        println(Math.sum([1, 2, 3]), Math.sum([1, 2.5]), Math.mean([1, 2, 3, 4]));
+        println(Math.median([3, 1, 2]), Math.median([4, 1, 3, 2]));
+        println(Math.stdev([2, 4, 4, 4, 5, 5, 7, 9]), Math.stdev([1, 2, 3, 4], {"sample": true}));
+        println(Math.max([3, 9, 2]), Math.min([3, 9, 2]), Math.max(3, 9, 2), Math.min(3, 9, 2));
+        println(Math.sum([1, "a", 2, null], {"ignoreNonNumbers": true}));
+    "#);
+
+    assert_eq!(out, "6\n3.5\n2.5\n2\n2.5\n2\n1.2909944487358056\n9\n2\n9\n2\n3\n");
+}
+
+#[test]
+fn empty_arrays_and_non_numbers_are_errors() {
+    let out = output(r#"
+        let calls = [
+            func () { return Math.sum([]); },
+            func () { return Math.mean([1, "a"]); },
+            func () { return Math.median([], {"ignoreNonNumbers": true}); },
+            func () { return Math.max([]); },
+            func () { return Math.stdev([1], {"sample": true}); }
+        ];
+
+        for call in calls {
+            try {
+                println(call());
+            } expect {
+                println(error);
+            }
+        }
+    "#);
+
+    assert_eq!(out, concat!(
+        "[Math.sum]: Expected a non-empty array of numbers.\n",
+        "[Math.mean]: Expected an array of numbers but found string.\n",
+        "[Math.median]: Expected a non-empty array of numbers.\n",
+        "[Math.max]: Expected a non-empty array.\n",
+        "[Math.stdev]: Expected at least 2 numbers for the sample standard deviation.\n"
+    ));
+}
+
+// A xorshift generator so that the failing arrays can be reproduced from the seed.
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, bound: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % bound
+    }
+}
+
+// The results of the natives for random arrays are compared with the ones of the
+// plain definitions of the statistics.
+#[test]
+fn statistics_match_their_definitions_for_random_arrays() {
+    let mut random = Random(0x2545F4914F6CDD1D);
+
+    for _ in 0..50 {
+        let numbers: Vec<f64> = (0..1 + random.below(20)).map(|_| random.below(2000) as f64 / 8.0 - 100.0).collect();
+        let array = numbers.iter().map(|number| format!("{:?}", number)).collect::<Vec<_>>().join(", ");
+        let out = output(&format!(
+            "let a = [{}];\nprintln(Math.sum(a), Math.mean(a), Math.median(a), Math.stdev(a), Math.max(a), Math.min(a));",
+            array
+        ));
+
+        let len = numbers.len() as f64;
+        let sum: f64 = numbers.iter().sum();
+        let mean = sum / len;
+        let mut sorted = numbers.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let middle = sorted.len() / 2;
+        let median = if sorted.len() % 2 == 0 { (sorted[middle - 1] + sorted[middle]) / 2.0 } else { sorted[middle] };
+        let stdev = (numbers.iter().map(|number| (number - mean).powi(2)).sum::<f64>() / len).sqrt();
+        let expected = [sum, mean, median, stdev, sorted[sorted.len() - 1], sorted[0]];
+
+        let results: Vec<f64> = out.lines().map(|line| line.parse().unwrap()).collect();
+        assert_eq!(results.len(), expected.len(), "{}", array);
+        for (result, expected) in results.iter().zip(expected.iter()) {
+            assert!((result - expected).abs() < 1e-9, "{} != {} for [{}]", result, expected, array);
+        }
+    }
+}
+
+#[test]
+fn gcd_and_lcm_take_the_magnitudes_of_the_integers() {
+    let out = output("
+        println(Math.gcd(12, 18), Math.gcd(-12, 18), Math.gcd(-12, -18), Math.gcd(0, 0), Math.gcd(9007199254740991, 2), Math.gcd(6.0, 4));
+        println(Math.lcm(4, 6), Math.lcm(-4, 6), Math.lcm(0, 5));
+    ");
+
+    assert_eq!(out, "6\n6\n6\n0\n1\n2\n12\n12\n0\n");
+}
+
+#[test]
+fn factorials_stop_at_the_safe_integers() {
+    assert_eq!(output("println(Math.factorial(0), Math.factorial(1), Math.factorial(18));"), "1\n1\n6402373705728000\n");
+    assert_eq!(run("Math.factorial(19);").error().message(), "[Math.factorial]: The factorial of 19 is above 2^53 - 1.");
+    assert_eq!(run("Math.factorial(-1);").error().message(), "[Math.factorial]: Expected a non-negative integer but found -1.");
+}
+
+#[test]
+fn integer_helpers_reject_the_unsafe_and_fractional_numbers() {
+    let expected = "Expected an integer between -2^53 and 2^53 exclusive but found";
+    assert_eq!(run("Math.gcd(9007199254740992, 2);").error().message(), format!("[Math.gcd]: {} 9007199254740992.", expected));
+    assert_eq!(run("Math.gcd(1.5, 2);").error().message(), format!("[Math.gcd]: {} 1.5.", expected));
+    assert_eq!(run("Math.factorial(2.5);").error().message(), format!("[Math.factorial]: {} 2.5.", expected));
+    assert_eq!(run("Math.lcm(9007199254740991, 9007199254740990);").error().message(), "[Math.lcm]: The result is above 2^53 - 1.");
+
+    let out = output("
+        println(Math.isInteger(2.0), Math.isInteger(2.5), Math.isInteger(\"2\"));
+        println(Math.isSafeInteger(9007199254740991), Math.isSafeInteger(9007199254740992));
+        println(Math.isSafeInteger(-9007199254740991), Math.isSafeInteger(-9007199254740992));
+    ");
+    assert_eq!(out, "true\nfalse\nfalse\ntrue\nfalse\ntrue\nfalse\n");
+}
+
+// The quotient is floored, so the remainder takes the sign of the divisor unlike
+// the one of `%`.
+#[test]
+fn divmod_floors_the_quotient() {
+    let out = output("
+        let cases = [[7, 2], [-7, 2], [7, -2], [-7, -2], [7.5, 2]];
+        for pair in cases {
+            let result = Math.divmod(pair[0], pair[1]);
+            println(result.join(\" \"));
+        }
+        println(-7 % 2);
+    ");
+
+    assert_eq!(out, "3 1 \n-4 1 \n-4 -1 \n3 -1 \n3 1.5 \n-1\n");
+    assert_eq!(run("Math.divmod(1, 0);").error().message(), "[Math.divmod]: Division by zero.");
+}
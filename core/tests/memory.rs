@@ -0,0 +1,100 @@
+mod common;
+
+use dashscript_core::RuntimeErrorKind;
+use dashscript_core::runtime::memory::parse_byte_size;
+use common::{output, run_with};
+
+fn memory(source: &str) -> String {
+    let result = run_with(source, &["use-memory"]);
+    if let Some(error) = result.error {
+        panic!("The script has thrown {}. Its output was:\n{}", error, result.out);
+    }
+
+    result.out
+}
+
+#[test]
+fn refs_read_and_write_their_slot() {
+    let out = memory(r#"
+        let r = Memory.ref(5);
+        println(Memory.deref(r));
+        Memory.setRef(r, 6);
+        println(Memory.getByPointer(r));
+        println(typeof(r));
+    "#);
+
+    assert_eq!(out, "5\n6\nref\n");
+}
+
+#[test]
+fn refs_cannot_be_forged_from_dicts() {
+    let out = memory(r#"
+        let r = Memory.ref(5);
+        try { Memory.deref({ slot: 0, generation: 0 }); } expect { println(error); }
+        try { Memory.setRef(0, 1); } expect { println(error); }
+        println(Memory.deref(r));
+    "#);
+
+    assert_eq!(out, "[Memory.deref]: expected ref at argument 1, got object.\n[Memory.setRef]: expected ref at argument 1, got int.\n5\n");
+}
+
+#[test]
+fn released_refs_are_stale_even_when_their_slot_is_reused() {
+    let out = memory(r#"
+        let old = Memory.ref(1);
+        Memory.release(old);
+        let new = Memory.ref(2);
+        println(old == new);
+        try { Memory.deref(old); } expect { println(error); }
+        println(Memory.deref(new));
+    "#);
+
+    assert_eq!(out, "false\n[Memory.deref]: The ref is stale since its slot has been released.\n2\n");
+}
+
+// A thousand strings of 1000 bytes are counted along with their objects and the
+// entries of the array which keeps them.
+#[test]
+fn approx_bytes_grow_with_the_retained_strings() {
+    let out = output(r#"
+        let before = window.memory.stats().approxBytes;
+        let kept = [];
+        let i = 0;
+        while i < 1000 {
+            kept.push("x".repeat(1000));
+            i += 1;
+        }
+        println(window.memory.stats().approxBytes - before, window.memory.stats().maxHeap);
+    "#);
+
+    let (grown, max_heap) = out.split_once('\n').unwrap();
+    let grown: usize = grown.parse().unwrap();
+    assert!((1_000_000..1_300_000).contains(&grown), "{}", grown);
+    assert_eq!(max_heap, "null\n");
+}
+
+#[test]
+fn exceeding_the_heap_limit_stops_the_script() {
+    let result = run_with(r#"
+        println(window.memory.stats().maxHeap);
+        let kept = [];
+        while true {
+            try { kept.push("x".repeat(1000)); } expect { error }
+        }
+    "#, &["max-heap=1mb"]);
+
+    assert_eq!(result.out, "1048576\n");
+    assert_eq!(result.error().kind(), RuntimeErrorKind::ResourceExhausted);
+    assert_eq!(result.error().message(), "[VM]: The scripts have exceeded the heap limit of 1048576 bytes.");
+}
+
+#[test]
+fn heap_sizes_are_parsed_in_powers_of_1024() {
+    assert_eq!(parse_byte_size("512"), Some(512));
+    assert_eq!(parse_byte_size("64kb"), Some(64 << 10));
+    assert_eq!(parse_byte_size(" 64MB "), Some(64 << 20));
+    assert_eq!(parse_byte_size("1g"), Some(1 << 30));
+    assert_eq!(parse_byte_size("12xb"), None);
+    assert_eq!(parse_byte_size("mb"), None);
+    assert_eq!(parse_byte_size(&format!("{}gb", usize::MAX)), None);
+}
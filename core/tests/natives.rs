@@ -0,0 +1,21 @@
+mod common;
+
+use common::{output, run};
+
+#[test]
+fn inspect_shows_the_names_of_the_functions() {
+    let out = output("
+        func add(a, b) { return a + b; }
+        let functions = [Math.floor, add, func (x) { return x; }, [1].map, window.env.get];
+        println(functions.map(func (function) { return window.inspect(function); }).join(\" \"));
+        println(window.inspect(1), window.inspect(\"floor\"));
+    ");
+
+    assert_eq!(out, "[Function floor] [Function add] [Function anonymous] [Function map] [Function get] \n1\nfloor\n");
+}
+
+#[test]
+fn frames_of_the_natives_carry_their_names() {
+    let error = run("[1].map(func (x) { x(); });").error().to_string();
+    assert!(error.contains("caused by: You cannot call a number. (line 1)\n    at anonymous\n    at map\n    at runtime"), "{}", error);
+}
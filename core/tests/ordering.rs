@@ -0,0 +1,99 @@
+mod common;
+
+use std::cmp::Ordering;
+use common::{output, run};
+
+// A xorshift generator so that the random arrays are the same on every run.
+struct Random(u64);
+
+impl Random {
+    fn below(&mut self, bound: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % bound
+    }
+}
+
+// The numbers as they are written in the scripts, their values and how they are
+// printed. A literal -0.0 is read as 0 so the negative zero is computed.
+const NUMBERS: [(&str, f64, &str); 9] = [
+    ("0.0 / 0.0", f64::NAN, "NaN"),
+    ("0.0 * -1", -0.0, "0"),
+    ("0.0", 0.0, "0"),
+    ("1.0 / 0.0", f64::INFINITY, "inf"),
+    ("-1.0 / 0.0", f64::NEG_INFINITY, "-inf"),
+    ("1", 1.0, "1"),
+    ("-2", -2.0, "-2"),
+    ("1.5", 1.5, "1.5"),
+    ("-3.25", -3.25, "-3.25")
+];
+
+fn nan_last(a: &f64, b: &f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        _ => a.total_cmp(b)
+    }
+}
+
+// Sorting an array of these numbers twice gives the order of `nan_last` both
+// times, with -0 before 0 which is told apart by dividing by it.
+#[test]
+fn sort_is_total_and_idempotent_for_random_numbers() {
+    let mut random = Random(0x9E3779B97F4A7C15);
+
+    for _ in 0..50 {
+        let picked: Vec<usize> = (0..1 + random.below(16)).map(|_| random.below(NUMBERS.len() as u64) as usize).collect();
+        let array = picked.iter().map(|index| NUMBERS[*index].0).collect::<Vec<_>>().join(", ");
+        let out = output(&format!(r#"
+            func text(items) {{
+                return items.map(func (item) {{ return item == 0.0 ? "" + (1 / item) : "" + item; }}).join(" ");
+            }}
+            let once = [{}].sort();
+            println(text(once));
+            println(text(once.map(func (item) {{ return item; }}).sort()));
+        "#, array));
+
+        let mut sorted: Vec<usize> = picked.clone();
+        sorted.sort_by(|a, b| nan_last(&NUMBERS[*a].1, &NUMBERS[*b].1));
+        let expected = sorted.iter().map(|index| match NUMBERS[*index].1 {
+            zero if zero == 0.0 => if zero.is_sign_negative() { "-inf" } else { "inf" },
+            _ => NUMBERS[*index].2
+        }).map(|text| format!("{} ", text)).collect::<String>();
+
+        assert_eq!(out, format!("{}\n{}\n", expected, expected), "[{}]", array);
+    }
+}
+
+#[test]
+fn min_max_and_median_put_nan_above_every_number() {
+    let out = output("
+        let nan = 0.0 / 0.0;
+        println(Math.max(1, nan), Math.min([2, nan, -1]), Math.median([nan, 1, 2]));
+        let negativeZero = 0.0 * -1;
+        println(1 / Math.max([negativeZero, 0.0]), 1 / Math.min(0.0, negativeZero));
+    ");
+
+    assert_eq!(out, "NaN\n-1\n2\ninf\n-inf\n");
+}
+
+#[test]
+fn values_of_other_types_are_compared_only_when_lenient() {
+    assert_eq!(run("Math.max(1, \"a\");").error().message(), "[Math.max]: Cannot compare number with string.");
+    assert_eq!(
+        run("[1, \"a\"].sort();").error().message(),
+        "[Array.sort]: Cannot compare number with string. Pass { lenient: true } to order the values by their types."
+    );
+
+    let out = output("
+        let items = [2, \"a\", 1];
+        try { items.sort(); } expect { error }
+        println(items.join(\" \"));
+        println([\"b\", null, \"a\"].sort().join(\" \"));
+        println([1, \"a\", null, true].sort({ lenient: true }).join(\" \"));
+    ");
+
+    assert_eq!(out, "2 a 1 \nnull a b \nnull true 1 a \n");
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use std::io::BufWriter;
+use common::{run_into, run_with, Capture};
+
+#[test]
+fn print_writes_the_exact_bytes_into_the_captured_stdout() {
+    let result = run_with(r#"print("a", 1); println("b", 2.5); print("c");"#, &[]);
+
+    assert_eq!(result.out.as_bytes(), b"a1b\n2.5\nc");
+    assert_eq!(result.err, "");
+}
+
+#[test]
+fn reported_errors_go_to_the_captured_stderr() {
+    let result = run_with(r#"let missing = null; window.onExit(func () { missing(); }); println("out");"#, &[]);
+
+    assert_eq!(result.out, "out\n");
+    assert!(result.err.starts_with("You cannot call a null. (line 1)"), "{:?}", result.err);
+}
+
+// The writers are buffered so that nothing reaches the capture until the vm
+// flushes them when it is dropped.
+#[test]
+fn buffered_output_is_flushed_when_the_vm_is_dropped() {
+    let (out, err) = (Capture::default(), Capture::default());
+    let error = run_into(
+        r#"println("before"); throw("failed");"#,
+        &[],
+        Box::new(BufWriter::with_capacity(1024, out.clone())),
+        Box::new(BufWriter::with_capacity(1024, err.clone()))
+    );
+
+    assert!(error.is_some());
+    assert_eq!(out.text(), "before\n");
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use common::{run_interactive, run_with_input};
+
+const SOURCE: &str = r#"
+    for i in range(0, 3) {
+        try {
+            window.cwd();
+            println("granted");
+        } expect {
+            println(error);
+        }
+    }
+"#;
+
+const PROMPT: &str = "Script wants read access to read the directory of the script. Allow? [y/n/a] ";
+const DENIED: &str = "[window.cwd]: The read access requires the `--use-read` flag.";
+
+#[test]
+fn yes_grants_the_permission_for_one_call() {
+    let output = run_interactive(SOURCE, &["prompt-permissions"], "y\nn\n");
+    assert_eq!(output.out, format!("{0}granted\n{0}{1}\n{0}{1}\n", PROMPT, DENIED));
+}
+
+#[test]
+fn always_grants_the_permission_for_the_rest_of_the_run() {
+    let output = run_interactive(SOURCE, &["prompt-permissions"], "maybe\na\n");
+    assert_eq!(output.out, format!(
+        "{0}Expected y to allow once, a to allow for the rest of the run or n to deny.\n{0}granted\ngranted\ngranted\n",
+        PROMPT
+    ));
+}
+
+#[test]
+fn no_denies_the_call() {
+    let output = run_interactive(SOURCE, &["prompt-permissions"], "n\nno\ny\n");
+    assert_eq!(output.out, format!("{0}{1}\n{0}{1}\n{0}granted\n", PROMPT, DENIED));
+}
+
+// Nothing is asked without the flag, which leaves the gated natives undefined, or
+// without someone to answer.
+#[test]
+fn the_permissions_are_only_asked_with_the_flag_and_an_interactive_input() {
+    assert_eq!(run_interactive(SOURCE, &[], "a\n").out, "You cannot call a null.\n".repeat(3));
+    assert_eq!(run_with_input(SOURCE, &["prompt-permissions"], "a\n").out, format!("{}\n", DENIED).repeat(3));
+}
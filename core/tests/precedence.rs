@@ -0,0 +1,69 @@
+mod common;
+
+use common::output;
+
+// The precedence and associativity table documented above `binary_operator`.
+// Each expression is run along with the same expression parenthesized by hand and
+// both have to give the value in the table. The values tell apart the groupings
+// which were parsed otherwise before the table was introduced.
+const CASES: [(&str, &str, &str); 34] = [
+    // Multiplicative over additive.
+    ("2 * 3 + 4", "(2 * 3) + 4", "10"),
+    ("2 + 3 * 4", "2 + (3 * 4)", "14"),
+    ("10 - 4 / 2", "10 - (4 / 2)", "8"),
+    ("7 + 8 % 3", "7 + (8 % 3)", "9"),
+    // Left associativity.
+    ("1 - 2 - 3", "(1 - 2) - 3", "-4"),
+    ("12 / 2 / 3", "(12 / 2) / 3", "2"),
+    ("10 / 2 * 5", "(10 / 2) * 5", "25"),
+    ("2 * 3 % 4", "(2 * 3) % 4", "2"),
+    ("10 % 4 * 2", "(10 % 4) * 2", "4"),
+    ("100 >> 2 >> 1", "(100 >> 2) >> 1", "12"),
+    // Power groups to the right and binds tighter than the minus.
+    ("2 ** 3 ** 2", "2 ** (3 ** 2)", "512"),
+    ("2 * 3 ** 2", "2 * (3 ** 2)", "18"),
+    ("-2 ** 2", "-(2 ** 2)", "-4"),
+    ("2 ** -1", "2 ** (-1)", "0.5"),
+    // Shifts below additive.
+    ("1 << 2 + 1", "1 << (2 + 1)", "8"),
+    ("8 >> 1 - 1", "8 >> (1 - 1)", "8"),
+    // Bitwise operators: and over xor over or, all over the comparisons.
+    ("6 | 1 ^ 3 & 2", "6 | (1 ^ (3 & 2))", "7"),
+    ("5 & 1 == 1", "(5 & 1) == 1", "true"),
+    ("1 | 2 < 4", "(1 | 2) < 4", "true"),
+    // Comparison over equality over and over or.
+    ("3 > 2 == true", "(3 > 2) == true", "true"),
+    ("1 + 2 > 2 && false", "((1 + 2) > 2) && false", "false"),
+    ("1 || 0 && 0", "1 || (0 && 0)", "1"),
+    ("false && true || true", "(false && true) || true", "true"),
+    ("1 < 2 == 2 < 3", "(1 < 2) == (2 < 3)", "true"),
+    ("2 != 2 == false", "(2 != 2) == false", "true"),
+    // Not binds to its operand only.
+    ("!true == false", "(!true) == false", "true"),
+    ("!false && false", "(!false) && false", "false"),
+    // Null coalescing below or.
+    ("null ?? false || true", "null ?? (false || true)", "true"),
+    ("null ?? 1 + 2", "null ?? (1 + 2)", "3"),
+    // Ternaries below everything but the assignment, nested ones group to the right.
+    ("true ? 1 : false ? 2 : 3", "true ? 1 : (false ? 2 : 3)", "1"),
+    ("false ? 1 : true ? 2 : 3", "false ? 1 : (true ? 2 : 3)", "2"),
+    ("1 > 2 ? 3 : 4 + 5", "(1 > 2) ? 3 : (4 + 5)", "9"),
+    // Calls, attributes and indexes over everything.
+    ("[1, 2, 3][1] * 3 ** 2", "([1, 2, 3][1]) * (3 ** 2)", "18"),
+    ("-\"abc\".len() + 1", "(-(\"abc\".len())) + 1", "-2")
+];
+
+#[test]
+fn expressions_follow_the_precedence_table() {
+    for (expression, parenthesized, expected) in CASES.iter() {
+        let out = output(&format!("println({}); println({});", expression, parenthesized));
+        assert_eq!(out, format!("{}\n{}\n", expected, expected), "{} and {}", expression, parenthesized);
+    }
+}
+
+#[test]
+fn assignments_take_the_whole_expression() {
+    assert_eq!(output("let x = 0; x = 1 + 2 * 3; println(x);"), "7\n");
+    assert_eq!(output("let x = 2; x += 1 + 2 * 2; println(x);"), "7\n");
+    assert_eq!(output("let x = 0; x = true ? 1 : 2; println(x);"), "1\n");
+}
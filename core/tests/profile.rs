@@ -0,0 +1,55 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+use common::run_with;
+
+const NESTED: &str = r#"
+    func inner() { let i = 0; while i < 2000 { i += 1; } }
+    func outer() { let n = 0; while n < 20 { inner(); n += 1; } }
+    outer();
+"#;
+
+// Runs the script with `--profile-output` into a file of the test and returns
+// what has been written there.
+fn profile(test: &str, name: &str, source: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(test).join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    run_with(source, &[&format!("profile-output={}", path.display())]);
+    fs::read_to_string(&path).expect("The profile has been written.")
+}
+
+// The samples are taken every 100 instructions, so the stacks of the loops which
+// run the most get the most samples.
+#[test]
+fn collapsed_stacks_follow_the_nested_calls() {
+    let out = profile("profile-collapsed", "profile.txt", NESTED);
+    let stacks: Vec<(&str, u64)> = out.lines()
+        .map(|line| {
+            let (stack, count) = line.rsplit_once(' ').expect("The line ends with its count.");
+            (stack, count.parse().unwrap())
+        })
+        .collect();
+
+    let names: Vec<&str> = stacks.iter().map(|(stack, _)| *stack).collect();
+    assert_eq!(names, ["main", "main;outer", "main;outer;inner"], "{}", out);
+
+    let inner = stacks[2].1;
+    assert!(inner > 1000 && inner > stacks[1].1 * 100, "{}", out);
+}
+
+#[test]
+fn json_files_get_the_speedscope_format() {
+    let out = profile("profile-json", "profile.json", NESTED);
+
+    assert!(out.starts_with("{\"$schema\":\"https://www.speedscope.app/file-format-schema.json\""), "{}", out);
+    assert!(out.contains("\"frames\":[{\"name\":\"main\"},{\"name\":\"outer\"},{\"name\":\"inner\"}]"), "{}", out);
+    assert!(out.contains("\"type\":\"sampled\""), "{}", out);
+    assert!(out.contains("\"samples\":[[0],[0,1],[0,1,2]]"), "{}", out);
+}
+
+#[test]
+fn failed_scripts_still_write_their_profile() {
+    let out = profile("profile-failed", "profile.txt", "func spin() { let i = 0; while i < 5000 { i += 1; } throw(\"done\"); }\nspin();");
+    assert!(out.lines().any(|line| line.starts_with("main;spin ")), "{}", out);
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use common::run_with_input;
+
+// Without a terminal the menus read the numbers of the options line by line and
+// ask again until the answer is valid.
+#[test]
+fn menus_read_the_numbers_of_the_options() {
+    let output = run_with_input(r#"
+        let chosen = window.select("Pick one", ["red", "green"]);
+        let many = window.multiSelect("Pick many", ["a", "b", "c"]);
+        println(chosen, many.len(), many[0], many[1]);
+    "#, &[], "3\n2\n3, 1 3\n");
+
+    assert!(output.error.is_none(), "{}", output.err);
+    assert_eq!(output.out, concat!(
+        "Pick one\n  1) red\n  2) green\n",
+        "Enter a number: Expected a number from 1 to 2.\n",
+        "Enter a number: ",
+        "Pick many\n  1) a\n  2) b\n  3) c\n",
+        "Enter the numbers separated by commas: ",
+        "1\n2\n0\n2\n"
+    ));
+}
+
+#[test]
+fn menus_give_null_when_the_input_ends() {
+    let output = run_with_input("println(window.select(\"Pick\", [\"a\"]));", &[], "");
+    assert_eq!(output.out, "Pick\n  1) a\nEnter a number: null\n");
+}
+
+// The editor reads the lines until a single "." without a terminal.
+#[test]
+fn the_editor_reads_lines_without_a_terminal() {
+    let output = run_with_input("let text = window.editor(\"draft\");\nprint(text);", &["use-child-process"], "first\nsecond\n.\nleft\n");
+
+    assert!(output.error.is_none(), "{}", output.err);
+    assert_eq!(output.out, "Enter the text and end it with a line containing only \".\":\nfirst\nsecond\n");
+}
+
+// The editor is only defined with the permission to start the processes.
+#[test]
+fn the_editor_needs_the_child_process_permission() {
+    let output = run_with_input("println(window.editor);", &[], "");
+    assert_eq!(output.out, "null\n");
+}
@@ -0,0 +1,60 @@
+use dashscript_core::AST;
+
+// The line, the column and the message of each syntax error of the source.
+fn errors(source: &str) -> Vec<(usize, usize, String)> {
+    match AST::compile(&String::from("test.ds"), &source.to_owned()) {
+        Ok(_) => Vec::new(),
+        Err(errors) => errors.iter().map(|error| {
+            let (_, line, col) = error.position();
+            (line, col, error.to_string())
+        }).collect()
+    }
+}
+
+fn error(line: usize, col: usize, message: &str) -> (usize, usize, String) {
+    (line, col, message.to_owned())
+}
+
+#[test]
+fn independent_mistakes_are_each_reported_once() {
+    assert_eq!(errors("let a = ;\nlet b = 1 +;\nprintln(1;\n"), vec![
+        error(1, 9, "Expected a value here."),
+        error(2, 12, "Never expected an expression."),
+        error(3, 10, "Never expected an expression.")
+    ]);
+}
+
+// The statement after the failing one is parsed whether it starts with a keyword
+// or not, even on the same line.
+#[test]
+fn recovery_stops_at_the_end_of_the_failing_statement() {
+    assert_eq!(errors("let b = 1 +; let c = ;"), vec![
+        error(1, 12, "Never expected an expression."),
+        error(1, 22, "Expected a value here.")
+    ]);
+    assert_eq!(errors("let b = 1 +;\nx = 2;\ny(;\n"), vec![
+        error(1, 12, "Never expected an expression."),
+        error(3, 3, "Never expected an expression.")
+    ]);
+}
+
+#[test]
+fn mistakes_in_blocks_are_recovered_in_their_block() {
+    assert_eq!(errors("func f() {\n    let a = ;\n    return 1 +;\n}\nlet b = ;\n"), vec![
+        error(2, 13, "Expected a value here."),
+        error(3, 15, "Never expected an expression."),
+        error(5, 9, "Expected a value here.")
+    ]);
+}
+
+#[test]
+fn valid_statements_between_the_mistakes_add_no_errors() {
+    assert_eq!(errors("let a = 1;\nlet b = ;\nprintln(a);\nlet c = [1, 2];\nlet d = c[;\n").len(), 2);
+}
+
+#[test]
+fn errors_stop_at_the_maximum() {
+    let source = "let a = ;\n".repeat(30);
+    assert_eq!(errors(&source).len(), AST::DEFAULT_MAX_ERRORS);
+    assert_eq!(AST::compile_with_max_errors(&String::from("test.ds"), &source, 3).err().map(|errors| errors.len()), Some(3));
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use common::output;
+
+// Random expression trees of bounded depth which are written out as scripts,
+// compiled and executed, and then compared with their value computed directly
+// from the tree. The bytecode reader is fuzzed by `runtime::fuzz`.
+
+// A xorshift generator so that the failing trees can be reproduced from the seed.
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+enum Int {
+    Literal(isize),
+    // The parameter of the function which the tree is wrapped in.
+    Param,
+    Add(Box<Int>, Box<Int>),
+    Sub(Box<Int>, Box<Int>),
+    Mul(Box<Int>, Box<Int>),
+    Negate(Box<Int>),
+    Ternary(Box<Bool>, Box<Int>, Box<Int>)
+}
+
+enum Bool {
+    Literal(bool),
+    Less(Box<Int>, Box<Int>),
+    Equal(Box<Int>, Box<Int>),
+    Not(Box<Bool>),
+    And(Box<Bool>, Box<Bool>),
+    Or(Box<Bool>, Box<Bool>)
+}
+
+impl Int {
+    fn generate(random: &mut Random, depth: usize, param: bool) -> Self {
+        let leaf = depth == 0 || random.below(5) == 0;
+        let next = |random: &mut Random| Box::new(Self::generate(random, depth.saturating_sub(1), param));
+        let choice = if leaf { random.below(2) } else { 2 + random.below(3) };
+
+        match choice {
+            0 if param => Self::Param,
+            0 | 1 => Self::Literal(random.below(19) as isize - 9),
+            2 => match random.below(3) {
+                0 => Self::Add(next(random), next(random)),
+                1 => Self::Sub(next(random), next(random)),
+                _ => Self::Mul(next(random), next(random))
+            },
+            3 => Self::Negate(next(random)),
+            _ => Self::Ternary(Box::new(Bool::generate(random, depth - 1, param)), next(random), next(random))
+        }
+    }
+
+    fn source(&self) -> String {
+        match self {
+            Self::Literal(int) if *int < 0 => format!("-{}", -int),
+            Self::Literal(int) => int.to_string(),
+            Self::Param => "x".to_owned(),
+            Self::Add(lhs, rhs) => format!("({} + {})", lhs.source(), rhs.source()),
+            Self::Sub(lhs, rhs) => format!("({} - {})", lhs.source(), rhs.source()),
+            Self::Mul(lhs, rhs) => format!("({} * {})", lhs.source(), rhs.source()),
+            Self::Negate(int) => format!("-({})", int.source()),
+            Self::Ternary(condition, truthy, falsy) => format!("({} ? {} : {})", condition.source(), truthy.source(), falsy.source())
+        }
+    }
+
+    fn value(&self, x: isize) -> isize {
+        match self {
+            Self::Literal(int) => *int,
+            Self::Param => x,
+            Self::Add(lhs, rhs) => lhs.value(x) + rhs.value(x),
+            Self::Sub(lhs, rhs) => lhs.value(x) - rhs.value(x),
+            Self::Mul(lhs, rhs) => lhs.value(x) * rhs.value(x),
+            Self::Negate(int) => -int.value(x),
+            Self::Ternary(condition, truthy, falsy) => match condition.value(x) {
+                true => truthy.value(x),
+                false => falsy.value(x)
+            }
+        }
+    }
+}
+
+impl Bool {
+    fn generate(random: &mut Random, depth: usize, param: bool) -> Self {
+        let int = |random: &mut Random| Box::new(Int::generate(random, depth.saturating_sub(1), param));
+        let boolean = |random: &mut Random| Box::new(Self::generate(random, depth.saturating_sub(1), param));
+
+        let choice = if depth == 0 { 0 } else { 1 + random.below(5) };
+
+        match choice {
+            0 => Self::Literal(random.below(2) == 0),
+            1 => Self::Less(int(random), int(random)),
+            2 => Self::Equal(int(random), int(random)),
+            3 => Self::Not(boolean(random)),
+            4 => Self::And(boolean(random), boolean(random)),
+            _ => Self::Or(boolean(random), boolean(random))
+        }
+    }
+
+    fn source(&self) -> String {
+        match self {
+            Self::Literal(boolean) => boolean.to_string(),
+            Self::Less(lhs, rhs) => format!("({} < {})", lhs.source(), rhs.source()),
+            Self::Equal(lhs, rhs) => format!("({} == {})", lhs.source(), rhs.source()),
+            Self::Not(boolean) => format!("!({})", boolean.source()),
+            Self::And(lhs, rhs) => format!("({} && {})", lhs.source(), rhs.source()),
+            Self::Or(lhs, rhs) => format!("({} || {})", lhs.source(), rhs.source())
+        }
+    }
+
+    fn value(&self, x: isize) -> bool {
+        match self {
+            Self::Literal(boolean) => *boolean,
+            Self::Less(lhs, rhs) => lhs.value(x) < rhs.value(x),
+            Self::Equal(lhs, rhs) => lhs.value(x) == rhs.value(x),
+            Self::Not(boolean) => !boolean.value(x),
+            Self::And(lhs, rhs) => lhs.value(x) && rhs.value(x),
+            Self::Or(lhs, rhs) => lhs.value(x) || rhs.value(x)
+        }
+    }
+}
+
+const DEPTH: usize = 4;
+const TREES: usize = 150;
+
+#[test]
+fn expressions_at_the_top_level_match_their_tree() {
+    let mut random = Random(0x2545_f491_4f6c_dd1d);
+    for _ in 0..TREES {
+        let tree = Int::generate(&mut random, DEPTH, false);
+        let source = format!("println({});", tree.source());
+        assert_eq!(output(&source), format!("{}\n", tree.value(0)), "{}", source);
+    }
+}
+
+// The trees are compiled into the body of a function and use its parameter so
+// that the locals and the calls are decoded as well.
+#[test]
+fn expressions_in_functions_match_their_tree() {
+    let mut random = Random(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..TREES {
+        let tree = Int::generate(&mut random, DEPTH, true);
+        let x = random.below(19) as isize - 9;
+        let source = format!("func f(x) {{ let y = {}; return y; }} println(f({}));", tree.source(), x);
+        assert_eq!(output(&source), format!("{}\n", tree.value(x)), "{}", source);
+    }
+}
+
+#[test]
+fn conditions_match_their_tree() {
+    let mut random = Random(0x1234_5678_9abc_def1);
+    for _ in 0..TREES {
+        let tree = Bool::generate(&mut random, DEPTH, true);
+        let x = random.below(19) as isize - 9;
+        let source = format!("let x = {}; if ({}) {{ println(true); }} else {{ println(false); }}", x, tree.source());
+        assert_eq!(output(&source), format!("{}\n", tree.value(x)), "{}", source);
+    }
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::run_with;
+
+const SOURCE: &str = r#"
+    let numbers = [];
+    for i in range(0, 5) {
+        numbers.push(Math.random());
+    }
+    println(numbers);
+    println(Date.now());
+    window.sleep(5);
+    println(Date.now(), Date.monotonic());
+"#;
+
+fn run_seeded(seed: &str) -> String {
+    let output = run_with(SOURCE, &[&format!("seed={}", seed)]);
+    assert!(output.error.is_none(), "{}", output.err);
+    output.out
+}
+
+#[test]
+fn the_same_seed_gives_the_same_run() {
+    let first = run_seeded("42");
+    assert_eq!(first, run_seeded("42"));
+    assert!(first.ends_with("946684800000\n946684800005\n5\n"), "{}", first);
+}
+
+#[test]
+fn other_seeds_give_other_random_numbers() {
+    let numbers = |seed| run_seeded(seed).lines().take(7).collect::<Vec<_>>().join("\n");
+    assert_ne!(numbers("42"), numbers("43"));
+    assert_ne!(numbers("0"), numbers("1"));
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{output, run};
+
+#[test]
+fn members_keep_their_insertion_order_and_floats_match_ints() {
+    let out = output("
+        let set = Set([1, 2.0, \"a\", true, null, 1.0]);
+        println(set.size, set.values().join(\" \"));
+        println(set.has(2), set.has(1.0), set.has(\"b\"));
+
+        set.add(3);
+        set.delete(1);
+        set.add(1);
+        println(set.values().join(\" \"));
+        println(set.delete(5), set.delete(\"a\"), set.size);
+    ");
+
+    assert_eq!(out, "5\n1 2 a true null \ntrue\ntrue\nfalse\n2 a true null 3 1 \nfalse\ntrue\n5\n");
+}
+
+#[test]
+fn union_intersect_and_difference_keep_the_order_of_the_first_set() {
+    let out = output("
+        let a = Set([3, 1, 2]);
+        let b = Set([4, 2, 3]);
+        println(a.union(b).values().join(\" \"));
+        println(a.intersect(b).values().join(\" \"));
+        println(a.difference(b).values().join(\" \"));
+        println(a.values().join(\" \"), b.values().join(\" \"));
+    ");
+
+    assert_eq!(out, "3 1 2 4 \n3 2 \n1 \n3 1 2 \n4 2 3 \n");
+}
+
+#[test]
+fn sets_are_iterated_by_for_loops() {
+    let out = output("
+        let seen = [];
+        for member in Set([\"x\", \"y\", \"x\"]) {
+            seen.push(member);
+        }
+        println(seen.join(\",\"));
+    ");
+
+    assert_eq!(out, "x,y,\n");
+}
+
+#[test]
+fn containers_nan_and_size_are_rejected() {
+    let only = "Only numbers, strings, booleans and null can be members.";
+    assert_eq!(run("Set([[1]]);").error().message(), format!("[Set.init]: Cannot use a array as a member of a set. {}", only));
+    assert_eq!(run("Set().add({});").error().message(), format!("[Set.add]: Cannot use a object as a member of a set. {}", only));
+    assert_eq!(run("Set([0.0 / 0.0]);").error().message(), "[Set.init]: NaN cannot be a member of a set.");
+    assert_eq!(run("let set = Set(); set.size = 3;").error().message(), "Cannot assign value to property size which is a readonly property.");
+}
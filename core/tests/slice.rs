@@ -0,0 +1,85 @@
+mod common;
+
+use common::{compile_errors, output, run};
+
+// Prints the items of an array on one line.
+const SHOW: &str = r#"
+    func show(items) {
+        let text = "";
+        for item in items {
+            if (text.len() != 0) {
+                text += "|";
+            }
+            text += "" + item;
+        }
+        println(text);
+    }
+"#;
+
+fn show(source: &str) -> String {
+    output(&format!("{}{}", SHOW, source))
+}
+
+#[test]
+fn bounds_default_to_the_ends_and_count_from_the_end_when_negative() {
+    let out = show("
+        let a = [0, 1, 2, 3, 4, 5];
+        show(a[1:3]);
+        show(a[:2]);
+        show(a[4:]);
+        show(a[:]);
+        show(a[-2:]);
+        show(a[::2]);
+    ");
+
+    assert_eq!(out, "1|2\n0|1\n4|5\n0|1|2|3|4|5\n4|5\n0|2|4\n");
+}
+
+#[test]
+fn negative_steps_walk_backwards() {
+    let out = show("
+        let a = [0, 1, 2, 3, 4, 5];
+        show(a[::-1]);
+        show(a[5:1:-2]);
+        println(\"abc\"[::-1]);
+    ");
+
+    assert_eq!(out, "5|4|3|2|1|0\n5|3\ncba\n");
+}
+
+// The strings of the scripts have no unicode escapes so the texts below are
+// escaped by Rust.
+#[test]
+fn strings_are_sliced_by_characters() {
+    assert_eq!(output("println(\"h\u{e9}llo\"[1:4]);"), "\u{e9}ll\n");
+}
+
+#[test]
+fn zero_steps_and_other_values_cannot_be_sliced() {
+    assert_eq!(run("let a = [1]; a[::0];").error().message(), "Cannot slice with a step of 0.");
+    assert_eq!(run("5[1:2];").error().message(), "Cannot slice number. Expected an array, a string or bytes.");
+}
+
+// The colons of a slice sit next to the ones of the ternaries and the dicts, so
+// each of them is parsed where it is mixed with the others.
+#[test]
+fn slices_are_parsed_next_to_ternaries_and_dicts() {
+    let out = show("
+        let a = [1, 2, 3];
+        let i = 1;
+        println(true ? a[1] : a[0]);
+        show(true ? a[1:2] : 0);
+        println({ \"k\": a[0:1] }[\"k\"].len());
+        println(a[i:i + 1][0]);
+        println(a[true ? 0 : 1:2].len());
+    ");
+
+    assert_eq!(out, "2\n2\n1\n2\n2\n");
+}
+
+#[test]
+fn slices_take_at_most_three_parts() {
+    let errors = compile_errors("let a = [1]; a[1:2:3:4];");
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("array[start:end:step]"), "{}", errors[0]);
+}
@@ -0,0 +1,58 @@
+use dashscript_core::{AST, BytecodeCompiler, CompileStats};
+use dashscript_core::opcode::{FUNC, JUMP_NOT_IF, STRING};
+
+const SCRIPT: &str = r#"func greet(name) {
+    if (name == "a") {
+        return "hi " + name;
+    }
+    return "bye";
+}
+let i = 0;
+while i < 2 {
+    println(greet("a"));
+    i += 1;
+}
+"#;
+
+fn stats(source: &str) -> CompileStats {
+    let filename = String::from("stats.ds");
+    let build = AST::compile(&filename, &source.to_owned()).expect("The script parses.");
+    BytecodeCompiler::new(build).expect("The script compiles.").stats()
+}
+
+// The stats are pinned so that the changes to the encoding show up here.
+#[test]
+fn stats_of_a_fixed_script() {
+    let stats = stats(SCRIPT);
+
+    assert_eq!(stats.bytecode_len, 72);
+    assert_eq!((stats.string_constants, stats.string_constant_bytes, stats.string_loads), (9, 38, 4));
+    assert_eq!((stats.unused_string_constants, stats.unused_string_constant_bytes), (1, 4));
+    assert_eq!((stats.functions, stats.loops, stats.conditions, stats.max_depth), (1, 1, 1, 2));
+    assert_eq!((stats.opcodes[&FUNC], stats.opcodes[&JUMP_NOT_IF], stats.opcodes[&STRING]), (1, 2, 4));
+    assert_eq!(stats.opcodes.values().sum::<usize>(), 34);
+}
+
+#[test]
+fn stats_are_printed_with_the_opcode_counts() {
+    let printed = stats(SCRIPT).to_string();
+
+    assert!(printed.starts_with(concat!(
+        "Bytecode length: 72 bytes\n",
+        "String constants: 9 (38 bytes, loaded 4 times)\n",
+        "Unused string constants: 1 (4 bytes)\n",
+        "Functions: 1, loops: 1, conditions: 1\n",
+        "Deepest nesting level: 2\n",
+        "Opcodes:\n"
+    )), "{}", printed);
+    assert!(printed.contains("\n    COMPOUND_ASSIGN  1\n"), "{}", printed);
+}
+
+#[test]
+fn repeated_strings_are_one_constant() {
+    let once = stats("let a = \"same\";");
+    let twice = stats("let a = \"same\"; let b = \"same\";");
+
+    assert_eq!(twice.string_constants, once.string_constants + 1);
+    assert_eq!(twice.string_loads, 2);
+}
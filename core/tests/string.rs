@@ -0,0 +1,155 @@
+mod common;
+
+use common::output;
+
+// Prints the items of an array on one line.
+const SHOW: &str = r#"
+    func show(items) {
+        let text = "";
+        for item in items {
+            if (text.len() != 0) {
+                text += "|";
+            }
+            text += "" + item;
+        }
+        println(text);
+    }
+"#;
+
+fn show(source: &str) -> String {
+    output(&format!("{}{}", SHOW, source))
+}
+
+// The strings of the scripts have no unicode escapes so the texts below are
+// escaped by Rust.
+#[test]
+fn composed_and_decomposed_accents_are_one_grapheme() {
+    let out = show("
+        let composed = \"\u{e9}\";
+        let decomposed = \"e\u{301}\";
+        show(composed.codePoints());
+        show(composed.bytes());
+        println(composed.byteLength());
+        show(decomposed.codePoints());
+        show(decomposed.bytes());
+        println(decomposed.byteLength());
+        show(composed.graphemes());
+        show(decomposed.graphemes());
+    ");
+
+    assert_eq!(out, "233\n195|169\n2\n101|769\n101|204|129\n3\n\u{e9}\ne\u{301}\n");
+}
+
+#[test]
+fn regional_indicators_pair_into_flags() {
+    let out = show("
+        let flags = \"\u{1f1eb}\u{1f1f7}\u{1f1e9}\u{1f1ea}\";
+        show(flags.codePoints());
+        println(flags.graphemes().len());
+        show(flags.graphemes());
+    ");
+
+    assert_eq!(out, "127467|127479|127465|127466\n2\n\u{1f1eb}\u{1f1f7}|\u{1f1e9}\u{1f1ea}\n");
+}
+
+#[test]
+fn zwj_sequences_are_one_grapheme() {
+    let out = show("
+        let family = \"\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}\";
+        println(family.codePoints().len(), family.byteLength(), family.graphemes().len());
+        println(family.graphemes()[0] == family);
+    ");
+
+    assert_eq!(out, "5\n18\n1\ntrue\n");
+}
+
+#[test]
+fn crlf_is_one_grapheme() {
+    let out = show(r#"
+        show("a\r\nb".graphemes().map(func (grapheme) { return grapheme.byteLength(); }));
+    "#);
+
+    assert_eq!(out, "1|2|1\n");
+}
+
+// The indexes count chars like `codePoints` does while the emoji take four bytes.
+#[test]
+fn searches_return_char_indexes() {
+    let out = show("
+        let text = \"a\u{1f600}b\u{1f600}c\";
+        show([text.indexOf(\"\u{1f600}\"), text.indexOf(\"\u{1f600}\", 2), text.indexOf(\"x\")]);
+        show([text.lastIndexOf(\"\u{1f600}\"), text.lastIndexOf(\"\u{1f600}\", 2), text.lastIndexOf(\"a\", 0)]);
+        println(text.count(\"\u{1f600}\"));
+
+        let far = \"\u{1f600}\u{1f600}\u{1f600}\u{1f600}\u{1f600}\u{1f600}\u{1f600}\u{1f600}needle\";
+        show([far.indexOf(\"needle\"), far.lastIndexOf(\"needle\"), far.byteLength()]);
+    ");
+
+    assert_eq!(out, "1|3|-1\n3|1|0\n2\n8|8|38\n");
+}
+
+#[test]
+fn count_is_of_non_overlapping_occurrences() {
+    let out = show(r#"
+        show(["aaaa".count("aa"), "aaa".count("aa"), "abc".count("d")]);
+    "#);
+
+    assert_eq!(out, "2|1|0\n");
+}
+
+// The empty needles are found at the start index like in javascript.
+#[test]
+fn empty_needles_follow_javascript() {
+    let out = show(r#"
+        let text = "abc";
+        show([text.indexOf(""), text.indexOf("", 2), text.indexOf("", 10), text.lastIndexOf(""), text.count("")]);
+    "#);
+
+    assert_eq!(out, "0|2|3|3|4\n");
+}
+
+#[test]
+fn lines_words_and_chars_split_the_text() {
+    let out = show("
+        show(\"a\\r\\nb\\n\\nc\\r\\n\".lines());
+        show(\" one  two\\tthree\u{3000}four\\n\".words());
+        show(\"h\u{e9}!\".chars());
+        show([\"\".lines().len(), \"\".words().len(), \"\".chars().len(), \" \\t\\n \".words().len(), \"\\n\".lines().len()]);
+    ");
+
+    assert_eq!(out, "a|b||c\none|two|three|four\nh|\u{e9}|!\n0|0|0|0|1\n");
+}
+
+// A rough check that splitting many lines is not quadratic.
+#[test]
+fn lines_splits_a_large_text() {
+    let text = "line\r\n".repeat(100_000);
+    let out = output(&format!("let lines = \"{}\".lines();\nprintln(lines.len(), lines[99999]);", text.replace('\r', "\\r").replace('\n', "\\n")));
+
+    assert_eq!(out, "100000\nline\n");
+}
+
+#[test]
+fn hash_is_the_same_for_equal_strings_and_finds_long_keys() {
+    let out = output("
+        println((\"ab\" + \"c\").hash() == \"abc\".hash(), \"abc\".hash() == \"abd\".hash());
+
+        let prefix = \"k\".repeat(300);
+        let keys = {};
+        let i = 0;
+        while i < 1000 {
+            keys[prefix + i] = i;
+            i += 1;
+        }
+
+        let sum = 0;
+        i = 0;
+        while i < 1000 {
+            sum += keys[prefix + i];
+            i += 1;
+        }
+        println(sum, keys[prefix] == null);
+    ");
+
+    assert_eq!(out, "true\nfalse\n499500\ntrue\n");
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::output;
+
+#[test]
+fn values_are_stringified_the_way_plus_does() {
+    let out = output(r#"
+        let builder = StringBuilder();
+        builder.append("a").append(1).append(2.5).append(null).append(true).append([1]);
+        println(builder.toString() == "a" + 1 + 2.5 + null + true + [1]);
+        println(builder.toString());
+    "#);
+
+    assert_eq!(out, "true\na12.5nulltrue[Array]\n");
+}
+
+#[test]
+fn builders_append_the_contents_of_other_builders() {
+    let out = output(r#"
+        let inner = StringBuilder("in");
+        let outer = StringBuilder("out:");
+        outer.append(inner).appendLine().appendLine("end");
+        inner.append("ner");
+        println(outer.toString() == "out:in\nend\n", inner.toString());
+    "#);
+
+    assert_eq!(out, "true\ninner\n");
+}
+
+#[test]
+fn length_counts_bytes_and_clear_empties_the_builder() {
+    let out = output("
+        let builder = StringBuilder(\"h\u{e9}\");
+        println(builder.length);
+        builder.clear();
+        println(builder.length, builder.toString() == \"\");
+    ");
+
+    assert_eq!(out, "3\n0\ntrue\n");
+}
+
+#[test]
+fn many_pieces_are_appended() {
+    let out = output(r#"
+        let builder = StringBuilder();
+        let i = 0;
+        while i < 100000 {
+            builder.append("ab");
+            i += 1;
+        }
+        let text = builder.toString();
+        println(builder.length, text.len(), text[199998:]);
+    "#);
+
+    assert_eq!(out, "200000\n200000\nab\n");
+}
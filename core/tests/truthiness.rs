@@ -0,0 +1,77 @@
+mod common;
+
+use common::{output, run};
+
+// Only false, null, 0, NaN and "" are falsy.
+const CASES: [(&str, bool); 19] = [
+    ("false", false),
+    ("null", false),
+    ("0", false),
+    ("0.0", false),
+    ("NaN", false),
+    ("\"\"", false),
+    ("true", true),
+    ("1", true),
+    ("-1", true),
+    ("0.5", true),
+    ("inf", true),
+    ("\" \"", true),
+    ("\"false\"", true),
+    ("[]", true),
+    ("[0]", true),
+    ("{}", true),
+    ("func () {}", true),
+    ("Boolean", true),
+    ("Bytes.from(\"\")", true)
+];
+
+#[test]
+fn boolean_follows_the_truthiness_table() {
+    for (source, expected) in CASES.iter() {
+        let out = output(&format!("println(Boolean({}));", source));
+        assert_eq!(out, format!("{}\n", expected), "Boolean({})", source);
+    }
+}
+
+#[test]
+fn conditions_follow_the_truthiness_table() {
+    for (source, expected) in CASES.iter() {
+        let out = output(&format!("if ({}) {{ println(true); }} else {{ println(false); }}", source));
+        assert_eq!(out, format!("{}\n", expected), "if ({})", source);
+    }
+}
+
+#[test]
+fn boolean_from_is_an_alias() {
+    assert_eq!(output("println(Boolean.from(\"\")); println(Boolean.from(1)); println(Boolean());"), "false\ntrue\nfalse\n");
+}
+
+#[test]
+fn objects_of_the_scripts_are_not_callable() {
+    let result = run("let object = { \"__call\": func () { return 1; } }; object();");
+    assert!(result.error().to_string().contains("Cannot call object"), "{}", result.error());
+}
+
+// The literals are negated by the compiler and the variables by the vm, so both
+// are checked against the table.
+#[test]
+fn invert_follows_the_truthiness_table() {
+    for (source, expected) in CASES.iter() {
+        let out = output(&format!("println(!{0}, !!{0}); let value = {0}; println(!value, !!value);", source));
+        assert_eq!(out, format!("{}\n{}\n{}\n{}\n", !expected, expected, !expected, expected), "!{}", source);
+    }
+}
+
+#[test]
+fn invert_applies_to_its_operand_with_its_calls_and_attributes() {
+    let out = output(r#"
+        let x = null;
+        let empty = "";
+        let items = [1];
+        println(!x == null, x != null, !(x == null));
+        println(!empty.isEmpty(), !items.len(), !!!0);
+        println(!true || true, !false && false);
+    "#);
+
+    assert_eq!(out, "false\nfalse\nfalse\nfalse\nfalse\ntrue\ntrue\nfalse\n");
+}
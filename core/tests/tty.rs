@@ -0,0 +1,32 @@
+mod common;
+
+use std::time::UNIX_EPOCH;
+use dashscript_core::core::{log, tty::{self, Color}};
+use common::run_with;
+
+#[test]
+fn paint_colors_only_for_terminals() {
+    assert_eq!(tty::paint("error", Color::Red, true), "\x1b[31merror\x1b[0m");
+    assert_eq!(tty::paint("warn", Color::Yellow, true), "\x1b[33mwarn\x1b[0m");
+    assert_eq!(tty::paint("error", Color::Red, false), "error");
+}
+
+#[test]
+fn strip_ansi_removes_the_painted_colors() {
+    assert_eq!(tty::strip_ansi(&tty::paint("error", Color::Red, true)), "error");
+    assert_eq!(tty::strip_ansi("\x1b]0;title\x07a\x1b[2Kb"), "ab");
+}
+
+#[test]
+fn log_lines_color_their_level_through_paint() {
+    assert_eq!(log::line(log::Level::Error, "m", true, UNIX_EPOCH), "1970-01-01T00:00:00.000Z \x1b[31mERROR\x1b[0m m\n");
+    assert_eq!(log::line(log::Level::Error, "m", false, UNIX_EPOCH), "1970-01-01T00:00:00.000Z ERROR m\n");
+    assert_eq!(log::line(log::Level::Info, "m", true, UNIX_EPOCH), "1970-01-01T00:00:00.000Z INFO  m\n");
+}
+
+#[test]
+fn reported_errors_are_not_colored_when_captured() {
+    let result = run_with("window.onExit(func () { throw(\"late\"); });", &[]);
+    assert!(!result.err.is_empty());
+    assert!(!result.err.contains('\x1b'), "{:?}", result.err);
+}
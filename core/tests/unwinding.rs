@@ -0,0 +1,121 @@
+mod common;
+
+use common::{output, run_with};
+
+// The scripts run with `--paranoid` so that the vm checks its stack and frames
+// after every instruction.
+fn checked(source: &str) -> String {
+    let result = run_with(source, &["paranoid"]);
+    if let Some(error) = result.error {
+        panic!("The script has thrown {}. Its output was:\n{}", error, result.out);
+    }
+
+    result.out
+}
+
+#[test]
+fn errors_after_a_try_block_are_not_caught_by_it() {
+    let result = run_with(r#"
+        try { println("body"); } expect { println("caught"); }
+        throw("after");
+    "#, &["paranoid"]);
+
+    assert_eq!(result.out, "body\n");
+    assert!(result.error.is_some());
+}
+
+#[test]
+fn the_innermost_try_block_catches() {
+    let out = checked(r#"
+        try {
+            try { throw("inner"); } expect { println("inner caught"); }
+            throw("outer");
+        } expect { println("outer caught"); }
+    "#);
+
+    assert_eq!(out, "inner caught\nouter caught\n");
+}
+
+#[test]
+fn errors_unwind_the_frames_of_the_calls() {
+    let out = checked(r#"
+        func h(x) { let unused = x; throw("deep"); return x; }
+        func g(x) { let y = x + 1; return h(y); }
+        func f() {
+            let kept = 42;
+            try { g(1); } expect { println("caught"); }
+            return kept;
+        }
+
+        println(f());
+        println(f());
+    "#);
+
+    assert_eq!(out, "caught\n42\ncaught\n42\n");
+}
+
+#[test]
+fn errors_in_callbacks_resume_after_the_native() {
+    let out = checked(r#"
+        let before = "kept";
+        try {
+            [1, 2, 3].map(func (x) {
+                if x == 2 { throw("in callback"); }
+                return x;
+            });
+            println("not reached");
+        } expect { println("caught"); }
+
+        println(before);
+        println([1, 2].map(func (x) { return x * 10; })[1]);
+    "#);
+
+    assert_eq!(out, "caught\nkept\n20\n");
+}
+
+#[test]
+fn errors_in_nested_callbacks_are_caught_inside_them() {
+    let out = checked(r#"
+        let result = [1, 2].map(func (x) {
+            let inner = [x].map(func (y) {
+                try { throw("nested"); } expect { return y * 100; }
+                return y;
+            });
+
+            return inner[0];
+        });
+
+        println(result[0]);
+        println(result[1]);
+    "#);
+
+    assert_eq!(out, "100\n200\n");
+}
+
+#[test]
+fn try_blocks_leave_the_stack_as_it_was() {
+    // The try statement used to pop a value which it never pushed.
+    let out = checked(r#"
+        let a = 1;
+        let b = 2;
+        let i = 0;
+        while i < 1000 {
+            try { if i == 500 { throw("x"); } } expect { }
+            i += 1;
+        }
+
+        println(a);
+        println(b);
+        println(i);
+    "#);
+
+    assert_eq!(out, "1\n2\n1000\n");
+}
+
+#[test]
+fn uncaught_errors_in_callbacks_end_the_script() {
+    let result = run_with("[1].map(func (x) { throw(\"boom\"); return x; }); println(\"not reached\");", &["paranoid"]);
+    assert_eq!(result.out, "");
+    assert!(result.error().cause().is_some());
+    assert_eq!(output("println(1);"), "1\n");
+}